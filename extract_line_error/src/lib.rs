@@ -1,18 +1,109 @@
 pub fn extract_error_code(code: &str, begin: usize, end: usize) -> String {
+    extract_code(code, begin, end, "Error")
+}
+
+pub fn extract_warning_code(code: &str, begin: usize, end: usize) -> String {
+    extract_code(code, begin, end, "Warning")
+}
+
+fn extract_code(code: &str, begin: usize, end: usize, severity: &str) -> String {
     let (lines, token) = find_wrong_code(code, begin, end);
 
     let block = &code[token.begin..token.end];
     let descr = match lines {
         //humans count from 1
-        WrongLines::Single(line) => format!("Error on line: {}", line + 1),
+        WrongLines::Single(line) => format!("{} on line: {}", severity, line + 1),
         WrongLines::Multiple(from, to) => {
-            format!("Error from line: {} to line: {}", from + 1, to + 1)
+            format!("{} from line: {} to line: {}", severity, from + 1, to + 1)
         }
     };
 
     format!("{}\n{}", descr, block)
 }
 
+/*
+    A program made of several files is seen as the
+    concatenation of its files: every file starts at its own
+    base offset, so a location identifies both the file
+    and the code inside it. Errors are reported with the name
+    of the file only when the program has more than one file.
+*/
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+struct SourceFile {
+    name: String,
+    code: String,
+    base: usize,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // returns the base offset of the new file
+    pub fn add_file(&mut self, name: String, code: String) -> usize {
+        let base = match self.files.last() {
+            Some(file) => file.base + file.code.len() + 1,
+            None => 0,
+        };
+        self.files.push(SourceFile { name, code, base });
+        base
+    }
+
+    pub fn file_name(&self, offset: usize) -> &str {
+        &self.find_file(offset).name
+    }
+
+    pub fn extract_error_code(&self, begin: usize, end: usize) -> String {
+        self.extract_code(begin, end, extract_error_code)
+    }
+
+    pub fn extract_warning_code(&self, begin: usize, end: usize) -> String {
+        self.extract_code(begin, end, extract_warning_code)
+    }
+
+    fn extract_code(
+        &self,
+        begin: usize,
+        end: usize,
+        extract: fn(&str, usize, usize) -> String,
+    ) -> String {
+        let file = self.find_file(begin);
+        let output = extract(&file.code, begin - file.base, end - file.base);
+        if self.files.len() > 1 {
+            format!("In file: {}\n{}", file.name, output)
+        } else {
+            output
+        }
+    }
+
+    // line of the offset inside its own file, humans count from 1
+    pub fn line_number(&self, offset: usize) -> usize {
+        let file = self.find_file(offset);
+        file.code[..offset - file.base].matches('\n').count() + 1
+    }
+
+    fn find_file(&self, offset: usize) -> &SourceFile {
+        self.files
+            .iter()
+            .rev()
+            .find(|file| file.base <= offset)
+            .expect("empty source map")
+    }
+}
+
+impl From<&str> for SourceMap {
+    fn from(code: &str) -> Self {
+        let mut output = Self::new();
+        output.add_file(String::new(), code.to_owned());
+        output
+    }
+}
+
 struct Token {
     begin: usize,
     end: usize,
@@ -40,9 +131,12 @@ fn find_wrong_code(code: &str, begin: usize, end: usize) -> (WrongLines, Token)
     let mut begin_line = 0;
     let mut end_line = 0;
     let mut stat = false;
+    let mut done = false;
+    let mut last_line = (0, 0);
     let mut token = Token::new();
     for (n, line) in code.lines().enumerate() {
         let curr_end = curr_begin + line.len();
+        last_line = (n, curr_begin);
         if (!stat) && curr_end >= begin {
             begin_line = n;
             token.set_begin(curr_begin);
@@ -51,12 +145,25 @@ fn find_wrong_code(code: &str, begin: usize, end: usize) -> (WrongLines, Token)
         if stat && curr_end >= end {
             end_line = n;
             token.set_end(curr_end);
+            done = true;
             break;
         } else {
             curr_begin = curr_end + 1;
         }
     }
 
+    // the wrong code reaches the end of the file, past the last line
+    if !done {
+        let (n, line_begin) = last_line;
+        if !stat {
+            begin_line = n;
+            token.set_begin(line_begin);
+        }
+        end_line = n;
+        let code_end = code.trim_end_matches(|c| c == '\n' || c == '\r').len();
+        token.set_end(code_end.max(token.begin));
+    }
+
     if begin_line == end_line {
         (WrongLines::Single(begin_line), token)
     } else {
@@ -108,6 +215,55 @@ mod test {
         assert_eq!(token.end, 287);
     }
 
+    #[test]
+    fn test_wrong_code_at_end_of_file() {
+        let code = "body\n  (* open\nend.\n";
+        let (lines, token) = find_wrong_code(code, 7, code.len());
+        assert!(matches!(lines, WrongLines::Multiple(begin, end)
+             if begin == 1 && end == 2));
+        assert_eq!(&code[token.begin..token.end], "  (* open\nend.");
+
+        let (lines, token) = find_wrong_code(code, code.len(), code.len());
+        assert!(matches!(lines, WrongLines::Single(n) if n == 2));
+        assert_eq!(&code[token.begin..token.end], "end.");
+
+        assert_eq!(
+            extract_error_code("", 0, 0),
+            "Error on line: 1\n".to_owned()
+        );
+    }
+
+    #[test]
+    fn test_source_map() {
+        let single = SourceMap::from(CODE);
+        let ans = single.extract_error_code(ONE_LINE_ERROR.0, ONE_LINE_ERROR.1);
+        assert_eq!(
+            ans,
+            extract_error_code(&CODE, ONE_LINE_ERROR.0, ONE_LINE_ERROR.1)
+        );
+
+        let mut multiple = SourceMap::new();
+        assert_eq!(
+            multiple.add_file("main.simpla".to_owned(), "body\nend.".to_owned()),
+            0
+        );
+        let base = multiple.add_file("lib.simpla".to_owned(), CODE.to_owned());
+        assert_eq!(base, 10);
+        assert_eq!(multiple.file_name(3), "main.simpla");
+        assert_eq!(multiple.file_name(base), "lib.simpla");
+
+        let ans = multiple.extract_error_code(ONE_LINE_ERROR.0 + base, ONE_LINE_ERROR.1 + base);
+        let correct_format = format!(
+            "In file: lib.simpla\nError on line: 6\n{}",
+            "        quis nostrum exercitationem ullamco laboriosam,"
+        );
+        assert_eq!(ans, correct_format);
+
+        assert_eq!(multiple.line_number(0), 1);
+        assert_eq!(multiple.line_number(5), 2);
+        assert_eq!(multiple.line_number(ONE_LINE_ERROR.0 + base), 6);
+    }
+
     #[test]
     fn test_format_wrong_code() {
         let wrong_line = "        quis nostrum exercitationem ullamco laboriosam,";
@@ -121,5 +277,9 @@ mod test {
         let correct_format = format!("Error from line: 5 to line: 7\n{}", wrong_lines);
         let ans = extract_error_code(&CODE, MULTIPLE_LINE_ERROR.0, MULTIPLE_LINE_ERROR.1);
         assert_eq!(ans, correct_format);
+
+        let correct_format = format!("Warning on line: 6\n{}", wrong_line);
+        let ans = extract_warning_code(&CODE, ONE_LINE_ERROR.0, ONE_LINE_ERROR.1);
+        assert_eq!(correct_format, ans);
     }
 }
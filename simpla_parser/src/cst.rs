@@ -0,0 +1,113 @@
+use crate::lexer::{Lexer, Tok};
+
+/*
+    Lossless token stream of the code: every byte, white
+    spaces and comments included, is in exactly one token,
+    so joining the texts of the tokens in order gives back
+    the code. The tokens are the ones of the lexer the
+    parser reads, so a tool can edit a name and keep the
+    layout and the comments around it. There is no tree
+    over the tokens: tools that need the syntax tree of an
+    edited text parse it again.
+*/
+#[derive(Debug, PartialEq, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TokenKind {
+    Whitespace,
+    Comment,
+    Keyword,
+    Identifier,
+    Number,
+    Str,
+    Char,
+    Punct,
+    // an unterminated string or comment, or an invalid character
+    Unknown,
+}
+
+impl TokenKind {
+    pub fn is_trivia(self) -> bool {
+        matches!(self, Self::Whitespace | Self::Comment)
+    }
+}
+
+// the tokens of the lexer of the parser, trivia included
+pub fn tokenize(code: &str) -> Vec<Token> {
+    Lexer::new(code)
+        .map(|(_, tok, _)| Token {
+            kind: TokenKind::from(tok),
+            text: tok.text().to_owned(),
+        })
+        .collect()
+}
+
+impl From<Tok<'_>> for TokenKind {
+    fn from(tok: Tok) -> Self {
+        match tok {
+            Tok::Whitespace(_) => Self::Whitespace,
+            Tok::Comment(_) => Self::Comment,
+            Tok::Keyword(_) => Self::Keyword,
+            Tok::Punct(_) => Self::Punct,
+            Tok::Id(_) => Self::Identifier,
+            Tok::Int(_) | Tok::Long(_) | Tok::Real(_) => Self::Number,
+            Tok::Str(_) => Self::Str,
+            Tok::Char(_) => Self::Char,
+            Tok::Unknown(_) => Self::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_lossless() {
+        let code = "# count\nx: integer; (* the first one *)\nbody x = 0x1F; end.\n";
+        let tokens = tokenize(code);
+        let text: String = tokens.iter().map(|token| token.text.as_str()).collect();
+        assert_eq!(text, code);
+
+        let comments: Vec<&str> = tokens
+            .iter()
+            .filter(|token| token.kind == TokenKind::Comment)
+            .map(|token| token.text.as_str())
+            .collect();
+        assert_eq!(comments, ["# count", "(* the first one *)"]);
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("if iffy <= 1.5e3 then x = 0x1FL; 'a' \"#\" ? downto writeln");
+        let kinds: Vec<TokenKind> = tokens
+            .iter()
+            .filter(|token| !token.kind.is_trivia())
+            .map(|token| token.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                TokenKind::Keyword,
+                TokenKind::Identifier,
+                TokenKind::Punct,
+                TokenKind::Number,
+                TokenKind::Keyword,
+                TokenKind::Identifier,
+                TokenKind::Punct,
+                TokenKind::Number,
+                TokenKind::Punct,
+                TokenKind::Char,
+                TokenKind::Str,
+                TokenKind::Unknown,
+                TokenKind::Keyword,
+                TokenKind::Keyword
+            ]
+        );
+    }
+}
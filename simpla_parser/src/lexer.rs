@@ -0,0 +1,341 @@
+use crate::literal::LexicalError;
+
+/*
+    The lexer of the language, the only definition of its
+    tokens: the parser reads the significant ones, the
+    concrete syntax tree keeps every token, white spaces
+    and comments included. The longest token wins, a keyword
+    wins over an identifier of the same length.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tok<'input> {
+    Whitespace(&'input str),
+    Comment(&'input str),
+    Keyword(&'input str),
+    Punct(&'input str),
+    Id(&'input str),
+    Int(&'input str),
+    Long(&'input str),
+    Real(&'input str),
+    Str(&'input str),
+    Char(&'input str),
+    // an unterminated string or comment, or a character no token begins with
+    Unknown(&'input str),
+}
+
+const KEYWORDS: &[&str] = &[
+    "if", "import", "func", "body", "end", "break", "continue", "repeat", "until", "case", "var",
+    "of", "then", "else", "while", "for", "do", "to", "downto", "step", "return", "assert", "halt",
+    "read", "write", "writeln", "and", "or", "not", "integer", "long", "real", "string", "char",
+    "boolean", "void", "true", "false",
+];
+
+const PUNCTS: &[&str] = &[
+    "==", "!=", ">=", "<=", ".", ":", ";", ",", "(", ")", "[", "]", "=", "+", "-", "*", "/", "<",
+    ">",
+];
+
+impl<'input> Tok<'input> {
+    pub fn text(self) -> &'input str {
+        match self {
+            Self::Whitespace(text)
+            | Self::Comment(text)
+            | Self::Keyword(text)
+            | Self::Punct(text)
+            | Self::Id(text)
+            | Self::Int(text)
+            | Self::Long(text)
+            | Self::Real(text)
+            | Self::Str(text)
+            | Self::Char(text)
+            | Self::Unknown(text) => text,
+        }
+    }
+
+    pub fn is_trivia(self) -> bool {
+        matches!(self, Self::Whitespace(_) | Self::Comment(_))
+    }
+}
+
+// every token of the code with its begin and end offset
+pub struct Lexer<'input> {
+    code: &'input str,
+    offset: usize,
+}
+
+impl<'input> Lexer<'input> {
+    pub fn new(code: &'input str) -> Self {
+        Self { code, offset: 0 }
+    }
+}
+
+impl<'input> Iterator for Lexer<'input> {
+    type Item = (usize, Tok<'input>, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.code[self.offset..];
+        let first = rest.chars().next()?;
+        let (len, kind): (usize, fn(&'input str) -> Tok<'input>) = if first.is_whitespace() {
+            (whitespace_len(rest), Tok::Whitespace)
+        } else if first == '#' {
+            (rest.find('\n').unwrap_or(rest.len()), Tok::Comment)
+        } else if let Some(comment) = rest.strip_prefix("(*") {
+            match comment.find("*)") {
+                Some(end) => (end + 4, Tok::Comment),
+                None => (rest.len(), Tok::Unknown),
+            }
+        } else if first.is_ascii_alphabetic() {
+            let len = word_len(rest);
+            if KEYWORDS.contains(&&rest[..len]) {
+                (len, Tok::Keyword)
+            } else {
+                (len, Tok::Id)
+            }
+        } else if first.is_ascii_digit() || (rest.starts_with('.') && real_len(rest) > 0) {
+            number(rest)
+        } else if first == '"' {
+            match quoted_len(rest, '"') {
+                Ok(len) => (len, Tok::Str),
+                Err(len) => (len, Tok::Unknown),
+            }
+        } else if first == '\'' {
+            match quoted_len(rest, '\'') {
+                Ok(len) => (len, Tok::Char),
+                Err(_) => (1, Tok::Unknown),
+            }
+        } else if let Some(punct) = PUNCTS.iter().find(|punct| rest.starts_with(*punct)) {
+            (punct.len(), Tok::Punct)
+        } else {
+            (first.len_utf8(), Tok::Unknown)
+        };
+        let begin = self.offset;
+        self.offset += len;
+        Some((begin, kind(&self.code[begin..self.offset]), self.offset))
+    }
+}
+
+/*
+    The tokens read by the parser: white spaces and comments
+    are skipped, a token no rule accepts is an error.
+*/
+pub fn parser_tokens(
+    code: &str,
+) -> impl Iterator<Item = Result<(usize, Tok<'_>, usize), LexicalError>> {
+    Lexer::new(code)
+        .filter(|(_, tok, _)| !tok.is_trivia())
+        .map(|(begin, tok, end)| match tok {
+            Tok::Unknown(text) => Err(LexicalError::new_unknown_token(text, begin)),
+            tok => Ok((begin, tok, end)),
+        })
+}
+
+fn whitespace_len(rest: &str) -> usize {
+    rest.find(|c: char| !c.is_whitespace())
+        .unwrap_or(rest.len())
+}
+
+fn word_len(rest: &str) -> usize {
+    rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(rest.len())
+}
+
+// a digit followed by digits and underscores
+fn decimal_len(rest: &str) -> usize {
+    match rest.chars().next() {
+        Some(c) if c.is_ascii_digit() => rest
+            .find(|c: char| !c.is_ascii_digit() && c != '_')
+            .unwrap_or(rest.len()),
+        _ => 0,
+    }
+}
+
+// the decimal, hexadecimal or binary digits of an integer
+fn integer_len(rest: &str) -> usize {
+    decimal_len(rest)
+        .max(prefixed_len(rest, ["0x", "0X"]))
+        .max(prefixed_len(rest, ["0b", "0B"]))
+}
+
+/*
+    A prefix takes every hexadecimal digit and underscore
+    after it, even none: a malformed run stays one token,
+    the parser reports it with its whole text.
+*/
+fn prefixed_len(rest: &str, prefixes: [&str; 2]) -> usize {
+    if !prefixes.iter().any(|prefix| rest.starts_with(prefix)) {
+        return 0;
+    }
+    let digits = &rest[2..];
+    2 + digits
+        .find(|c: char| !c.is_ascii_hexdigit() && c != '_')
+        .unwrap_or(digits.len())
+}
+
+fn exponent_len(rest: &str) -> usize {
+    if !rest.starts_with(['e', 'E']) {
+        return 0;
+    }
+    let sign = usize::from(rest[1..].starts_with(['+', '-']));
+    let digits = rest[1 + sign..]
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len() - 1 - sign);
+    if digits == 0 {
+        0
+    } else {
+        1 + sign + digits
+    }
+}
+
+// with a fractional part, or with an exponent only
+fn real_len(rest: &str) -> usize {
+    let int = decimal_len(rest);
+    let after = &rest[int..];
+    if let Some(decimals) = after.strip_prefix('.') {
+        match decimal_len(decimals) {
+            0 => 0,
+            fraction => {
+                let len = int + 1 + fraction;
+                len + exponent_len(&rest[len..])
+            }
+        }
+    } else if int > 0 {
+        match exponent_len(after) {
+            0 => 0,
+            exponent => int + exponent,
+        }
+    } else {
+        0
+    }
+}
+
+fn number<'input>(rest: &'input str) -> (usize, fn(&'input str) -> Tok<'input>) {
+    let int = integer_len(rest);
+    let real = real_len(rest);
+    if real > int {
+        (real, Tok::Real)
+    } else if rest[int..].starts_with('L') {
+        (int + 1, Tok::Long)
+    } else {
+        (int, Tok::Int)
+    }
+}
+
+/*
+    The length of a string or char literal, a backslash
+    escapes any character but the new line. Without the
+    closing quote the error holds the length of the line,
+    a backslash at its end included.
+*/
+fn quoted_len(rest: &str, quote: char) -> Result<usize, usize> {
+    let mut chars = rest.char_indices().skip(1);
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, '\n')) | None => return Err(index + 1),
+                Some(_) => {}
+            },
+            '\n' => return Err(index),
+            c if c == quote => return Ok(index + 1),
+            _ => {}
+        }
+    }
+    Err(rest.len())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn tokens(code: &str) -> Vec<Tok<'_>> {
+        Lexer::new(code)
+            .map(|(_, tok, _)| tok)
+            .filter(|tok| !tok.is_trivia())
+            .collect()
+    }
+
+    #[test]
+    fn test_tokens() {
+        let code = "if iffy <= 1.5e3 then x = 0x1FL; 'a' \"#\" ? downto writeln";
+        assert_eq!(
+            tokens(code),
+            [
+                Tok::Keyword("if"),
+                Tok::Id("iffy"),
+                Tok::Punct("<="),
+                Tok::Real("1.5e3"),
+                Tok::Keyword("then"),
+                Tok::Id("x"),
+                Tok::Punct("="),
+                Tok::Long("0x1FL"),
+                Tok::Punct(";"),
+                Tok::Char("'a'"),
+                Tok::Str("\"#\""),
+                Tok::Unknown("?"),
+                Tok::Keyword("downto"),
+                Tok::Keyword("writeln")
+            ]
+        );
+        let text: String = Lexer::new(code).map(|(_, tok, _)| tok.text()).collect();
+        assert_eq!(text, code);
+    }
+
+    #[test]
+    fn test_numbers() {
+        let cases = [
+            ("1_000", Tok::Int("1_000")),
+            ("0b101L", Tok::Long("0b101L")),
+            ("0x", Tok::Int("0x")),
+            ("0b12L", Tok::Long("0b12L")),
+            ("1__0_", Tok::Int("1__0_")),
+            (".5", Tok::Real(".5")),
+            ("2E3", Tok::Real("2E3")),
+            ("2e+", Tok::Int("2")),
+            ("1.e3", Tok::Int("1")),
+            ("0.000_5e-8", Tok::Real("0.000_5e-8")),
+        ];
+        for (code, expected) in cases.iter() {
+            assert_eq!(tokens(code)[0], *expected, "{}", code);
+        }
+        assert_eq!(tokens("x.y"), [Tok::Id("x"), Tok::Punct("."), Tok::Id("y")]);
+    }
+
+    #[test]
+    fn test_comments_and_literals() {
+        let code = "(* a *) b *) (**) (*) # c\n\"d\\\" e\" 'f";
+        assert_eq!(
+            Lexer::new(code).map(|(_, tok, _)| tok).collect::<Vec<_>>(),
+            [
+                Tok::Comment("(* a *)"),
+                Tok::Whitespace(" "),
+                Tok::Id("b"),
+                Tok::Whitespace(" "),
+                Tok::Punct("*"),
+                Tok::Punct(")"),
+                Tok::Whitespace(" "),
+                Tok::Comment("(**)"),
+                Tok::Whitespace(" "),
+                Tok::Unknown("(*) # c\n\"d\\\" e\" 'f"),
+            ]
+        );
+        assert_eq!(
+            tokens("\"open\\\n\"line\n'"),
+            [
+                Tok::Unknown("\"open\\"),
+                Tok::Unknown("\"line"),
+                Tok::Unknown("'")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parser_tokens() {
+        let code = "x # y\n (* open";
+        let tokens: Vec<_> = parser_tokens(code).collect();
+        assert_eq!(tokens[0], Ok((0, Tok::Id("x"), 1)));
+        let err = tokens[1].as_ref().unwrap_err();
+        assert_eq!(err.description(), "Unterminated Comment");
+        assert_eq!(err.loc.begin, 7);
+        assert_eq!(err.loc.end, code.len());
+    }
+}
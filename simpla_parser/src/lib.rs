@@ -2,45 +2,119 @@
 extern crate lalrpop_util;
 lalrpop_mod!(simpla);
 
-use lalrpop_util::lexer::Token;
 use lalrpop_util::ParseError;
 
 use extract_line_error::extract_error_code;
+use lexer::Tok;
+use literal::LexicalError;
 
+pub mod cst;
+pub mod lexer;
+pub mod literal;
+pub mod printer;
+pub mod relocate;
 pub mod syntax_tree;
-pub use simpla::ProgramParser;
+pub mod trivia;
+pub mod visit;
 
-pub type SyntaxError<'a> = ParseError<usize, Token<'a>, &'static str>;
+pub type SyntaxError<'a> = ParseError<usize, Tok<'a>, LexicalError>;
+
+/*
+    The parsers of the grammar read the tokens of the lexer,
+    these take the code and split it with the lexer.
+*/
+macro_rules! parser {
+    ($name:ident, $output:ty) => {
+        pub struct $name(simpla::$name);
+
+        impl $name {
+            pub fn new() -> Self {
+                Self(simpla::$name::new())
+            }
+
+            pub fn parse<'a>(&self, code: &'a str) -> Result<$output, SyntaxError<'a>> {
+                self.0.parse(code, lexer::parser_tokens(code))
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+    };
+}
+
+parser!(ProgramParser, syntax_tree::Program);
+parser!(LibraryParser, syntax_tree::Library);
+parser!(VarDeclParser, syntax_tree::VarDecl);
+parser!(FuncDeclParser, syntax_tree::FuncDecl);
+parser!(StatParser, syntax_tree::Stat);
+parser!(ExprParser, syntax_tree::Expr);
 
 pub fn format_syntax_error<'a>(code: &str, error: SyntaxError<'a>) -> String {
+    let loc = syntax_error_location(&error);
+    let wrong_code = extract_error_code(code, loc.begin, loc.end);
+    let title = syntax_error_title(&error);
+    match &error {
+        SyntaxError::UnrecognizedEOF { expected, .. } => format!(
+            "Error: {}:\n{}\nExpecting: {}",
+            title,
+            wrong_code,
+            expected.join(", ")
+        ),
+        SyntaxError::UnrecognizedToken { expected, .. } => format!(
+            "Error: {}:\n{}\nExpecting {}",
+            title,
+            wrong_code,
+            expected.join(", ")
+        ),
+        _ => format!("Error: {}:\n{}", title, wrong_code),
+    }
+}
+
+/*
+    The description of a syntax error without the code
+    where it is found, for the tools that show the location
+    on their own, like the language server.
+*/
+pub fn syntax_error_message(error: &SyntaxError) -> String {
+    let title = syntax_error_title(error);
+    match error {
+        SyntaxError::UnrecognizedEOF { expected, .. } => {
+            format!("{}, expecting: {}", title, expected.join(", "))
+        }
+        SyntaxError::UnrecognizedToken { expected, .. } => {
+            format!("{}, expecting {}", title, expected.join(", "))
+        }
+        _ => title,
+    }
+}
+
+fn syntax_error_title(error: &SyntaxError) -> String {
+    match error {
+        SyntaxError::ExtraToken { .. } => "Extra Token".to_owned(),
+        SyntaxError::InvalidToken { .. } => "Invalid Token".to_owned(),
+        SyntaxError::UnrecognizedEOF { .. } => "Unexpected EOF".to_owned(),
+        SyntaxError::UnrecognizedToken { .. } => "Unrecognized Token".to_owned(),
+        SyntaxError::User { error } => error.description(),
+    }
+}
+
+// the code where the error is found
+pub fn syntax_error_location(error: &SyntaxError) -> syntax_tree::Location {
     match error {
         SyntaxError::ExtraToken {
             token: (begin, _, end),
-        } => format!(
-            "Error: Extra Token:\n{}",
-            extract_error_code(code, begin, end)
-        ),
-        SyntaxError::InvalidToken { location: loc } => format!(
-            "Error: Invalid Token:\n{}",
-            extract_error_code(code, loc, loc)
-        ),
-        SyntaxError::UnrecognizedEOF {
-            location: loc,
-            expected: exp,
-        } => format!(
-            "Error: Unexpected EOF:\n{}\nExpecting: {}",
-            extract_error_code(code, loc, loc),
-            exp.join(", ")
-        ),
-        SyntaxError::UnrecognizedToken {
+        }
+        | SyntaxError::UnrecognizedToken {
             token: (begin, _, end),
-            expected: exp,
-        } => format!(
-            "Error: Unrecognized Token:\n{}\nExpecting {}",
-            extract_error_code(code, begin, end),
-            exp.join(", ")
-        ),
-        SyntaxError::User { error: e } => e.to_owned(),
+            ..
+        } => syntax_tree::Location::new(*begin, *end),
+        SyntaxError::InvalidToken { location } | SyntaxError::UnrecognizedEOF { location, .. } => {
+            syntax_tree::Location::new(*location, *location)
+        }
+        SyntaxError::User { error } => error.loc.clone(),
     }
 }
 
@@ -48,7 +122,7 @@ pub fn format_syntax_error<'a>(code: &str, error: SyntaxError<'a>) -> String {
 mod tests {
 
     use super::*;
-    use crate::simpla;
+    use crate::relocate::Relocate;
     use crate::syntax_tree::*;
 
     #[test]
@@ -83,7 +157,7 @@ mod tests {
                 print_factorials(number);
             end;
         end."#;
-        let parser = simpla::ProgramParser::new();
+        let parser = ProgramParser::new();
         match parser.parse(code) {
             Ok(_) => assert!(true),
             Err(err) => assert!(false, "{:?}", err),
@@ -154,6 +228,8 @@ mod tests {
                                     85,
                                 ),
                             ],
+                            68,
+                            86,
                         )),
                         68,
                         86,
@@ -178,6 +254,8 @@ mod tests {
                                 144,
                                 149,
                             )],
+                            129,
+                            150,
                         )),
                         129,
                         150,
@@ -281,9 +359,10 @@ mod tests {
     fn test_keywords() {
         // ensure that keywords get the correct precedence over ids
         let keywords = [
-            "if", "func", "body", "end", "break", "then", "else", "while", "for", "do", "to",
-            "return", "read", "write", "writeln", "and", "or", "not", "integer", "real", "string",
-            "boolean", "void", "true", "false",
+            "if", "import", "func", "body", "end", "break", "continue", "then", "else", "while",
+            "for", "do", "to", "downto", "step", "repeat", "until", "case", "of", "var", "return",
+            "assert", "halt", "read", "write", "writeln", "and", "or", "not", "integer", "real",
+            "long", "string", "char", "boolean", "void", "true", "false",
         ];
         for kw in &keywords {
             assign_keyword(kw);
@@ -345,6 +424,330 @@ mod tests {
         parse_correct_code(code);
     }
 
+    #[test]
+    fn test_block_comment() {
+        let code = r#"
+            body
+                (* this comment spans
+                   more lines ** and contains *) some code *)
+                writeln("Hello, (* World *)!");
+            end.
+        "#;
+        let err = ProgramParser::new().parse(code).unwrap_err();
+        assert!(matches!(err, SyntaxError::UnrecognizedToken { .. }));
+
+        let code = r#"
+            body
+                (* this comment spans
+                   more lines ** and contains some code *)
+                writeln("Hello, (* World *)!");
+            end.
+        "#;
+        let program = parse_correct_code(code);
+        match &program.body[0].stat {
+            StatType::WriteStat(WriteStat::WriteLine(list)) => assert_eq!(
+                list[0].expr,
+                ExprTree::Factor(Factor::new(FactorValue::Const(Const::StrConst(
+                    "Hello, (* World *)!".to_owned()
+                ))))
+            ),
+            other => panic!("expected writeln statement, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let code = r#"
+            body
+                writeln("tab\tquote\"slash\\new\nline\u{e8}");
+            end.
+        "#;
+        let program = parse_correct_code(code);
+        match &program.body[0].stat {
+            StatType::WriteStat(WriteStat::WriteLine(list)) => assert_eq!(
+                list[0].expr,
+                ExprTree::Factor(Factor::new(FactorValue::Const(Const::StrConst(
+                    "tab\tquote\"slash\\new\nline\u{e8}".to_owned()
+                ))))
+            ),
+            other => panic!("expected writeln statement, found {:?}", other),
+        }
+
+        let code = "body writeln(\"ab\\qc\"); end.";
+        let err = ProgramParser::new().parse(code).unwrap_err();
+        let loc = Location::new(16, 18);
+        let expected = literal::LexicalError {
+            loc,
+            error: literal::LexicalErrorType::InvalidEscape("\\q".to_owned()),
+        };
+        assert_eq!(err, SyntaxError::User { error: expected });
+
+        for escape in &["\\u{}", "\\u{110000}", "\\u{zz}", "\\u12"] {
+            let code = format!("body writeln(\"{}\"); end.", escape);
+            let err = ProgramParser::new().parse(&code).unwrap_err();
+            assert!(
+                matches!(&err, SyntaxError::User { error }
+                    if matches!(error.error, literal::LexicalErrorType::InvalidUnicode(_))),
+                "{:?}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_numeric_literals() {
+        let cases = [
+            ("0x1F", Const::IntConst(31)),
+            ("0b101", Const::IntConst(5)),
+            ("1_000_000", Const::IntConst(1_000_000)),
+            ("2147483647", Const::IntConst(i32::MAX)),
+            ("1.5e-8", Const::RealConst(1.5e-8)),
+            ("2E3", Const::RealConst(2000.0)),
+            ("0.000_5", Const::RealConst(0.0005)),
+            ("0.0e-999", Const::RealConst(0.0)),
+            ("99999999999L", Const::LongConst(99_999_999_999)),
+            ("0xFFL", Const::LongConst(255)),
+            ("-2147483648", Const::IntConst(i32::MIN)),
+            ("- 0x8000_0000", Const::IntConst(i32::MIN)),
+            ("-7", Const::IntConst(-7)),
+            ("-9223372036854775808L", Const::LongConst(i64::MIN)),
+        ];
+        for (literal, expected) in cases.iter() {
+            let code = format!("body writeln({}); end.", literal);
+            let program = parse_correct_code(&code);
+            match &program.body[0].stat {
+                StatType::WriteStat(WriteStat::WriteLine(list)) => match &list[0].expr {
+                    ExprTree::Factor(Factor {
+                        fact: FactorValue::Const(value),
+                        ..
+                    }) => assert_eq!(value, expected),
+                    other => panic!("expected constant, found {:?}", other),
+                },
+                other => panic!("expected writeln statement, found {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_out_of_range_literals() {
+        let cases = [
+            ("99999999999", true),
+            ("0xFFFFFFFF", true),
+            ("0b1_0000_0000_0000_0000_0000_0000_0000_0000", true),
+            ("9223372036854775808L", true),
+            ("1.0e400", false),
+            ("1e-400", false),
+        ];
+        for (literal, integer) in cases.iter() {
+            let code = format!("body writeln({}); end.", literal);
+            let err = ProgramParser::new().parse(&code).unwrap_err();
+            let error = if *integer {
+                literal::LexicalErrorType::IntegerOverflow(literal.to_string())
+            } else {
+                literal::LexicalErrorType::UnrepresentableReal(literal.to_string())
+            };
+            let expected = literal::LexicalError {
+                loc: Location::new(13, 13 + literal.len()),
+                error,
+            };
+            assert_eq!(err, SyntaxError::User { error: expected });
+        }
+
+        let code = "body writeln(-2147483649); end.";
+        let err = ProgramParser::new().parse(code).unwrap_err();
+        let msg = format_syntax_error(code, err);
+        assert!(
+            msg.starts_with(
+                "Error: Integer Literal -2147483649 Out Of Range, minimum is -2147483648"
+            ),
+            "{}",
+            msg
+        );
+        let err = ProgramParser::new()
+            .parse("body writeln(2147483648); end.")
+            .unwrap_err();
+        assert!(matches!(err, SyntaxError::User { .. }));
+    }
+
+    #[test]
+    fn test_malformed_literals() {
+        let cases = [
+            "0x", "0B", "0xL", "0b102", "1__0", "1_", "0x_F", "1_000_L", "1_.5", "0.5_e3",
+        ];
+        for literal in cases.iter() {
+            let code = format!("body writeln({}); end.", literal);
+            let err = ProgramParser::new().parse(&code).unwrap_err();
+            let expected = literal::LexicalError {
+                loc: Location::new(13, 13 + literal.len()),
+                error: literal::LexicalErrorType::MalformedNumber(literal.to_string()),
+            };
+            assert_eq!(err, SyntaxError::User { error: expected }, "{}", literal);
+        }
+
+        let code = "body writeln(-0x); end.";
+        let err = ProgramParser::new().parse(code).unwrap_err();
+        let msg = format_syntax_error(code, err);
+        assert!(
+            msg.starts_with("Error: Malformed Number Literal 0x"),
+            "{}",
+            msg
+        );
+    }
+
+    #[test]
+    fn test_negative_literals() {
+        let code = "body x = - -1 * 2; case x of -2147483648: x = 0; end; end.";
+        let program = parse_correct_code(code);
+        match &program.body[0].stat {
+            StatType::AssignStat(assign) => match &assign.expr.expr {
+                ExprTree::Node(left, Operator::Mul, _) => assert!(matches!(
+                    &left.expr,
+                    ExprTree::Factor(Factor {
+                        fact: FactorValue::UnaryOp(UnaryOp::Minus(operand)),
+                        ..
+                    }) if operand.fact == FactorValue::Const(Const::IntConst(-1))
+                )),
+                other => panic!("expected multiplication, found {:?}", other),
+            },
+            other => panic!("expected assignment, found {:?}", other),
+        }
+        match &program.body[1].stat {
+            StatType::CaseStat(case_stat) => assert_eq!(
+                case_stat.branches[0].labels[0].value,
+                Const::IntConst(i32::MIN)
+            ),
+            other => panic!("expected case statement, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_long_type() {
+        let code = r#"
+            n: long;
+            body
+                n = long(3) * 2L;
+            end.
+        "#;
+        let program = parse_correct_code(code);
+        assert_eq!(program.global_vars[0].kind, Kind::Long);
+        match &program.body[0].stat {
+            StatType::AssignStat(assign) => match &assign.expr.expr {
+                ExprTree::Node(left, Operator::Mul, _) => assert!(matches!(
+                    left.expr,
+                    ExprTree::Factor(Factor {
+                        fact: FactorValue::CastExpr(CastExpr::Long(_)),
+                        ..
+                    })
+                )),
+                other => panic!("expected multiplication, found {:?}", other),
+            },
+            other => panic!("expected assignment, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_char_type() {
+        let code = r#"
+            c: char;
+            s: string;
+            body
+                c = s[i + 1];
+                c = char(integer('\n') + 1);
+            end.
+        "#;
+        let program = parse_correct_code(code);
+        assert_eq!(program.global_vars[0].kind, Kind::Char);
+        match &program.body[0].stat {
+            StatType::AssignStat(assign) => match &assign.expr.expr {
+                ExprTree::Factor(Factor {
+                    fact: FactorValue::IndexExpr(index),
+                    ..
+                }) => {
+                    assert_eq!(index.id, "s");
+                    assert!(matches!(
+                        index.index.expr,
+                        ExprTree::Node(_, Operator::Add, _)
+                    ));
+                }
+                other => panic!("expected index expression, found {:?}", other),
+            },
+            other => panic!("expected assignment, found {:?}", other),
+        }
+        match &program.body[1].stat {
+            StatType::AssignStat(assign) => assert!(matches!(
+                assign.expr.expr,
+                ExprTree::Factor(Factor {
+                    fact: FactorValue::CastExpr(CastExpr::Char(_)),
+                    ..
+                })
+            )),
+            other => panic!("expected assignment, found {:?}", other),
+        }
+
+        let cases = [
+            ("'a'", Some('a')),
+            ("'\\''", Some('\'')),
+            ("'\\u{e8}'", Some('\u{e8}')),
+            ("'\"'", Some('"')),
+            ("''", None),
+            ("'ab'", None),
+        ];
+        for (literal, expected) in cases.iter() {
+            let code = format!("body writeln({}); end.", literal);
+            let result = ProgramParser::new().parse(&code);
+            match (result, expected) {
+                (Ok(program), Some(c)) => match &program.body[0].stat {
+                    StatType::WriteStat(WriteStat::WriteLine(list)) => assert!(matches!(
+                        &list[0].expr,
+                        ExprTree::Factor(Factor {
+                            fact: FactorValue::Const(Const::CharConst(value)),
+                            ..
+                        }) if value == c
+                    )),
+                    other => panic!("expected writeln statement, found {:?}", other),
+                },
+                (Err(SyntaxError::User { error }), None) => assert_eq!(
+                    error.error,
+                    literal::LexicalErrorType::InvalidChar(literal.to_string())
+                ),
+                (result, _) => panic!("unexpected result for {}: {:?}", literal, result),
+            }
+        }
+    }
+
+    #[test]
+    fn test_unterminated_literals() {
+        let code = "body\n    writeln(\"abc);\nend.";
+        let err = ProgramParser::new().parse(code).unwrap_err();
+        let msg = format_syntax_error(code, err);
+        assert_eq!(
+            msg,
+            "Error: Unterminated String:\nError on line: 2\n    writeln(\"abc);"
+        );
+
+        let code = "body\n    writeln(\"abc\\\");\nend.";
+        let err = ProgramParser::new().parse(code).unwrap_err();
+        let msg = format_syntax_error(code, err);
+        assert!(msg.starts_with("Error: Unterminated String:"), "{}", msg);
+
+        let code = "body\n    (* open\n    writeln(1);\nend.";
+        let err = ProgramParser::new().parse(code).unwrap_err();
+        let msg = format_syntax_error(code, err);
+        assert!(
+            msg.starts_with("Error: Unterminated Comment:\nError from line: 2 to line: 4"),
+            "{}",
+            msg
+        );
+
+        let code = "body\n    (* open\n    writeln(1);\nend.\n";
+        let err = ProgramParser::new().parse(code).unwrap_err();
+        let msg = format_syntax_error(code, err);
+        assert_eq!(
+            msg,
+            "Error: Unterminated Comment:\nError from line: 2 to line: 4\n    (* open\n    writeln(1);\nend."
+        );
+    }
+
     #[test]
     fn test_type_cast() {
         let code = r#"
@@ -396,6 +799,8 @@ mod tests {
                                     105,
                                     106,
                                 )],
+                                96,
+                                107,
                             )))),
                             96,
                             107,
@@ -449,7 +854,7 @@ mod tests {
             vec![
                 FuncDecl::new(
                     "do_stuff".to_owned(),
-                    vec![ParamDecl::new("a".to_owned(), Kind::Int)],
+                    vec![ParamDecl::new("a".to_owned(), Kind::Int, 52, 62)],
                     Kind::Void,
                     vec![
                         VarDecl::new(vec!["i".to_owned(), "j".to_owned()], Kind::Int, 87, 102),
@@ -486,13 +891,16 @@ mod tests {
                         ),
                         Stat::new(
                             StatType::ForStat(ForStat::new(
+                                None,
                                 "i".to_owned(),
                                 Expr::new(
                                     ExprTree::Factor(Factor::new(FactorValue::Const(Const::IntConst(0)))),
                                     270,
                                     271,
                                 ),
+                                ForDirection::Up,
                                 Expr::new(ExprTree::Factor(Factor::new(FactorValue::Id("a".to_owned()))), 275, 276),
+                                None,
                                 vec![
                                     Stat::new(
                                         StatType::WriteStat(WriteStat::Write(vec![Expr::new(
@@ -517,6 +925,7 @@ mod tests {
                                     ),
                                     Stat::new(
                                         StatType::WhileStat(WhileStat::new(
+                                            None,
                                             Expr::new(
                                                 ExprTree::Node(
                                                     Box::new(Expr::new(
@@ -679,6 +1088,8 @@ mod tests {
                             780,
                             781,
                         )],
+                        771,
+                        782,
                     )),
                     771,
                     782,
@@ -701,6 +1112,208 @@ mod tests {
         let _tree = parse_correct_code(code);
     }
 
+    #[test]
+    fn test_for_direction_and_step() {
+        let code = r#"
+            body
+                for i = 10 downto 1 do
+                    writeln(i);
+                end;
+                for i = 0 to 100 step 5 * n do
+                    writeln(i);
+                end;
+            end.
+        "#;
+
+        let tree = parse_correct_code(code);
+        match (&tree.body[0].stat, &tree.body[1].stat) {
+            (StatType::ForStat(down), StatType::ForStat(up)) => {
+                assert_eq!(down.direction, ForDirection::Down);
+                assert!(down.step.is_none());
+                assert_eq!(up.direction, ForDirection::Up);
+                assert!(matches!(
+                    up.step,
+                    Some(Expr {
+                        expr: ExprTree::Node(_, Operator::Mul, _),
+                        ..
+                    })
+                ));
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_loop_labels() {
+        let code = r#"
+            body
+                outer: while a do
+                    for i = 0 to 10 do
+                        continue outer;
+                    end;
+                    inner: for i = 0 to 10 do
+                        break;
+                        continue;
+                        break inner;
+                    end;
+                end;
+            end.
+        "#;
+
+        let tree = parse_correct_code(code);
+        let outer = match &tree.body[0].stat {
+            StatType::WhileStat(outer) => outer,
+            _ => panic!(),
+        };
+        assert_eq!(outer.label, Some("outer".to_owned()));
+        match (&outer.body[0].stat, &outer.body[1].stat) {
+            (StatType::ForStat(first), StatType::ForStat(second)) => {
+                assert_eq!(first.label, None);
+                assert_eq!(
+                    first.body[0].stat,
+                    StatType::Continue(Some("outer".to_owned()))
+                );
+                assert_eq!(second.label, Some("inner".to_owned()));
+                assert_eq!(second.body[0].stat, StatType::Break(None));
+                assert_eq!(second.body[1].stat, StatType::Continue(None));
+                assert_eq!(
+                    second.body[2].stat,
+                    StatType::Break(Some("inner".to_owned()))
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_repeat_stat() {
+        let code = r#"
+            body
+                repeat
+                    a = a + 1;
+                    break;
+                until a > 10;
+            end.
+        "#;
+
+        let tree = parse_correct_code(code);
+        match &tree.body[0].stat {
+            StatType::RepeatStat(repeat_stat) => {
+                assert_eq!(repeat_stat.label, None);
+                assert_eq!(repeat_stat.body.len(), 2);
+                assert!(matches!(
+                    repeat_stat.cond.expr,
+                    ExprTree::Node(_, Operator::Greater, _)
+                ));
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_case_stat() {
+        let code = r#"
+            body
+                case n of
+                    1, -2: writeln("small");
+                    3:
+                        writeln("three");
+                        n = 0;
+                    else
+                        writeln("other");
+                end;
+                case name of
+                    "a": writeln();
+                end;
+            end.
+        "#;
+
+        let tree = parse_correct_code(code);
+        match &tree.body[0].stat {
+            StatType::CaseStat(case_stat) => {
+                assert_eq!(case_stat.branches.len(), 2);
+                let labels: Vec<&Const> = case_stat.branches[0]
+                    .labels
+                    .iter()
+                    .map(|label| &label.value)
+                    .collect();
+                assert_eq!(labels, vec![&Const::IntConst(1), &Const::IntConst(-2)]);
+                assert_eq!(case_stat.branches[1].body.len(), 2);
+                assert!(case_stat.else_body.is_some());
+            }
+            _ => panic!(),
+        }
+        match &tree.body[1].stat {
+            StatType::CaseStat(case_stat) => {
+                assert_eq!(
+                    case_stat.branches[0].labels[0].value,
+                    Const::StrConst("a".to_owned())
+                );
+                assert!(case_stat.else_body.is_none());
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_assert_and_halt() {
+        let code = r#"
+            body
+                assert(n > 0, "n must be positive");
+                halt(2);
+            end.
+        "#;
+
+        let tree = parse_correct_code(code);
+        match &tree.body[0].stat {
+            StatType::AssertStat(assert_stat) => {
+                assert!(matches!(assert_stat.cond.expr, ExprTree::Node(..)));
+                assert_eq!(
+                    assert_stat.message.expr,
+                    ExprTree::Factor(Factor::new(FactorValue::Const(Const::StrConst(
+                        "n must be positive".to_owned()
+                    ))))
+                );
+            }
+            other => panic!("expected assert statement, found {:?}", other),
+        }
+        match &tree.body[1].stat {
+            StatType::HaltStat(code) => assert_eq!(
+                code.expr,
+                ExprTree::Factor(Factor::new(FactorValue::Const(Const::IntConst(2))))
+            ),
+            other => panic!("expected halt statement, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reference_parameters() {
+        let code = r#"
+            func swap(var a: integer, var b: integer, c: integer): void
+            body
+                c = a;
+                a = b;
+                b = c;
+            end;
+
+            body
+                swap(x, y, 0);
+            end.
+        "#;
+
+        let tree = parse_correct_code(code);
+        let params = &tree.functions[0].params;
+        assert_eq!(
+            params[0],
+            ParamDecl::new_reference("a".to_owned(), Kind::Int, 23, 37)
+        );
+        assert_eq!(
+            params[1],
+            ParamDecl::new_reference("b".to_owned(), Kind::Int, 39, 53)
+        );
+        assert_eq!(params[2], ParamDecl::new("c".to_owned(), Kind::Int, 55, 65));
+    }
+
     #[test]
     fn test_brackets_removal() {
         /*
@@ -823,6 +1436,167 @@ mod tests {
         assert_eq!(correct, tree);
     }
 
+    #[test]
+    fn test_nested_functions() {
+        let code = r#"
+            func outer(n: integer): integer
+                total: integer;
+                func inner(i: integer): void
+                    func innermost(): void
+                    body
+                        total = total + 1;
+                    end;
+                body
+                    innermost();
+                end;
+            body
+                inner(n);
+                return total;
+            end;
+            body
+                writeln(outer(3));
+            end.
+        "#;
+        let program = parse_correct_code(code);
+        let outer = &program.functions[0];
+        assert_eq!(outer.vars.len(), 1);
+        assert_eq!(outer.functions.len(), 1);
+        let inner = &outer.functions[0];
+        assert_eq!(inner.id, "inner");
+        assert_eq!(inner.functions.len(), 1);
+        assert_eq!(inner.functions[0].id, "innermost");
+        assert!(inner.functions[0].functions.is_empty());
+    }
+
+    #[test]
+    fn test_function_type() {
+        let code = r#"
+            op: func(integer, real): real;
+            func apply(f: func(): void): func(string): boolean
+            body
+                f();
+            end;
+            body
+                writeln(1);
+            end.
+        "#;
+        let program = parse_correct_code(code);
+        let expected = Kind::Func(FuncKind::new(vec![Kind::Int, Kind::Real], Kind::Real));
+        assert_eq!(program.global_vars[0].kind, expected);
+
+        let apply = &program.functions[0];
+        let expected = Kind::Func(FuncKind::new(vec![], Kind::Void));
+        assert_eq!(apply.params[0].kind, expected);
+        let expected = Kind::Func(FuncKind::new(vec![Kind::Str], Kind::Bool));
+        assert_eq!(apply.kind, expected);
+    }
+
+    #[test]
+    fn test_void_function_type_parameter() {
+        let parser = ProgramParser::new();
+        let code = "f: func(void): integer; body writeln(1); end.";
+        assert!(parser.parse(code).is_err());
+        let code = "func apply(f: func(integer, void): void): void body writeln(1); end; body writeln(1); end.";
+        assert!(parser.parse(code).is_err());
+        let code = "f: func(func(void): void): void; body writeln(1); end.";
+        assert!(parser.parse(code).is_err());
+    }
+
+    #[test]
+    fn test_library() {
+        let code = r#"
+            import "geometry.simpla";
+            pi: real;
+            func area(r: real): real
+            body
+                return pi * r * r;
+            end;
+        "#;
+        let parser = LibraryParser::new();
+        let mut library = parser.parse(code).unwrap();
+        assert_eq!(
+            library.imports,
+            vec![Import::new("geometry.simpla".to_owned(), 13, 38)]
+        );
+        assert_eq!(library.global_vars.len(), 1);
+        assert_eq!(library.functions.len(), 1);
+
+        library.relocate(100);
+        assert_eq!(library.imports[0].loc, Location::new(113, 138));
+        assert_eq!(library.global_vars[0].loc, Location::new(151, 160));
+
+        // a library has no main body
+        let code = r#"
+            func area(r: real): real
+            body
+                return r * r;
+            end;
+            body
+                writeln(area(2.0));
+            end.
+        "#;
+        assert!(parser.parse(code).is_err());
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let code = r#"
+            ## The ratio of a circle's circumference to its diameter.
+            pi: real;
+            # not a doc comment
+            scale: real;
+
+            ## Area of the circle of radius r.
+            ## Negative radii are not checked.
+            func area(r: real): real
+                ## kept on local variables too
+                square: real;
+            body
+                square = r * r;
+                return pi * square;
+            end;
+        "#;
+        let library = LibraryParser::new().parse(code).unwrap();
+        let pi = &library.global_vars[0];
+        assert_eq!(
+            pi.doc.as_deref(),
+            Some("The ratio of a circle's circumference to its diameter.")
+        );
+        assert_eq!(library.global_vars[1].doc, None);
+        let area = &library.functions[0];
+        assert_eq!(
+            area.doc.as_deref(),
+            Some("Area of the circle of radius r.\nNegative radii are not checked.")
+        );
+        assert_eq!(
+            area.vars[0].doc.as_deref(),
+            Some("kept on local variables too")
+        );
+    }
+
+    #[test]
+    fn test_fragments() {
+        let var_decl = VarDeclParser::new().parse("a, b: real;").unwrap();
+        assert_eq!(var_decl.id_list, vec!["a", "b"]);
+        assert_eq!(var_decl.loc, Location::new(0, 11));
+
+        let code = "func one(): integer body return 1; end;";
+        let func_decl = FuncDeclParser::new().parse(code).unwrap();
+        assert_eq!(func_decl.id, "one");
+
+        let stat = StatParser::new().parse("x = x + 1").unwrap();
+        assert!(matches!(stat.stat, StatType::AssignStat(_)));
+        assert!(StatParser::new().parse("x = x + 1;").is_err());
+
+        let expr = ExprParser::new().parse("max(a, 2) * 3").unwrap();
+        assert!(matches!(expr.expr, ExprTree::Node(_, Operator::Mul, _)));
+        let incomplete = ExprParser::new().parse("max(a,");
+        assert!(matches!(
+            incomplete,
+            Err(SyntaxError::UnrecognizedEOF { .. })
+        ));
+    }
+
     fn assign_keyword(word: &str) {
         let code = format!(
             r#"
@@ -833,7 +1607,7 @@ mod tests {
             word
         );
 
-        let parser = simpla::ProgramParser::new();
+        let parser = ProgramParser::new();
         let result = parser.parse(&code);
         match result {
             Ok(_) => panic!("Success with: {}\nCode: {}", word, code),
@@ -842,7 +1616,7 @@ mod tests {
     }
 
     fn parse_correct_code(code: &str) -> syntax_tree::Program {
-        let parser = simpla::ProgramParser::new();
+        let parser = ProgramParser::new();
         let result = parser.parse(code);
         match result {
             Ok(output) => output,
@@ -0,0 +1,281 @@
+use crate::syntax_tree::Location;
+use extract_line_error::extract_error_code;
+
+/*
+    Errors found while splitting the code in tokens or while
+    decoding the text of a literal token: the location covers
+    only the wrong part of the literal, for example the single
+    invalid escape sequence.
+*/
+#[derive(Debug, PartialEq)]
+pub struct LexicalError {
+    pub loc: Location,
+    pub error: LexicalErrorType,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LexicalErrorType {
+    InvalidEscape(String),
+    InvalidUnicode(String),
+    IntegerOverflow(String),
+    MalformedNumber(String),
+    UnrepresentableReal(String),
+    InvalidChar(String),
+    UnterminatedString,
+    UnterminatedComment,
+    InvalidToken(String),
+}
+
+impl LexicalError {
+    fn new_invalid_escape(escape: &str, begin: usize) -> Self {
+        let error = LexicalErrorType::InvalidEscape(escape.to_owned());
+        let loc = Location::new(begin, begin + escape.len());
+        Self { loc, error }
+    }
+
+    fn new_invalid_unicode(escape: &str, begin: usize) -> Self {
+        let error = LexicalErrorType::InvalidUnicode(escape.to_owned());
+        let loc = Location::new(begin, begin + escape.len());
+        Self { loc, error }
+    }
+
+    fn new_integer_overflow(literal: &str, begin: usize) -> Self {
+        let error = LexicalErrorType::IntegerOverflow(literal.to_owned());
+        let loc = Location::new(begin, begin + literal.len());
+        Self { loc, error }
+    }
+
+    // the location covers only the digits, the text includes the minus
+    fn new_negative_overflow(literal: &str, begin: usize) -> Self {
+        let error = LexicalErrorType::IntegerOverflow(format!("-{}", literal));
+        let loc = Location::new(begin, begin + literal.len());
+        Self { loc, error }
+    }
+
+    fn new_malformed_number(literal: &str, begin: usize) -> Self {
+        let error = LexicalErrorType::MalformedNumber(literal.to_owned());
+        let loc = Location::new(begin, begin + literal.len());
+        Self { loc, error }
+    }
+
+    fn new_unrepresentable_real(literal: &str, begin: usize) -> Self {
+        let error = LexicalErrorType::UnrepresentableReal(literal.to_owned());
+        let loc = Location::new(begin, begin + literal.len());
+        Self { loc, error }
+    }
+
+    fn new_invalid_char(literal: &str, begin: usize) -> Self {
+        let error = LexicalErrorType::InvalidChar(literal.to_owned());
+        let loc = Location::new(begin, begin + literal.len());
+        Self { loc, error }
+    }
+
+    // an unknown token of the lexer, see lexer::Tok
+    pub(crate) fn new_unknown_token(text: &str, begin: usize) -> Self {
+        let error = if text.starts_with('"') {
+            LexicalErrorType::UnterminatedString
+        } else if text.starts_with("(*") {
+            LexicalErrorType::UnterminatedComment
+        } else {
+            LexicalErrorType::InvalidToken(text.to_owned())
+        };
+        let loc = Location::new(begin, begin + text.len());
+        Self { loc, error }
+    }
+
+    pub fn format_error(&self, code: &str) -> String {
+        format!(
+            "Error: {}:\n{}",
+            self.description(),
+            extract_error_code(code, self.loc.begin, self.loc.end)
+        )
+    }
+
+    pub fn description(&self) -> String {
+        match &self.error {
+            LexicalErrorType::InvalidEscape(escape) => {
+                format!("Invalid Escape Sequence {}", escape)
+            }
+            LexicalErrorType::InvalidUnicode(escape) => {
+                format!("Invalid Unicode Escape {}", escape)
+            }
+            LexicalErrorType::IntegerOverflow(literal) => {
+                let (bound, value) = match (literal.starts_with('-'), literal.ends_with('L')) {
+                    (false, false) => ("maximum", i32::MAX.to_string()),
+                    (false, true) => ("maximum", i64::MAX.to_string()),
+                    (true, false) => ("minimum", i32::MIN.to_string()),
+                    (true, true) => ("minimum", i64::MIN.to_string()),
+                };
+                format!(
+                    "Integer Literal {} Out Of Range, {} is {}",
+                    literal, bound, value
+                )
+            }
+            LexicalErrorType::MalformedNumber(literal) => {
+                format!("Malformed Number Literal {}", literal)
+            }
+            LexicalErrorType::UnrepresentableReal(literal) => {
+                format!("Real Literal {} Cannot Be Represented", literal)
+            }
+            LexicalErrorType::InvalidChar(literal) => {
+                format!("Char Literal {} Must Contain One Character", literal)
+            }
+            LexicalErrorType::UnterminatedString => "Unterminated String".to_owned(),
+            LexicalErrorType::UnterminatedComment => "Unterminated Comment".to_owned(),
+            LexicalErrorType::InvalidToken(text) => format!("Invalid Token {}", text),
+        }
+    }
+}
+
+/*
+    Decodes a string literal, quotes included, that begins
+    at the given offset of the code. Allowed escapes are
+    \n, \t, \", \', \\ and \u{...} with one to six hex digits.
+*/
+pub fn decode_string(literal: &str, begin: usize) -> Result<String, LexicalError> {
+    let body = &literal[1..literal.len() - 1];
+    let begin = begin + 1;
+    let mut output = String::with_capacity(body.len());
+    let mut chars = body.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+        let escape_end = match chars.next() {
+            Some((next, e)) => next + e.len_utf8(),
+            None => body.len(),
+        };
+        match &body[index..escape_end] {
+            "\\n" => output.push('\n'),
+            "\\t" => output.push('\t'),
+            "\\\"" => output.push('"'),
+            "\\'" => output.push('\''),
+            "\\\\" => output.push('\\'),
+            "\\u" => {
+                let end = match body[escape_end..].find('}') {
+                    Some(close) if body[escape_end..].starts_with('{') => escape_end + close + 1,
+                    _ => return Err(LexicalError::new_invalid_unicode("\\u", begin + index)),
+                };
+                let escape = &body[index..end];
+                output.push(decode_unicode(escape, begin + index)?);
+                while chars.next_if(|(next, _)| *next < end).is_some() {}
+            }
+            escape => return Err(LexicalError::new_invalid_escape(escape, begin + index)),
+        }
+    }
+    Ok(output)
+}
+
+/*
+    Decodes a char literal, quotes included: the same
+    escapes of strings are allowed, but the literal
+    must contain exactly one character.
+*/
+pub fn decode_char(literal: &str, begin: usize) -> Result<char, LexicalError> {
+    let decoded = decode_string(literal, begin)?;
+    let mut chars = decoded.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(LexicalError::new_invalid_char(literal, begin)),
+    }
+}
+
+// escape is \u{...}
+fn decode_unicode(escape: &str, begin: usize) -> Result<char, LexicalError> {
+    let digits = &escape[3..escape.len() - 1];
+    if digits.is_empty() || digits.len() > 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(LexicalError::new_invalid_unicode(escape, begin));
+    }
+    u32::from_str_radix(digits, 16)
+        .ok()
+        .and_then(std::char::from_u32)
+        .ok_or_else(|| LexicalError::new_invalid_unicode(escape, begin))
+}
+
+/*
+    Decodes an integer literal: decimal, hexadecimal with
+    the 0x prefix or binary with the 0b prefix, digits may
+    be separated by single underscores. Long literals end with L.
+*/
+pub fn decode_integer(literal: &str, begin: usize) -> Result<i32, LexicalError> {
+    let (digits, radix) = integer_digits(literal, begin)?;
+    i32::from_str_radix(&digits, radix)
+        .map_err(|_| LexicalError::new_integer_overflow(literal, begin))
+}
+
+pub fn decode_long(literal: &str, begin: usize) -> Result<i64, LexicalError> {
+    let (digits, radix) = integer_digits(literal, begin)?;
+    i64::from_str_radix(&digits, radix)
+        .map_err(|_| LexicalError::new_integer_overflow(literal, begin))
+}
+
+/*
+    Decodes the literal after a minus, returning the negative
+    value: the range is one larger than the one of the
+    positive literals, so that the smallest value can be written.
+*/
+pub fn decode_negative_integer(literal: &str, begin: usize) -> Result<i32, LexicalError> {
+    let (digits, radix) = integer_digits(literal, begin)?;
+    i32::from_str_radix(&format!("-{}", digits), radix)
+        .map_err(|_| LexicalError::new_negative_overflow(literal, begin))
+}
+
+pub fn decode_negative_long(literal: &str, begin: usize) -> Result<i64, LexicalError> {
+    let (digits, radix) = integer_digits(literal, begin)?;
+    i64::from_str_radix(&format!("-{}", digits), radix)
+        .map_err(|_| LexicalError::new_negative_overflow(literal, begin))
+}
+
+// the digits without underscores and the radix, the L of a long is dropped
+fn integer_digits(literal: &str, begin: usize) -> Result<(String, u32), LexicalError> {
+    let text = literal.strip_suffix('L').unwrap_or(literal);
+    let (digits, radix) = match text.get(..2) {
+        Some("0x") | Some("0X") => (&text[2..], 16),
+        Some("0b") | Some("0B") => (&text[2..], 2),
+        _ => (text, 10),
+    };
+    if well_formed(digits, radix) {
+        Ok((digits.replace('_', ""), radix))
+    } else {
+        Err(LexicalError::new_malformed_number(literal, begin))
+    }
+}
+
+/*
+    The lexer reads a whole run of digits and underscores,
+    after a prefix any hexadecimal digit too: the digits
+    must belong to the radix, an underscore must stand
+    between two of them.
+*/
+fn well_formed(digits: &str, radix: u32) -> bool {
+    !digits.is_empty()
+        && !digits.starts_with('_')
+        && !digits.ends_with('_')
+        && !digits.contains("__")
+        && digits.chars().all(|c| c == '_' || c.is_digit(radix))
+}
+
+/*
+    Decodes a real literal, with an optional exponent:
+    a literal is rejected when it does not fit in a real,
+    either overflowing or rounding a non zero value to zero.
+*/
+pub fn decode_real(literal: &str, begin: usize) -> Result<f64, LexicalError> {
+    let mantissa = literal.split(['e', 'E']).next().unwrap();
+    let mut parts = mantissa.split('.');
+    let int = parts.next().unwrap();
+    let fraction = parts.next().unwrap_or("0");
+    if !(int.is_empty() || well_formed(int, 10)) || !well_formed(fraction, 10) {
+        return Err(LexicalError::new_malformed_number(literal, begin));
+    }
+    let text = literal.replace('_', "");
+    let value: f64 = text.parse().unwrap();
+    let mantissa = text.split(['e', 'E']).next().unwrap();
+    let non_zero = mantissa.chars().any(|c| c.is_ascii_digit() && c != '0');
+    if value.is_finite() && (value != 0.0 || !non_zero) {
+        Ok(value)
+    } else {
+        Err(LexicalError::new_unrepresentable_real(literal, begin))
+    }
+}
@@ -0,0 +1,795 @@
+use std::collections::VecDeque;
+
+use crate::lexer::{Lexer, Tok};
+use crate::literal;
+use crate::syntax_tree::*;
+use crate::trivia::{collect_comments, skip_trivia, Comment};
+
+const INDENT: &str = "    ";
+
+/*
+    Canonical pretty printer: the tree is emitted with four
+    spaces of indentation for every block, one space around
+    binary operators and only the parenthesis required by the
+    precedence of the operators. Literals keep the text they
+    have in the code, so hex numbers and escapes are not
+    rewritten. Comments are taken from the code and placed
+    before the node that follows them, or at the end of the
+    line of the outermost node that ends before them on the
+    same line; single blank lines between nodes are kept.
+*/
+pub fn format_program(program: &Program, code: &str) -> String {
+    let mut printer = Printer::new(code);
+    printer.declarations(&program.imports, &program.global_vars, &program.functions);
+    printer.blank_line();
+    let body = printer.find_token(printer.last_end, Tok::Keyword("body"));
+    printer.begin_item(body);
+    printer.line("body");
+    let end = printer.block(&program.body);
+    printer.line("end.");
+    let end = (printer.find_token(end, Tok::Punct(".")) + 1).min(code.len());
+    printer.end_item(end);
+    printer.finish()
+}
+
+pub fn format_library(library: &Library, code: &str) -> String {
+    let mut printer = Printer::new(code);
+    printer.declarations(&library.imports, &library.global_vars, &library.functions);
+    printer.finish()
+}
+
+struct Printer<'a> {
+    code: &'a str,
+    comments: VecDeque<Comment>,
+    output: String,
+    indent: usize,
+    last_end: usize,
+    block_start: bool,
+}
+
+impl<'a> Printer<'a> {
+    fn new(code: &'a str) -> Self {
+        Self {
+            code,
+            comments: collect_comments(code).into(),
+            output: String::new(),
+            indent: 0,
+            last_end: 0,
+            block_start: true,
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.flush_comments(self.code.len());
+        self.output
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.indent {
+            self.output.push_str(INDENT);
+        }
+        self.output.push_str(text);
+        self.output.push('\n');
+        self.block_start = false;
+    }
+
+    fn blank_line(&mut self) {
+        if !self.block_start {
+            self.output.push('\n');
+            self.block_start = true;
+        }
+    }
+
+    fn keep_blank_line(&mut self, begin: usize) {
+        if begin > self.last_end && has_blank_line(&self.code[self.last_end..begin]) {
+            self.blank_line();
+        }
+    }
+
+    fn flush_comments(&mut self, begin: usize) {
+        while self.comments.front().is_some_and(|c| c.loc.begin < begin) {
+            let comment = self.comments.pop_front().unwrap();
+            self.keep_blank_line(comment.loc.begin);
+            self.line(&comment.text);
+            self.last_end = comment.loc.end;
+        }
+    }
+
+    fn begin_item(&mut self, begin: usize) {
+        self.flush_comments(begin);
+        self.keep_blank_line(begin);
+    }
+
+    /*
+        A comment after the end of the node on the same line
+        goes at the end of its line, unless there is code
+        other than semicolons in between: then the comment
+        follows an enclosing node, like the end of an if.
+    */
+    fn end_item(&mut self, end: usize) {
+        self.last_end = self.last_end.max(end);
+        let trailing = match self.comments.front() {
+            Some(comment) => {
+                comment.loc.begin >= end
+                    && !self.code[end..comment.loc.begin].contains('\n')
+                    && skip_trivia(self.code, end) > comment.loc.begin
+            }
+            None => false,
+        };
+        if trailing {
+            let comment = self.comments.pop_front().unwrap();
+            self.output.pop();
+            self.output.push(' ');
+            self.output.push_str(&comment.text);
+            self.output.push('\n');
+            self.last_end = comment.loc.end;
+        }
+    }
+
+    /*
+        Keywords without a node, like body, have no location:
+        the offset is found reading the tokens after the
+        previous node, where only declarations may appear.
+    */
+    fn find_token(&self, begin: usize, token: Tok) -> usize {
+        Lexer::new(&self.code[begin..])
+            .find(|(_, tok, _)| *tok == token)
+            .map_or(self.code.len(), |(offset, _, _)| begin + offset)
+    }
+
+    fn declarations(&mut self, imports: &[Import], vars: &[VarDecl], functions: &[FuncDecl]) {
+        for import in imports {
+            self.begin_item(import.loc.begin);
+            self.line(&format!("import {};", escape(&import.path, '"')));
+            self.end_item(import.loc.end);
+        }
+        self.blank_line();
+        for var in vars {
+            self.var_decl(var);
+        }
+        for function in functions {
+            self.blank_line();
+            self.func_decl(function);
+        }
+    }
+
+    fn var_decl(&mut self, var: &VarDecl) {
+        self.begin_item(var.loc.begin);
+        self.line(&format!(
+            "{}: {};",
+            var.id_list.join(", "),
+            kind_name(&var.kind)
+        ));
+        self.end_item(var.loc.end);
+    }
+
+    fn func_decl(&mut self, function: &FuncDecl) {
+        self.begin_item(function.loc.begin);
+        self.line(&signature(function));
+        self.last_end = function.loc.begin;
+        self.indent += 1;
+        self.block_start = true;
+        for var in &function.vars {
+            self.var_decl(var);
+        }
+        for nested in &function.functions {
+            self.func_decl(nested);
+        }
+        self.indent -= 1;
+        let body = self.find_token(self.last_end, Tok::Keyword("body"));
+        self.begin_item(body);
+        self.line("body");
+        self.block(&function.body);
+        self.line("end;");
+        self.end_item(function.loc.end);
+    }
+
+    /*
+        Prints the statements one level deeper, followed by
+        the comments before the keyword that closes the block,
+        and returns the offset of that keyword.
+    */
+    fn block(&mut self, stats: &[Stat]) -> usize {
+        self.indent += 1;
+        self.block_start = true;
+        for stat in stats {
+            self.stat(stat);
+        }
+        let end = stats.last().map_or(self.last_end, |stat| stat.loc.end);
+        let next = skip_trivia(self.code, end);
+        self.flush_comments(next);
+        self.indent -= 1;
+        next
+    }
+
+    fn stat(&mut self, stat: &Stat) {
+        self.begin_item(stat.loc.begin);
+        match &stat.stat {
+            StatType::IfStat(if_stat) => self.if_stat(if_stat),
+            StatType::WhileStat(while_stat) => {
+                self.line(&format!(
+                    "{}while {} do",
+                    loop_label(&while_stat.label),
+                    self.expr(&while_stat.cond, 0)
+                ));
+                self.block(&while_stat.body);
+                self.line("end;");
+            }
+            StatType::ForStat(for_stat) => self.for_stat(for_stat),
+            StatType::RepeatStat(repeat_stat) => {
+                self.line(&format!("{}repeat", loop_label(&repeat_stat.label)));
+                self.block(&repeat_stat.body);
+                self.line(&format!("until {};", self.expr(&repeat_stat.cond, 0)));
+            }
+            StatType::CaseStat(case_stat) => self.case_stat(case_stat),
+            other => {
+                let text = self.simple_stat(other);
+                self.line(&format!("{};", text));
+            }
+        }
+        self.end_item(stat.loc.end);
+    }
+
+    fn if_stat(&mut self, if_stat: &IfStat) {
+        self.line(&format!("if {} then", self.expr(&if_stat.cond, 0)));
+        self.block(&if_stat.if_body);
+        if let Some(else_body) = &if_stat.else_body {
+            self.line("else");
+            self.block(else_body);
+        }
+        self.line("end;");
+    }
+
+    fn for_stat(&mut self, for_stat: &ForStat) {
+        let direction = match for_stat.direction {
+            ForDirection::Up => "to",
+            ForDirection::Down => "downto",
+        };
+        let step = match &for_stat.step {
+            Some(step) => format!(" step {}", self.expr(step, 0)),
+            None => String::new(),
+        };
+        self.line(&format!(
+            "{}for {} = {} {} {}{} do",
+            loop_label(&for_stat.label),
+            for_stat.id,
+            self.expr(&for_stat.begin_expr, 0),
+            direction,
+            self.expr(&for_stat.end_expr, 0),
+            step
+        ));
+        self.block(&for_stat.body);
+        self.line("end;");
+    }
+
+    /*
+        A branch made of a single simple statement
+        is printed on the same line of its labels.
+    */
+    fn case_stat(&mut self, case_stat: &CaseStat) {
+        self.line(&format!("case {} of", self.expr(&case_stat.expr, 0)));
+        self.indent += 1;
+        self.block_start = true;
+        for branch in &case_stat.branches {
+            self.begin_item(branch.labels[0].loc.begin);
+            let labels: Vec<String> = branch.labels.iter().map(|l| self.case_label(l)).collect();
+            let labels = labels.join(", ");
+            match self.inline_stat(&branch.body) {
+                Some((text, end)) => {
+                    self.line(&format!("{}: {};", labels, text));
+                    self.end_item(end);
+                }
+                None => {
+                    self.line(&format!("{}:", labels));
+                    self.indent += 1;
+                    self.block_start = true;
+                    for stat in &branch.body {
+                        self.stat(stat);
+                    }
+                    self.indent -= 1;
+                }
+            }
+        }
+        if let Some(else_body) = &case_stat.else_body {
+            self.line("else");
+            self.block(else_body);
+        } else if let Some(branch) = case_stat.branches.last() {
+            let end = branch
+                .body
+                .last()
+                .map_or(self.last_end, |stat| stat.loc.end);
+            self.flush_comments(skip_trivia(self.code, end));
+        }
+        self.indent -= 1;
+        self.line("end;");
+    }
+
+    fn inline_stat(&self, body: &[Stat]) -> Option<(String, usize)> {
+        match body {
+            [stat] if !is_compound(&stat.stat) => {
+                let comment_inside = self
+                    .comments
+                    .front()
+                    .is_some_and(|c| c.loc.begin < stat.loc.end);
+                if comment_inside {
+                    None
+                } else {
+                    Some((self.simple_stat(&stat.stat), stat.loc.end))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn simple_stat(&self, stat: &StatType) -> String {
+        match stat {
+            StatType::AssignStat(assign) => {
+                format!("{} = {}", assign.id, self.expr(&assign.expr, 0))
+            }
+            StatType::ReturnStat(Some(expr)) => format!("return {}", self.expr(expr, 0)),
+            StatType::ReturnStat(None) => "return".to_owned(),
+            StatType::ReadStat(ids) => format!("read({})", ids.join(", ")),
+            StatType::WriteStat(WriteStat::Write(args)) => {
+                format!("write({})", self.expr_list(args))
+            }
+            StatType::WriteStat(WriteStat::WriteLine(args)) => {
+                format!("writeln({})", self.expr_list(args))
+            }
+            StatType::FuncCall(call) => self.func_call(call),
+            StatType::Break(label) => jump("break", label),
+            StatType::Continue(label) => jump("continue", label),
+            StatType::AssertStat(assert) => format!(
+                "assert({}, {})",
+                self.expr(&assert.cond, 0),
+                self.expr(&assert.message, 0)
+            ),
+            StatType::HaltStat(code) => format!("halt({})", self.expr(code, 0)),
+            _ => unreachable!("compound statement"),
+        }
+    }
+
+    /*
+        Prints the expression, surrounded by parenthesis
+        when its precedence is lower than the given one.
+    */
+    fn expr(&self, expr: &Expr, min_level: u8) -> String {
+        match &expr.expr {
+            ExprTree::Factor(Factor {
+                fact: FactorValue::HighPrecedence(inner),
+                ..
+            }) => self.expr(inner, min_level),
+            ExprTree::Factor(fact) => {
+                self.factor(fact, self.code.get(expr.loc.begin..expr.loc.end))
+            }
+            ExprTree::Node(left, op, right) => {
+                let (level, left_level, right_level) = precedence(op);
+                let text = format!(
+                    "{} {} {}",
+                    self.expr(left, left_level),
+                    operator(op),
+                    self.expr(right, right_level)
+                );
+                if level < min_level {
+                    format!("({})", text)
+                } else {
+                    text
+                }
+            }
+        }
+    }
+
+    fn expr_list(&self, list: &[Expr]) -> String {
+        let list: Vec<String> = list.iter().map(|expr| self.expr(expr, 0)).collect();
+        list.join(", ")
+    }
+
+    fn func_call(&self, call: &FuncCall) -> String {
+        format!("{}({})", call.id, self.expr_list(&call.args))
+    }
+
+    // source is the text of the factor in the code, when known
+    fn factor(&self, fact: &Factor, source: Option<&str>) -> String {
+        match &fact.fact {
+            FactorValue::Id(id) => id.clone(),
+            FactorValue::IndexExpr(index) => {
+                format!("{}[{}]", index.id, self.expr(&index.index, 0))
+            }
+            FactorValue::UnaryOp(UnaryOp::Minus(operand)) => {
+                let source = source
+                    .and_then(|s| s.strip_prefix('-'))
+                    .map(str::trim_start);
+                let operand = self.factor(operand, source);
+                if operand.starts_with('-') {
+                    format!("- {}", operand)
+                } else {
+                    format!("-{}", operand)
+                }
+            }
+            FactorValue::UnaryOp(UnaryOp::Negate(operand)) => {
+                let source = source
+                    .and_then(|s| s.strip_prefix("not"))
+                    .map(str::trim_start);
+                format!("not {}", self.factor(operand, source))
+            }
+            FactorValue::CondExpr(cond) => format!(
+                "if {} then {} else {} end",
+                self.expr(&cond.cond, 0),
+                self.expr(&cond.true_stat, 0),
+                self.expr(&cond.false_stat, 0)
+            ),
+            FactorValue::CastExpr(cast) => {
+                let (name, expr) = match cast {
+                    CastExpr::Integer(expr) => ("integer", expr),
+                    CastExpr::Long(expr) => ("long", expr),
+                    CastExpr::Real(expr) => ("real", expr),
+                    CastExpr::Char(expr) => ("char", expr),
+                };
+                format!("{}({})", name, self.expr(expr, 0))
+            }
+            FactorValue::FuncCall(call) => self.func_call(call),
+            FactorValue::Const(value) => const_literal(value, source),
+            FactorValue::HighPrecedence(expr) => self.expr(expr, FACTOR_LEVEL),
+        }
+    }
+
+    fn case_label(&self, label: &CaseLabel) -> String {
+        let source = &self.code[label.loc.begin..label.loc.end];
+        const_literal(&label.value, Some(source))
+    }
+}
+
+const FACTOR_LEVEL: u8 = 4;
+
+/*
+    Returns the level of the operator and the minimum
+    levels of its operands, following the grammar:
+    logic operators are left associative, relational
+    ones are not associative, arithmetic ones are left
+    associative and bind tighter.
+*/
+fn precedence(op: &Operator) -> (u8, u8, u8) {
+    match op {
+        Operator::And | Operator::Or => (0, 0, 1),
+        Operator::Equal
+        | Operator::NotEqual
+        | Operator::Greater
+        | Operator::GreaterEqual
+        | Operator::Less
+        | Operator::LessEqual => (1, 2, 2),
+        Operator::Add | Operator::Sub => (2, 2, 3),
+        Operator::Mul | Operator::Div => (3, 3, FACTOR_LEVEL),
+    }
+}
+
+fn operator(op: &Operator) -> &'static str {
+    match op {
+        Operator::Equal => "==",
+        Operator::NotEqual => "!=",
+        Operator::Greater => ">",
+        Operator::GreaterEqual => ">=",
+        Operator::Less => "<",
+        Operator::LessEqual => "<=",
+        Operator::Add => "+",
+        Operator::Sub => "-",
+        Operator::Mul => "*",
+        Operator::Div => "/",
+        Operator::And => "and",
+        Operator::Or => "or",
+    }
+}
+
+fn is_compound(stat: &StatType) -> bool {
+    matches!(
+        stat,
+        StatType::IfStat(_)
+            | StatType::WhileStat(_)
+            | StatType::ForStat(_)
+            | StatType::RepeatStat(_)
+            | StatType::CaseStat(_)
+    )
+}
+
+fn loop_label(label: &Option<String>) -> String {
+    match label {
+        Some(label) => format!("{}: ", label),
+        None => String::new(),
+    }
+}
+
+fn jump(keyword: &str, label: &Option<String>) -> String {
+    match label {
+        Some(label) => format!("{} {}", keyword, label),
+        None => keyword.to_owned(),
+    }
+}
+
+fn param_decl(param: &ParamDecl) -> String {
+    let prefix = if param.by_ref { "var " } else { "" };
+    format!("{}{}: {}", prefix, param.id, kind_name(&param.kind))
+}
+
+// the header of a function, as the formatter prints it
+pub fn signature(function: &FuncDecl) -> String {
+    let params: Vec<String> = function.params.iter().map(param_decl).collect();
+    format!(
+        "func {}({}): {}",
+        function.id,
+        params.join(", "),
+        kind_name(&function.kind)
+    )
+}
+
+pub fn kind_name(kind: &Kind) -> String {
+    match kind {
+        Kind::Int => "integer".to_owned(),
+        Kind::Long => "long".to_owned(),
+        Kind::Real => "real".to_owned(),
+        Kind::Str => "string".to_owned(),
+        Kind::Char => "char".to_owned(),
+        Kind::Bool => "boolean".to_owned(),
+        Kind::Void => "void".to_owned(),
+        Kind::Func(func) => {
+            let params: Vec<String> = func.params.iter().map(kind_name).collect();
+            format!("func({}): {}", params.join(", "), kind_name(&func.kind))
+        }
+    }
+}
+
+/*
+    The text of the literal in the code is kept when it
+    still denotes the same value, otherwise the literal
+    is printed in its canonical form.
+*/
+fn const_literal(value: &Const, source: Option<&str>) -> String {
+    if let Some(source) = source {
+        if same_literal(value, source) {
+            return source.to_owned();
+        }
+        if let Some(literal) = negative_literal(value, source) {
+            return literal;
+        }
+    }
+    match value {
+        Const::IntConst(value) => value.to_string(),
+        Const::LongConst(value) => format!("{}L", value),
+        Const::RealConst(value) => format!("{:?}", value),
+        Const::StrConst(value) => escape(value, '"'),
+        Const::CharConst(value) => escape(&value.to_string(), '\''),
+        Const::BoolConst(value) => value.to_string(),
+    }
+}
+
+fn same_literal(value: &Const, source: &str) -> bool {
+    let quoted = |quote| source.len() >= 2 && source.starts_with(quote) && source.ends_with(quote);
+    match value {
+        Const::IntConst(value) => literal::decode_integer(source, 0).as_ref() == Ok(value),
+        Const::LongConst(value) => {
+            source.ends_with('L') && literal::decode_long(source, 0).as_ref() == Ok(value)
+        }
+        Const::RealConst(value) => source.replace('_', "").parse::<f64>().as_ref() == Ok(value),
+        Const::StrConst(value) => {
+            quoted('"') && literal::decode_string(source, 0).as_ref() == Ok(value)
+        }
+        Const::CharConst(value) => {
+            quoted('\'') && literal::decode_char(source, 0).as_ref() == Ok(value)
+        }
+        Const::BoolConst(_) => false,
+    }
+}
+
+// a negative integer keeps the literal written after the minus
+fn negative_literal(value: &Const, source: &str) -> Option<String> {
+    let digits = source.strip_prefix('-')?.trim_start();
+    let same = match value {
+        Const::IntConst(value) => literal::decode_negative_integer(digits, 0).as_ref() == Ok(value),
+        Const::LongConst(value) => {
+            digits.ends_with('L') && literal::decode_negative_long(digits, 0).as_ref() == Ok(value)
+        }
+        _ => false,
+    };
+    if same {
+        Some(format!("-{}", digits))
+    } else {
+        None
+    }
+}
+
+fn escape(text: &str, quote: char) -> String {
+    let mut output = String::with_capacity(text.len() + 2);
+    output.push(quote);
+    for c in text.chars() {
+        match c {
+            '\n' => output.push_str("\\n"),
+            '\t' => output.push_str("\\t"),
+            '\\' => output.push_str("\\\\"),
+            c if c == quote => {
+                output.push('\\');
+                output.push(c);
+            }
+            c if c.is_control() => output.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push(quote);
+    output
+}
+
+fn has_blank_line(text: &str) -> bool {
+    let lines: Vec<&str> = text.split('\n').collect();
+    lines.len() > 2
+        && lines[1..lines.len() - 1]
+            .iter()
+            .any(|l| l.trim().is_empty())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::ProgramParser;
+
+    fn format(code: &str) -> String {
+        let program = ProgramParser::new().parse(code).unwrap();
+        format_program(&program, code)
+    }
+
+    #[test]
+    fn test_format_layout() {
+        let code = "n:integer; func f(var a:integer,b:func(real):void):integer body \
+            if a>0 then a=a-1; else b(1.5); end; return a; end; \
+            body lbl: while n<10 do n=n+1; if n==5 then break lbl; end; end; \
+            for n=10 downto 0 step 2 do writeln(n); end; \
+            repeat read(n); until n>0; end.";
+        let expected = "\
+n: integer;
+
+func f(var a: integer, b: func(real): void): integer
+body
+    if a > 0 then
+        a = a - 1;
+    else
+        b(1.5);
+    end;
+    return a;
+end;
+
+body
+    lbl: while n < 10 do
+        n = n + 1;
+        if n == 5 then
+            break lbl;
+        end;
+    end;
+    for n = 10 downto 0 step 2 do
+        writeln(n);
+    end;
+    repeat
+        read(n);
+    until n > 0;
+end.
+";
+        assert_eq!(format(code), expected);
+        assert_eq!(format(expected), expected);
+    }
+
+    #[test]
+    fn test_minimal_parenthesis() {
+        let code = "body x = ((a + b)) * c - (d - e) + (f * g); \
+            y = (a - b) - c; z = (a < b) and (c or d) or (e == f); \
+            w = -(a + b) * not (c); v = (if (a) then (1) else 2 end); end.";
+        let expected = "\
+body
+    x = (a + b) * c - (d - e) + f * g;
+    y = a - b - c;
+    z = a < b and (c or d) or e == f;
+    w = -(a + b) * not c;
+    v = if a then 1 else 2 end;
+end.
+";
+        assert_eq!(format(code), expected);
+    }
+
+    #[test]
+    fn test_keep_literals() {
+        let code = "body x = 0xFF + 1_000L - -0b1 + 1e3; c = '\\n'; \
+            s = \"tab\\t\\u{41}\"; case x of -0x1, - 2147483648: x = 1; 'a': x = 2; end; end.";
+        let expected = "\
+body
+    x = 0xFF + 1_000L - -0b1 + 1e3;
+    c = '\\n';
+    s = \"tab\\t\\u{41}\";
+    case x of
+        -0x1, -2147483648: x = 1;
+        'a': x = 2;
+    end;
+end.
+";
+        assert_eq!(format(code), expected);
+    }
+
+    #[test]
+    fn test_keep_comments() {
+        let code = "\
+# header
+
+x: integer; # counter
+(* functions *)
+func f(): void
+body
+    x = x + 1; # increment
+
+    # before the end
+end;
+body
+    case x of
+        1: # one
+            f();
+        else f();
+        # else
+    end;
+    if x > 0 then   x = 0;   # reset
+    # no else
+    end;
+end.
+# footer
+";
+        let expected = "\
+# header
+
+x: integer; # counter
+
+(* functions *)
+func f(): void
+body
+    x = x + 1; # increment
+
+    # before the end
+end;
+
+body
+    case x of
+        1:
+            # one
+            f();
+        else
+            f();
+            # else
+    end;
+    if x > 0 then
+        x = 0; # reset
+        # no else
+    end;
+end.
+# footer
+";
+        assert_eq!(format(code), expected);
+        assert_eq!(format(expected), expected);
+    }
+
+    #[test]
+    fn test_trailing_comment_of_outer_statement() {
+        let code = "\
+body
+    if c then r = 0; end; # after if
+    while c do if c then c = false; end; end; # after while
+    x = 1; y = 2; # after y
+end. # footer
+";
+        let expected = "\
+body
+    if c then
+        r = 0;
+    end; # after if
+    while c do
+        if c then
+            c = false;
+        end;
+    end; # after while
+    x = 1;
+    y = 2; # after y
+end. # footer
+";
+        assert_eq!(format(code), expected);
+        assert_eq!(format(expected), expected);
+    }
+}
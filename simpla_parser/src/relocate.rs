@@ -0,0 +1,72 @@
+use crate::syntax_tree::*;
+
+/*
+    The parser produces locations relative to the
+    beginning of the parsed code. When a program is
+    made of several files every file is parsed on its
+    own, so the locations of an imported file must be
+    moved by the offset that the file has in the whole
+    program source.
+*/
+pub trait Relocate {
+    fn relocate(&mut self, base: usize);
+}
+
+impl<T: Relocate> Relocate for Vec<T> {
+    fn relocate(&mut self, base: usize) {
+        for item in self {
+            item.relocate(base);
+        }
+    }
+}
+
+impl<T: Relocate> Relocate for Option<T> {
+    fn relocate(&mut self, base: usize) {
+        if let Some(item) = self {
+            item.relocate(base);
+        }
+    }
+}
+
+impl<T: Relocate> Relocate for Box<T> {
+    fn relocate(&mut self, base: usize) {
+        self.as_mut().relocate(base);
+    }
+}
+
+impl Relocate for Location {
+    fn relocate(&mut self, base: usize) {
+        self.begin += base;
+        self.end += base;
+    }
+}
+
+// every node holding a location
+macro_rules! relocate_node {
+    ($node:ty, $visit:ident) => {
+        impl Relocate for $node {
+            fn relocate(&mut self, base: usize) {
+                Relocator { base }.$visit(self);
+            }
+        }
+    };
+}
+
+relocate_node!(Program, visit_program_mut);
+relocate_node!(Library, visit_library_mut);
+relocate_node!(Import, visit_import_mut);
+relocate_node!(VarDecl, visit_var_decl_mut);
+relocate_node!(FuncDecl, visit_func_decl_mut);
+relocate_node!(Stat, visit_stat_mut);
+relocate_node!(Expr, visit_expr_mut);
+relocate_node!(Factor, visit_factor_mut);
+
+struct Relocator {
+    base: usize,
+}
+
+impl VisitorMut for Relocator {
+    fn visit_location_mut(&mut self, loc: &mut Location) {
+        loc.relocate(self.base);
+    }
+}
@@ -1,7 +1,13 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 
+pub use crate::visit::*;
+
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Program {
+    pub imports: ImportList,
     pub global_vars: VarDeclList,
     pub functions: FuncDeclList,
     pub body: StatList,
@@ -9,7 +15,17 @@ pub struct Program {
 
 impl Program {
     pub fn new(global_vars: VarDeclList, functions: FuncDeclList, body: StatList) -> Self {
+        Self::new_with_imports(Vec::new(), global_vars, functions, body)
+    }
+
+    pub fn new_with_imports(
+        imports: ImportList,
+        global_vars: VarDeclList,
+        functions: FuncDeclList,
+        body: StatList,
+    ) -> Self {
         Self {
+            imports,
             global_vars,
             functions,
             body,
@@ -17,7 +33,42 @@ impl Program {
     }
 }
 
+#[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Library {
+    pub imports: ImportList,
+    pub global_vars: VarDeclList,
+    pub functions: FuncDeclList,
+}
+
+impl Library {
+    pub fn new(imports: ImportList, global_vars: VarDeclList, functions: FuncDeclList) -> Self {
+        Self {
+            imports,
+            global_vars,
+            functions,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Import {
+    pub path: String,
+    pub loc: Location,
+}
+
+impl Import {
+    pub fn new(path: String, begin: usize, end: usize) -> Self {
+        Self {
+            path,
+            loc: Location::new(begin, end),
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Location {
     pub begin: usize,
     pub end: usize,
@@ -30,15 +81,36 @@ impl Location {
 }
 
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Kind {
     Int,
+    Long,
     Real,
     Str,
+    Char,
     Bool,
     Void,
+    Func(FuncKind),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FuncKind {
+    pub params: Vec<Kind>,
+    pub kind: Box<Kind>,
+}
+
+impl FuncKind {
+    pub fn new(params: Vec<Kind>, kind: Kind) -> Self {
+        Self {
+            params,
+            kind: Box::new(kind),
+        }
+    }
 }
 
 #[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Operator {
     Equal,
     NotEqual,
@@ -55,10 +127,16 @@ pub enum Operator {
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct VarDecl {
     pub id_list: IdList,
     pub kind: Kind,
     pub loc: Location,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub doc: Option<String>,
 }
 
 impl VarDecl {
@@ -67,30 +145,60 @@ impl VarDecl {
             id_list,
             kind,
             loc: Location::new(begin, end),
+            doc: None,
         }
     }
+
+    pub fn with_doc(mut self, doc: Option<String>) -> Self {
+        self.doc = doc;
+        self
+    }
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ParamDecl {
     pub id: String,
     pub kind: Kind,
+    pub by_ref: bool,
+    pub loc: Location,
 }
 
 impl ParamDecl {
-    pub fn new(id: String, kind: Kind) -> Self {
-        Self { id, kind }
+    pub fn new(id: String, kind: Kind, begin: usize, end: usize) -> Self {
+        Self {
+            id,
+            kind,
+            by_ref: false,
+            loc: Location::new(begin, end),
+        }
+    }
+
+    pub fn new_reference(id: String, kind: Kind, begin: usize, end: usize) -> Self {
+        Self {
+            id,
+            kind,
+            by_ref: true,
+            loc: Location::new(begin, end),
+        }
     }
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct FuncDecl {
     pub id: String,
     pub kind: Kind,
     pub params: ParamList,
     pub vars: VarDeclList,
+    pub functions: FuncDeclList,
     pub body: StatList,
     pub loc: Location,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub doc: Option<String>,
 }
 
 impl FuncDecl {
@@ -108,13 +216,26 @@ impl FuncDecl {
             params,
             kind,
             vars,
+            functions: Vec::new(),
             body,
             loc: Location::new(begin, end),
+            doc: None,
         }
     }
+
+    pub fn with_functions(mut self, functions: FuncDeclList) -> Self {
+        self.functions = functions;
+        self
+    }
+
+    pub fn with_doc(mut self, doc: Option<String>) -> Self {
+        self.doc = doc;
+        self
+    }
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Stat {
     pub loc: Location,
     pub stat: StatType,
@@ -130,19 +251,26 @@ impl Stat {
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum StatType {
     AssignStat(AssignStat),
     IfStat(IfStat),
     WhileStat(WhileStat),
     ForStat(ForStat),
+    RepeatStat(RepeatStat),
+    CaseStat(CaseStat),
     ReturnStat(Option<Expr>),
     ReadStat(IdList),
     WriteStat(WriteStat),
     FuncCall(FuncCall),
-    Break,
+    Break(Option<String>),
+    Continue(Option<String>),
+    AssertStat(AssertStat),
+    HaltStat(Expr),
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct AssignStat {
     pub id: String,
     pub expr: Expr,
@@ -155,6 +283,7 @@ impl AssignStat {
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct IfStat {
     pub cond: Expr,
     pub if_body: StatList,
@@ -172,59 +301,166 @@ impl IfStat {
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct WhileStat {
+    pub label: Option<String>,
     pub cond: Expr,
     pub body: StatList,
 }
 
 impl WhileStat {
-    pub fn new(cond: Expr, body: StatList) -> Self {
-        Self { cond, body }
+    pub fn new(label: Option<String>, cond: Expr, body: StatList) -> Self {
+        Self { label, cond, body }
     }
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ForStat {
+    pub label: Option<String>,
     pub id: String,
     pub begin_expr: Expr,
+    pub direction: ForDirection,
     pub end_expr: Expr,
+    pub step: Option<Expr>,
     pub body: StatList,
 }
 
 impl ForStat {
-    pub fn new(id: String, begin_expr: Expr, end_expr: Expr, body: StatList) -> Self {
+    pub fn new(
+        label: Option<String>,
+        id: String,
+        begin_expr: Expr,
+        direction: ForDirection,
+        end_expr: Expr,
+        step: Option<Expr>,
+        body: StatList,
+    ) -> Self {
         Self {
+            label,
             id,
             begin_expr,
+            direction,
             end_expr,
+            step,
             body,
         }
     }
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ForDirection {
+    Up,
+    Down,
+}
+
+#[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RepeatStat {
+    pub label: Option<String>,
+    pub body: StatList,
+    pub cond: Expr,
+}
+
+impl RepeatStat {
+    pub fn new(label: Option<String>, body: StatList, cond: Expr) -> Self {
+        Self { label, body, cond }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CaseStat {
+    pub expr: Expr,
+    pub branches: Vec<CaseBranch>,
+    pub else_body: Option<StatList>,
+}
+
+impl CaseStat {
+    pub fn new(expr: Expr, branches: Vec<CaseBranch>, else_body: Option<StatList>) -> Self {
+        Self {
+            expr,
+            branches,
+            else_body,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CaseBranch {
+    pub labels: Vec<CaseLabel>,
+    pub body: StatList,
+}
+
+impl CaseBranch {
+    pub fn new(labels: Vec<CaseLabel>, body: StatList) -> Self {
+        Self { labels, body }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CaseLabel {
+    pub value: Const,
+    pub loc: Location,
+}
+
+impl CaseLabel {
+    pub fn new(value: Const, begin: usize, end: usize) -> Self {
+        Self {
+            value,
+            loc: Location::new(begin, end),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AssertStat {
+    pub cond: Expr,
+    pub message: Expr,
+}
+
+impl AssertStat {
+    pub fn new(cond: Expr, message: Expr) -> Self {
+        Self { cond, message }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum WriteStat {
     WriteLine(ExprList),
     Write(ExprList),
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct FuncCall {
     pub id: String,
     pub args: ExprList,
+    pub loc: Location,
 }
 
 impl FuncCall {
-    pub fn new(id: String, args: ExprList) -> Self {
-        Self { id, args }
+    pub fn new(id: String, args: ExprList, begin: usize, end: usize) -> Self {
+        Self {
+            id,
+            args,
+            loc: Location::new(begin, end),
+        }
     }
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Expr {
     pub loc: Location,
     pub expr: ExprTree,
     pub kind: RefCell<Option<Kind>>,
+    pub coercion: RefCell<Option<Kind>>,
 }
 
 impl Expr {
@@ -233,17 +469,20 @@ impl Expr {
             expr,
             loc: Location::new(begin, end),
             kind: RefCell::new(None),
+            coercion: RefCell::new(None),
         }
     }
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ExprTree {
     Node(Box<Expr>, Operator, Box<Expr>),
     Factor(Factor),
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Factor {
     pub fact: FactorValue,
     pub kind: RefCell<Option<Kind>>
@@ -260,6 +499,7 @@ impl Factor {
 
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FactorValue {
     Id(String),
     UnaryOp(UnaryOp),
@@ -268,23 +508,29 @@ pub enum FactorValue {
     FuncCall(FuncCall),
     Const(Const),
     HighPrecedence(Box<Expr>),
+    IndexExpr(IndexExpr),
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Const {
     IntConst(i32),
+    LongConst(i64),
     RealConst(f64),
     StrConst(String),
+    CharConst(char),
     BoolConst(bool),
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum UnaryOp {
     Negate(Box<Factor>),
     Minus(Box<Factor>),
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CondExpr {
     pub cond: Box<Expr>,
     pub true_stat: Box<Expr>,
@@ -302,11 +548,35 @@ impl CondExpr {
 }
 
 #[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum CastExpr {
     Integer(Box<Expr>),
+    Long(Box<Expr>),
     Real(Box<Expr>),
+    Char(Box<Expr>),
+}
+
+/*
+    Read access to a character of a string variable,
+    the first character has index 0.
+*/
+#[derive(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IndexExpr {
+    pub id: String,
+    pub index: Box<Expr>,
+}
+
+impl IndexExpr {
+    pub fn new(id: String, index: Expr) -> Self {
+        Self {
+            id,
+            index: Box::new(index),
+        }
+    }
 }
 
+pub type ImportList = Vec<Import>;
 pub type StatList = Vec<Stat>;
 pub type VarDeclList = Vec<VarDecl>;
 pub type ParamList = Vec<ParamDecl>;
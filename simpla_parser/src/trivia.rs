@@ -0,0 +1,100 @@
+use crate::lexer::{Lexer, Tok};
+use crate::syntax_tree::Location;
+
+/*
+    Comments are trivia for the lexer, so they never reach
+    the syntax tree. Tools that re-emit the source, like the
+    formatter, take them from the tokens of the lexer and
+    attach them to the nodes by location: a comment belongs
+    to the first node that begins after it, or to the
+    outermost node that ends on the same line before it.
+*/
+#[derive(Debug, PartialEq, Clone)]
+pub struct Comment {
+    pub text: String,
+    pub loc: Location,
+}
+
+// the line comments and the block comments of the code, in order
+pub fn collect_comments(code: &str) -> Vec<Comment> {
+    Lexer::new(code)
+        .filter_map(|(begin, tok, end)| match tok {
+            Tok::Comment(text) => Some(Comment {
+                text: text.to_owned(),
+                loc: Location::new(begin, end),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/*
+    Skips white spaces, semicolons and comments,
+    returns the offset of the next token.
+*/
+pub fn skip_trivia(code: &str, begin: usize) -> usize {
+    Lexer::new(&code[begin..])
+        .find(|(_, tok, _)| !tok.is_trivia() && *tok != Tok::Punct(";"))
+        .map_or(code.len(), |(offset, _, _)| begin + offset)
+}
+
+/*
+    Returns the text of the doc comment of the declaration
+    beginning at the offset: the ## line comments right
+    above it, with no blank line between, without the ##
+    and the space after it.
+*/
+pub fn doc_comment(code: &str, begin: usize) -> Option<String> {
+    let before = &code[..begin];
+    let line_begin = before.rfind('\n').map_or(0, |index| index + 1);
+    if !before[line_begin..].trim().is_empty() {
+        return None;
+    }
+    let mut lines: Vec<&str> = before[..line_begin]
+        .lines()
+        .rev()
+        .map(str::trim)
+        .take_while(|line| line.starts_with("##"))
+        .map(|line| {
+            let text = &line[2..];
+            text.strip_prefix(' ').unwrap_or(text).trim_end()
+        })
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+    lines.reverse();
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_collect_comments() {
+        let code = "a = \"# no\"; # yes\nc = '#'; (* block\n # *) b = 1;";
+        let comments = collect_comments(code);
+        let text: Vec<&str> = comments.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(text, vec!["# yes", "(* block\n # *)"]);
+        assert_eq!(comments[0].loc, Location::new(12, 17));
+    }
+
+    #[test]
+    fn test_doc_comment() {
+        let code = "## first\n##second\n  ##\n  x: integer;\n\n## far\n\ny: real; ## z\nz: char;";
+        let doc = doc_comment(code, code.find("x:").unwrap());
+        assert_eq!(doc, Some("first\nsecond\n".to_owned()));
+        assert_eq!(doc_comment(code, code.find("y:").unwrap()), None);
+        assert_eq!(doc_comment(code, code.find("z:").unwrap()), None);
+        assert_eq!(doc_comment(code, 0), None);
+    }
+
+    #[test]
+    fn test_skip_trivia() {
+        let code = "x = 1; # comment\n (* block *) end;";
+        assert_eq!(skip_trivia(code, 5), code.find("end").unwrap());
+        assert_eq!(skip_trivia(code, 0), 0);
+    }
+}
@@ -0,0 +1,519 @@
+use crate::syntax_tree::*;
+
+/*
+    Traversal of the syntax tree: every visit method of
+    Visitor calls the walk function for the same node, which
+    visits the children of the node in the order they have
+    in the source code. An analysis overrides the methods of
+    the nodes it cares about and calls the walk function
+    itself when it needs the children visited too.
+    Identifiers and constants are not nodes, they are seen
+    through the node that holds them.
+*/
+pub trait Visitor<'a> {
+    fn visit_program(&mut self, program: &'a Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_library(&mut self, library: &'a Library) {
+        walk_library(self, library);
+    }
+
+    fn visit_import(&mut self, import: &'a Import) {
+        walk_import(self, import);
+    }
+
+    fn visit_var_decl(&mut self, var_decl: &'a VarDecl) {
+        walk_var_decl(self, var_decl);
+    }
+
+    fn visit_param_decl(&mut self, param_decl: &'a ParamDecl) {
+        walk_param_decl(self, param_decl);
+    }
+
+    fn visit_func_decl(&mut self, func_decl: &'a FuncDecl) {
+        walk_func_decl(self, func_decl);
+    }
+
+    fn visit_stat(&mut self, stat: &'a Stat) {
+        walk_stat(self, stat);
+    }
+
+    fn visit_case_branch(&mut self, branch: &'a CaseBranch) {
+        walk_case_branch(self, branch);
+    }
+
+    fn visit_case_label(&mut self, label: &'a CaseLabel) {
+        walk_case_label(self, label);
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_factor(&mut self, factor: &'a Factor) {
+        walk_factor(self, factor);
+    }
+
+    fn visit_func_call(&mut self, func_call: &'a FuncCall) {
+        walk_func_call(self, func_call);
+    }
+
+    fn visit_location(&mut self, _loc: &'a Location) {}
+}
+
+pub fn walk_program<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, program: &'a Program) {
+    for import in &program.imports {
+        visitor.visit_import(import);
+    }
+    for var_decl in &program.global_vars {
+        visitor.visit_var_decl(var_decl);
+    }
+    for func_decl in &program.functions {
+        visitor.visit_func_decl(func_decl);
+    }
+    walk_stat_list(visitor, &program.body);
+}
+
+pub fn walk_library<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, library: &'a Library) {
+    for import in &library.imports {
+        visitor.visit_import(import);
+    }
+    for var_decl in &library.global_vars {
+        visitor.visit_var_decl(var_decl);
+    }
+    for func_decl in &library.functions {
+        visitor.visit_func_decl(func_decl);
+    }
+}
+
+pub fn walk_import<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, import: &'a Import) {
+    visitor.visit_location(&import.loc);
+}
+
+pub fn walk_var_decl<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, var_decl: &'a VarDecl) {
+    visitor.visit_location(&var_decl.loc);
+}
+
+pub fn walk_param_decl<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, param_decl: &'a ParamDecl) {
+    visitor.visit_location(&param_decl.loc);
+}
+
+pub fn walk_func_decl<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, func_decl: &'a FuncDecl) {
+    visitor.visit_location(&func_decl.loc);
+    for param in &func_decl.params {
+        visitor.visit_param_decl(param);
+    }
+    for var_decl in &func_decl.vars {
+        visitor.visit_var_decl(var_decl);
+    }
+    for nested in &func_decl.functions {
+        visitor.visit_func_decl(nested);
+    }
+    walk_stat_list(visitor, &func_decl.body);
+}
+
+pub fn walk_stat_list<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, stat_list: &'a [Stat]) {
+    for stat in stat_list {
+        visitor.visit_stat(stat);
+    }
+}
+
+pub fn walk_stat<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, stat: &'a Stat) {
+    visitor.visit_location(&stat.loc);
+    match &stat.stat {
+        StatType::AssignStat(assign) => visitor.visit_expr(&assign.expr),
+        StatType::IfStat(if_stat) => {
+            visitor.visit_expr(&if_stat.cond);
+            walk_stat_list(visitor, &if_stat.if_body);
+            if let Some(else_body) = &if_stat.else_body {
+                walk_stat_list(visitor, else_body);
+            }
+        }
+        StatType::WhileStat(while_stat) => {
+            visitor.visit_expr(&while_stat.cond);
+            walk_stat_list(visitor, &while_stat.body);
+        }
+        StatType::ForStat(for_stat) => {
+            visitor.visit_expr(&for_stat.begin_expr);
+            visitor.visit_expr(&for_stat.end_expr);
+            if let Some(step) = &for_stat.step {
+                visitor.visit_expr(step);
+            }
+            walk_stat_list(visitor, &for_stat.body);
+        }
+        StatType::RepeatStat(repeat_stat) => {
+            walk_stat_list(visitor, &repeat_stat.body);
+            visitor.visit_expr(&repeat_stat.cond);
+        }
+        StatType::CaseStat(case_stat) => {
+            visitor.visit_expr(&case_stat.expr);
+            for branch in &case_stat.branches {
+                visitor.visit_case_branch(branch);
+            }
+            if let Some(else_body) = &case_stat.else_body {
+                walk_stat_list(visitor, else_body);
+            }
+        }
+        StatType::ReturnStat(expr) => {
+            if let Some(expr) = expr {
+                visitor.visit_expr(expr);
+            }
+        }
+        StatType::WriteStat(WriteStat::Write(expr_list))
+        | StatType::WriteStat(WriteStat::WriteLine(expr_list)) => {
+            for expr in expr_list {
+                visitor.visit_expr(expr);
+            }
+        }
+        StatType::FuncCall(func_call) => visitor.visit_func_call(func_call),
+        StatType::AssertStat(assert_stat) => {
+            visitor.visit_expr(&assert_stat.cond);
+            visitor.visit_expr(&assert_stat.message);
+        }
+        StatType::HaltStat(code) => visitor.visit_expr(code),
+        StatType::ReadStat(_) | StatType::Break(_) | StatType::Continue(_) => {}
+    }
+}
+
+pub fn walk_case_branch<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, branch: &'a CaseBranch) {
+    for label in &branch.labels {
+        visitor.visit_case_label(label);
+    }
+    walk_stat_list(visitor, &branch.body);
+}
+
+pub fn walk_case_label<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, label: &'a CaseLabel) {
+    visitor.visit_location(&label.loc);
+}
+
+pub fn walk_expr<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, expr: &'a Expr) {
+    visitor.visit_location(&expr.loc);
+    match &expr.expr {
+        ExprTree::Node(lhs, _, rhs) => {
+            visitor.visit_expr(lhs);
+            visitor.visit_expr(rhs);
+        }
+        ExprTree::Factor(factor) => visitor.visit_factor(factor),
+    }
+}
+
+pub fn walk_factor<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, factor: &'a Factor) {
+    match &factor.fact {
+        FactorValue::UnaryOp(UnaryOp::Negate(inner))
+        | FactorValue::UnaryOp(UnaryOp::Minus(inner)) => visitor.visit_factor(inner),
+        FactorValue::CondExpr(cond_expr) => {
+            visitor.visit_expr(&cond_expr.cond);
+            visitor.visit_expr(&cond_expr.true_stat);
+            visitor.visit_expr(&cond_expr.false_stat);
+        }
+        FactorValue::CastExpr(CastExpr::Integer(expr))
+        | FactorValue::CastExpr(CastExpr::Long(expr))
+        | FactorValue::CastExpr(CastExpr::Real(expr))
+        | FactorValue::CastExpr(CastExpr::Char(expr)) => visitor.visit_expr(expr),
+        FactorValue::FuncCall(func_call) => visitor.visit_func_call(func_call),
+        FactorValue::HighPrecedence(expr) => visitor.visit_expr(expr),
+        FactorValue::IndexExpr(index) => visitor.visit_expr(&index.index),
+        FactorValue::Id(_) | FactorValue::Const(_) => {}
+    }
+}
+
+pub fn walk_func_call<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, func_call: &'a FuncCall) {
+    visitor.visit_location(&func_call.loc);
+    for arg in &func_call.args {
+        visitor.visit_expr(arg);
+    }
+}
+
+/*
+    Like Visitor, but every node can be changed in place,
+    for transformations of the tree.
+*/
+pub trait VisitorMut {
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        walk_program_mut(self, program);
+    }
+
+    fn visit_library_mut(&mut self, library: &mut Library) {
+        walk_library_mut(self, library);
+    }
+
+    fn visit_import_mut(&mut self, import: &mut Import) {
+        walk_import_mut(self, import);
+    }
+
+    fn visit_var_decl_mut(&mut self, var_decl: &mut VarDecl) {
+        walk_var_decl_mut(self, var_decl);
+    }
+
+    fn visit_param_decl_mut(&mut self, param_decl: &mut ParamDecl) {
+        walk_param_decl_mut(self, param_decl);
+    }
+
+    fn visit_func_decl_mut(&mut self, func_decl: &mut FuncDecl) {
+        walk_func_decl_mut(self, func_decl);
+    }
+
+    fn visit_stat_mut(&mut self, stat: &mut Stat) {
+        walk_stat_mut(self, stat);
+    }
+
+    fn visit_case_branch_mut(&mut self, branch: &mut CaseBranch) {
+        walk_case_branch_mut(self, branch);
+    }
+
+    fn visit_case_label_mut(&mut self, label: &mut CaseLabel) {
+        walk_case_label_mut(self, label);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr);
+    }
+
+    fn visit_factor_mut(&mut self, factor: &mut Factor) {
+        walk_factor_mut(self, factor);
+    }
+
+    fn visit_func_call_mut(&mut self, func_call: &mut FuncCall) {
+        walk_func_call_mut(self, func_call);
+    }
+
+    fn visit_location_mut(&mut self, _loc: &mut Location) {}
+}
+
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(visitor: &mut V, program: &mut Program) {
+    for import in &mut program.imports {
+        visitor.visit_import_mut(import);
+    }
+    for var_decl in &mut program.global_vars {
+        visitor.visit_var_decl_mut(var_decl);
+    }
+    for func_decl in &mut program.functions {
+        visitor.visit_func_decl_mut(func_decl);
+    }
+    walk_stat_list_mut(visitor, &mut program.body);
+}
+
+pub fn walk_library_mut<V: VisitorMut + ?Sized>(visitor: &mut V, library: &mut Library) {
+    for import in &mut library.imports {
+        visitor.visit_import_mut(import);
+    }
+    for var_decl in &mut library.global_vars {
+        visitor.visit_var_decl_mut(var_decl);
+    }
+    for func_decl in &mut library.functions {
+        visitor.visit_func_decl_mut(func_decl);
+    }
+}
+
+pub fn walk_import_mut<V: VisitorMut + ?Sized>(visitor: &mut V, import: &mut Import) {
+    visitor.visit_location_mut(&mut import.loc);
+}
+
+pub fn walk_var_decl_mut<V: VisitorMut + ?Sized>(visitor: &mut V, var_decl: &mut VarDecl) {
+    visitor.visit_location_mut(&mut var_decl.loc);
+}
+
+pub fn walk_param_decl_mut<V: VisitorMut + ?Sized>(visitor: &mut V, param_decl: &mut ParamDecl) {
+    visitor.visit_location_mut(&mut param_decl.loc);
+}
+
+pub fn walk_func_decl_mut<V: VisitorMut + ?Sized>(visitor: &mut V, func_decl: &mut FuncDecl) {
+    visitor.visit_location_mut(&mut func_decl.loc);
+    for param in &mut func_decl.params {
+        visitor.visit_param_decl_mut(param);
+    }
+    for var_decl in &mut func_decl.vars {
+        visitor.visit_var_decl_mut(var_decl);
+    }
+    for nested in &mut func_decl.functions {
+        visitor.visit_func_decl_mut(nested);
+    }
+    walk_stat_list_mut(visitor, &mut func_decl.body);
+}
+
+pub fn walk_stat_list_mut<V: VisitorMut + ?Sized>(visitor: &mut V, stat_list: &mut [Stat]) {
+    for stat in stat_list {
+        visitor.visit_stat_mut(stat);
+    }
+}
+
+pub fn walk_stat_mut<V: VisitorMut + ?Sized>(visitor: &mut V, stat: &mut Stat) {
+    visitor.visit_location_mut(&mut stat.loc);
+    match &mut stat.stat {
+        StatType::AssignStat(assign) => visitor.visit_expr_mut(&mut assign.expr),
+        StatType::IfStat(if_stat) => {
+            visitor.visit_expr_mut(&mut if_stat.cond);
+            walk_stat_list_mut(visitor, &mut if_stat.if_body);
+            if let Some(else_body) = &mut if_stat.else_body {
+                walk_stat_list_mut(visitor, else_body);
+            }
+        }
+        StatType::WhileStat(while_stat) => {
+            visitor.visit_expr_mut(&mut while_stat.cond);
+            walk_stat_list_mut(visitor, &mut while_stat.body);
+        }
+        StatType::ForStat(for_stat) => {
+            visitor.visit_expr_mut(&mut for_stat.begin_expr);
+            visitor.visit_expr_mut(&mut for_stat.end_expr);
+            if let Some(step) = &mut for_stat.step {
+                visitor.visit_expr_mut(step);
+            }
+            walk_stat_list_mut(visitor, &mut for_stat.body);
+        }
+        StatType::RepeatStat(repeat_stat) => {
+            walk_stat_list_mut(visitor, &mut repeat_stat.body);
+            visitor.visit_expr_mut(&mut repeat_stat.cond);
+        }
+        StatType::CaseStat(case_stat) => {
+            visitor.visit_expr_mut(&mut case_stat.expr);
+            for branch in &mut case_stat.branches {
+                visitor.visit_case_branch_mut(branch);
+            }
+            if let Some(else_body) = &mut case_stat.else_body {
+                walk_stat_list_mut(visitor, else_body);
+            }
+        }
+        StatType::ReturnStat(expr) => {
+            if let Some(expr) = expr {
+                visitor.visit_expr_mut(expr);
+            }
+        }
+        StatType::WriteStat(WriteStat::Write(expr_list))
+        | StatType::WriteStat(WriteStat::WriteLine(expr_list)) => {
+            for expr in expr_list {
+                visitor.visit_expr_mut(expr);
+            }
+        }
+        StatType::FuncCall(func_call) => visitor.visit_func_call_mut(func_call),
+        StatType::AssertStat(assert_stat) => {
+            visitor.visit_expr_mut(&mut assert_stat.cond);
+            visitor.visit_expr_mut(&mut assert_stat.message);
+        }
+        StatType::HaltStat(code) => visitor.visit_expr_mut(code),
+        StatType::ReadStat(_) | StatType::Break(_) | StatType::Continue(_) => {}
+    }
+}
+
+pub fn walk_case_branch_mut<V: VisitorMut + ?Sized>(visitor: &mut V, branch: &mut CaseBranch) {
+    for label in &mut branch.labels {
+        visitor.visit_case_label_mut(label);
+    }
+    walk_stat_list_mut(visitor, &mut branch.body);
+}
+
+pub fn walk_case_label_mut<V: VisitorMut + ?Sized>(visitor: &mut V, label: &mut CaseLabel) {
+    visitor.visit_location_mut(&mut label.loc);
+}
+
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expr) {
+    visitor.visit_location_mut(&mut expr.loc);
+    match &mut expr.expr {
+        ExprTree::Node(lhs, _, rhs) => {
+            visitor.visit_expr_mut(lhs);
+            visitor.visit_expr_mut(rhs);
+        }
+        ExprTree::Factor(factor) => visitor.visit_factor_mut(factor),
+    }
+}
+
+pub fn walk_factor_mut<V: VisitorMut + ?Sized>(visitor: &mut V, factor: &mut Factor) {
+    match &mut factor.fact {
+        FactorValue::UnaryOp(UnaryOp::Negate(inner))
+        | FactorValue::UnaryOp(UnaryOp::Minus(inner)) => visitor.visit_factor_mut(inner),
+        FactorValue::CondExpr(cond_expr) => {
+            visitor.visit_expr_mut(&mut cond_expr.cond);
+            visitor.visit_expr_mut(&mut cond_expr.true_stat);
+            visitor.visit_expr_mut(&mut cond_expr.false_stat);
+        }
+        FactorValue::CastExpr(CastExpr::Integer(expr))
+        | FactorValue::CastExpr(CastExpr::Long(expr))
+        | FactorValue::CastExpr(CastExpr::Real(expr))
+        | FactorValue::CastExpr(CastExpr::Char(expr)) => visitor.visit_expr_mut(expr),
+        FactorValue::FuncCall(func_call) => visitor.visit_func_call_mut(func_call),
+        FactorValue::HighPrecedence(expr) => visitor.visit_expr_mut(expr),
+        FactorValue::IndexExpr(index) => visitor.visit_expr_mut(&mut index.index),
+        FactorValue::Id(_) | FactorValue::Const(_) => {}
+    }
+}
+
+pub fn walk_func_call_mut<V: VisitorMut + ?Sized>(visitor: &mut V, func_call: &mut FuncCall) {
+    visitor.visit_location_mut(&mut func_call.loc);
+    for arg in &mut func_call.args {
+        visitor.visit_expr_mut(arg);
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::ProgramParser;
+
+    const CODE: &str = "n: integer;
+        func f(x: integer): integer
+        body
+            return -x + g(x, 2);
+        end;
+        body
+            n = f(1);
+            case n of
+                1, 2: writeln(\"small\", n);
+            end;
+        end.";
+
+    #[derive(Default)]
+    struct Counter<'a> {
+        calls: Vec<&'a str>,
+        ids: usize,
+        labels: usize,
+    }
+
+    impl<'a> Visitor<'a> for Counter<'a> {
+        fn visit_func_call(&mut self, func_call: &'a FuncCall) {
+            self.calls.push(&func_call.id);
+            walk_func_call(self, func_call);
+        }
+
+        fn visit_factor(&mut self, factor: &'a Factor) {
+            if let FactorValue::Id(_) = factor.fact {
+                self.ids += 1;
+            }
+            walk_factor(self, factor);
+        }
+
+        fn visit_case_label(&mut self, _label: &'a CaseLabel) {
+            self.labels += 1;
+        }
+    }
+
+    struct Renamer;
+
+    impl VisitorMut for Renamer {
+        fn visit_factor_mut(&mut self, factor: &mut Factor) {
+            if let FactorValue::Id(id) = &mut factor.fact {
+                id.push('_');
+            }
+            walk_factor_mut(self, factor);
+        }
+    }
+
+    #[test]
+    fn test_visitor() {
+        let program = ProgramParser::new().parse(CODE).unwrap();
+        let mut counter = Counter::default();
+        counter.visit_program(&program);
+        assert_eq!(counter.calls, ["g", "f"]);
+        assert_eq!(counter.ids, 4);
+        assert_eq!(counter.labels, 2);
+    }
+
+    #[test]
+    fn test_visitor_mut() {
+        let mut program = ProgramParser::new().parse(CODE).unwrap();
+        Renamer.visit_program_mut(&mut program);
+        let code = crate::printer::format_program(&program, CODE);
+        assert!(code.contains("return -x_ + g(x_, 2);"), "{}", code);
+        assert!(code.contains("writeln(\"small\", n_);"), "{}", code);
+    }
+}
@@ -0,0 +1,127 @@
+mod node;
+mod render;
+
+use simpla_parser::syntax_tree::Program;
+use std::str::FromStr;
+
+/*
+    Textual forms of the syntax tree, used to debug the
+    parser and the semantic analysis: every node carries
+    its location and, once the program has been checked,
+    expressions and factors carry their inferred kind.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DumpFormat {
+    Json,
+    Sexp,
+    Tree,
+}
+
+impl FromStr for DumpFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "sexp" => Ok(Self::Sexp),
+            "tree" => Ok(Self::Tree),
+            other => Err(format!(
+                "unknown dump format {}, expected json, sexp or tree",
+                other
+            )),
+        }
+    }
+}
+
+pub fn dump_program(program: &Program, format: DumpFormat) -> Result<String, String> {
+    match format {
+        DumpFormat::Json => serde_json::to_string_pretty(program)
+            .map(|json| json + "\n")
+            .map_err(|err| err.to_string()),
+        DumpFormat::Sexp => node::to_node(program)
+            .map(|node| render::sexp(&node))
+            .map_err(|err| err.to_string()),
+        DumpFormat::Tree => node::to_node(program)
+            .map(|node| render::tree(&node))
+            .map_err(|err| err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use simpla_parser::syntax_tree::{Kind, Program, StatType};
+    use simpla_parser::ProgramParser;
+
+    fn parse(code: &str) -> Program {
+        ProgramParser::new().parse(code).unwrap()
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("sexp".parse(), Ok(DumpFormat::Sexp));
+        assert!("xml".parse::<DumpFormat>().is_err());
+    }
+
+    #[test]
+    fn test_dump_json() {
+        let program = parse("body halt(0); end.");
+        if let StatType::HaltStat(expr) = &program.body[0].stat {
+            *expr.kind.borrow_mut() = Some(Kind::Int);
+        }
+        let json = dump_program(&program, DumpFormat::Json).unwrap();
+        assert!(json.contains("\"HaltStat\": {"));
+        assert!(json.contains("\"begin\": 10"));
+        assert!(json.contains("\"kind\": \"Int\""));
+    }
+
+    #[test]
+    fn test_dump_sexp() {
+        let program = parse("body x = -y; end.");
+        let expected = "(Program\n \
+            :imports ()\n \
+            :global_vars ()\n \
+            :functions ()\n \
+            :body ((Stat\n         \
+            :loc (Location :begin 5 :end 11)\n         \
+            :stat (AssignStat\n                \
+            :id \"x\"\n                \
+            :expr (Expr\n                       \
+            :loc (Location :begin 9 :end 11)\n                       \
+            :expr (Factor\n                              \
+            :fact (UnaryOp\n                                     \
+            (Minus (Factor :fact (Id \"y\") :kind nil)))\n                              \
+            :kind nil)\n                       \
+            :kind nil\n                       \
+            :coercion nil)))))\n";
+        assert_eq!(dump_program(&program, DumpFormat::Sexp).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_dump_tree() {
+        let program = parse("body halt(0); end.");
+        if let StatType::HaltStat(expr) = &program.body[0].stat {
+            *expr.kind.borrow_mut() = Some(Kind::Int);
+        }
+        let expected = "\
+Program
+  imports: []
+  global_vars: []
+  functions: []
+  body:
+    - Stat
+      loc: 5..12
+      stat: HaltStat
+        Expr
+          loc: 10..11
+          expr: Factor
+            fact: Const
+              IntConst 0
+            kind: nil
+          kind: Int
+          coercion: nil
+";
+        assert_eq!(dump_program(&program, DumpFormat::Tree).unwrap(), expected);
+    }
+}
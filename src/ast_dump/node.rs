@@ -0,0 +1,362 @@
+use serde::ser::{self, Serialize};
+use std::fmt;
+
+/*
+    Generic shape of a serialized tree: unlike a JSON value
+    it keeps the names of structs and enum variants, that
+    the S-expression and the tree dumps print as node heads.
+    Sequences have no name, their children have no field.
+*/
+#[derive(Debug, PartialEq)]
+pub enum Node {
+    Atom(String),
+    Tree {
+        name: Option<&'static str>,
+        children: Vec<(Option<&'static str>, Node)>,
+    },
+}
+
+impl Node {
+    fn named(name: &'static str) -> Self {
+        Self::Tree {
+            name: Some(name),
+            children: Vec::new(),
+        }
+    }
+
+    fn sequence() -> Self {
+        Self::Tree {
+            name: None,
+            children: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, field: Option<&'static str>, node: Node) {
+        if let Self::Tree { children, .. } = self {
+            children.push((field, node));
+        }
+    }
+}
+
+pub fn to_node<T: ?Sized + Serialize>(value: &T) -> Result<Node, NodeError> {
+    value.serialize(NodeSerializer)
+}
+
+#[derive(Debug)]
+pub struct NodeError(String);
+
+impl fmt::Display for NodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for NodeError {}
+
+impl ser::Error for NodeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+struct NodeSerializer;
+
+/*
+    Collects the children of a compound value; a newtype
+    variant wrapping a struct of the same name, like
+    StatType::IfStat(IfStat), becomes a single node.
+*/
+pub struct NodeBuilder {
+    node: Node,
+}
+
+impl NodeBuilder {
+    fn new(node: Node) -> Self {
+        Self { node }
+    }
+
+    fn add<T: ?Sized + Serialize>(
+        &mut self,
+        field: Option<&'static str>,
+        value: &T,
+    ) -> Result<(), NodeError> {
+        let child = to_node(value)?;
+        self.node.push(field, child);
+        Ok(())
+    }
+}
+
+fn atom<T: fmt::Display>(value: T) -> Result<Node, NodeError> {
+    Ok(Node::Atom(value.to_string()))
+}
+
+impl ser::Serializer for NodeSerializer {
+    type Ok = Node;
+    type Error = NodeError;
+    type SerializeSeq = NodeBuilder;
+    type SerializeTuple = NodeBuilder;
+    type SerializeTupleStruct = NodeBuilder;
+    type SerializeTupleVariant = NodeBuilder;
+    type SerializeMap = NodeBuilder;
+    type SerializeStruct = NodeBuilder;
+    type SerializeStructVariant = NodeBuilder;
+
+    fn serialize_bool(self, v: bool) -> Result<Node, NodeError> {
+        atom(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Node, NodeError> {
+        atom(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Node, NodeError> {
+        atom(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Node, NodeError> {
+        atom(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Node, NodeError> {
+        atom(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Node, NodeError> {
+        atom(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Node, NodeError> {
+        atom(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Node, NodeError> {
+        atom(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Node, NodeError> {
+        atom(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Node, NodeError> {
+        atom(format!("{:?}", v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Node, NodeError> {
+        atom(format!("{:?}", v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Node, NodeError> {
+        atom(format!("{:?}", v))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Node, NodeError> {
+        atom(format!("{:?}", v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Node, NodeError> {
+        atom(format!("{:?}", v))
+    }
+
+    fn serialize_none(self) -> Result<Node, NodeError> {
+        atom("nil")
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Node, NodeError> {
+        to_node(value)
+    }
+
+    fn serialize_unit(self) -> Result<Node, NodeError> {
+        atom("nil")
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Node, NodeError> {
+        atom(name)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Node, NodeError> {
+        atom(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Node, NodeError> {
+        to_node(value)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Node, NodeError> {
+        match to_node(value)? {
+            Node::Tree {
+                name: Some(name),
+                children,
+            } if name == variant => Ok(Node::Tree {
+                name: Some(name),
+                children,
+            }),
+            inner => {
+                let mut node = Node::named(variant);
+                node.push(None, inner);
+                Ok(node)
+            }
+        }
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<NodeBuilder, NodeError> {
+        Ok(NodeBuilder::new(Node::sequence()))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<NodeBuilder, NodeError> {
+        Ok(NodeBuilder::new(Node::sequence()))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<NodeBuilder, NodeError> {
+        Ok(NodeBuilder::new(Node::named(name)))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<NodeBuilder, NodeError> {
+        Ok(NodeBuilder::new(Node::named(variant)))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<NodeBuilder, NodeError> {
+        Err(ser::Error::custom("maps are not part of the syntax tree"))
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<NodeBuilder, NodeError> {
+        Ok(NodeBuilder::new(Node::named(name)))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<NodeBuilder, NodeError> {
+        Ok(NodeBuilder::new(Node::named(variant)))
+    }
+}
+
+impl ser::SerializeSeq for NodeBuilder {
+    type Ok = Node;
+    type Error = NodeError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), NodeError> {
+        self.add(None, value)
+    }
+
+    fn end(self) -> Result<Node, NodeError> {
+        Ok(self.node)
+    }
+}
+
+impl ser::SerializeTuple for NodeBuilder {
+    type Ok = Node;
+    type Error = NodeError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), NodeError> {
+        self.add(None, value)
+    }
+
+    fn end(self) -> Result<Node, NodeError> {
+        Ok(self.node)
+    }
+}
+
+impl ser::SerializeTupleStruct for NodeBuilder {
+    type Ok = Node;
+    type Error = NodeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), NodeError> {
+        self.add(None, value)
+    }
+
+    fn end(self) -> Result<Node, NodeError> {
+        Ok(self.node)
+    }
+}
+
+impl ser::SerializeTupleVariant for NodeBuilder {
+    type Ok = Node;
+    type Error = NodeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), NodeError> {
+        self.add(None, value)
+    }
+
+    fn end(self) -> Result<Node, NodeError> {
+        Ok(self.node)
+    }
+}
+
+impl ser::SerializeMap for NodeBuilder {
+    type Ok = Node;
+    type Error = NodeError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, _key: &T) -> Result<(), NodeError> {
+        unreachable!("maps are rejected by serialize_map")
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, _value: &T) -> Result<(), NodeError> {
+        unreachable!("maps are rejected by serialize_map")
+    }
+
+    fn end(self) -> Result<Node, NodeError> {
+        Ok(self.node)
+    }
+}
+
+impl ser::SerializeStruct for NodeBuilder {
+    type Ok = Node;
+    type Error = NodeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), NodeError> {
+        self.add(Some(key), value)
+    }
+
+    fn end(self) -> Result<Node, NodeError> {
+        Ok(self.node)
+    }
+}
+
+impl ser::SerializeStructVariant for NodeBuilder {
+    type Ok = Node;
+    type Error = NodeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), NodeError> {
+        self.add(Some(key), value)
+    }
+
+    fn end(self) -> Result<Node, NodeError> {
+        Ok(self.node)
+    }
+}
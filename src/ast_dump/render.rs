@@ -0,0 +1,137 @@
+use super::node::Node;
+
+const LINE_WIDTH: usize = 80;
+
+/*
+    Prints a node as an S-expression: the name of the node
+    comes first, fields are introduced by a keyword. A node
+    that does not fit in a line has one child per line.
+*/
+pub fn sexp(node: &Node) -> String {
+    let mut output = String::new();
+    write_sexp(node, 0, &mut output);
+    output.push('\n');
+    output
+}
+
+fn write_sexp(node: &Node, indent: usize, output: &mut String) {
+    let flat = flat_sexp(node);
+    let children = match node {
+        Node::Tree { children, .. } if indent + flat.len() > LINE_WIDTH => children,
+        _ => {
+            output.push_str(&flat);
+            return;
+        }
+    };
+    output.push('(');
+    if let Node::Tree {
+        name: Some(name), ..
+    } = node
+    {
+        output.push_str(name);
+    }
+    for (index, (field, child)) in children.iter().enumerate() {
+        if index > 0 || has_name(node) {
+            output.push('\n');
+            output.push_str(&" ".repeat(indent + 1));
+        }
+        let mut child_indent = indent + 1;
+        if let Some(field) = field {
+            output.push(':');
+            output.push_str(field);
+            output.push(' ');
+            child_indent += field.len() + 2;
+        }
+        write_sexp(child, child_indent, output);
+    }
+    output.push(')');
+}
+
+fn flat_sexp(node: &Node) -> String {
+    match node {
+        Node::Atom(atom) => atom.clone(),
+        Node::Tree { name, children } => {
+            let mut parts: Vec<String> = name.iter().map(|name| name.to_string()).collect();
+            for (field, child) in children {
+                match field {
+                    Some(field) => parts.push(format!(":{} {}", field, flat_sexp(child))),
+                    None => parts.push(flat_sexp(child)),
+                }
+            }
+            format!("({})", parts.join(" "))
+        }
+    }
+}
+
+fn has_name(node: &Node) -> bool {
+    matches!(node, Node::Tree { name: Some(_), .. })
+}
+
+/*
+    Prints a node as an indented outline, one field per
+    line; locations are shortened to begin..end and a
+    variant holding a single value is kept on one line.
+*/
+pub fn tree(node: &Node) -> String {
+    let mut output = String::new();
+    write_tree("", node, 0, &mut output);
+    output
+}
+
+fn write_tree(prefix: &str, node: &Node, indent: usize, output: &mut String) {
+    output.push_str(&" ".repeat(indent));
+    match node {
+        Node::Atom(atom) => {
+            output.push_str(prefix);
+            output.push_str(atom);
+            output.push('\n');
+        }
+        Node::Tree { children, .. } if children.is_empty() && !has_name(node) => {
+            output.push_str(prefix);
+            output.push_str("[]\n");
+        }
+        Node::Tree { name, children } => {
+            if let Some(span) = location(node) {
+                output.push_str(prefix);
+                output.push_str(&span);
+                output.push('\n');
+                return;
+            }
+            match (name, children.as_slice()) {
+                (Some(name), [(None, Node::Atom(atom))]) => {
+                    output.push_str(&format!("{}{} {}\n", prefix, name, atom));
+                    return;
+                }
+                (Some(name), _) => {
+                    output.push_str(prefix);
+                    output.push_str(name);
+                }
+                (None, _) => output.push_str(prefix.trim_end()),
+            }
+            output.push('\n');
+            for (field, child) in children {
+                let prefix = match field {
+                    Some(field) => format!("{}: ", field),
+                    None if name.is_none() => "- ".to_owned(),
+                    None => String::new(),
+                };
+                write_tree(&prefix, child, indent + 2, output);
+            }
+        }
+    }
+}
+
+fn location(node: &Node) -> Option<String> {
+    match node {
+        Node::Tree {
+            name: Some("Location"),
+            children,
+        } => match children.as_slice() {
+            [(Some("begin"), Node::Atom(begin)), (Some("end"), Node::Atom(end))] => {
+                Some(format!("{}..{}", begin, end))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,135 @@
+use simpla_parser::syntax_tree::Kind;
+
+/*
+    Built-in functions are implemented natively by the
+    virtual machine and called with CALLB <id>, where
+    id is the position of the overload in BUILT_IN_FUNCTIONS.
+    A user defined function with the same name shadows
+    every overload of the built-in one.
+*/
+
+pub struct BuiltInFunction {
+    pub name: &'static str,
+    pub params: &'static [Kind],
+    pub kind: Kind,
+}
+
+impl BuiltInFunction {
+    const fn new(name: &'static str, params: &'static [Kind], kind: Kind) -> Self {
+        Self { name, params, kind }
+    }
+}
+
+static BUILT_IN_FUNCTIONS: &[BuiltInFunction] = &[
+    BuiltInFunction::new("sqrt", &[Kind::Real], Kind::Real),
+    BuiltInFunction::new("abs", &[Kind::Int], Kind::Int),
+    BuiltInFunction::new("abs", &[Kind::Real], Kind::Real),
+    BuiltInFunction::new("sin", &[Kind::Real], Kind::Real),
+    BuiltInFunction::new("cos", &[Kind::Real], Kind::Real),
+    BuiltInFunction::new("exp", &[Kind::Real], Kind::Real),
+    BuiltInFunction::new("log", &[Kind::Real], Kind::Real),
+    BuiltInFunction::new("floor", &[Kind::Real], Kind::Int),
+    BuiltInFunction::new("ceil", &[Kind::Real], Kind::Int),
+    BuiltInFunction::new("round", &[Kind::Real], Kind::Int),
+    BuiltInFunction::new("min", &[Kind::Int, Kind::Int], Kind::Int),
+    BuiltInFunction::new("min", &[Kind::Real, Kind::Real], Kind::Real),
+    BuiltInFunction::new("max", &[Kind::Int, Kind::Int], Kind::Int),
+    BuiltInFunction::new("max", &[Kind::Real, Kind::Real], Kind::Real),
+    BuiltInFunction::new("abs", &[Kind::Long], Kind::Long),
+    BuiltInFunction::new("min", &[Kind::Long, Kind::Long], Kind::Long),
+    BuiltInFunction::new("max", &[Kind::Long, Kind::Long], Kind::Long),
+    BuiltInFunction::new("ord", &[Kind::Char], Kind::Int),
+    BuiltInFunction::new("chr", &[Kind::Int], Kind::Char),
+    BuiltInFunction::new("length", &[Kind::Str], Kind::Int),
+];
+
+pub fn is_built_in(name: &str) -> bool {
+    BUILT_IN_FUNCTIONS.iter().any(|func| func.name == name)
+}
+
+// every name once, in alphabetical order
+pub fn built_in_names() -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = BUILT_IN_FUNCTIONS.iter().map(|func| func.name).collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+pub fn get_overloads(name: &str) -> impl Iterator<Item = &'static BuiltInFunction> + '_ {
+    BUILT_IN_FUNCTIONS
+        .iter()
+        .filter(move |func| func.name == name)
+}
+
+pub fn find_built_in(name: &str, args: &[Kind]) -> Option<(usize, &'static BuiltInFunction)> {
+    BUILT_IN_FUNCTIONS
+        .iter()
+        .enumerate()
+        .find(|(_, func)| func.name == name && func.params == args)
+}
+
+pub fn find_promoted_built_in(
+    name: &str,
+    args: &[Kind],
+) -> Option<(usize, &'static BuiltInFunction)> {
+    BUILT_IN_FUNCTIONS.iter().enumerate().find(|(_, func)| {
+        func.name == name
+            && func.params.len() == args.len()
+            && func
+                .params
+                .iter()
+                .zip(args)
+                .all(|(param, arg)| param == arg || (param, arg) == (&Kind::Real, &Kind::Int))
+    })
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_find_built_in() {
+        let (abs_int, func) = find_built_in("abs", &[Kind::Int]).unwrap();
+        assert_eq!(func.kind, Kind::Int);
+        let (abs_real, func) = find_built_in("abs", &[Kind::Real]).unwrap();
+        assert_eq!(func.kind, Kind::Real);
+        assert_ne!(abs_int, abs_real);
+
+        let (_, func) = find_built_in("abs", &[Kind::Long]).unwrap();
+        assert_eq!(func.kind, Kind::Long);
+
+        let (_, func) = find_built_in("ord", &[Kind::Char]).unwrap();
+        assert_eq!(func.kind, Kind::Int);
+        let (_, func) = find_built_in("chr", &[Kind::Int]).unwrap();
+        assert_eq!(func.kind, Kind::Char);
+
+        let (_, func) = find_built_in("round", &[Kind::Real]).unwrap();
+        assert_eq!(func.kind, Kind::Int);
+
+        assert!(find_built_in("sqrt", &[Kind::Int]).is_none());
+        assert!(find_built_in("max", &[Kind::Int, Kind::Real]).is_none());
+        assert!(find_built_in("min", &[Kind::Int]).is_none());
+        assert!(find_built_in("factorial", &[Kind::Int]).is_none());
+    }
+
+    #[test]
+    fn test_find_promoted_built_in() {
+        let (_, func) = find_promoted_built_in("sqrt", &[Kind::Int]).unwrap();
+        assert_eq!(func.params, &[Kind::Real]);
+        let (_, func) = find_promoted_built_in("max", &[Kind::Int, Kind::Real]).unwrap();
+        assert_eq!(func.kind, Kind::Real);
+        let (_, func) = find_promoted_built_in("abs", &[Kind::Int]).unwrap();
+        assert_eq!(func.kind, Kind::Int);
+        assert!(find_promoted_built_in("floor", &[Kind::Str]).is_none());
+    }
+
+    #[test]
+    fn test_overloads() {
+        assert!(is_built_in("sqrt"));
+        assert!(!is_built_in("square_root"));
+        assert_eq!(get_overloads("max").count(), 3);
+        assert_eq!(get_overloads("sin").count(), 1);
+        assert_eq!(get_overloads("fibonacci").count(), 0);
+    }
+}
@@ -0,0 +1,266 @@
+use crate::ast_dump::{self, DumpFormat};
+use crate::generator::{self, ByteCodeFormat, LimitError};
+use crate::loader::{self, LoadedProgram};
+use crate::rename::{self, Position};
+use crate::semantic_analysis::{self, Promotion};
+use crate::xref::{self, GraphFormat};
+use crate::{lsp, repl};
+use extract_line_error::SourceMap;
+use simpla_parser::syntax_tree::{Location, Program};
+use simpla_parser::ProgramParser;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/*
+    The options of a compilation, set like on the command
+    line: by default implicit promotions are forbidden,
+    imports are searched only next to the importing file
+    and the bytecode uses the v1 format.
+*/
+#[derive(Debug, Clone)]
+pub struct Compiler {
+    include_paths: Vec<PathBuf>,
+    promotion: Promotion,
+    format: ByteCodeFormat,
+}
+
+/*
+    A message of the compiler: the text is formatted like
+    the command line prints it, the location is given when
+    the code that caused it is known.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub loc: Option<Location>,
+    pub file: Option<String>,
+    pub line: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/*
+    A parsed program, together with the source of every
+    file it is made of. After the semantic analysis the
+    expressions in the tree carry their kind.
+*/
+pub struct Compilation {
+    pub program: Program,
+    pub sources: SourceMap,
+    pub warnings: Vec<Diagnostic>,
+    format: ByteCodeFormat,
+    checked: bool,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self {
+            include_paths: Vec::new(),
+            promotion: Promotion::Forbid,
+            format: ByteCodeFormat::V1,
+        }
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn include_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.include_paths.push(path.into());
+        self
+    }
+
+    pub fn promotion(mut self, promotion: Promotion) -> Self {
+        self.promotion = promotion;
+        self
+    }
+
+    pub fn format(mut self, format: ByteCodeFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    // the imports are loaded, but the program is not checked
+    pub fn parse_path(&self, path: &Path) -> Result<Compilation, Diagnostic> {
+        let code = loader::read_file(path).map_err(Diagnostic::unlocated)?;
+        self.parse_source(path, code)
+    }
+
+    /*
+        The path names the file in the messages and is
+        where relative imports are searched; it has to
+        exist only when the code imports something.
+    */
+    pub fn parse_source(&self, path: &Path, code: String) -> Result<Compilation, Diagnostic> {
+        let program = match ProgramParser::new().parse(&code) {
+            Ok(program) => program,
+            Err(err) => {
+                let loc = simpla_parser::syntax_error_location(&err);
+                let mut sources = SourceMap::new();
+                sources.add_file(loader::file_name(path), code.clone());
+                let message = simpla_parser::format_syntax_error(&code, err);
+                return Err(Diagnostic::new(Severity::Error, message, loc, &sources));
+            }
+        };
+        let loaded = loader::link_program(path, program, code, &self.include_paths)
+            .map_err(Diagnostic::unlocated)?;
+        Ok(self.compilation(loaded))
+    }
+
+    pub fn check_path(&self, path: &Path) -> Result<Compilation, Diagnostic> {
+        let compilation = self.parse_path(path)?;
+        self.check(compilation)
+    }
+
+    pub fn check_source(&self, path: &Path, code: String) -> Result<Compilation, Diagnostic> {
+        let compilation = self.parse_source(path, code)?;
+        self.check(compilation)
+    }
+
+    fn check(&self, mut compilation: Compilation) -> Result<Compilation, Diagnostic> {
+        let analysis = semantic_analysis::analyze_program(
+            &compilation.program,
+            &compilation.sources,
+            self.promotion,
+        );
+        match analysis {
+            Ok(conversions) => {
+                compilation.checked = true;
+                compilation.warnings = conversions
+                    .iter()
+                    .map(|conversion| {
+                        let message = conversion.format_warning(&compilation.sources);
+                        let loc = conversion.loc.clone();
+                        Diagnostic::new(Severity::Warning, message, loc, &compilation.sources)
+                    })
+                    .collect();
+                Ok(compilation)
+            }
+            Err(err) => Err(match err.loc {
+                Some(loc) => {
+                    Diagnostic::new(Severity::Error, err.message, loc, &compilation.sources)
+                }
+                None => Diagnostic::unlocated(err.message),
+            }),
+        }
+    }
+
+    // returns the number of renamed occurrences
+    pub fn rename_file(&self, path: &Path, at: Position, to: &str) -> Result<usize, String> {
+        rename::rename_file(self, path, at, to)
+    }
+
+    // serves the language server protocol on stdin and stdout
+    pub fn run_language_server(&self) -> Result<(), String> {
+        lsp::run_server(self.include_paths.clone(), self.promotion)
+    }
+
+    // returns the exit code given to halt, or zero
+    pub fn run_repl(&self) -> Result<i32, String> {
+        repl::run_repl(self.promotion)
+    }
+
+    fn compilation(&self, loaded: LoadedProgram) -> Compilation {
+        Compilation {
+            program: loaded.program,
+            sources: loaded.sources,
+            warnings: Vec::new(),
+            format: self.format,
+            checked: false,
+        }
+    }
+}
+
+impl Compilation {
+    /*
+        Only a checked program has the kinds the translation
+        needs. Every operand limit exceeded by the code gives
+        its own error, at the code that exceeded it.
+    */
+    pub fn bytecode(&self) -> Result<Vec<u8>, Vec<Diagnostic>> {
+        self.require_checked("the translation")
+            .map_err(|err| vec![err])?;
+        generator::translate_to_byte_code(&self.program, &self.sources, self.format).map_err(
+            |errors| {
+                errors
+                    .into_iter()
+                    .map(|err| self.limit_diagnostic(err))
+                    .collect()
+            },
+        )
+    }
+
+    pub fn dump_ast(&self, format: DumpFormat) -> Result<String, String> {
+        ast_dump::dump_program(&self.program, format)
+    }
+
+    // every name of a checked program resolves to a declaration
+    pub fn cross_reference(&self) -> Result<String, Diagnostic> {
+        self.require_checked("the cross reference")?;
+        let entries = xref::cross_reference(&self.program);
+        Ok(xref::format_cross_reference(&entries, &self.sources))
+    }
+
+    pub fn call_graph(&self, format: GraphFormat) -> Result<String, Diagnostic> {
+        self.require_checked("the call graph")?;
+        let entries = xref::cross_reference(&self.program);
+        Ok(xref::call_graph(&entries, format))
+    }
+
+    fn require_checked(&self, action: &str) -> Result<(), Diagnostic> {
+        if self.checked {
+            Ok(())
+        } else {
+            let message = format!("the program must be checked before {}", action);
+            Err(Diagnostic::unlocated(message))
+        }
+    }
+
+    // shown with the code that exceeded the limit, like the semantic errors
+    fn limit_diagnostic(&self, err: LimitError) -> Diagnostic {
+        match &err.loc {
+            Some(loc) => {
+                let code = self.sources.extract_error_code(loc.begin, loc.end);
+                let message = format!("{}\n{}", err, code);
+                Diagnostic::new(Severity::Error, message, loc.clone(), &self.sources)
+            }
+            None => Diagnostic::unlocated(err.to_string()),
+        }
+    }
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, message: String, loc: Location, sources: &SourceMap) -> Self {
+        Self {
+            severity,
+            message,
+            file: Some(sources.file_name(loc.begin).to_owned()),
+            line: Some(sources.line_number(loc.begin)),
+            loc: Some(loc),
+        }
+    }
+
+    fn unlocated(message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+            loc: None,
+            file: None,
+            line: None,
+        }
+    }
+}
+
+// the message, which already shows the code at the location
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
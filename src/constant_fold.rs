@@ -0,0 +1,135 @@
+use simpla_parser::syntax_tree::{
+    CastExpr, Const, Expr, ExprTree, Factor, FactorValue, Kind, Operator, UnaryOp,
+};
+use std::convert::TryFrom;
+
+/*
+    Folding of integer and long constant expressions:
+    an expression made only of literals, parenthesis,
+    unary minus and arithmetic operators is evaluated at
+    compile time with overflow checks, so that the semantic
+    check can reject it and the generator can emit a single
+    constant. Folding must run after the type check, because
+    it follows the implicit conversions recorded on the tree.
+*/
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ConstValue {
+    Int(i32),
+    Long(i64),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FoldError {
+    Overflow,
+    DivisionByZero,
+}
+
+impl ConstValue {
+    pub fn to_const(self) -> Const {
+        match self {
+            Self::Int(value) => Const::IntConst(value),
+            Self::Long(value) => Const::LongConst(value),
+        }
+    }
+
+    fn widen(self, target: &Kind) -> Self {
+        match (self, target) {
+            (Self::Int(value), Kind::Long) => Self::Long(value.into()),
+            (value, _) => value,
+        }
+    }
+}
+
+/*
+    Returns None when the expression is not constant.
+    The implicit conversion of the expression itself is
+    not applied, the conversions of its operands are.
+*/
+pub fn fold_expr(expr: &Expr) -> Option<Result<ConstValue, FoldError>> {
+    match &expr.expr {
+        ExprTree::Node(left, op, right) => fold_node(left, op, right),
+        ExprTree::Factor(fact) => fold_factor(fact),
+    }
+}
+
+fn fold_operand(expr: &Expr) -> Option<Result<ConstValue, FoldError>> {
+    let value = fold_expr(expr)?;
+    match expr.coercion.borrow().as_ref() {
+        Some(Kind::Long) => Some(value.map(|value| value.widen(&Kind::Long))),
+        Some(_) => None,
+        None => Some(value),
+    }
+}
+
+fn fold_node(left: &Expr, op: &Operator, right: &Expr) -> Option<Result<ConstValue, FoldError>> {
+    if !matches!(
+        op,
+        Operator::Add | Operator::Sub | Operator::Mul | Operator::Div
+    ) {
+        return None;
+    }
+    let left = fold_operand(left)?;
+    let right = fold_operand(right)?;
+    Some(left.and_then(|left| right.and_then(|right| apply_operator(left, op, right))))
+}
+
+/*
+    Operands are evaluated in i128, that cannot overflow for
+    i64 operands, and the result is narrowed back to their kind.
+*/
+fn apply_operator(
+    left: ConstValue,
+    op: &Operator,
+    right: ConstValue,
+) -> Result<ConstValue, FoldError> {
+    match (left, right) {
+        (ConstValue::Int(l), ConstValue::Int(r)) => {
+            let value = wide_operation(l.into(), op, r.into())?;
+            i32::try_from(value)
+                .map(ConstValue::Int)
+                .map_err(|_| FoldError::Overflow)
+        }
+        (ConstValue::Long(l), ConstValue::Long(r)) => {
+            let value = wide_operation(l.into(), op, r.into())?;
+            i64::try_from(value)
+                .map(ConstValue::Long)
+                .map_err(|_| FoldError::Overflow)
+        }
+        _ => unreachable!("operands of different kinds"),
+    }
+}
+
+fn wide_operation(left: i128, op: &Operator, right: i128) -> Result<i128, FoldError> {
+    match op {
+        Operator::Add => Ok(left + right),
+        Operator::Sub => Ok(left - right),
+        Operator::Mul => Ok(left * right),
+        Operator::Div if right == 0 => Err(FoldError::DivisionByZero),
+        Operator::Div => Ok(left / right),
+        _ => unreachable!(),
+    }
+}
+
+fn fold_factor(fact: &Factor) -> Option<Result<ConstValue, FoldError>> {
+    match &fact.fact {
+        FactorValue::Const(Const::IntConst(value)) => Some(Ok(ConstValue::Int(*value))),
+        FactorValue::Const(Const::LongConst(value)) => Some(Ok(ConstValue::Long(*value))),
+        FactorValue::HighPrecedence(expr) => fold_operand(expr),
+        FactorValue::UnaryOp(UnaryOp::Minus(fact)) => {
+            let value = fold_factor(fact)?;
+            Some(value.and_then(|value| {
+                match value {
+                    ConstValue::Int(v) => v.checked_neg().map(ConstValue::Int),
+                    ConstValue::Long(v) => v.checked_neg().map(ConstValue::Long),
+                }
+                .ok_or(FoldError::Overflow)
+            }))
+        }
+        FactorValue::CastExpr(CastExpr::Long(expr)) => {
+            let value = fold_operand(expr)?;
+            Some(value.map(|value| value.widen(&Kind::Long)))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,234 @@
+mod render;
+
+use crate::loader;
+use extract_line_error::SourceMap;
+use simpla_parser::printer::{kind_name, signature};
+use simpla_parser::syntax_tree::{FuncDeclList, VarDeclList};
+use simpla_parser::{LibraryParser, ProgramParser};
+use std::path::Path;
+use std::str::FromStr;
+
+/*
+    Reference pages of the global variables and of the
+    functions declared in a file, with the text of their
+    ## doc comments. Libraries are documented like programs,
+    the imported files are not part of the page.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocFormat {
+    Markdown,
+    Html,
+}
+
+impl FromStr for DocFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "markdown" | "md" => Ok(Self::Markdown),
+            "html" => Ok(Self::Html),
+            other => Err(format!(
+                "unknown doc format {}, expected markdown or html",
+                other
+            )),
+        }
+    }
+}
+
+// a documented declaration, in the order of the file
+struct Entry {
+    title: String,
+    params: Vec<Param>,
+    returns: Option<String>,
+    doc: Option<String>,
+    location: String,
+}
+
+struct Param {
+    name: String,
+    kind: String,
+    by_ref: bool,
+}
+
+struct Page {
+    title: String,
+    globals: Vec<Entry>,
+    functions: Vec<Entry>,
+}
+
+pub fn document_file(path: &Path, format: DocFormat) -> Result<String, String> {
+    let code = loader::read_file(path)?;
+    document(&loader::file_name(path), code, format)
+}
+
+/*
+    The code may be a program or a library: when both
+    parsers fail, the error reported is the one found
+    further in the code.
+*/
+pub fn document(file_name: &str, code: String, format: DocFormat) -> Result<String, String> {
+    let (global_vars, functions) = match ProgramParser::new().parse(&code) {
+        Ok(program) => (program.global_vars, program.functions),
+        Err(program_err) => match LibraryParser::new().parse(&code) {
+            Ok(library) => (library.global_vars, library.functions),
+            Err(library_err) => {
+                let program_end = simpla_parser::syntax_error_location(&program_err).end;
+                let library_end = simpla_parser::syntax_error_location(&library_err).end;
+                let err = if library_end > program_end {
+                    library_err
+                } else {
+                    program_err
+                };
+                return Err(simpla_parser::format_syntax_error(&code, err));
+            }
+        },
+    };
+    let mut sources = SourceMap::new();
+    sources.add_file(file_name.to_owned(), code);
+    let page = Page {
+        title: file_name.to_owned(),
+        globals: global_entries(&global_vars, &sources),
+        functions: function_entries(&functions, &sources),
+    };
+    Ok(match format {
+        DocFormat::Markdown => render::markdown(&page),
+        DocFormat::Html => render::html(&page),
+    })
+}
+
+fn location(offset: usize, sources: &SourceMap) -> String {
+    format!(
+        "{}:{}",
+        sources.file_name(offset),
+        sources.line_number(offset)
+    )
+}
+
+fn global_entries(global_vars: &VarDeclList, sources: &SourceMap) -> Vec<Entry> {
+    global_vars
+        .iter()
+        .map(|var| Entry {
+            title: format!("{}: {}", var.id_list.join(", "), kind_name(&var.kind)),
+            params: Vec::new(),
+            returns: None,
+            doc: var.doc.clone(),
+            location: location(var.loc.begin, sources),
+        })
+        .collect()
+}
+
+fn function_entries(functions: &FuncDeclList, sources: &SourceMap) -> Vec<Entry> {
+    functions
+        .iter()
+        .map(|function| Entry {
+            title: signature(function),
+            params: function
+                .params
+                .iter()
+                .map(|param| Param {
+                    name: param.id.clone(),
+                    kind: kind_name(&param.kind),
+                    by_ref: param.by_ref,
+                })
+                .collect(),
+            returns: Some(kind_name(&function.kind)),
+            doc: function.doc.clone(),
+            location: location(function.loc.begin, sources),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    const CODE: &str = "## Scale applied by every function.
+scale: real;
+
+## Area of the circle of radius r.
+## The radius is multiplied by scale first.
+func area(r: real): real
+body
+    return 3.14 * r * r * scale;
+end;
+
+func swap(var a: integer, var b: integer): void
+    t: integer;
+body
+    t = a; a = b; b = t;
+end;
+";
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("md".parse(), Ok(DocFormat::Markdown));
+        assert_eq!("html".parse(), Ok(DocFormat::Html));
+        assert!("pdf".parse::<DocFormat>().is_err());
+    }
+
+    #[test]
+    fn test_markdown() {
+        let page = document("circle.simpla", CODE.to_owned(), DocFormat::Markdown).unwrap();
+        let expected = "\
+# circle.simpla
+
+## Global variables
+
+### `scale: real`
+
+Scale applied by every function.
+
+Defined at circle.simpla:2
+
+## Functions
+
+### `func area(r: real): real`
+
+Area of the circle of radius r.
+The radius is multiplied by scale first.
+
+Parameters:
+
+- `r`: real
+
+Returns: real
+
+Defined at circle.simpla:6
+
+### `func swap(var a: integer, var b: integer): void`
+
+Parameters:
+
+- `a`: integer, by reference
+- `b`: integer, by reference
+
+Returns: void
+
+Defined at circle.simpla:11
+";
+        assert_eq!(page, expected);
+    }
+
+    #[test]
+    fn test_html() {
+        let code = format!("{}body\n    writeln(area(1.0) < 4.0);\nend.\n", CODE);
+        let page = document("circle.simpla", code, DocFormat::Html).unwrap();
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("<title>circle.simpla</title>"));
+        assert!(page.contains("<code>func swap(var a: integer, var b: integer): void</code>"));
+        assert!(page.contains("<p>Area of the circle of radius r.\nThe radius"));
+        assert!(page.contains("<li><code>r</code>: real</li>"));
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        // neither a program nor a library
+        let err = document(
+            "bad.simpla",
+            "x: integer;\nx = 1;".to_owned(),
+            DocFormat::Html,
+        );
+        assert!(err.unwrap_err().contains("x = 1;"));
+    }
+}
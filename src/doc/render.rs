@@ -0,0 +1,101 @@
+use super::{Entry, Page};
+
+/*
+    Markdown keeps the doc comments as they are written,
+    so instructors can use Markdown inside them.
+*/
+pub fn markdown(page: &Page) -> String {
+    let mut output = format!("# {}\n", page.title);
+    for (heading, entries) in sections(page) {
+        output.push_str(&format!("\n## {}\n", heading));
+        for entry in entries {
+            output.push_str(&format!("\n### `{}`\n", entry.title));
+            if let Some(doc) = &entry.doc {
+                output.push_str(&format!("\n{}\n", doc));
+            }
+            if !entry.params.is_empty() {
+                output.push_str("\nParameters:\n\n");
+                for param in &entry.params {
+                    let by_ref = if param.by_ref { ", by reference" } else { "" };
+                    output.push_str(&format!("- `{}`: {}{}\n", param.name, param.kind, by_ref));
+                }
+            }
+            if let Some(kind) = &entry.returns {
+                output.push_str(&format!("\nReturns: {}\n", kind));
+            }
+            output.push_str(&format!("\nDefined at {}\n", entry.location));
+        }
+    }
+    output
+}
+
+// a standalone page, the doc comments are plain text
+pub fn html(page: &Page) -> String {
+    let mut output = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    output.push_str(&format!("<title>{}</title>\n", escape(&page.title)));
+    output.push_str(STYLE);
+    output.push_str("</head>\n<body>\n");
+    output.push_str(&format!("<h1>{}</h1>\n", escape(&page.title)));
+    for (heading, entries) in sections(page) {
+        output.push_str(&format!("<h2>{}</h2>\n", heading));
+        for entry in entries {
+            output.push_str(&format!(
+                "<section>\n<h3><code>{}</code></h3>\n",
+                escape(&entry.title)
+            ));
+            if let Some(doc) = &entry.doc {
+                for paragraph in doc.split("\n\n").filter(|p| !p.trim().is_empty()) {
+                    output.push_str(&format!("<p>{}</p>\n", escape(paragraph.trim())));
+                }
+            }
+            if !entry.params.is_empty() {
+                output.push_str("<p>Parameters:</p>\n<ul>\n");
+                for param in &entry.params {
+                    let by_ref = if param.by_ref { ", by reference" } else { "" };
+                    output.push_str(&format!(
+                        "<li><code>{}</code>: {}{}</li>\n",
+                        escape(&param.name),
+                        escape(&param.kind),
+                        by_ref
+                    ));
+                }
+                output.push_str("</ul>\n");
+            }
+            if let Some(kind) = &entry.returns {
+                output.push_str(&format!("<p>Returns: {}</p>\n", escape(kind)));
+            }
+            output.push_str(&format!(
+                "<p class=\"location\">Defined at {}</p>\n</section>\n",
+                escape(&entry.location)
+            ));
+        }
+    }
+    output.push_str("</body>\n</html>\n");
+    output
+}
+
+const STYLE: &str = "<style>
+body { font-family: sans-serif; max-width: 50em; margin: auto; }
+section { border-top: 1px solid #ccc; }
+p { white-space: pre-line; }
+.location { color: #666; font-size: small; }
+</style>
+";
+
+// sections without entries are left out
+fn sections(page: &Page) -> Vec<(&'static str, &[Entry])> {
+    vec![
+        ("Global variables", page.globals.as_slice()),
+        ("Functions", page.functions.as_slice()),
+    ]
+    .into_iter()
+    .filter(|(_, entries)| !entries.is_empty())
+    .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,33 @@
+use simpla_parser::printer::{format_library, format_program};
+use simpla_parser::{LibraryParser, ProgramParser};
+use std::fs;
+use std::path::Path;
+
+/*
+    Rewrites a source file in the canonical layout. Imports are
+    not followed, every file is formatted on its own: a file that
+    is not a program is formatted as a library. In check mode the
+    file is left untouched and an error is returned when its
+    layout is not the canonical one.
+*/
+pub fn format_file(path: &Path, check: bool) -> Result<(), String> {
+    let code = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let formatted = format_code(&code)?;
+    if formatted == code {
+        Ok(())
+    } else if check {
+        Err(format!("{}: not formatted", path.display()))
+    } else {
+        fs::write(path, formatted).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+}
+
+fn format_code(code: &str) -> Result<String, String> {
+    match ProgramParser::new().parse(code) {
+        Ok(program) => Ok(format_program(&program, code)),
+        Err(err) => match LibraryParser::new().parse(code) {
+            Ok(library) => Ok(format_library(&library, code)),
+            Err(_) => Err(simpla_parser::format_syntax_error(code, err)),
+        },
+    }
+}
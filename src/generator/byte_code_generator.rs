@@ -1,85 +1,165 @@
 use super::code_generator::*;
+use super::format::{ByteCodeFormat, Limit, LimitError};
 use super::function_index::FunctionIndex;
 use super::opcode;
 use super::simple_counter::{AddrSize, SimpleCounter};
-use super::var_cache::{ParameterAddress, VarLookup, VariableType};
+use super::var_cache::{function_value_parameters, ParameterAddress, VarLookup, VariableType};
 
+use crate::built_in;
+use crate::constant_fold;
+use extract_line_error::SourceMap;
 use simpla_parser::syntax_tree;
 
-const ADDR_SIZE_ZERO: AddrSize = 0;
-const LOCAL_MASK: AddrSize = 1 << (ADDR_SIZE_ZERO.count_zeros() - 1);
-const MAX_STR_LEN: usize = (1 << (ADDR_SIZE_ZERO.count_zeros())) - 1;
+// reported by the ASSERT on a step that is not a constant
+pub const STEP_MESSAGE: &str = "the step of a for loop must be positive";
+
+struct LoopLabels<'a> {
+    name: Option<&'a str>,
+    // the label of a for loop is allocated by its first continue
+    continue_label: Option<AddrSize>,
+    exit_label: AddrSize,
+    for_loop: bool,
+    open_cases: usize,
+}
 
 pub struct ByteCodeGenerator<'a> {
     buff: Vec<u8>,
     function_index: FunctionIndex<'a>,
     label_counter: SimpleCounter,
-    loop_exit_label: Vec<AddrSize>,
+    loop_labels: Vec<LoopLabels<'a>>,
+    // case statements open outside of any loop
+    open_cases: usize,
     local_cache: VarLookup<'a>,
-    param_addr: ParameterAddress<'a>,
+    param_addr: ParameterAddress,
+    scope: Vec<AddrSize>,
+    sources: &'a SourceMap,
+    format: ByteCodeFormat,
+    errors: Vec<LimitError>,
+    // the code being generated, given to the limit errors
+    loc: Option<&'a syntax_tree::Location>,
+    // the first hidden integer slot of the frame, see step_slots
+    step_base: AddrSize,
+    open_steps: AddrSize,
 }
 
 impl<'a> ByteCodeGenerator<'a> {
     pub fn new(
         function_index: FunctionIndex<'a>,
         local_cache: VarLookup<'a>,
-        param_addr: ParameterAddress<'a>,
+        param_addr: ParameterAddress,
+        sources: &'a SourceMap,
+        format: ByteCodeFormat,
     ) -> Self {
+        let buff = match format {
+            ByteCodeFormat::V1 => Vec::new(),
+            ByteCodeFormat::V2 => vec![opcode::VERSION, format.version()],
+        };
         Self {
-            buff: Vec::new(),
+            buff,
             local_cache,
             function_index,
             label_counter: SimpleCounter::new(),
-            loop_exit_label: Vec::new(),
+            loop_labels: Vec::new(),
+            open_cases: 0,
             param_addr,
+            scope: Vec::new(),
+            sources,
+            format,
+            errors: Vec::new(),
+            loc: None,
+            step_base: 0,
+            open_steps: 0,
         }
     }
+
+    // only the first operand exceeding each limit is reported
+    fn report_limit(&mut self, limit: Limit, value: u64, max: u64) {
+        if self.errors.iter().all(|err| err.limit != limit) {
+            let loc = self.loc.cloned();
+            let err = LimitError::new(limit, value, max, self.format, loc);
+            self.errors.push(err);
+        }
+    }
+
+    fn insert_operand(&mut self, value: AddrSize, limit: Limit) {
+        let max = self.format.max_operand();
+        if value > max {
+            self.report_limit(limit, value.into(), max.into());
+        }
+        self.format.encode(value, &mut self.buff);
+    }
+
+    /*
+        The highest bit of a variable address tells local
+        variables from global ones, so the ids of both kinds
+        must stay below it.
+    */
+    fn insert_variable_id(&mut self, id: AddrSize, local: bool) {
+        let mask = self.format.local_mask();
+        if id >= mask {
+            self.report_limit(Limit::Variables, u64::from(id) + 1, mask.into());
+        }
+        let address = if local { id | mask } else { id };
+        self.format.encode(address, &mut self.buff);
+    }
+
     fn insert_multi_byte_command(&mut self, cmd: u8, data: &[u8]) {
         self.buff.push(cmd);
         self.insert_bytes(data);
     }
 
-    fn insert_address_command(&mut self, cmd: u8, index: AddrSize) {
-        self.insert_multi_byte_command(cmd, &index.to_be_bytes());
+    fn insert_address_command(&mut self, cmd: u8, index: AddrSize, limit: Limit) {
+        self.buff.push(cmd);
+        self.insert_operand(index, limit);
     }
 
     fn insert_uncond_jump(&mut self, index: AddrSize) {
-        self.insert_address_command(opcode::JUMP, index);
+        self.insert_address_command(opcode::JUMP, index, Limit::Labels);
     }
 
     fn insert_false_cond_jump(&mut self, index: AddrSize) {
-        self.insert_address_command(opcode::JNE, index);
+        self.insert_address_command(opcode::JNE, index, Limit::Labels);
     }
 
     fn insert_true_cond_jump(&mut self, index: AddrSize) {
-        self.insert_address_command(opcode::JEQ, index);
+        self.insert_address_command(opcode::JEQ, index, Limit::Labels);
     }
 
     fn insert_label(&mut self, index: AddrSize) {
-        self.insert_address_command(opcode::LBL, index);
+        self.insert_address_command(opcode::LBL, index, Limit::Labels);
     }
 
     fn memory_command<F>(&mut self, name: &str, convert: F)
     where
-        F: Fn(&syntax_tree::Kind) -> u8,
+        F: Fn(&syntax_tree::Kind, &VariableType) -> u8,
     {
         let ((kind, id), ref scope) = self.local_cache.lookup(name);
-        let id = match scope {
-            VariableType::Global => *id,
-            VariableType::Local => *id + LOCAL_MASK,
-        };
-        let cmd = convert(kind);
-        self.insert_multi_byte_command(cmd, &id.to_be_bytes());
+        let cmd = convert(kind, scope);
+        self.buff.push(cmd);
+        self.insert_variable_address(*id, scope);
+    }
+
+    fn insert_variable_address(&mut self, id: AddrSize, scope: &VariableType) {
+        if let VariableType::Enclosing(depth) | VariableType::EnclosingReference(depth) = scope {
+            self.insert_operand(*depth, Limit::NestingDepth);
+        }
+        let local = !matches!(scope, VariableType::Global);
+        self.insert_variable_id(id, local);
     }
 
     fn load_variable(&mut self, name: &str) {
-        self.memory_command(name, load_by_kind);
+        self.memory_command(name, load_by_scope);
     }
 
     fn convert_expression(&mut self, expr: &'a syntax_tree::Expr) {
-        match &expr.expr {
-            syntax_tree::ExprTree::Node(lhs, op, rhs) => self.convert_node(lhs, op, rhs),
-            syntax_tree::ExprTree::Factor(fact) => self.convert_factor(fact),
+        match (constant_fold::fold_expr(expr), &expr.expr) {
+            (Some(Ok(value)), _) => self.convert_constant(&value.to_const()),
+            (_, syntax_tree::ExprTree::Node(lhs, op, rhs)) => self.convert_node(lhs, op, rhs),
+            (_, syntax_tree::ExprTree::Factor(fact)) => self.convert_factor(fact),
+        }
+        if let Some(target) = expr.coercion.borrow().as_ref() {
+            let kind = expr.kind.borrow();
+            self.buff.push(cast_by_kind(kind.as_ref().unwrap(), target));
         }
     }
 
@@ -138,22 +218,28 @@ impl<'a> ByteCodeGenerator<'a> {
     ) {
         self.convert_expression(lhs);
         self.convert_expression(rhs);
-        self.buff
-            .push(operator_by_kind(op, lhs.kind.borrow().as_ref().unwrap()));
+        self.buff.push(operator_by_kind(op, &value_kind(lhs)));
     }
 
     fn convert_factor(&mut self, fact: &'a syntax_tree::Factor) {
         match &fact.fact {
-            syntax_tree::FactorValue::Id(id) => self.load_variable(id),
+            syntax_tree::FactorValue::Id(id) => self.convert_id(id),
             syntax_tree::FactorValue::CastExpr(cast) => self.convert_cast_expr(cast),
             syntax_tree::FactorValue::CondExpr(cond_expr) => self.convert_cond_expr(cond_expr),
             syntax_tree::FactorValue::Const(cons) => self.convert_constant(cons),
             syntax_tree::FactorValue::FuncCall(f_call) => self.convert_func_call(f_call),
             syntax_tree::FactorValue::HighPrecedence(expr) => self.convert_expression(expr),
             syntax_tree::FactorValue::UnaryOp(unary) => self.convert_unary_op(unary),
+            syntax_tree::FactorValue::IndexExpr(index) => self.convert_index_expr(index),
         }
     }
 
+    fn convert_index_expr(&mut self, index: &'a syntax_tree::IndexExpr) {
+        self.load_variable(&index.id);
+        self.convert_expression(&index.index);
+        self.buff.push(opcode::IDXS);
+    }
+
     fn convert_cond_expr(&mut self, cond_expr: &'a syntax_tree::CondExpr) {
         let false_lbl = self.label_counter.count_one();
         let end_lbl = self.label_counter.count_one();
@@ -179,12 +265,14 @@ impl<'a> ByteCodeGenerator<'a> {
     }
 
     fn convert_cast_expr(&mut self, cast: &'a syntax_tree::CastExpr) {
-        let (expr, mode) = match cast {
-            syntax_tree::CastExpr::Integer(expr) => (expr, CastOp::ToInt),
-            syntax_tree::CastExpr::Real(expr) => (expr, CastOp::ToReal),
+        let (expr, target) = match cast {
+            syntax_tree::CastExpr::Integer(expr) => (expr, syntax_tree::Kind::Int),
+            syntax_tree::CastExpr::Long(expr) => (expr, syntax_tree::Kind::Long),
+            syntax_tree::CastExpr::Real(expr) => (expr, syntax_tree::Kind::Real),
+            syntax_tree::CastExpr::Char(expr) => (expr, syntax_tree::Kind::Char),
         };
         self.convert_expression(expr);
-        self.buff.push(mode.get_command());
+        self.buff.push(cast_by_kind(&value_kind(expr), &target));
     }
 
     fn convert_constant(&mut self, const_val: &syntax_tree::Const) {
@@ -192,6 +280,9 @@ impl<'a> ByteCodeGenerator<'a> {
             syntax_tree::Const::IntConst(i) => {
                 self.insert_multi_byte_command(opcode::LDIC, &i.to_be_bytes())
             }
+            syntax_tree::Const::LongConst(l) => {
+                self.insert_multi_byte_command(opcode::LDLC, &l.to_be_bytes())
+            }
             syntax_tree::Const::RealConst(r) => {
                 self.insert_multi_byte_command(opcode::LDRC, &r.to_be_bytes())
             }
@@ -199,18 +290,24 @@ impl<'a> ByteCodeGenerator<'a> {
                 self.insert_multi_byte_command(opcode::LDBC, &[if *b { 255 } else { 0 }])
             }
             syntax_tree::Const::StrConst(s) => self.insert_string(s),
+            syntax_tree::Const::CharConst(c) => {
+                self.insert_multi_byte_command(opcode::LDCHC, &u32::from(*c).to_be_bytes())
+            }
         }
     }
 
     fn insert_string(&mut self, s: &str) {
-        let str_bytes = truncate_str_to_byte_len(s, MAX_STR_LEN);
-        let len = str_bytes.len() as AddrSize;
-        self.insert_multi_byte_command(opcode::LDSC, &len.to_be_bytes());
-        self.insert_bytes(str_bytes);
+        let max = self.format.max_operand();
+        if s.len() as u64 > max.into() {
+            self.report_limit(Limit::StringLength, s.len() as u64, max.into());
+        }
+        self.buff.push(opcode::LDSC);
+        self.format.encode(s.len() as AddrSize, &mut self.buff);
+        self.insert_bytes(s.as_bytes());
     }
 
     fn assign_value(&mut self, name: &str) {
-        self.memory_command(name, store_by_kind);
+        self.memory_command(name, store_by_scope);
     }
 
     fn eval_and_assign(&mut self, name: &str, expr: &'a syntax_tree::Expr) {
@@ -219,17 +316,26 @@ impl<'a> ByteCodeGenerator<'a> {
     }
 
     fn convert_statement(&mut self, stat: &'a syntax_tree::Stat) {
+        let enclosing = self.loc.replace(&stat.loc);
         match &stat.stat {
             syntax_tree::StatType::AssignStat(assign_stat) => self.convert_assign_stat(assign_stat),
             syntax_tree::StatType::IfStat(if_stat) => self.convert_if_stat(if_stat),
             syntax_tree::StatType::WhileStat(while_stat) => self.convert_while_stat(while_stat),
             syntax_tree::StatType::ForStat(for_stat) => self.convert_for_stat(for_stat),
+            syntax_tree::StatType::RepeatStat(repeat_stat) => self.convert_repeat_stat(repeat_stat),
+            syntax_tree::StatType::CaseStat(case_stat) => self.convert_case_stat(case_stat),
             syntax_tree::StatType::ReturnStat(return_stat) => self.convert_return_stat(return_stat),
             syntax_tree::StatType::ReadStat(read_stat) => self.convert_read_stat(read_stat),
             syntax_tree::StatType::WriteStat(write_stat) => self.convert_write_stat(write_stat),
-            syntax_tree::StatType::Break => self.convert_break_stat(),
+            syntax_tree::StatType::Break(label) => self.convert_break_stat(label),
+            syntax_tree::StatType::Continue(label) => self.convert_continue_stat(label),
             syntax_tree::StatType::FuncCall(func_call) => self.convert_func_call(func_call),
+            syntax_tree::StatType::AssertStat(assert_stat) => {
+                self.convert_assert_stat(assert_stat, &stat.loc)
+            }
+            syntax_tree::StatType::HaltStat(code) => self.convert_halt_stat(code),
         }
+        self.loc = enclosing;
     }
 
     fn insert_bytes(&mut self, bytes: &[u8]) {
@@ -262,46 +368,231 @@ impl<'a> ByteCodeGenerator<'a> {
     fn convert_while_stat(&mut self, while_stat: &'a syntax_tree::WhileStat) {
         let while_lbl = self.label_counter.count_one();
         let end_lbl = self.label_counter.count_one();
-        self.loop_exit_label.push(end_lbl);
+        self.loop_labels.push(LoopLabels {
+            name: while_stat.label.as_deref(),
+            continue_label: Some(while_lbl),
+            exit_label: end_lbl,
+            for_loop: false,
+            open_cases: 0,
+        });
         self.insert_label(while_lbl);
         self.convert_expression(&while_stat.cond);
         self.insert_false_cond_jump(end_lbl);
         self.gen_block(&while_stat.body, BlockType::General);
         self.insert_uncond_jump(while_lbl);
         self.insert_label(end_lbl);
-        self.loop_exit_label.pop();
+        self.loop_labels.pop();
     }
 
     fn convert_for_stat(&mut self, for_stat: &'a syntax_tree::ForStat) {
         let for_lbl = self.label_counter.count_one();
         let end_lbl = self.label_counter.count_one();
-        self.loop_exit_label.push(end_lbl);
+        self.loop_labels.push(LoopLabels {
+            name: for_stat.label.as_deref(),
+            continue_label: None,
+            exit_label: end_lbl,
+            for_loop: true,
+            open_cases: 0,
+        });
         self.eval_and_assign(&for_stat.id, &for_stat.begin_expr);
         self.convert_expression(&for_stat.end_expr);
         self.buff.push(opcode::BFOR);
+        let step_slot = self.store_for_step(&for_stat.step);
         self.insert_label(for_lbl);
         self.load_variable(&for_stat.id);
         self.buff.push(opcode::CFOR);
-        self.buff.push(opcode::LEQI);
+        match for_stat.direction {
+            syntax_tree::ForDirection::Up => self.buff.push(opcode::LEQI),
+            syntax_tree::ForDirection::Down => self.buff.push(opcode::GEQI),
+        }
         self.insert_false_cond_jump(end_lbl);
         self.gen_block(&for_stat.body, BlockType::General);
-        self.convert_constant(&syntax_tree::Const::IntConst(1));
-        self.load_variable(&for_stat.id);
-        self.buff.push(opcode::ADDI);
+        if let Some(next_lbl) = self.loop_labels.last().unwrap().continue_label {
+            self.insert_label(next_lbl);
+        }
+        // the step is loaded first when it is added, as a for loop without step always did
+        match for_stat.direction {
+            syntax_tree::ForDirection::Up => {
+                self.load_for_step(&for_stat.step, step_slot);
+                self.load_variable(&for_stat.id);
+                self.buff.push(opcode::ADDI);
+            }
+            syntax_tree::ForDirection::Down => {
+                self.load_variable(&for_stat.id);
+                self.load_for_step(&for_stat.step, step_slot);
+                self.buff.push(opcode::SUBI);
+            }
+        }
         self.assign_value(&for_stat.id);
         self.insert_uncond_jump(for_lbl);
         self.insert_label(end_lbl);
         self.buff.push(opcode::EFOR);
-        self.loop_exit_label.pop();
+        if step_slot.is_some() {
+            self.open_steps -= 1;
+        }
+        self.loop_labels.pop();
+    }
+
+    fn convert_repeat_stat(&mut self, repeat_stat: &'a syntax_tree::RepeatStat) {
+        let repeat_lbl = self.label_counter.count_one();
+        let cond_lbl = self.label_counter.count_one();
+        let end_lbl = self.label_counter.count_one();
+        self.loop_labels.push(LoopLabels {
+            name: repeat_stat.label.as_deref(),
+            continue_label: Some(cond_lbl),
+            exit_label: end_lbl,
+            for_loop: false,
+            open_cases: 0,
+        });
+        self.insert_label(repeat_lbl);
+        self.gen_block(&repeat_stat.body, BlockType::General);
+        self.insert_label(cond_lbl);
+        self.convert_expression(&repeat_stat.cond);
+        self.insert_false_cond_jump(repeat_lbl);
+        self.insert_label(end_lbl);
+        self.loop_labels.pop();
+    }
+
+    /*
+        The selector is evaluated once and moved on the case stack
+        by BCASE, each label is compared against a copy pushed by
+        CCASE and ECASE drops the selector after the last branch.
+    */
+    fn convert_case_stat(&mut self, case_stat: &'a syntax_tree::CaseStat) {
+        let kind = case_stat.expr.kind.borrow().as_ref().unwrap().clone();
+        self.convert_expression(&case_stat.expr);
+        self.buff.push(opcode::BCASE);
+        self.enter_case();
+
+        let branch_lbls: Vec<AddrSize> = case_stat
+            .branches
+            .iter()
+            .map(|_| self.label_counter.count_one())
+            .collect();
+        let else_lbl = self.label_counter.count_one();
+        let end_lbl = self.label_counter.count_one();
+
+        for (branch, branch_lbl) in case_stat.branches.iter().zip(&branch_lbls) {
+            for label in &branch.labels {
+                self.buff.push(opcode::CCASE);
+                self.convert_constant(&label.value);
+                self.buff
+                    .push(operator_by_kind(&syntax_tree::Operator::Equal, &kind));
+                self.insert_true_cond_jump(*branch_lbl);
+            }
+        }
+        self.insert_uncond_jump(else_lbl);
+
+        for (branch, branch_lbl) in case_stat.branches.iter().zip(branch_lbls) {
+            self.insert_label(branch_lbl);
+            self.gen_block(&branch.body, BlockType::General);
+            self.insert_uncond_jump(end_lbl);
+        }
+
+        self.insert_label(else_lbl);
+        if let Some(else_body) = &case_stat.else_body {
+            self.gen_block(else_body, BlockType::General);
+        }
+        self.insert_label(end_lbl);
+        self.buff.push(opcode::ECASE);
+        self.exit_case();
+    }
+
+    fn enter_case(&mut self) {
+        match self.loop_labels.last_mut() {
+            Some(labels) => labels.open_cases += 1,
+            None => self.open_cases += 1,
+        }
+    }
+
+    fn exit_case(&mut self) {
+        match self.loop_labels.last_mut() {
+            Some(labels) => labels.open_cases -= 1,
+            None => self.open_cases -= 1,
+        }
+    }
+
+    /*
+        Like the end bound, a step that is not a constant is
+        evaluated once before the loop: it is kept in a hidden
+        integer slot of the frame, one for each nesting level.
+        A constant step is checked by the semantic analysis,
+        this one is checked by an ASSERT before the first
+        iteration, so a step that is not positive stops the
+        program instead of looping forever.
+    */
+    fn store_for_step(&mut self, step: &'a Option<syntax_tree::Expr>) -> Option<AddrSize> {
+        let step = step.as_ref().filter(|step| is_variable_step(step))?;
+        let slot = self.step_base + self.open_steps;
+        self.open_steps += 1;
+        self.convert_expression(step);
+        self.buff.push(opcode::STRI);
+        self.insert_variable_id(slot, !self.scope.is_empty());
+
+        self.buff.push(opcode::LDI);
+        self.insert_variable_id(slot, !self.scope.is_empty());
+        self.convert_constant(&syntax_tree::Const::IntConst(0));
+        self.buff.push(opcode::GRI);
+        let message = syntax_tree::Const::StrConst(STEP_MESSAGE.to_owned());
+        self.convert_constant(&message);
+        let line = self.sources.line_number(step.loc.begin) as AddrSize;
+        self.insert_address_command(opcode::ASSERT, line, Limit::SourceLines);
+        Some(slot)
+    }
+
+    fn load_for_step(&mut self, step: &'a Option<syntax_tree::Expr>, slot: Option<AddrSize>) {
+        match (step, slot) {
+            (_, Some(slot)) => {
+                self.buff.push(opcode::LDI);
+                self.insert_variable_id(slot, !self.scope.is_empty());
+            }
+            (Some(step), None) => self.convert_expression(step),
+            (None, None) => self.convert_constant(&syntax_tree::Const::IntConst(1)),
+        }
     }
 
+    // like a break, a return closes every case and for loop it leaves
     fn convert_return_stat(&mut self, return_stat: &'a Option<syntax_tree::Expr>) {
         if let Some(expr) = return_stat {
             self.convert_expression(expr);
         }
+        let open_cases = self.open_cases
+            + self
+                .loop_labels
+                .iter()
+                .map(|labels| labels.open_cases)
+                .sum::<usize>();
+        let for_loops = self
+            .loop_labels
+            .iter()
+            .filter(|labels| labels.for_loop)
+            .count();
+        self.close_blocks(open_cases, for_loops);
         self.buff.push(opcode::RET);
     }
 
+    /*
+        ASSERT <line> pops the message and the condition: when
+        the condition is false the program stops, reporting the
+        message and the line of the assert in its source file.
+        HALT pops the exit code and stops the program.
+    */
+    fn convert_assert_stat(
+        &mut self,
+        assert_stat: &'a syntax_tree::AssertStat,
+        loc: &syntax_tree::Location,
+    ) {
+        self.convert_expression(&assert_stat.cond);
+        self.convert_expression(&assert_stat.message);
+        let line = self.sources.line_number(loc.begin) as AddrSize;
+        self.insert_address_command(opcode::ASSERT, line, Limit::SourceLines);
+    }
+
+    fn convert_halt_stat(&mut self, code: &'a syntax_tree::Expr) {
+        self.convert_expression(code);
+        self.buff.push(opcode::HALT);
+    }
+
     fn convert_read_stat(&mut self, read_stat: &'a Vec<String>) {
         for id in read_stat {
             self.read_value(id);
@@ -328,60 +619,250 @@ impl<'a> ByteCodeGenerator<'a> {
 
     fn write_value(&mut self, expr: &'a syntax_tree::Expr) {
         self.convert_expression(expr);
-        self.buff
-            .push(write_by_kind(expr.kind.borrow().as_ref().unwrap()));
+        self.buff.push(write_by_kind(&value_kind(expr)));
+    }
+
+    fn convert_break_stat(&mut self, label: &'a Option<String>) {
+        let target = self.leave_inner_loops(label);
+        let index = self.loop_labels[target].exit_label;
+        self.insert_uncond_jump(index);
+    }
+
+    fn convert_continue_stat(&mut self, label: &'a Option<String>) {
+        let target = self.leave_inner_loops(label);
+        let index = match self.loop_labels[target].continue_label {
+            Some(index) => index,
+            None => {
+                let index = self.label_counter.count_one();
+                self.loop_labels[target].continue_label = Some(index);
+                index
+            }
+        };
+        self.insert_uncond_jump(index);
+    }
+
+    /*
+        Finds the loop targeted by a break or continue and closes
+        every for loop nested inside it and every case statement
+        left by the jump, so that the virtual machine for-stack and
+        case-stack stay balanced. The targeted loop itself is closed
+        by its own EFOR.
+    */
+    fn leave_inner_loops(&mut self, label: &'a Option<String>) -> usize {
+        let target = match label {
+            Some(label) => self
+                .loop_labels
+                .iter()
+                .rposition(|labels| labels.name == Some(label.as_str())),
+            None => self.loop_labels.len().checked_sub(1),
+        };
+        let target = target.expect("break or continue outside loop");
+        let open_cases: usize = self.loop_labels[target..]
+            .iter()
+            .map(|labels| labels.open_cases)
+            .sum();
+        let inner_for_loops = self.loop_labels[target + 1..]
+            .iter()
+            .filter(|labels| labels.for_loop)
+            .count();
+        self.close_blocks(open_cases, inner_for_loops);
+        target
     }
 
-    fn convert_break_stat(&mut self) {
-        let index = self.loop_exit_label.last();
-        if let Some(index) = index {
-            let index = *index;
-            self.insert_uncond_jump(index);
+    fn close_blocks(&mut self, open_cases: usize, for_loops: usize) {
+        for _ in 0..open_cases {
+            self.buff.push(opcode::ECASE);
+        }
+        for _ in 0..for_loops {
+            self.buff.push(opcode::EFOR);
+        }
+    }
+
+    // a function used as a value is its index in the function table
+    fn convert_id(&mut self, id: &str) {
+        if self.local_cache.contains(id) {
+            self.load_variable(id);
         } else {
-            panic!("break outside loop");
+            let f_id = self.function_index.resolve(id, &self.scope).unwrap();
+            self.insert_multi_byte_command(opcode::LDIC, &(f_id as i32).to_be_bytes());
         }
     }
 
     fn convert_func_call(&mut self, func_call: &'a syntax_tree::FuncCall) {
-        let f_id = self.function_index.get_function_index(&func_call.id);
-        self.insert_address_command(opcode::PARAM, f_id);
+        if self.local_cache.contains(&func_call.id) {
+            self.convert_function_value_call(func_call);
+            return;
+        }
+        match self.function_index.resolve(&func_call.id, &self.scope) {
+            Some(f_id) => self.convert_declared_func_call(func_call, f_id),
+            None => self.convert_built_in_call(func_call),
+        }
+    }
 
-        for (index, expr) in func_call.args.iter().enumerate() {
-            let addr = self.param_addr.get_parameter_address(&func_call.id, index);
+    /*
+        A call through a function value cannot name its callee:
+        PARAMI pops the function index and prepares the frame
+        of that function, CALLI calls the function prepared by
+        the last PARAMI. Function values only refer to top level
+        functions taking every argument by value, so the address
+        of each parameter only depends on the parameter kinds.
+    */
+    fn convert_function_value_call(&mut self, func_call: &'a syntax_tree::FuncCall) {
+        self.load_variable(&func_call.id);
+        self.buff.push(opcode::PARAMI);
+
+        let ((kind, _), _) = self.local_cache.lookup(&func_call.id);
+        let addresses = match kind {
+            syntax_tree::Kind::Func(func_kind) => function_value_parameters(&func_kind.params),
+            _ => unreachable!("only function values can be called"),
+        };
+        for (expr, addr) in func_call.args.iter().zip(addresses) {
             self.convert_parameter(expr, addr);
         }
 
-        self.insert_address_command(opcode::CALL, f_id);
+        self.buff.push(opcode::CALLI);
+    }
+
+    fn convert_built_in_call(&mut self, func_call: &'a syntax_tree::FuncCall) {
+        let mut args = Vec::with_capacity(func_call.args.len());
+        for expr in &func_call.args {
+            self.convert_expression(expr);
+            args.push(value_kind(expr));
+        }
+        let (b_id, _) = built_in::find_built_in(&func_call.id, &args).unwrap();
+        self.insert_address_command(opcode::CALLB, b_id as AddrSize, Limit::Functions);
+    }
+
+    fn convert_declared_func_call(&mut self, func_call: &'a syntax_tree::FuncCall, f_id: AddrSize) {
+        self.insert_address_command(opcode::PARAM, f_id, Limit::Functions);
+
+        for (index, expr) in func_call.args.iter().enumerate() {
+            let addr = self.param_addr.get_parameter_address(f_id, index);
+            if self.param_addr.is_reference_parameter(f_id, index) {
+                self.convert_reference_parameter(expr, addr);
+            } else {
+                self.convert_parameter(expr, addr);
+            }
+        }
+
+        let callee_depth = self.function_index.get_scope(f_id).len();
+        if callee_depth == 1 {
+            self.insert_address_command(opcode::CALL, f_id, Limit::Functions);
+        } else {
+            self.insert_nested_call(f_id, callee_depth);
+        }
+    }
+
+    /*
+        A nested function gets a static link to the frame
+        of the function that declares it: CALLN <f_id> <depth>
+        finds that frame following depth static links from
+        the frame of the caller, 0 when the caller itself
+        declares the function. Enclosing variables are then
+        reached with the LDxU, STRxU, LDxAU, STRxAU and REFxPU
+        commands, that take the number of static links to
+        follow before the address of the variable.
+    */
+    fn insert_nested_call(&mut self, f_id: AddrSize, callee_depth: usize) {
+        let depth = (self.scope.len() + 1 - callee_depth) as AddrSize;
+        self.insert_address_command(opcode::CALLN, f_id, Limit::Functions);
+        self.insert_operand(depth, Limit::NestingDepth);
     }
 
     fn convert_parameter(&mut self, expr: &'a syntax_tree::Expr, addr: AddrSize) {
         self.convert_expression(expr);
-        let expr_kind = expr.kind.borrow();
-        let kind = expr_kind.as_ref().unwrap();
-        let store = store_param_by_kind(&kind);
+        let store = store_param_by_kind(&value_kind(expr));
         self.buff.push(store);
-        let param_id = addr + LOCAL_MASK;
-        self.insert_bytes(&param_id.to_be_bytes());
+        self.insert_variable_id(addr, true);
+    }
+
+    /*
+        A var parameter does not copy a value: REFxP binds the
+        parameter slot in the callee frame to the caller variable,
+        resolving it first when the caller variable is a reference
+        itself. LDxA and STRxA then read and write through the slot.
+    */
+    fn convert_reference_parameter(&mut self, expr: &'a syntax_tree::Expr, addr: AddrSize) {
+        let name = match &expr.expr {
+            syntax_tree::ExprTree::Factor(syntax_tree::Factor {
+                fact: syntax_tree::FactorValue::Id(name),
+                ..
+            }) => name,
+            _ => unreachable!("var parameter argument must be a variable"),
+        };
+        let ((kind, id), ref scope) = self.local_cache.lookup(name);
+        let cmd = reference_param_by_scope(kind, scope);
+        self.buff.push(cmd);
+        self.insert_variable_address(*id, scope);
+        self.insert_variable_id(addr, true);
     }
 
     pub fn switch_local_cache(&mut self, local: VarLookup<'a>) {
         self.local_cache = local;
     }
 
-    fn allocate_variables(&mut self, var_decl_list: &syntax_tree::VarDeclList, cmd: u8) {
+    pub fn switch_scope(&mut self, scope: &[AddrSize]) {
+        self.scope = scope.to_vec();
+    }
+
+    fn allocate_variables(
+        &mut self,
+        var_decl_list: &'a syntax_tree::VarDeclList,
+        body: &syntax_tree::StatList,
+    ) {
+        self.loc = var_decl_list.first().map(|var_decl| &var_decl.loc);
         let var_count = VariableCounter::count_variables(var_decl_list);
-        self.buff.push(cmd);
-        self.insert_bytes(&var_count.vectorize());
+        let var_count = self.reserve_step_slots(var_count, body);
+        self.insert_variable_counts(var_count);
+    }
+
+    fn reserve_step_slots(
+        &mut self,
+        mut var_count: VariableCounter,
+        body: &syntax_tree::StatList,
+    ) -> VariableCounter {
+        self.step_base = var_count.integer_count;
+        self.open_steps = 0;
+        var_count.integer_count += step_slots(body);
+        var_count
+    }
+
+    /*
+        Version 1 keeps the INIT of the original storage classes,
+        so the code of a program without long and char variables
+        does not change: a frame with long or char variables
+        starts with INITX and all the counts instead. Version 2 always
+        lists all the counts after INIT.
+    */
+    fn insert_variable_counts(&mut self, var_count: VariableCounter) {
+        let counts = match self.format {
+            ByteCodeFormat::V1 if !var_count.is_extended() => {
+                self.buff.push(opcode::INIT);
+                var_count.base_counts()
+            }
+            ByteCodeFormat::V1 => {
+                self.buff.push(opcode::INITX);
+                var_count.vectorize()
+            }
+            ByteCodeFormat::V2 => {
+                self.buff.push(opcode::INIT);
+                var_count.vectorize()
+            }
+        };
+        for count in counts {
+            self.insert_operand(count, Limit::Variables);
+        }
     }
 }
 
 impl<'a> CodeGenerator<'a> for ByteCodeGenerator<'a> {
     fn gen_function(&mut self, func: &'a syntax_tree::FuncDecl) {
+        self.loc = Some(&func.loc);
         self.buff.push(opcode::FUNC);
         let var_count = VariableCounter::count_variables(&func.vars);
         let var_count = var_count.count_parameters(&func.params);
-        self.buff.push(opcode::INIT);
-        self.insert_bytes(&var_count.vectorize());
+        let var_count = self.reserve_step_slots(var_count, &func.body);
+        self.insert_variable_counts(var_count);
         self.gen_block(&func.body, BlockType::General);
         if *self.buff.last().unwrap() != opcode::RET {
             self.buff.push(opcode::RET);
@@ -398,34 +879,80 @@ impl<'a> CodeGenerator<'a> for ByteCodeGenerator<'a> {
         }
     }
 
-    fn gen_variables(&mut self, var_decl_list: &'a syntax_tree::VarDeclList) {
-        self.allocate_variables(var_decl_list, opcode::INIT);
+    fn gen_variables(
+        &mut self,
+        var_decl_list: &'a syntax_tree::VarDeclList,
+        body: &'a syntax_tree::StatList,
+    ) {
+        self.allocate_variables(var_decl_list, body);
     }
 
-    fn get_result(self) -> Vec<u8> {
-        self.buff
+    fn get_result(self) -> Result<Vec<u8>, Vec<LimitError>> {
+        if self.errors.is_empty() {
+            Ok(self.buff)
+        } else {
+            Err(self.errors)
+        }
     }
 }
 
+fn is_variable_step(step: &syntax_tree::Expr) -> bool {
+    !matches!(constant_fold::fold_expr(step), Some(Ok(_)))
+}
+
+// the deepest nesting of for loops with a step that is not a constant
+fn step_slots(stat_list: &syntax_tree::StatList) -> AddrSize {
+    stat_list
+        .iter()
+        .map(|stat| match &stat.stat {
+            syntax_tree::StatType::ForStat(for_stat) => {
+                let own = for_stat.step.as_ref().filter(|step| is_variable_step(step));
+                step_slots(&for_stat.body) + own.map_or(0, |_| 1)
+            }
+            syntax_tree::StatType::IfStat(if_stat) => {
+                let else_slots = if_stat.else_body.as_ref().map_or(0, step_slots);
+                step_slots(&if_stat.if_body).max(else_slots)
+            }
+            syntax_tree::StatType::WhileStat(while_stat) => step_slots(&while_stat.body),
+            syntax_tree::StatType::RepeatStat(repeat_stat) => step_slots(&repeat_stat.body),
+            syntax_tree::StatType::CaseStat(case_stat) => case_stat
+                .branches
+                .iter()
+                .map(|branch| step_slots(&branch.body))
+                .chain(case_stat.else_body.as_ref().map(step_slots))
+                .max()
+                .unwrap_or(0),
+            _ => 0,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
 #[derive(std::default::Default, Debug)]
 struct VariableCounter {
-    integer_count: u16,
-    real_count: u16,
-    boolean_count: u16,
-    string_count: u16,
+    integer_count: AddrSize,
+    real_count: AddrSize,
+    boolean_count: AddrSize,
+    string_count: AddrSize,
+    long_count: AddrSize,
+    char_count: AddrSize,
 }
 
 impl VariableCounter {
     fn count_variables(var_decl_list: &syntax_tree::VarDeclList) -> Self {
         var_decl_list
             .iter()
-            .map(|decl| (&decl.kind, decl.id_list.len() as u16))
+            .map(|decl| (&decl.kind, decl.id_list.len() as AddrSize))
             .fold(Self::default(), |mut acc, (kind, count)| {
                 match kind {
-                    syntax_tree::Kind::Int => acc.integer_count += count,
+                    syntax_tree::Kind::Int | syntax_tree::Kind::Func(_) => {
+                        acc.integer_count += count
+                    }
                     syntax_tree::Kind::Real => acc.real_count += count,
                     syntax_tree::Kind::Bool => acc.boolean_count += count,
                     syntax_tree::Kind::Str => acc.string_count += count,
+                    syntax_tree::Kind::Long => acc.long_count += count,
+                    syntax_tree::Kind::Char => acc.char_count += count,
                     _ => unreachable!(),
                 }
                 acc
@@ -435,22 +962,40 @@ impl VariableCounter {
     fn count_parameters(mut self, param_list: &syntax_tree::ParamList) -> Self {
         for par_decl in param_list {
             match par_decl.kind {
-                syntax_tree::Kind::Int => self.integer_count += 1,
+                syntax_tree::Kind::Int | syntax_tree::Kind::Func(_) => self.integer_count += 1,
                 syntax_tree::Kind::Real => self.real_count += 1,
                 syntax_tree::Kind::Str => self.string_count += 1,
                 syntax_tree::Kind::Bool => self.boolean_count += 1,
+                syntax_tree::Kind::Long => self.long_count += 1,
+                syntax_tree::Kind::Char => self.char_count += 1,
                 _ => unreachable!()
             }
         }
         self
     }
 
-    fn vectorize(self) -> [u8; 4 * 2] {
-        let [i1, i2] = self.integer_count.to_be_bytes();
-        let [r1, r2] = self.real_count.to_be_bytes();
-        let [b1, b2] = self.boolean_count.to_be_bytes();
-        let [s1, s2] = self.string_count.to_be_bytes();
-        [i1, i2, r1, r2, b1, b2, s1, s2]
+    fn is_extended(&self) -> bool {
+        self.long_count > 0 || self.char_count > 0
+    }
+
+    fn base_counts(&self) -> Vec<AddrSize> {
+        vec![
+            self.integer_count,
+            self.real_count,
+            self.boolean_count,
+            self.string_count,
+        ]
+    }
+
+    fn vectorize(&self) -> Vec<AddrSize> {
+        vec![
+            self.integer_count,
+            self.real_count,
+            self.boolean_count,
+            self.string_count,
+            self.long_count,
+            self.char_count,
+        ]
     }
 }
 
@@ -472,28 +1017,31 @@ fn write_by_kind(k: &syntax_tree::Kind) -> u8 {
     match k {
         syntax_tree::Kind::Bool => opcode::WRB,
         syntax_tree::Kind::Int => opcode::WRI,
+        syntax_tree::Kind::Long => opcode::WRL,
+        syntax_tree::Kind::Char => opcode::WRCH,
         syntax_tree::Kind::Real => opcode::WRR,
         syntax_tree::Kind::Str => opcode::WRS,
-        syntax_tree::Kind::Void => unreachable!(),
+        syntax_tree::Kind::Void | syntax_tree::Kind::Func(_) => unreachable!(),
     }
 }
 
-enum CastOp {
-    ToInt,
-    ToReal,
-}
-
-impl CastOp {
-    fn get_command(&self) -> u8 {
-        match self {
-            Self::ToInt => opcode::CSTI,
-            Self::ToReal => opcode::CSTR,
-        }
+fn cast_by_kind(from: &syntax_tree::Kind, to: &syntax_tree::Kind) -> u8 {
+    match (from, to) {
+        (syntax_tree::Kind::Real, syntax_tree::Kind::Int) => opcode::CSTI,
+        (syntax_tree::Kind::Int, syntax_tree::Kind::Real) => opcode::CSTR,
+        (syntax_tree::Kind::Long, syntax_tree::Kind::Int) => opcode::CSTIL,
+        (syntax_tree::Kind::Long, syntax_tree::Kind::Real) => opcode::CSTRL,
+        (syntax_tree::Kind::Int, syntax_tree::Kind::Long) => opcode::CSTLI,
+        (syntax_tree::Kind::Real, syntax_tree::Kind::Long) => opcode::CSTLR,
+        (syntax_tree::Kind::Char, syntax_tree::Kind::Int) => opcode::CSTICH,
+        (syntax_tree::Kind::Int, syntax_tree::Kind::Char) => opcode::CSTCHI,
+        _ => unreachable!(),
     }
 }
 
 enum UnaryOp {
     IntNeg,
+    LongNeg,
     RealNeg,
     BoolNeg,
 }
@@ -502,6 +1050,7 @@ impl UnaryOp {
     fn from_kind(kind: &syntax_tree::Kind) -> Self {
         match kind {
             syntax_tree::Kind::Int => Self::IntNeg,
+            syntax_tree::Kind::Long => Self::LongNeg,
             syntax_tree::Kind::Real => Self::RealNeg,
             _ => unreachable!(),
         }
@@ -511,15 +1060,151 @@ impl UnaryOp {
         match self {
             Self::RealNeg => opcode::NEGR,
             Self::IntNeg => opcode::NEGI,
+            Self::LongNeg => opcode::NEGL,
             Self::BoolNeg => opcode::NOT,
         }
     }
 }
 
+// kind of the value left on the stack, after any implicit conversion
+fn value_kind(expr: &syntax_tree::Expr) -> syntax_tree::Kind {
+    match expr.coercion.borrow().as_ref() {
+        Some(kind) => kind.clone(),
+        None => expr.kind.borrow().as_ref().unwrap().clone(),
+    }
+}
+
+fn store_by_scope(k: &syntax_tree::Kind, scope: &VariableType) -> u8 {
+    match scope {
+        VariableType::Reference => store_reference_by_kind(k),
+        VariableType::Global | VariableType::Local => store_by_kind(k),
+        VariableType::Enclosing(_) => store_enclosing_by_kind(k),
+        VariableType::EnclosingReference(_) => store_enclosing_reference_by_kind(k),
+    }
+}
+
+fn load_by_scope(k: &syntax_tree::Kind, scope: &VariableType) -> u8 {
+    match scope {
+        VariableType::Reference => load_reference_by_kind(k),
+        VariableType::Global | VariableType::Local => load_by_kind(k),
+        VariableType::Enclosing(_) => load_enclosing_by_kind(k),
+        VariableType::EnclosingReference(_) => load_enclosing_reference_by_kind(k),
+    }
+}
+
+fn reference_param_by_scope(k: &syntax_tree::Kind, scope: &VariableType) -> u8 {
+    match scope {
+        VariableType::Enclosing(_) | VariableType::EnclosingReference(_) => {
+            enclosing_reference_param_by_kind(k)
+        }
+        VariableType::Global | VariableType::Local | VariableType::Reference => {
+            reference_param_by_kind(k)
+        }
+    }
+}
+
+fn store_enclosing_by_kind(k: &syntax_tree::Kind) -> u8 {
+    match k {
+        syntax_tree::Kind::Bool => opcode::STRBU,
+        syntax_tree::Kind::Int | syntax_tree::Kind::Func(_) => opcode::STRIU,
+        syntax_tree::Kind::Long => opcode::STRLU,
+        syntax_tree::Kind::Char => opcode::STRCHU,
+        syntax_tree::Kind::Real => opcode::STRRU,
+        syntax_tree::Kind::Str => opcode::STRSU,
+        syntax_tree::Kind::Void => unreachable!(),
+    }
+}
+
+fn load_enclosing_by_kind(k: &syntax_tree::Kind) -> u8 {
+    match k {
+        syntax_tree::Kind::Bool => opcode::LDBU,
+        syntax_tree::Kind::Int | syntax_tree::Kind::Func(_) => opcode::LDIU,
+        syntax_tree::Kind::Long => opcode::LDLU,
+        syntax_tree::Kind::Char => opcode::LDCHU,
+        syntax_tree::Kind::Real => opcode::LDRU,
+        syntax_tree::Kind::Str => opcode::LDSU,
+        syntax_tree::Kind::Void => unreachable!(),
+    }
+}
+
+fn store_enclosing_reference_by_kind(k: &syntax_tree::Kind) -> u8 {
+    match k {
+        syntax_tree::Kind::Bool => opcode::STRBAU,
+        syntax_tree::Kind::Int | syntax_tree::Kind::Func(_) => opcode::STRIAU,
+        syntax_tree::Kind::Long => opcode::STRLAU,
+        syntax_tree::Kind::Char => opcode::STRCHAU,
+        syntax_tree::Kind::Real => opcode::STRRAU,
+        syntax_tree::Kind::Str => opcode::STRSAU,
+        syntax_tree::Kind::Void => unreachable!(),
+    }
+}
+
+fn load_enclosing_reference_by_kind(k: &syntax_tree::Kind) -> u8 {
+    match k {
+        syntax_tree::Kind::Bool => opcode::LDBAU,
+        syntax_tree::Kind::Int | syntax_tree::Kind::Func(_) => opcode::LDIAU,
+        syntax_tree::Kind::Long => opcode::LDLAU,
+        syntax_tree::Kind::Char => opcode::LDCHAU,
+        syntax_tree::Kind::Real => opcode::LDRAU,
+        syntax_tree::Kind::Str => opcode::LDSAU,
+        syntax_tree::Kind::Void => unreachable!(),
+    }
+}
+
+fn enclosing_reference_param_by_kind(k: &syntax_tree::Kind) -> u8 {
+    match k {
+        syntax_tree::Kind::Bool => opcode::REFBPU,
+        syntax_tree::Kind::Int | syntax_tree::Kind::Func(_) => opcode::REFIPU,
+        syntax_tree::Kind::Long => opcode::REFLPU,
+        syntax_tree::Kind::Char => opcode::REFCHPU,
+        syntax_tree::Kind::Real => opcode::REFRPU,
+        syntax_tree::Kind::Str => opcode::REFSPU,
+        syntax_tree::Kind::Void => unreachable!(),
+    }
+}
+
+fn store_reference_by_kind(k: &syntax_tree::Kind) -> u8 {
+    match k {
+        syntax_tree::Kind::Bool => opcode::STRBA,
+        syntax_tree::Kind::Int | syntax_tree::Kind::Func(_) => opcode::STRIA,
+        syntax_tree::Kind::Long => opcode::STRLA,
+        syntax_tree::Kind::Char => opcode::STRCHA,
+        syntax_tree::Kind::Real => opcode::STRRA,
+        syntax_tree::Kind::Str => opcode::STRSA,
+        syntax_tree::Kind::Void => unreachable!(),
+    }
+}
+
+fn load_reference_by_kind(k: &syntax_tree::Kind) -> u8 {
+    match k {
+        syntax_tree::Kind::Bool => opcode::LDBA,
+        syntax_tree::Kind::Int | syntax_tree::Kind::Func(_) => opcode::LDIA,
+        syntax_tree::Kind::Long => opcode::LDLA,
+        syntax_tree::Kind::Char => opcode::LDCHA,
+        syntax_tree::Kind::Real => opcode::LDRA,
+        syntax_tree::Kind::Str => opcode::LDSA,
+        syntax_tree::Kind::Void => unreachable!(),
+    }
+}
+
+fn reference_param_by_kind(k: &syntax_tree::Kind) -> u8 {
+    match k {
+        syntax_tree::Kind::Bool => opcode::REFBP,
+        syntax_tree::Kind::Int | syntax_tree::Kind::Func(_) => opcode::REFIP,
+        syntax_tree::Kind::Long => opcode::REFLP,
+        syntax_tree::Kind::Char => opcode::REFCHP,
+        syntax_tree::Kind::Real => opcode::REFRP,
+        syntax_tree::Kind::Str => opcode::REFSP,
+        syntax_tree::Kind::Void => unreachable!(),
+    }
+}
+
 fn store_by_kind(k: &syntax_tree::Kind) -> u8 {
     match k {
         syntax_tree::Kind::Bool => opcode::STRB,
-        syntax_tree::Kind::Int => opcode::STRI,
+        syntax_tree::Kind::Int | syntax_tree::Kind::Func(_) => opcode::STRI,
+        syntax_tree::Kind::Long => opcode::STRL,
+        syntax_tree::Kind::Char => opcode::STRCH,
         syntax_tree::Kind::Real => opcode::STRR,
         syntax_tree::Kind::Str => opcode::STRS,
         syntax_tree::Kind::Void => unreachable!(),
@@ -529,7 +1214,9 @@ fn store_by_kind(k: &syntax_tree::Kind) -> u8 {
 fn load_by_kind(k: &syntax_tree::Kind) -> u8 {
     match k {
         syntax_tree::Kind::Bool => opcode::LDB,
-        syntax_tree::Kind::Int => opcode::LDI,
+        syntax_tree::Kind::Int | syntax_tree::Kind::Func(_) => opcode::LDI,
+        syntax_tree::Kind::Long => opcode::LDL,
+        syntax_tree::Kind::Char => opcode::LDCH,
         syntax_tree::Kind::Real => opcode::LDR,
         syntax_tree::Kind::Str => opcode::LDS,
         syntax_tree::Kind::Void => unreachable!(),
@@ -540,16 +1227,20 @@ fn read_by_kind(k: &syntax_tree::Kind) -> u8 {
     match k {
         syntax_tree::Kind::Bool => opcode::RDB,
         syntax_tree::Kind::Int => opcode::RDI,
+        syntax_tree::Kind::Long => opcode::RDL,
+        syntax_tree::Kind::Char => opcode::RDCH,
         syntax_tree::Kind::Real => opcode::RDR,
         syntax_tree::Kind::Str => opcode::RDS,
-        syntax_tree::Kind::Void => unreachable!(),
+        syntax_tree::Kind::Void | syntax_tree::Kind::Func(_) => unreachable!(),
     }
 }
 
 fn store_param_by_kind(k: &syntax_tree::Kind) -> u8 {
     match k {
         syntax_tree::Kind::Bool => opcode::STRBP,
-        syntax_tree::Kind::Int => opcode::STRIP,
+        syntax_tree::Kind::Int | syntax_tree::Kind::Func(_) => opcode::STRIP,
+        syntax_tree::Kind::Long => opcode::STRLP,
+        syntax_tree::Kind::Char => opcode::STRCHP,
         syntax_tree::Kind::Real => opcode::STRRP,
         syntax_tree::Kind::Str => opcode::STRSP,
         syntax_tree::Kind::Void => unreachable!(),
@@ -559,10 +1250,12 @@ fn store_param_by_kind(k: &syntax_tree::Kind) -> u8 {
 fn operator_by_kind(op: &syntax_tree::Operator, k: &syntax_tree::Kind) -> u8 {
     match k {
         syntax_tree::Kind::Int => integer_operator(op),
+        syntax_tree::Kind::Long => long_operator(op),
+        syntax_tree::Kind::Char => char_operator(op),
         syntax_tree::Kind::Real => real_operator(op),
         syntax_tree::Kind::Bool => bool_operator(op),
         syntax_tree::Kind::Str => str_operator(op),
-        syntax_tree::Kind::Void => unreachable!(),
+        syntax_tree::Kind::Void | syntax_tree::Kind::Func(_) => unreachable!(),
     }
 }
 
@@ -583,6 +1276,35 @@ fn integer_operator(op: &syntax_tree::Operator) -> u8 {
     }
 }
 
+fn long_operator(op: &syntax_tree::Operator) -> u8 {
+    match op {
+        syntax_tree::Operator::Equal => opcode::EQL,
+        syntax_tree::Operator::NotEqual => opcode::NEL,
+        syntax_tree::Operator::Greater => opcode::GRL,
+        syntax_tree::Operator::GreaterEqual => opcode::GEQL,
+        syntax_tree::Operator::Less => opcode::LESQL,
+        syntax_tree::Operator::LessEqual => opcode::LEQL,
+        syntax_tree::Operator::Add => opcode::ADDL,
+        syntax_tree::Operator::Sub => opcode::SUBL,
+        syntax_tree::Operator::Mul => opcode::MULL,
+        syntax_tree::Operator::Div => opcode::DIVL,
+        syntax_tree::Operator::And => unreachable!(),
+        syntax_tree::Operator::Or => unreachable!(),
+    }
+}
+
+fn char_operator(op: &syntax_tree::Operator) -> u8 {
+    match op {
+        syntax_tree::Operator::Equal => opcode::EQCH,
+        syntax_tree::Operator::NotEqual => opcode::NECH,
+        syntax_tree::Operator::Greater => opcode::GRCH,
+        syntax_tree::Operator::GreaterEqual => opcode::GEQCH,
+        syntax_tree::Operator::Less => opcode::LESQCH,
+        syntax_tree::Operator::LessEqual => opcode::LEQCH,
+        _ => unreachable!(),
+    }
+}
+
 fn real_operator(op: &syntax_tree::Operator) -> u8 {
     match op {
         syntax_tree::Operator::Equal => opcode::EQR,
@@ -624,18 +1346,6 @@ fn str_operator(op: &syntax_tree::Operator) -> u8 {
     }
 }
 
-fn truncate_str_to_byte_len(string: &str, byte_count: usize) -> &[u8] {
-    let mut output_len = if string.len() > byte_count {
-        byte_count
-    } else {
-        string.len()
-    };
-    while !string.is_char_boundary(output_len) {
-        output_len -= 1;
-    }
-    string[..output_len].as_bytes()
-}
-
 fn is_short_circuit_operator(op: &syntax_tree::Operator) -> bool {
     match op {
         syntax_tree::Operator::And | syntax_tree::Operator::Or => true,
@@ -648,30 +1358,6 @@ mod test {
 
     use super::*;
 
-    #[test]
-    fn test_truncate_string_with_long_string() {
-        // each emoji requires 4 bytes
-        let string = "😁😁😁😁😁😁😁😁😁😁😁";
-        let byte_count = 5;
-        let bytes = truncate_str_to_byte_len(string, byte_count);
-        assert!(bytes.len() < byte_count);
-        let byte_vec = Vec::from(bytes);
-        let new_string = String::from_utf8(byte_vec).unwrap();
-        // so a string long at most 5 bytes can contain just one emoji
-        assert_eq!(new_string, "😁");
-    }
-
-    #[test]
-    fn test_truncate_string_with_short_string() {
-        let string = "😁😁😁😁😁😁😁😁😁😁😁";
-        let byte_count = 100;
-        let bytes = truncate_str_to_byte_len(string, byte_count);
-        assert_eq!(bytes.len(), string.len());
-        let byte_vec = Vec::from(bytes);
-        let new_string = String::from_utf8(byte_vec).unwrap();
-        assert_eq!(new_string, string);
-    }
-
     use simpla_parser;
 
     #[test]
@@ -702,10 +1388,10 @@ mod test {
 
     fn run_variable_count_test(
         code: &str,
-        int_count: u16,
-        real_count: u16,
-        bool_count: u16,
-        str_count: u16,
+        int_count: AddrSize,
+        real_count: AddrSize,
+        bool_count: AddrSize,
+        str_count: AddrSize,
     ) {
         let parser = simpla_parser::ProgramParser::new();
         let tree = parser.parse(code).unwrap();
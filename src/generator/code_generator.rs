@@ -1,3 +1,4 @@
+use super::format::LimitError;
 use simpla_parser::syntax_tree::{FuncDecl, StatList, VarDeclList};
 
 pub enum BlockType {
@@ -9,6 +10,6 @@ pub enum BlockType {
 pub trait CodeGenerator<'a> {
     fn gen_function(&mut self, func: &'a FuncDecl);
     fn gen_block(&mut self, bloc: &'a StatList, block: BlockType);
-    fn gen_variables(&mut self, vars: &'a VarDeclList);
-    fn get_result(self) -> Vec<u8>;
+    fn gen_variables(&mut self, vars: &'a VarDeclList, body: &'a StatList);
+    fn get_result(self) -> Result<Vec<u8>, Vec<LimitError>>;
 }
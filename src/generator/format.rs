@@ -0,0 +1,154 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::simple_counter::AddrSize;
+use simpla_parser::syntax_tree::Location;
+
+/*
+    Layout of the operands of the bytecode. Version 1 stores
+    addresses, labels, function ids, string lengths and variable
+    counts in two bytes. Version 2 stores them in four bytes and
+    the code starts with VERSION 2, so that the virtual machine
+    can tell the two layouts apart.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ByteCodeFormat {
+    V1,
+    V2,
+}
+
+impl ByteCodeFormat {
+    pub fn version(self) -> u8 {
+        match self {
+            Self::V1 => 1,
+            Self::V2 => 2,
+        }
+    }
+
+    pub fn max_operand(self) -> AddrSize {
+        match self {
+            Self::V1 => u16::MAX.into(),
+            Self::V2 => u32::MAX,
+        }
+    }
+
+    // the highest bit of a variable address marks local variables
+    pub fn local_mask(self) -> AddrSize {
+        match self {
+            Self::V1 => 1 << 15,
+            Self::V2 => 1 << 31,
+        }
+    }
+
+    pub fn encode(self, value: AddrSize, buff: &mut Vec<u8>) {
+        match self {
+            Self::V1 => buff.extend_from_slice(&(value as u16).to_be_bytes()),
+            Self::V2 => buff.extend_from_slice(&value.to_be_bytes()),
+        }
+    }
+}
+
+impl FromStr for ByteCodeFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "v1" | "1" => Ok(Self::V1),
+            "v2" | "2" => Ok(Self::V2),
+            other => Err(format!(
+                "unknown bytecode format {}, expected v1 or v2",
+                other
+            )),
+        }
+    }
+}
+
+/*
+    An operand that does not fit the selected format:
+    the code is not emitted, because the virtual machine
+    would read a different address than the intended one.
+    The location is the one of the statement, or of the
+    declarations, whose code exceeded the limit.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Limit {
+    Labels,
+    Functions,
+    Variables,
+    NestingDepth,
+    StringLength,
+    SourceLines,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LimitError {
+    pub limit: Limit,
+    pub value: u64,
+    pub max: u64,
+    pub format: ByteCodeFormat,
+    pub loc: Option<Location>,
+}
+
+impl LimitError {
+    pub fn new(
+        limit: Limit,
+        value: u64,
+        max: u64,
+        format: ByteCodeFormat,
+        loc: Option<Location>,
+    ) -> Self {
+        Self {
+            limit,
+            value,
+            max,
+            format,
+            loc,
+        }
+    }
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let descr = match self.limit {
+            Limit::Labels => "labels",
+            Limit::Functions => "functions",
+            Limit::Variables => "variables of the same type in a frame",
+            Limit::NestingDepth => "levels of nested functions",
+            Limit::StringLength => "bytes in a string constant",
+            Limit::SourceLines => "lines in a source file with an assert",
+        };
+        write!(
+            f,
+            "bytecode limit error: {} {} exceed the maximum of {} allowed by format v{}",
+            self.value,
+            descr,
+            self.max,
+            self.format.version()
+        )?;
+        if self.format == ByteCodeFormat::V1 {
+            write!(f, ", translate with --format v2")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let mut buff = Vec::new();
+        ByteCodeFormat::V1.encode(0x0102, &mut buff);
+        ByteCodeFormat::V2.encode(0x0102, &mut buff);
+        assert_eq!(buff, vec![1, 2, 0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("v1".parse(), Ok(ByteCodeFormat::V1));
+        assert_eq!("2".parse(), Ok(ByteCodeFormat::V2));
+        assert!("v3".parse::<ByteCodeFormat>().is_err());
+    }
+}
@@ -3,31 +3,141 @@ use std::collections::HashMap;
 
 use super::simple_counter::AddrSize;
 
+/*
+    Every function, nested or not, gets its own index:
+    top level functions come first, in declaration order,
+    followed by the nested ones, level by level.
+    The scope of a function is the chain of the indexes
+    of its enclosing functions, outermost first, ending
+    with the function itself: its length is the nesting depth.
+*/
+struct IndexEntry<'a> {
+    decl: &'a syntax_tree::FuncDecl,
+    scope: Vec<AddrSize>,
+    nested: HashMap<&'a str, AddrSize>,
+}
+
 pub struct FunctionIndex<'a> {
     index: HashMap<&'a str, AddrSize>,
+    functions: Vec<IndexEntry<'a>>,
 }
 
 impl<'a> FunctionIndex<'a> {
     fn new() -> Self {
         Self {
             index: HashMap::new(),
+            functions: Vec::new(),
+        }
+    }
+
+    fn add_function(
+        &mut self,
+        decl: &'a syntax_tree::FuncDecl,
+        mut scope: Vec<AddrSize>,
+    ) -> AddrSize {
+        let curr = self.functions.len() as AddrSize;
+        scope.push(curr);
+        self.functions.push(IndexEntry {
+            decl,
+            scope,
+            nested: HashMap::new(),
+        });
+        curr
+    }
+
+    fn add_nested_functions(&mut self, parent: usize) {
+        let decl = self.functions[parent].decl;
+        for nested in &decl.functions {
+            let scope = self.functions[parent].scope.clone();
+            let curr = self.add_function(nested, scope);
+            self.functions[parent].nested.insert(&nested.id, curr);
         }
     }
 
-    fn add_function(&mut self, name: &'a str) {
-        let curr = self.index.len() as AddrSize;
-        self.index.insert(name, curr);
+    // resolves a call made from the given scope
+    pub fn resolve(&self, name: &str, scope: &[AddrSize]) -> Option<AddrSize> {
+        scope
+            .iter()
+            .rev()
+            .find_map(|f_id| self.functions[*f_id as usize].nested.get(name))
+            .or_else(|| self.index.get(name))
+            .copied()
     }
 
-    pub fn get_function_index(&self, name: &str) -> AddrSize {
-        *self.index.get(name).unwrap()
+    pub fn get_scope(&self, f_id: AddrSize) -> &[AddrSize] {
+        &self.functions[f_id as usize].scope
+    }
+
+    pub fn functions(&self) -> impl Iterator<Item = (AddrSize, &'a syntax_tree::FuncDecl)> + '_ {
+        self.functions
+            .iter()
+            .enumerate()
+            .map(|(f_id, entry)| (f_id as AddrSize, entry.decl))
     }
 }
 
 pub fn build_function_index<'a>(func_decl_list: &'a syntax_tree::FuncDeclList) -> FunctionIndex {
     let mut function_index = FunctionIndex::new();
     for decl in func_decl_list {
-        function_index.add_function(&decl.id)
+        let curr = function_index.add_function(decl, Vec::new());
+        function_index.index.insert(&decl.id, curr);
+    }
+    let mut parent = 0;
+    while parent < function_index.functions.len() {
+        function_index.add_nested_functions(parent);
+        parent += 1;
     }
     function_index
 }
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_nested_function_index() {
+        let code = r#"
+            func outer(): integer
+                func helper(): integer
+                body
+                    return 1;
+                end;
+                func other(): integer
+                    func helper(): integer
+                    body
+                        return 2;
+                    end;
+                body
+                    return helper();
+                end;
+            body
+                return helper() + other();
+            end;
+
+            func helper(): integer
+            body
+                return 3;
+            end;
+
+            body
+                writeln(outer());
+            end.
+        "#;
+        let prog = simpla_parser::ProgramParser::new().parse(code).unwrap();
+        let index = build_function_index(&prog.functions);
+
+        let names: Vec<&str> = index.functions().map(|(_, f)| f.id.as_str()).collect();
+        assert_eq!(names, ["outer", "helper", "helper", "other", "helper"]);
+
+        assert_eq!(index.get_scope(0), &[0]);
+        assert_eq!(index.get_scope(3), &[0, 3]);
+        assert_eq!(index.get_scope(4), &[0, 3, 4]);
+
+        assert_eq!(index.resolve("helper", &[]), Some(1));
+        assert_eq!(index.resolve("helper", index.get_scope(0)), Some(2));
+        assert_eq!(index.resolve("helper", index.get_scope(3)), Some(4));
+        assert_eq!(index.resolve("other", index.get_scope(4)), Some(3));
+        assert_eq!(index.resolve("sqrt", index.get_scope(4)), None);
+    }
+}
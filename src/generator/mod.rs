@@ -1,9 +1,12 @@
 mod byte_code_generator;
 mod code_generator;
+mod format;
 mod function_index;
-mod opcode;
+pub(crate) mod opcode;
 mod simple_counter;
 mod translate;
 mod var_cache;
 
+pub use byte_code_generator::STEP_MESSAGE;
+pub use format::{ByteCodeFormat, LimitError};
 pub use translate::translate_to_byte_code;
@@ -77,3 +77,100 @@ pub const EQB: u8 = 77;
 pub const NEB: u8 = 78;
 
 pub const INIT: u8 = 80;
+pub const CALLB: u8 = 81;
+pub const BCASE: u8 = 82;
+pub const CCASE: u8 = 83;
+pub const ECASE: u8 = 84;
+pub const CALLN: u8 = 85;
+pub const PARAMI: u8 = 86;
+pub const CALLI: u8 = 87;
+
+pub const LDIA: u8 = 88; // 88 % 4 = 0
+pub const LDRA: u8 = 89; // 89 % 4 = 1
+pub const LDBA: u8 = 90; // 90 % 4 = 2
+pub const LDSA: u8 = 91; // 91 % 4 = 3
+pub const STRIA: u8 = 92; // 92 % 4 = 0
+pub const STRRA: u8 = 93; // 93 % 4 = 1
+pub const STRBA: u8 = 94; // 94 % 4 = 2
+pub const STRSA: u8 = 95; // 95 % 4 = 3
+pub const REFIP: u8 = 96; // 96 % 4 = 0
+pub const REFRP: u8 = 97; // 97 % 4 = 1
+pub const REFBP: u8 = 98; // 98 % 4 = 2
+pub const REFSP: u8 = 99; // 99 % 4 = 3
+
+pub const LDIU: u8 = 100; // 100 % 4 = 0
+pub const LDRU: u8 = 101; // 101 % 4 = 1
+pub const LDBU: u8 = 102; // 102 % 4 = 2
+pub const LDSU: u8 = 103; // 103 % 4 = 3
+pub const STRIU: u8 = 104; // 104 % 4 = 0
+pub const STRRU: u8 = 105; // 105 % 4 = 1
+pub const STRBU: u8 = 106; // 106 % 4 = 2
+pub const STRSU: u8 = 107; // 107 % 4 = 3
+pub const LDIAU: u8 = 108; // 108 % 4 = 0
+pub const LDRAU: u8 = 109; // 109 % 4 = 1
+pub const LDBAU: u8 = 110; // 110 % 4 = 2
+pub const LDSAU: u8 = 111; // 111 % 4 = 3
+pub const STRIAU: u8 = 112; // 112 % 4 = 0
+pub const STRRAU: u8 = 113; // 113 % 4 = 1
+pub const STRBAU: u8 = 114; // 114 % 4 = 2
+pub const STRSAU: u8 = 115; // 115 % 4 = 3
+pub const REFIPU: u8 = 116; // 116 % 4 = 0
+pub const REFRPU: u8 = 117; // 117 % 4 = 1
+pub const REFBPU: u8 = 118; // 118 % 4 = 2
+pub const REFSPU: u8 = 119; // 119 % 4 = 3
+pub const ASSERT: u8 = 120;
+pub const HALT: u8 = 121;
+pub const ADDL: u8 = 122;
+pub const SUBL: u8 = 123;
+pub const MULL: u8 = 124;
+pub const DIVL: u8 = 125;
+pub const GEQL: u8 = 126;
+pub const GRL: u8 = 127;
+pub const LEQL: u8 = 128;
+pub const LESQL: u8 = 129;
+pub const EQL: u8 = 130;
+pub const NEL: u8 = 131;
+pub const NEGL: u8 = 132;
+pub const CSTIL: u8 = 133;
+pub const CSTRL: u8 = 134;
+pub const CSTLI: u8 = 135;
+pub const CSTLR: u8 = 136;
+pub const RDL: u8 = 137;
+pub const WRL: u8 = 138;
+pub const LDL: u8 = 139;
+pub const STRL: u8 = 140;
+pub const LDLC: u8 = 141;
+pub const STRLP: u8 = 142;
+pub const LDLA: u8 = 143;
+pub const STRLA: u8 = 144;
+pub const REFLP: u8 = 145;
+pub const LDLU: u8 = 146;
+pub const STRLU: u8 = 147;
+pub const LDLAU: u8 = 148;
+pub const STRLAU: u8 = 149;
+pub const REFLPU: u8 = 150;
+pub const EQCH: u8 = 151;
+pub const NECH: u8 = 152;
+pub const GEQCH: u8 = 153;
+pub const GRCH: u8 = 154;
+pub const LEQCH: u8 = 155;
+pub const LESQCH: u8 = 156;
+pub const CSTICH: u8 = 157;
+pub const CSTCHI: u8 = 158;
+pub const RDCH: u8 = 159;
+pub const WRCH: u8 = 160;
+pub const LDCH: u8 = 161;
+pub const STRCH: u8 = 162;
+pub const LDCHC: u8 = 163;
+pub const STRCHP: u8 = 164;
+pub const LDCHA: u8 = 165;
+pub const STRCHA: u8 = 166;
+pub const REFCHP: u8 = 167;
+pub const LDCHU: u8 = 168;
+pub const STRCHU: u8 = 169;
+pub const LDCHAU: u8 = 170;
+pub const STRCHAU: u8 = 171;
+pub const REFCHPU: u8 = 172;
+pub const IDXS: u8 = 173;
+pub const VERSION: u8 = 22;
+pub const INITX: u8 = 174;
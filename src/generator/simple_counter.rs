@@ -1,4 +1,4 @@
-pub type AddrSize = u16;
+pub type AddrSize = u32;
 
 pub struct SimpleCounter {
     counter: AddrSize,
@@ -1,33 +1,196 @@
+use extract_line_error::SourceMap;
 use simpla_parser::syntax_tree::*;
 
 use super::byte_code_generator::ByteCodeGenerator;
 use super::code_generator::*;
+use super::format::{ByteCodeFormat, LimitError};
 use super::function_index::build_function_index;
+use super::simple_counter::AddrSize;
 use super::var_cache::{build_global_var_cache, GlobalVarCache};
 
-pub fn translate_to_byte_code<'a>(prog: &'a Program) -> Vec<u8> {
+/*
+    Operands that do not fit the format are reported as
+    errors, one for each exceeded limit.
+*/
+pub fn translate_to_byte_code<'a>(
+    prog: &'a Program,
+    sources: &'a SourceMap,
+    format: ByteCodeFormat,
+) -> Result<Vec<u8>, Vec<LimitError>> {
     let function_index = build_function_index(&prog.functions);
-    let (glob_var_cache, param_addr) = build_global_var_cache(prog);
+    let (glob_var_cache, param_addr) = build_global_var_cache(prog, &function_index);
+    let functions: Vec<(&FuncDecl, Vec<AddrSize>)> = function_index
+        .functions()
+        .map(|(f_id, func)| (func, function_index.get_scope(f_id).to_vec()))
+        .collect();
     let mut code_gen = ByteCodeGenerator::new(
         function_index,
         glob_var_cache.get_global_cache(),
         param_addr,
+        sources,
+        format,
     );
-    translate(prog, &mut code_gen, &glob_var_cache);
+    translate(prog, &functions, &mut code_gen, &glob_var_cache);
     code_gen.get_result()
 }
 
+// functions, nested ones included, are generated in the order of their index
 fn translate<'a>(
     prog: &'a Program,
+    functions: &[(&'a FuncDecl, Vec<AddrSize>)],
     tranlator: &mut ByteCodeGenerator<'a>,
     global_cache: &'a GlobalVarCache<'a>,
 ) {
-    tranlator.gen_variables(&prog.global_vars);
+    tranlator.gen_variables(&prog.global_vars, &prog.body);
 
     tranlator.gen_block(&prog.body, BlockType::Main);
 
-    for func in &prog.functions {
-        tranlator.switch_local_cache(global_cache.get_local_cache(&func.id));
+    for (func, scope) in functions {
+        tranlator.switch_local_cache(global_cache.get_local_cache(scope));
+        tranlator.switch_scope(scope);
         tranlator.gen_function(func);
     }
 }
+
+#[cfg(test)]
+mod test {
+
+    use super::super::opcode;
+    use super::*;
+    use crate::semantic_analysis::{analyze_program, Promotion};
+
+    fn translate_code(code: &str, format: ByteCodeFormat) -> Result<Vec<u8>, Vec<LimitError>> {
+        let prog = simpla_parser::ProgramParser::new().parse(code).unwrap();
+        let mut sources = SourceMap::new();
+        sources.add_file("test.simpla".to_owned(), code.to_owned());
+        translate_to_byte_code(&prog, &sources, format)
+    }
+
+    fn translate_checked(code: &str) -> Vec<u8> {
+        let prog = simpla_parser::ProgramParser::new().parse(code).unwrap();
+        let mut sources = SourceMap::new();
+        sources.add_file("test.simpla".to_owned(), code.to_owned());
+        analyze_program(&prog, &sources, Promotion::Forbid).unwrap();
+        translate_to_byte_code(&prog, &sources, ByteCodeFormat::V1).unwrap()
+    }
+
+    #[test]
+    fn test_string_length_limit() {
+        let code = format!("s: string; body s = \"{}\"; end.", "a".repeat(70_000));
+
+        let errors = translate_code(&code, ByteCodeFormat::V1).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        let message = errors[0].to_string();
+        assert!(
+            message.contains("70000 bytes in a string constant"),
+            "{}",
+            message
+        );
+        assert!(message.contains("--format v2"), "{}", message);
+        // the location of the assignment
+        let loc = errors[0].loc.as_ref().unwrap();
+        assert_eq!(&code[loc.begin..loc.begin + 5], "s = \"");
+
+        let output = translate_code(&code, ByteCodeFormat::V2).unwrap();
+        assert_eq!(output[..2], [opcode::VERSION, 2]);
+        let len_bytes = 70_000u32.to_be_bytes();
+        assert!(output
+            .windows(5)
+            .any(|w| w[0] == opcode::LDSC && w[1..] == len_bytes));
+    }
+
+    #[test]
+    fn test_operand_width() {
+        let code = "n: integer; body n = 1; end.";
+        let v1 = translate_code(code, ByteCodeFormat::V1).unwrap();
+        let v2 = translate_code(code, ByteCodeFormat::V2).unwrap();
+        // INIT with four counts in v1 and six in v2, then STRI with one address
+        assert_eq!(v1[0], opcode::INIT);
+        assert_eq!(v1[1..3], [0, 1]);
+        assert_eq!(v2[2], opcode::INIT);
+        assert_eq!(v2[3..7], [0, 0, 0, 1]);
+        assert_eq!(v2.len() - 2 - v1.len(), 6 * 4 - 4 * 2 + 2);
+    }
+
+    #[test]
+    fn test_extended_frame_header() {
+        let code = "n: long; body n = 1L; end.";
+        let output = translate_checked(code);
+        assert_eq!(output[0], opcode::INITX);
+        assert_eq!(output[1..13], [0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0]);
+
+        let code = "n: integer; func f(): void l: long; body l = 2L; end; body n = 1; f(); end.";
+        let output = translate_checked(code);
+        assert_eq!(
+            output[..10],
+            [opcode::INIT, 0, 1, 0, 0, 0, 0, 0, 0, opcode::LDIC]
+        );
+        let func = output.iter().position(|op| *op == opcode::FUNC).unwrap();
+        assert_eq!(output[func + 1], opcode::INITX);
+
+        let code = "c: char; body c = 'a'; end.";
+        let output = translate_checked(code);
+        assert_eq!(output[0], opcode::INITX);
+        assert_eq!(output[11..13], [0, 1]);
+    }
+
+    #[test]
+    fn test_for_step_evaluated_once() {
+        let code = "n, s: integer; body s = 2; for n = 1 to 9 step s do s = s + 1; end; end.";
+        let output = translate_checked(code);
+        // a hidden third integer keeps the step
+        assert_eq!(output[..3], [opcode::INIT, 0, 3]);
+        let count = |bytes: [u8; 3]| output.windows(3).filter(|w| *w == bytes).count();
+        assert_eq!(count([opcode::STRI, 0, 2]), 1);
+        // once by the check of the sign, once by each increment
+        assert_eq!(count([opcode::LDI, 0, 2]), 2);
+        let check = output
+            .windows(4)
+            .position(|w| w == [opcode::LDI, 0, 2, opcode::LDIC]);
+        let check = &output[check.unwrap() + 3..];
+        assert_eq!(check[..6], [opcode::LDIC, 0, 0, 0, 0, opcode::GRI]);
+        let assert = check.iter().position(|op| *op == opcode::ASSERT).unwrap();
+        assert_eq!(check[assert + 1..assert + 3], [0, 1]);
+
+        let code = "n: integer; body for n = 9 downto 1 step 2 + 1 do writeln(n); end; end.";
+        let output = translate_checked(code);
+        assert_eq!(output[..3], [opcode::INIT, 0, 1]);
+    }
+
+    #[test]
+    fn test_for_continue_label() {
+        let code = "i: integer; body for i = 1 to 3 do writeln(i); end; end.";
+        let output = translate_checked(code);
+        let labels = |output: &[u8]| output.iter().filter(|op| **op == opcode::LBL).count();
+        assert_eq!(labels(&output), 2);
+
+        let code = "i: integer; body for i = 1 to 3 do continue; end; end.";
+        let output = translate_checked(code);
+        // the continue label comes after the ones of the loop
+        assert_eq!(labels(&output), 3);
+        assert!(output.windows(3).any(|w| w == [opcode::JUMP, 0, 2]));
+        assert!(output.windows(3).any(|w| w == [opcode::LBL, 0, 2]));
+    }
+
+    #[test]
+    fn test_return_closes_blocks() {
+        let code = "func find(n: integer): integer
+    i: integer;
+body
+    case n of
+        0: return 0;
+    end;
+    for i = 1 to n do
+        case i of
+            3: while true do return i; end;
+        end;
+    end;
+    return -1;
+end;
+body writeln(find(5)); end.";
+        let output = translate_checked(code);
+        let count = |bytes: &[u8]| output.windows(bytes.len()).filter(|w| *w == bytes).count();
+        assert_eq!(count(&[opcode::ECASE, opcode::RET]), 1);
+        assert_eq!(count(&[opcode::ECASE, opcode::EFOR, opcode::RET]), 1);
+    }
+}
@@ -1,24 +1,29 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use super::function_index::FunctionIndex;
 use super::simple_counter::{AddrSize, SimpleCounter};
 use simpla_parser::syntax_tree::{FuncDecl, Kind, ParamList, Program, VarDecl, VarDeclList};
 
-pub fn build_global_var_cache<'a>(prog: &'a Program) -> (GlobalVarCache<'a>, ParameterAddress<'a>) {
+pub fn build_global_var_cache<'a>(
+    prog: &'a Program,
+    function_index: &FunctionIndex<'a>,
+) -> (GlobalVarCache<'a>, ParameterAddress) {
     let mut factory = GlobalVarCacheFactory::new();
     factory.cache_global_vars(&prog.global_vars);
 
     let mut factory = factory.switch_to_function_factory();
-    for func in &prog.functions {
+    for (_, func) in function_index.functions() {
         build_local_var_cache(func, &mut factory);
     }
 
     factory.build_var_cache()
 }
 
+// functions are cached in the order of their index
 fn build_local_var_cache<'a>(func: &'a FuncDecl, factory: &mut FunctionVarCacheFactory<'a>) {
-    factory.insert_function(&func.id);
-    factory.cache_params(&func.id, &func.params);
-    factory.cache_local_vars(&func.id, &func.vars);
+    let f_id = factory.insert_function();
+    factory.cache_params(f_id, &func.params);
+    factory.cache_local_vars(f_id, &func.vars);
 }
 
 struct GlobalVarCacheFactory<'a> {
@@ -43,42 +48,34 @@ impl<'a> GlobalVarCacheFactory<'a> {
 
 struct FunctionVarCacheFactory<'a> {
     global_vars: NameTable<'a>,
-    function_vars: HashMap<&'a str, NameTable<'a>>,
-    param_addr: HashMap<&'a str, Vec<AddrSize>>,
+    function_vars: Vec<NameTable<'a>>,
+    param_addr: Vec<Vec<ParamInfo>>,
 }
 
 impl<'a> FunctionVarCacheFactory<'a> {
     fn new(global_vars: NameTable<'a>) -> Self {
         Self {
             global_vars,
-            function_vars: HashMap::new(),
-            param_addr: HashMap::new(),
+            function_vars: Vec::new(),
+            param_addr: Vec::new(),
         }
     }
 
-    fn insert_function(&mut self, name: &'a str) {
-        let new_table = NameTable::new();
-        self.function_vars.insert(name, new_table);
+    fn insert_function(&mut self) -> usize {
+        self.function_vars.push(NameTable::new());
+        self.param_addr.push(Vec::new());
+        self.function_vars.len() - 1
     }
 
-    fn cache_local_vars(&mut self, name: &'a str, var_decl_list: &'a VarDeclList) {
-        if let Some(curr) = self.function_vars.get_mut(name) {
-            cache_var_decl_list(var_decl_list, curr);
-        } else {
-            panic!();
-        }
+    fn cache_local_vars(&mut self, f_id: usize, var_decl_list: &'a VarDeclList) {
+        cache_var_decl_list(var_decl_list, &mut self.function_vars[f_id]);
     }
 
-    fn cache_params(&mut self, name: &'a str, param_decl_list: &'a ParamList) {
-        if let Some(curr) = self.function_vars.get_mut(name) {
-            let param_addr = cache_param_decl(param_decl_list, curr);
-            self.param_addr.insert(name, param_addr);
-        } else {
-            panic!();
-        }
+    fn cache_params(&mut self, f_id: usize, param_decl_list: &'a ParamList) {
+        self.param_addr[f_id] = cache_param_decl(param_decl_list, &mut self.function_vars[f_id]);
     }
 
-    fn build_var_cache(self) -> (GlobalVarCache<'a>, ParameterAddress<'a>) {
+    fn build_var_cache(self) -> (GlobalVarCache<'a>, ParameterAddress) {
         (
             GlobalVarCache::new(self.global_vars, self.function_vars),
             ParameterAddress::new(self.param_addr),
@@ -86,40 +83,52 @@ impl<'a> FunctionVarCacheFactory<'a> {
     }
 }
 
-pub struct ParameterAddress<'a> {
-    param_addr: HashMap<&'a str, Vec<AddrSize>>,
+type ParamInfo = (AddrSize, bool);
+
+pub struct ParameterAddress {
+    param_addr: Vec<Vec<ParamInfo>>,
 }
 
-impl<'a> ParameterAddress<'a> {
-    fn new(param_addr: HashMap<&'a str, Vec<AddrSize>>) -> Self {
+impl ParameterAddress {
+    fn new(param_addr: Vec<Vec<ParamInfo>>) -> Self {
         Self { param_addr }
     }
 
-    pub fn get_parameter_address(&self, name: &str, index: usize) -> AddrSize {
-        let vect = self.param_addr.get(name).unwrap();
-        vect[index]
+    pub fn get_parameter_address(&self, f_id: AddrSize, index: usize) -> AddrSize {
+        self.param_addr[f_id as usize][index].0
+    }
+
+    pub fn is_reference_parameter(&self, f_id: AddrSize, index: usize) -> bool {
+        self.param_addr[f_id as usize][index].1
     }
 }
 
+type LocalTable<'a> = (VarTable<'a>, References<'a>);
+
 pub struct GlobalVarCache<'a> {
     global_vars: VarTable<'a>,
-    function_vars: HashMap<&'a str, VarTable<'a>>,
+    function_vars: Vec<LocalTable<'a>>,
 }
 
 impl<'a> GlobalVarCache<'a> {
-    fn new(global_vars: NameTable<'a>, function_vars: HashMap<&'a str, NameTable<'a>>) -> Self {
+    fn new(global_vars: NameTable<'a>, function_vars: Vec<NameTable<'a>>) -> Self {
+        let (global_vars, _) = global_vars.get_table();
         Self {
-            global_vars: global_vars.get_table(),
+            global_vars,
             function_vars: function_vars
                 .into_iter()
-                .map(|(name, table)| (name, table.get_table()))
+                .map(|table| table.get_table())
                 .collect(),
         }
     }
 
-    pub fn get_local_cache(&'a self, name: &str) -> VarLookup<'a> {
-        let local = self.function_vars.get(name).unwrap();
-        VarLookup::new_local(&self.global_vars, local)
+    // scope is the chain of function indexes, see FunctionIndex
+    pub fn get_local_cache(&'a self, scope: &[AddrSize]) -> VarLookup<'a> {
+        let local_vars = scope
+            .iter()
+            .map(|f_id| &self.function_vars[*f_id as usize])
+            .collect();
+        VarLookup::new_local(&self.global_vars, local_vars)
     }
 
     pub fn get_global_cache(&'a self) -> VarLookup<'a> {
@@ -129,23 +138,27 @@ impl<'a> GlobalVarCache<'a> {
 
 pub struct VarLookup<'a> {
     global_vars: &'a VarTable<'a>,
-    local_vars: Option<&'a VarTable<'a>>,
+    local_vars: Vec<&'a LocalTable<'a>>,
 }
 
 impl<'a> VarLookup<'a> {
-    fn new_local(global_vars: &'a VarTable<'a>, local_vars: &'a VarTable<'a>) -> Self {
+    fn new_local(global_vars: &'a VarTable<'a>, local_vars: Vec<&'a LocalTable<'a>>) -> Self {
         let mut output = Self::new_global(global_vars);
-        output.local_vars = Some(local_vars);
+        output.local_vars = local_vars;
         output
     }
 
     fn new_global(global_vars: &'a VarTable<'a>) -> Self {
         Self {
             global_vars,
-            local_vars: None,
+            local_vars: Vec::new(),
         }
     }
 
+    pub fn contains(&self, name: &str) -> bool {
+        self.local_lookup(name).is_some() || self.global_vars.contains_key(name)
+    }
+
     pub fn lookup(&self, name: &str) -> (&VarInfo, VariableType) {
         if let Some(output) = self.local_lookup(name) {
             output
@@ -155,33 +168,55 @@ impl<'a> VarLookup<'a> {
     }
 
     fn local_lookup(&self, name: &str) -> Option<(&VarInfo, VariableType)> {
-        match self.local_vars {
-            Some(local_vars) => {
-                if let Some(output) = local_vars.get(name) {
-                    Some((output, VariableType::Local))
-                } else {
-                    None
-                }
+        for (depth, (local_vars, references)) in self.local_vars.iter().rev().enumerate() {
+            if let Some(output) = local_vars.get(name) {
+                let depth = depth as AddrSize;
+                let reference = references.contains(name);
+                let scope = match (depth, reference) {
+                    (0, false) => VariableType::Local,
+                    (0, true) => VariableType::Reference,
+                    (depth, false) => VariableType::Enclosing(depth),
+                    (depth, true) => VariableType::EnclosingReference(depth),
+                };
+                return Some((output, scope));
             }
-            None => None,
         }
+        None
     }
 }
 
+/*
+    Enclosing variables belong to the frame of an
+    enclosing function: the value is the number of static
+    links to follow from the current frame to reach it.
+*/
 pub enum VariableType {
     Global,
     Local,
+    Reference,
+    Enclosing(AddrSize),
+    EnclosingReference(AddrSize),
 }
 
-fn cache_param_decl<'a>(param_list: &'a ParamList, map: &mut NameTable<'a>) -> Vec<AddrSize> {
+fn cache_param_decl<'a>(param_list: &'a ParamList, map: &mut NameTable<'a>) -> Vec<ParamInfo> {
     let mut output = Vec::with_capacity(param_list.len());
     for param in param_list {
-        let index = map.insert(&param.id, &param.kind);
-        output.push(index);
+        let index = if param.by_ref {
+            map.insert_reference(&param.id, &param.kind)
+        } else {
+            map.insert(&param.id, &param.kind)
+        };
+        output.push((index, param.by_ref));
     }
     output
 }
 
+// parameter addresses of any function with the given value parameter kinds
+pub fn function_value_parameters(params: &[Kind]) -> Vec<AddrSize> {
+    let mut counter = KindCounter::new();
+    params.iter().map(|k| counter.get_index(k)).collect()
+}
+
 fn cache_var_decl_list<'a>(var_decl_list: &'a VarDeclList, map: &mut NameTable<'a>) {
     for var_decl in var_decl_list {
         cache_var_decl(&var_decl, map);
@@ -196,9 +231,11 @@ fn cache_var_decl<'a>(var_decl: &'a VarDecl, map: &mut NameTable<'a>) {
 
 type VarInfo = (Kind, AddrSize);
 type VarTable<'a> = HashMap<&'a str, VarInfo>;
+type References<'a> = HashSet<&'a str>;
 
 struct NameTable<'a> {
     table: VarTable<'a>,
+    references: References<'a>,
     counter: KindCounter,
 }
 
@@ -206,6 +243,7 @@ impl<'a> NameTable<'a> {
     fn new() -> Self {
         Self {
             table: HashMap::new(),
+            references: HashSet::new(),
             counter: KindCounter::new(),
         }
     }
@@ -216,8 +254,13 @@ impl<'a> NameTable<'a> {
         index
     }
 
-    fn get_table(self) -> VarTable<'a> {
-        self.table
+    fn insert_reference(&mut self, name: &'a str, k: &Kind) -> AddrSize {
+        self.references.insert(name);
+        self.insert(name, k)
+    }
+
+    fn get_table(self) -> (VarTable<'a>, References<'a>) {
+        (self.table, self.references)
     }
 }
 
@@ -226,6 +269,8 @@ pub struct KindCounter {
     real_count: SimpleCounter,
     str_count: SimpleCounter,
     bool_count: SimpleCounter,
+    long_count: SimpleCounter,
+    char_count: SimpleCounter,
 }
 
 impl KindCounter {
@@ -235,13 +280,17 @@ impl KindCounter {
             real_count: SimpleCounter::new(),
             str_count: SimpleCounter::new(),
             bool_count: SimpleCounter::new(),
+            long_count: SimpleCounter::new(),
+            char_count: SimpleCounter::new(),
         }
     }
 
     pub fn get_index(&mut self, k: &Kind) -> AddrSize {
         match k {
             Kind::Bool => self.bool_count.count_one(),
-            Kind::Int => self.int_count.count_one(),
+            Kind::Int | Kind::Func(_) => self.int_count.count_one(),
+            Kind::Long => self.long_count.count_one(),
+            Kind::Char => self.char_count.count_one(),
             Kind::Real => self.real_count.count_one(),
             Kind::Str => self.str_count.count_one(),
             Kind::Void => panic!("void variable found!"),
@@ -254,6 +303,8 @@ impl KindCounter {
         self.int_count.reset();
         self.real_count.reset();
         self.str_count.reset();
+        self.long_count.reset();
+        self.char_count.reset();
     }
 }
 
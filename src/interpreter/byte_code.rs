@@ -0,0 +1,572 @@
+use super::value::{self, Value};
+use crate::built_in;
+use crate::generator::opcode;
+use simpla_parser::syntax_tree::Kind;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
+use std::ops::{Add, Div, Mul, Sub};
+use std::rc::Rc;
+use std::str::Lines;
+
+/*
+    Runs the version 2 bytecode of a whole program, reading
+    the opcodes as the virtual machine defines them: the
+    machine is not part of this repository, so the tests
+    compare the interpreter with this reader. Each line of
+    input is read and each value is written as the
+    interpreter does, so what is compared are the operators,
+    the conversions, the jumps, the calls and the variable
+    addresses the generator chooses. The reader trusts the
+    code: a runtime error, that the interpreter reports,
+    is a panic here.
+*/
+pub fn run(code: &[u8], input: &str) -> (String, Result<(), Exit>) {
+    let program = decode(code);
+    let mut machine = Machine {
+        globals: Frame::default(),
+        frames: Vec::new(),
+        prepared: Vec::new(),
+        stack: Vec::new(),
+        for_stack: Vec::new(),
+        case_stack: Vec::new(),
+        input: input.lines(),
+        output: String::new(),
+    };
+    let res = machine.execute(&program);
+    (machine.output, res)
+}
+
+// how a program stops before the end of its main body
+#[derive(Debug, PartialEq)]
+pub enum Exit {
+    AssertFailed { message: String, line: usize },
+    Halt(i32),
+}
+
+const LOCAL: u32 = 1 << 31;
+
+/*
+    The variable opcodes of each storage class, in the
+    order of the counts of INIT, and of each access below.
+*/
+const STORAGE: [[u8; 11]; 6] = {
+    use opcode::*;
+    [
+        [
+            LDI, STRI, STRIP, LDIA, STRIA, REFIP, LDIU, STRIU, LDIAU, STRIAU, REFIPU,
+        ],
+        [
+            LDR, STRR, STRRP, LDRA, STRRA, REFRP, LDRU, STRRU, LDRAU, STRRAU, REFRPU,
+        ],
+        [
+            LDB, STRB, STRBP, LDBA, STRBA, REFBP, LDBU, STRBU, LDBAU, STRBAU, REFBPU,
+        ],
+        [
+            LDS, STRS, STRSP, LDSA, STRSA, REFSP, LDSU, STRSU, LDSAU, STRSAU, REFSPU,
+        ],
+        [
+            LDL, STRL, STRLP, LDLA, STRLA, REFLP, LDLU, STRLU, LDLAU, STRLAU, REFLPU,
+        ],
+        [
+            LDCH, STRCH, STRCHP, LDCHA, STRCHA, REFCHP, LDCHU, STRCHU, LDCHAU, STRCHAU, REFCHPU,
+        ],
+    ]
+};
+const LOAD: usize = 0;
+const STORE: usize = 1;
+const STORE_PARAM: usize = 2;
+const LOAD_REF: usize = 3;
+const STORE_REF: usize = 4;
+const REF_PARAM: usize = 5;
+const LOAD_UP: usize = 6;
+const STORE_UP: usize = 7;
+const LOAD_REF_UP: usize = 8;
+const STORE_REF_UP: usize = 9;
+const REF_PARAM_UP: usize = 10;
+// the depth, if any, the address and the address of the parameter
+const ACCESS_OPERANDS: [usize; 11] = [1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 3];
+
+const READS: [u8; 6] = [
+    opcode::RDI,
+    opcode::RDR,
+    opcode::RDB,
+    opcode::RDS,
+    opcode::RDL,
+    opcode::RDCH,
+];
+const WRITES: [u8; 6] = [
+    opcode::WRI,
+    opcode::WRR,
+    opcode::WRB,
+    opcode::WRS,
+    opcode::WRL,
+    opcode::WRCH,
+];
+
+fn storage(op: u8) -> Option<(usize, usize)> {
+    STORAGE.iter().enumerate().find_map(|(class, ops)| {
+        let access = ops.iter().position(|code| *code == op)?;
+        Some((class, access))
+    })
+}
+
+fn default_value(class: usize) -> Value<'static> {
+    let kinds = [
+        Kind::Int,
+        Kind::Real,
+        Kind::Bool,
+        Kind::Str,
+        Kind::Long,
+        Kind::Char,
+    ];
+    Value::default_of(&kinds[class])
+}
+
+struct Instruction {
+    op: u8,
+    operands: Vec<u32>,
+    // the value pushed by the opcodes of the constants
+    constant: Option<Value<'static>>,
+}
+
+fn decode(mut code: &[u8]) -> Vec<Instruction> {
+    let mut program = Vec::new();
+    while !code.is_empty() {
+        let op = take(&mut code, 1)[0];
+        let constant = match op {
+            opcode::VERSION => {
+                assert_eq!(take(&mut code, 1), [2]);
+                None
+            }
+            opcode::LDBC => Some(Value::Bool(take(&mut code, 1)[0] != 0)),
+            opcode::LDIC => Some(Value::Int(i32::from_be_bytes(
+                take(&mut code, 4).try_into().unwrap(),
+            ))),
+            opcode::LDCHC => {
+                let code = operand(&mut code);
+                Some(Value::Char(std::char::from_u32(code).unwrap()))
+            }
+            opcode::LDLC => Some(Value::Long(i64::from_be_bytes(
+                take(&mut code, 8).try_into().unwrap(),
+            ))),
+            opcode::LDRC => Some(Value::Real(f64::from_be_bytes(
+                take(&mut code, 8).try_into().unwrap(),
+            ))),
+            opcode::LDSC => {
+                let len = operand(&mut code) as usize;
+                let bytes = take(&mut code, len).to_vec();
+                Some(Value::Str(String::from_utf8(bytes).unwrap()))
+            }
+            _ => None,
+        };
+        let count = match op {
+            // the six variable counts of a frame
+            opcode::INIT | opcode::INITX => 6,
+            opcode::JUMP | opcode::JEQ | opcode::JNE | opcode::LBL => 1,
+            opcode::PARAM | opcode::CALL | opcode::CALLB | opcode::ASSERT => 1,
+            opcode::CALLN => 2,
+            _ => storage(op).map_or(0, |(_, access)| ACCESS_OPERANDS[access]),
+        };
+        let operands = (0..count).map(|_| operand(&mut code)).collect();
+        program.push(Instruction {
+            op,
+            operands,
+            constant,
+        });
+    }
+    program
+}
+
+fn take<'c>(code: &mut &'c [u8], size: usize) -> &'c [u8] {
+    let (head, tail) = code.split_at(size);
+    *code = tail;
+    head
+}
+
+fn operand(code: &mut &[u8]) -> u32 {
+    u32::from_be_bytes(take(code, 4).try_into().unwrap())
+}
+
+fn local_id(address: u32) -> u32 {
+    assert_ne!(
+        address & LOCAL,
+        0,
+        "global address {} of a parameter",
+        address
+    );
+    address & !LOCAL
+}
+
+// a var parameter shares the slot of the argument, as in the interpreter
+type Slot = Rc<RefCell<Value<'static>>>;
+
+#[derive(Default)]
+struct Frame {
+    // the slots of each storage class, created by their first use
+    slots: HashMap<(usize, u32), Slot>,
+    references: HashSet<(usize, u32)>,
+    // the frame of the enclosing function, for a nested one
+    link: Option<usize>,
+    return_to: usize,
+}
+
+impl Frame {
+    fn slot(&mut self, class: usize, id: u32) -> Slot {
+        self.slots
+            .entry((class, id))
+            .or_insert_with(|| Rc::new(RefCell::new(default_value(class))))
+            .clone()
+    }
+}
+
+struct Machine<'c> {
+    globals: Frame,
+    frames: Vec<Frame>,
+    // the frames prepared by PARAM and PARAMI, with the function to call
+    prepared: Vec<(u32, Frame)>,
+    stack: Vec<Value<'static>>,
+    for_stack: Vec<Value<'static>>,
+    case_stack: Vec<Value<'static>>,
+    input: Lines<'c>,
+    output: String,
+}
+
+impl<'c> Machine<'c> {
+    fn execute(&mut self, program: &[Instruction]) -> Result<(), Exit> {
+        let labels: HashMap<u32, usize> = program
+            .iter()
+            .enumerate()
+            .filter(|(_, instruction)| instruction.op == opcode::LBL)
+            .map(|(pc, instruction)| (instruction.operands[0], pc))
+            .collect();
+        // the functions follow the main body in the order of their index
+        let functions: Vec<usize> = program
+            .iter()
+            .enumerate()
+            .filter(|(_, instruction)| instruction.op == opcode::FUNC)
+            .map(|(pc, _)| pc)
+            .collect();
+
+        let mut pc = 0;
+        loop {
+            let Instruction {
+                op,
+                operands,
+                constant,
+            } = &program[pc];
+            let op = *op;
+            pc += 1;
+            if let Some(value) = constant {
+                self.stack.push(value.clone());
+                continue;
+            }
+            if let Some((class, access)) = storage(op) {
+                self.access(class, access, operands);
+                continue;
+            }
+            if let Some(class) = READS.iter().position(|code| *code == op) {
+                let value = self.read(class);
+                self.stack.push(value);
+                continue;
+            }
+            if let Some(class) = WRITES.iter().position(|code| *code == op) {
+                let value = self.pop();
+                let expected = std::mem::discriminant(&default_value(class));
+                assert_eq!(
+                    std::mem::discriminant(&value),
+                    expected,
+                    "write of {:?}",
+                    value
+                );
+                self.output.push_str(&value.to_string());
+                continue;
+            }
+            match op {
+                opcode::VERSION | opcode::INIT | opcode::INITX | opcode::LBL | opcode::FLU => {}
+                opcode::FUNC => {}
+                opcode::FLN => self.output.push('\n'),
+                opcode::EXT => {
+                    // the breaks and the returns closed every for loop and case statement
+                    assert!(self.stack.is_empty(), "values left {:?}", self.stack);
+                    assert!(self.for_stack.is_empty() && self.case_stack.is_empty());
+                    assert!(self.frames.is_empty() && self.prepared.is_empty());
+                    return Ok(());
+                }
+                opcode::JUMP => pc = labels[&operands[0]],
+                opcode::JEQ | opcode::JNE => {
+                    if self.pop().to_bool() == (op == opcode::JEQ) {
+                        pc = labels[&operands[0]];
+                    }
+                }
+                opcode::BFOR => {
+                    let end = self.pop();
+                    self.for_stack.push(end);
+                }
+                opcode::CFOR => {
+                    let end = self.for_stack.last().unwrap().clone();
+                    self.stack.push(end);
+                }
+                opcode::EFOR => {
+                    self.for_stack.pop().unwrap();
+                }
+                opcode::BCASE => {
+                    let selector = self.pop();
+                    self.case_stack.push(selector);
+                }
+                opcode::CCASE => {
+                    let selector = self.case_stack.last().unwrap().clone();
+                    self.stack.push(selector);
+                }
+                opcode::ECASE => {
+                    self.case_stack.pop().unwrap();
+                }
+                opcode::PARAM => self.prepared.push((operands[0], Frame::default())),
+                opcode::PARAMI => match self.pop() {
+                    Value::Int(f_id) => self.prepared.push((f_id as u32, Frame::default())),
+                    value => panic!("PARAMI on {:?}", value),
+                },
+                opcode::CALL | opcode::CALLN | opcode::CALLI => {
+                    let (f_id, mut frame) = self.prepared.pop().unwrap();
+                    if op != opcode::CALLI {
+                        assert_eq!(f_id, operands[0], "call of a frame prepared for another");
+                    }
+                    if op == opcode::CALLN {
+                        frame.link = Some(self.enclosing(operands[1]));
+                    }
+                    frame.return_to = pc;
+                    self.frames.push(frame);
+                    pc = functions[f_id as usize];
+                }
+                opcode::RET => pc = self.frames.pop().unwrap().return_to,
+                opcode::CALLB => self.call_built_in(operands[0] as usize),
+                opcode::ASSERT => {
+                    let message = self.pop();
+                    if !self.pop().to_bool() {
+                        return Err(Exit::AssertFailed {
+                            message: message.to_string(),
+                            line: operands[0] as usize,
+                        });
+                    }
+                }
+                opcode::HALT => match self.pop() {
+                    Value::Int(code) => return Err(Exit::Halt(code)),
+                    value => panic!("HALT on {:?}", value),
+                },
+                opcode::IDXS => match (self.pop(), self.pop()) {
+                    (Value::Int(index), Value::Str(string)) => {
+                        self.stack.push(value::index(&string, index).unwrap())
+                    }
+                    (index, string) => panic!("IDXS on {:?} and {:?}", string, index),
+                },
+                opcode::NEGI | opcode::NEGL | opcode::NEGR | opcode::NOT => {
+                    let value = self.pop();
+                    self.stack.push(unary(op, value));
+                }
+                opcode::CSTI | opcode::CSTR | opcode::CSTIL | opcode::CSTRL => {
+                    let value = self.pop();
+                    self.stack.push(unary(op, value));
+                }
+                opcode::CSTLI | opcode::CSTLR | opcode::CSTICH | opcode::CSTCHI => {
+                    let value = self.pop();
+                    self.stack.push(unary(op, value));
+                }
+                _ => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    self.stack.push(binary(op, left, right));
+                }
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Value<'static> {
+        self.stack.pop().expect("pop from an empty stack")
+    }
+
+    fn access(&mut self, class: usize, access: usize, operands: &[u32]) {
+        match access {
+            LOAD | LOAD_UP | LOAD_REF | LOAD_REF_UP => {
+                let reference = access == LOAD_REF || access == LOAD_REF_UP;
+                let slot = self.slot(class, operands, Some(reference));
+                let value = slot.borrow().clone();
+                self.stack.push(value);
+            }
+            STORE | STORE_UP | STORE_REF | STORE_REF_UP => {
+                let reference = access == STORE_REF || access == STORE_REF_UP;
+                let value = self.pop();
+                let slot = self.slot(class, operands, Some(reference));
+                *slot.borrow_mut() = value;
+            }
+            STORE_PARAM => {
+                let value = self.pop();
+                let (_, frame) = self.prepared.last_mut().unwrap();
+                *frame.slot(class, local_id(operands[0])).borrow_mut() = value;
+            }
+            // the argument may be a reference itself
+            REF_PARAM | REF_PARAM_UP => {
+                let (parameter, argument) = operands.split_last().unwrap();
+                let slot = self.slot(class, argument, None);
+                let (_, frame) = self.prepared.last_mut().unwrap();
+                let key = (class, local_id(*parameter));
+                frame.slots.insert(key, slot);
+                frame.references.insert(key);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /*
+        The slot at an address, after the number of static
+        links to follow for an enclosing variable. The opcode
+        must tell a reference from a variable, when it does.
+    */
+    fn slot(&mut self, class: usize, operands: &[u32], reference: Option<bool>) -> Slot {
+        let (depth, address) = match operands {
+            [address] => (0, *address),
+            [depth, address] => (*depth, *address),
+            _ => unreachable!(),
+        };
+        let frame = if address & LOCAL == 0 {
+            assert_eq!(operands.len(), 1, "enclosing global address {}", address);
+            &mut self.globals
+        } else {
+            let index = self.enclosing(depth);
+            &mut self.frames[index]
+        };
+        let id = address & !LOCAL;
+        if let Some(reference) = reference {
+            let found = frame.references.contains(&(class, id));
+            assert_eq!(
+                found,
+                reference,
+                "opcode of a reference on {:?}",
+                (class, id)
+            );
+        }
+        frame.slot(class, id)
+    }
+
+    // the frame reached following depth static links from the current one
+    fn enclosing(&self, depth: u32) -> usize {
+        let mut index = self.frames.len() - 1;
+        for _ in 0..depth {
+            index = self.frames[index].link.expect("missing static link");
+        }
+        index
+    }
+
+    fn call_built_in(&mut self, id: usize) {
+        let func = built_in::built_in_names()
+            .into_iter()
+            .flat_map(|name| built_in::get_overloads(name))
+            .find(|func| {
+                built_in::find_built_in(func.name, func.params).map(|(found, _)| found) == Some(id)
+            })
+            .unwrap();
+        let args = self.stack.split_off(self.stack.len() - func.params.len());
+        let value = value::call_built_in(func.name, &args).unwrap();
+        self.stack.push(value);
+    }
+
+    // one value for each line of input
+    fn read(&mut self, class: usize) -> Value<'static> {
+        let line = self.input.next().expect("end of input");
+        let text = line.trim();
+        match default_value(class) {
+            Value::Int(_) => Value::Int(text.parse().unwrap()),
+            Value::Long(_) => Value::Long(text.parse().unwrap()),
+            Value::Real(_) => Value::Real(text.parse().unwrap()),
+            Value::Bool(_) => Value::Bool(text.parse().unwrap()),
+            Value::Char(_) => Value::Char(text.parse().unwrap()),
+            _ => Value::Str(line.to_owned()),
+        }
+    }
+}
+
+fn unary(op: u8, value: Value) -> Value {
+    match (op, value) {
+        (opcode::NEGI, Value::Int(value)) => Value::Int(-value),
+        (opcode::NEGL, Value::Long(value)) => Value::Long(-value),
+        (opcode::NEGR, Value::Real(value)) => Value::Real(-value),
+        (opcode::NOT, Value::Bool(value)) => Value::Bool(!value),
+        (opcode::CSTI, Value::Real(value)) => Value::Int(value as i32),
+        (opcode::CSTR, Value::Int(value)) => Value::Real(value.into()),
+        (opcode::CSTIL, Value::Long(value)) => Value::Int(value as i32),
+        (opcode::CSTRL, Value::Long(value)) => Value::Real(value as f64),
+        (opcode::CSTLI, Value::Int(value)) => Value::Long(value.into()),
+        (opcode::CSTLR, Value::Real(value)) => Value::Long(value as i64),
+        (opcode::CSTICH, Value::Char(value)) => Value::Int(value as i32),
+        (opcode::CSTCHI, Value::Int(value)) => {
+            Value::Char(std::char::from_u32(value as u32).unwrap())
+        }
+        (op, value) => panic!("opcode {} on {:?}", op, value),
+    }
+}
+
+fn binary<'a>(op: u8, left: Value<'a>, right: Value<'a>) -> Value<'a> {
+    use opcode::*;
+    // the arithmetic opcodes and the comparisons for the kind of the operands
+    let (arithmetic, comparison): (&[u8], [u8; 6]) = match (&left, &right) {
+        (Value::Int(_), Value::Int(_)) => (
+            &[ADDI, SUBI, MULI, DIVI],
+            [GEQI, GRI, LEQI, LESQI, EQI, NEI],
+        ),
+        (Value::Long(_), Value::Long(_)) => (
+            &[ADDL, SUBL, MULL, DIVL],
+            [GEQL, GRL, LEQL, LESQL, EQL, NEL],
+        ),
+        (Value::Real(_), Value::Real(_)) => (
+            &[ADDR, SUBR, MULR, DIVR],
+            [GEQR, GRR, LEQR, LESQR, EQR, NER],
+        ),
+        (Value::Str(_), Value::Str(_)) => (&[], [GEQS, GRS, LEQS, LESQS, EQS, NES]),
+        (Value::Char(_), Value::Char(_)) => (&[], [GEQCH, GRCH, LEQCH, LESQCH, EQCH, NECH]),
+        (Value::Bool(_), Value::Bool(_)) => (&[], [GEQB, GRB, LEQB, LESQB, EQB, NEB]),
+        _ => panic!("opcode {} on {:?} and {:?}", op, left, right),
+    };
+    if let Some(index) = comparison.iter().position(|code| *code == op) {
+        let ordering = match (&left, &right) {
+            (Value::Int(l), Value::Int(r)) => l.partial_cmp(r),
+            (Value::Long(l), Value::Long(r)) => l.partial_cmp(r),
+            (Value::Real(l), Value::Real(r)) => l.partial_cmp(r),
+            (Value::Str(l), Value::Str(r)) => l.partial_cmp(r),
+            (Value::Char(l), Value::Char(r)) => l.partial_cmp(r),
+            (Value::Bool(l), Value::Bool(r)) => l.partial_cmp(r),
+            _ => unreachable!(),
+        }
+        .unwrap();
+        let results = [
+            ordering != Ordering::Less,
+            ordering == Ordering::Greater,
+            ordering != Ordering::Greater,
+            ordering == Ordering::Less,
+            ordering == Ordering::Equal,
+            ordering != Ordering::Equal,
+        ];
+        return Value::Bool(results[index]);
+    }
+    let index = arithmetic
+        .iter()
+        .position(|code| *code == op)
+        .unwrap_or_else(|| panic!("opcode {} on {:?} and {:?}", op, left, right));
+    match (left, right) {
+        (Value::Int(l), Value::Int(r)) => Value::Int(arithmetic_op(index, l, r)),
+        (Value::Long(l), Value::Long(r)) => Value::Long(arithmetic_op(index, l, r)),
+        (Value::Real(l), Value::Real(r)) => Value::Real(arithmetic_op(index, l, r)),
+        _ => unreachable!(),
+    }
+}
+
+fn arithmetic_op<T>(index: usize, left: T, right: T) -> T
+where
+    T: Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Div<Output = T>,
+{
+    match index {
+        0 => left + right,
+        1 => left - right,
+        2 => left * right,
+        _ => left / right,
+    }
+}
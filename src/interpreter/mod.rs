@@ -0,0 +1,850 @@
+#[cfg(test)]
+mod byte_code;
+mod value;
+
+pub use value::Value;
+
+use crate::generator::STEP_MESSAGE;
+use extract_line_error::SourceMap;
+use simpla_parser::syntax_tree::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+use std::thread;
+
+// deeper recursions would exhaust the stack of the interpreter
+const MAX_CALL_DEPTH: usize = 1000;
+
+/*
+    The stack the interpreter needs to reach MAX_CALL_DEPTH
+    in a debug build, where a nested call takes tens of
+    kilobytes, with a wide margin for calls inside deeply
+    nested statements; the main thread has only 8 MB.
+*/
+const STACK_SIZE: usize = 256 << 20;
+
+/*
+    Runs the code on a thread with a stack large enough
+    for the interpreter, so that a runaway recursion is
+    reported as an error instead of aborting the process.
+*/
+pub fn with_interpreter_stack<T, F>(f: F) -> T
+where
+    T: Send,
+    F: FnOnce() -> T + Send,
+{
+    thread::scope(|scope| {
+        let handle = thread::Builder::new()
+            .name("interpreter".to_owned())
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, f)
+            .expect("cannot start the interpreter thread");
+        match handle.join() {
+            Ok(output) => output,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    })
+}
+
+/*
+    Runs a checked syntax tree directly, without translating
+    it to bytecode: the kinds and the implicit conversions
+    recorded by the semantic analysis are all it needs.
+    Global variables and functions persist between two
+    executions, so that a program can be run one statement
+    at a time and grow between them.
+    It serves the REPL only: compiled programs run on the
+    virtual machine, which takes a whole program and is not
+    part of this repository. Its results must match the
+    ones of the generated bytecode: the tests run every
+    sample program both ways, the bytecode on a reader of
+    the opcodes, and compare the output. The bytecode
+    formats and their limits do not apply.
+*/
+#[derive(Default)]
+pub struct Interpreter<'a> {
+    variables: HashMap<&'a str, Slot<'a>>,
+    functions: HashMap<&'a str, &'a FuncDecl>,
+}
+
+// a var parameter shares the slot of the argument
+type Slot<'a> = Rc<RefCell<Value<'a>>>;
+
+/*
+    A function activation: the static link is the frame
+    of the function declaring it, None for a top level one.
+*/
+struct Frame<'a> {
+    variables: HashMap<&'a str, Slot<'a>>,
+    functions: HashMap<&'a str, &'a FuncDecl>,
+    parent: Scope<'a>,
+}
+
+// None is the main body, that only sees the globals
+type Scope<'a> = Option<Rc<Frame<'a>>>;
+
+pub struct Console<'c> {
+    pub input: &'c mut dyn BufRead,
+    pub output: &'c mut dyn Write,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Stop {
+    Error { message: String, loc: Location },
+    AssertFailed { message: String, loc: Location },
+    Halt(i32),
+}
+
+impl Stop {
+    fn error(message: String, loc: &Location) -> Self {
+        Self::Error {
+            message,
+            loc: loc.clone(),
+        }
+    }
+
+    pub fn format(&self, code: &SourceMap) -> String {
+        match self {
+            Self::Error { message, loc } => format!(
+                "runtime error: {}\n{}",
+                message,
+                code.extract_error_code(loc.begin, loc.end)
+            ),
+            Self::AssertFailed { message, loc } => format!(
+                "assertion failed at line {}: {}",
+                code.line_number(loc.begin),
+                message
+            ),
+            Self::Halt(code) => format!("halted with exit code {}", code),
+        }
+    }
+}
+
+enum Flow<'a> {
+    Next,
+    Break(&'a Option<String>),
+    Continue(&'a Option<String>),
+    Return(Value<'a>),
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn declare_variables(&mut self, var_decl: &'a VarDecl) {
+        for id in &var_decl.id_list {
+            let slot = Rc::new(RefCell::new(Value::default_of(&var_decl.kind)));
+            self.variables.insert(id, slot);
+        }
+    }
+
+    pub fn declare_function(&mut self, func_decl: &'a FuncDecl) {
+        self.functions.insert(&func_decl.id, func_decl);
+    }
+
+    pub fn execute(&self, stat: &'a Stat, console: &mut Console) -> Result<(), Stop> {
+        let mut execution = Execution {
+            globals: self,
+            console,
+            depth: 0,
+        };
+        execution.stat(stat, &None).map(|_| ())
+    }
+
+    pub fn evaluate(&self, expr: &'a Expr, console: &mut Console) -> Result<Value<'a>, Stop> {
+        let mut execution = Execution {
+            globals: self,
+            console,
+            depth: 0,
+        };
+        execution.expr(expr, &None)
+    }
+}
+
+struct Execution<'i, 'a, 'c, 'd> {
+    globals: &'i Interpreter<'a>,
+    console: &'c mut Console<'d>,
+    depth: usize,
+}
+
+impl<'i, 'a, 'c, 'd> Execution<'i, 'a, 'c, 'd> {
+    fn stat_list(&mut self, stat_list: &'a [Stat], scope: &Scope<'a>) -> Result<Flow<'a>, Stop> {
+        for stat in stat_list {
+            match self.stat(stat, scope)? {
+                Flow::Next => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn stat(&mut self, stat: &'a Stat, scope: &Scope<'a>) -> Result<Flow<'a>, Stop> {
+        match &stat.stat {
+            StatType::AssignStat(assign) => {
+                let value = self.expr(&assign.expr, scope)?;
+                *self.variable(&assign.id, scope).borrow_mut() = value;
+            }
+            StatType::IfStat(if_stat) => {
+                if self.expr(&if_stat.cond, scope)?.to_bool() {
+                    return self.stat_list(&if_stat.if_body, scope);
+                } else if let Some(else_body) = &if_stat.else_body {
+                    return self.stat_list(else_body, scope);
+                }
+            }
+            StatType::WhileStat(while_stat) => {
+                while self.expr(&while_stat.cond, scope)?.to_bool() {
+                    let flow = self.stat_list(&while_stat.body, scope)?;
+                    if let Some(flow) = leave_loop(flow, &while_stat.label) {
+                        return Ok(flow);
+                    }
+                }
+            }
+            StatType::ForStat(for_stat) => return self.for_stat(for_stat, scope, &stat.loc),
+            StatType::RepeatStat(repeat_stat) => loop {
+                let flow = self.stat_list(&repeat_stat.body, scope)?;
+                if let Some(flow) = leave_loop(flow, &repeat_stat.label) {
+                    return Ok(flow);
+                }
+                if self.expr(&repeat_stat.cond, scope)?.to_bool() {
+                    break;
+                }
+            },
+            StatType::CaseStat(case_stat) => return self.case_stat(case_stat, scope),
+            StatType::ReturnStat(expr) => {
+                let value = match expr {
+                    Some(expr) => self.expr(expr, scope)?,
+                    None => Value::Void,
+                };
+                return Ok(Flow::Return(value));
+            }
+            StatType::ReadStat(id_list) => {
+                for id in id_list {
+                    let slot = self.variable(id, scope);
+                    let kind = kind_of(&slot.borrow());
+                    let value = self.read_value(&kind, &stat.loc)?;
+                    *slot.borrow_mut() = value;
+                }
+            }
+            StatType::WriteStat(write_stat) => self.write_stat(write_stat, scope, &stat.loc)?,
+            StatType::FuncCall(func_call) => {
+                self.func_call(func_call, scope, &stat.loc)?;
+            }
+            StatType::Break(label) => return Ok(Flow::Break(label)),
+            StatType::Continue(label) => return Ok(Flow::Continue(label)),
+            StatType::AssertStat(assert_stat) => {
+                if !self.expr(&assert_stat.cond, scope)?.to_bool() {
+                    let message = self.expr(&assert_stat.message, scope)?.to_string();
+                    return Err(Stop::AssertFailed {
+                        message,
+                        loc: stat.loc.clone(),
+                    });
+                }
+            }
+            StatType::HaltStat(code) => match self.expr(code, scope)? {
+                Value::Int(code) => return Err(Stop::Halt(code)),
+                value => unreachable!("exit code {:?}", value),
+            },
+        }
+        Ok(Flow::Next)
+    }
+
+    /*
+        The end and the step are evaluated once, like the
+        compiled code does, and a step that is not positive
+        fails as the ASSERT emitted by the generator.
+    */
+    fn for_stat(
+        &mut self,
+        for_stat: &'a ForStat,
+        scope: &Scope<'a>,
+        loc: &Location,
+    ) -> Result<Flow<'a>, Stop> {
+        let slot = self.variable(&for_stat.id, scope);
+        let begin = self.expr(&for_stat.begin_expr, scope)?;
+        *slot.borrow_mut() = begin;
+        let end = self.expr(&for_stat.end_expr, scope)?;
+        let step = match &for_stat.step {
+            Some(step) => match self.expr(step, scope)? {
+                Value::Int(value) if value <= 0 => {
+                    return Err(Stop::AssertFailed {
+                        message: STEP_MESSAGE.to_owned(),
+                        loc: step.loc.clone(),
+                    })
+                }
+                value => value,
+            },
+            None => Value::Int(1),
+        };
+        loop {
+            let current = slot.borrow().clone();
+            let op = match for_stat.direction {
+                ForDirection::Up => Operator::LessEqual,
+                ForDirection::Down => Operator::GreaterEqual,
+            };
+            let proceed = value::apply_operator(current, &op, end.clone());
+            if !proceed.map_err(|err| Stop::error(err, loc))?.to_bool() {
+                return Ok(Flow::Next);
+            }
+            let flow = self.stat_list(&for_stat.body, scope)?;
+            if let Some(flow) = leave_loop(flow, &for_stat.label) {
+                return Ok(flow);
+            }
+            let op = match for_stat.direction {
+                ForDirection::Up => Operator::Add,
+                ForDirection::Down => Operator::Sub,
+            };
+            let current = slot.borrow().clone();
+            let next = value::apply_operator(current, &op, step.clone());
+            *slot.borrow_mut() = next.map_err(|err| Stop::error(err, loc))?;
+        }
+    }
+
+    fn case_stat(&mut self, case_stat: &'a CaseStat, scope: &Scope<'a>) -> Result<Flow<'a>, Stop> {
+        let selector = self.expr(&case_stat.expr, scope)?;
+        for branch in &case_stat.branches {
+            if branch
+                .labels
+                .iter()
+                .any(|label| constant(&label.value) == selector)
+            {
+                return self.stat_list(&branch.body, scope);
+            }
+        }
+        match &case_stat.else_body {
+            Some(else_body) => self.stat_list(else_body, scope),
+            None => Ok(Flow::Next),
+        }
+    }
+
+    fn write_stat(
+        &mut self,
+        write_stat: &'a WriteStat,
+        scope: &Scope<'a>,
+        loc: &Location,
+    ) -> Result<(), Stop> {
+        let (expr_list, new_line) = match write_stat {
+            WriteStat::Write(expr_list) => (expr_list, false),
+            WriteStat::WriteLine(expr_list) => (expr_list, true),
+        };
+        // as in the bytecode, a value is written before the calls of the next one run
+        for expr in expr_list {
+            let value = self.expr(expr, scope)?;
+            self.write(&value.to_string(), loc)?;
+        }
+        if new_line {
+            self.write("\n", loc)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, text: &str, loc: &Location) -> Result<(), Stop> {
+        let output = &mut self.console.output;
+        output
+            .write_all(text.as_bytes())
+            .and_then(|_| output.flush())
+            .map_err(|err| Stop::error(err.to_string(), loc))
+    }
+
+    // one value for each line of input
+    fn read_value(&mut self, kind: &Kind, loc: &Location) -> Result<Value<'a>, Stop> {
+        let mut line = String::new();
+        let read = self.console.input.read_line(&mut line);
+        match read {
+            Ok(0) => return Err(Stop::error("end of input".to_owned(), loc)),
+            Ok(_) => {}
+            Err(err) => return Err(Stop::error(err.to_string(), loc)),
+        }
+        let line = line.trim_end_matches(['\n', '\r']);
+        let text = line.trim();
+        let value = match kind {
+            Kind::Int => text.parse().map(Value::Int).ok(),
+            Kind::Long => text.parse().map(Value::Long).ok(),
+            Kind::Real => text.parse().map(Value::Real).ok(),
+            Kind::Bool => text.parse().map(Value::Bool).ok(),
+            Kind::Char => text.parse().map(Value::Char).ok(),
+            _ => Some(Value::Str(line.to_owned())),
+        };
+        value.ok_or_else(|| {
+            let message = format!("cannot read {:?} as {}", text, kind_name(kind));
+            Stop::error(message, loc)
+        })
+    }
+
+    fn expr(&mut self, expr: &'a Expr, scope: &Scope<'a>) -> Result<Value<'a>, Stop> {
+        let value = match &expr.expr {
+            ExprTree::Node(left, op, right) => self.node(left, op, right, scope, &expr.loc)?,
+            ExprTree::Factor(fact) => self.factor(fact, scope, &expr.loc)?,
+        };
+        match expr.coercion.borrow().as_ref() {
+            Some(target) => value
+                .cast(target)
+                .map_err(|err| Stop::error(err, &expr.loc)),
+            None => Ok(value),
+        }
+    }
+
+    fn node(
+        &mut self,
+        left: &'a Expr,
+        op: &'a Operator,
+        right: &'a Expr,
+        scope: &Scope<'a>,
+        loc: &Location,
+    ) -> Result<Value<'a>, Stop> {
+        let left = self.expr(left, scope)?;
+        match (op, left.to_bool()) {
+            (Operator::And, false) => return Ok(Value::Bool(false)),
+            (Operator::Or, true) => return Ok(Value::Bool(true)),
+            _ => {}
+        }
+        let right = self.expr(right, scope)?;
+        value::apply_operator(left, op, right).map_err(|err| Stop::error(err, loc))
+    }
+
+    fn factor(
+        &mut self,
+        fact: &'a Factor,
+        scope: &Scope<'a>,
+        loc: &Location,
+    ) -> Result<Value<'a>, Stop> {
+        match &fact.fact {
+            FactorValue::Id(id) => match find_variable(id, scope, self.globals) {
+                Some(slot) => Ok(slot.borrow().clone()),
+                None => Ok(Value::Func(
+                    self.globals.functions.get(id.as_str()).copied(),
+                )),
+            },
+            FactorValue::UnaryOp(UnaryOp::Minus(fact))
+            | FactorValue::UnaryOp(UnaryOp::Negate(fact)) => {
+                let value = self.factor(fact, scope, loc)?;
+                value::negate(value).map_err(|err| Stop::error(err, loc))
+            }
+            FactorValue::CondExpr(cond_expr) => {
+                if self.expr(&cond_expr.cond, scope)?.to_bool() {
+                    self.expr(&cond_expr.true_stat, scope)
+                } else {
+                    self.expr(&cond_expr.false_stat, scope)
+                }
+            }
+            FactorValue::CastExpr(cast) => {
+                let (expr, target) = match cast {
+                    CastExpr::Integer(expr) => (expr, Kind::Int),
+                    CastExpr::Long(expr) => (expr, Kind::Long),
+                    CastExpr::Real(expr) => (expr, Kind::Real),
+                    CastExpr::Char(expr) => (expr, Kind::Char),
+                };
+                let value = self.expr(expr, scope)?;
+                value.cast(&target).map_err(|err| Stop::error(err, loc))
+            }
+            FactorValue::FuncCall(func_call) => self.func_call(func_call, scope, loc),
+            FactorValue::Const(value) => Ok(constant(value)),
+            FactorValue::HighPrecedence(expr) => self.expr(expr, scope),
+            FactorValue::IndexExpr(index) => {
+                let string = self.variable(&index.id, scope).borrow().clone();
+                let position = self.expr(&index.index, scope)?;
+                match (string, position) {
+                    (Value::Str(string), Value::Int(position)) => {
+                        value::index(&string, position).map_err(|err| Stop::error(err, loc))
+                    }
+                    (string, position) => unreachable!("index {:?}[{:?}]", string, position),
+                }
+            }
+        }
+    }
+
+    /*
+        A variable holding a function value hides a function
+        with the same name; the static link of a declared
+        function is the frame of the function declaring it.
+    */
+    fn func_call(
+        &mut self,
+        func_call: &'a FuncCall,
+        scope: &Scope<'a>,
+        loc: &Location,
+    ) -> Result<Value<'a>, Stop> {
+        if let Some(slot) = find_variable(&func_call.id, scope, self.globals) {
+            let callee = slot.borrow().clone();
+            return match callee {
+                Value::Func(Some(func_decl)) => {
+                    self.call(func_decl, None, &func_call.args, scope, loc)
+                }
+                _ => Err(Stop::error(
+                    format!("{} has no function assigned", func_call.id),
+                    loc,
+                )),
+            };
+        }
+        let mut frame = scope.clone();
+        while let Some(current) = frame {
+            if let Some(func_decl) = current.functions.get(func_call.id.as_str()) {
+                return self.call(
+                    func_decl,
+                    Some(current.clone()),
+                    &func_call.args,
+                    scope,
+                    loc,
+                );
+            }
+            frame = current.parent.clone();
+        }
+        if let Some(func_decl) = self.globals.functions.get(func_call.id.as_str()) {
+            return self.call(func_decl, None, &func_call.args, scope, loc);
+        }
+        let mut args = Vec::with_capacity(func_call.args.len());
+        for arg in &func_call.args {
+            args.push(self.expr(arg, scope)?);
+        }
+        value::call_built_in(&func_call.id, &args).map_err(|err| Stop::error(err, loc))
+    }
+
+    fn call(
+        &mut self,
+        func_decl: &'a FuncDecl,
+        parent: Scope<'a>,
+        args: &'a [Expr],
+        scope: &Scope<'a>,
+        loc: &Location,
+    ) -> Result<Value<'a>, Stop> {
+        if self.depth == MAX_CALL_DEPTH {
+            let message = format!("more than {} nested function calls", MAX_CALL_DEPTH);
+            return Err(Stop::error(message, loc));
+        }
+        let mut variables = HashMap::new();
+        for (param, arg) in func_decl.params.iter().zip(args) {
+            let slot = match (&arg.expr, param.by_ref) {
+                (
+                    ExprTree::Factor(Factor {
+                        fact: FactorValue::Id(name),
+                        ..
+                    }),
+                    true,
+                ) => self.variable(name, scope),
+                _ => Rc::new(RefCell::new(self.expr(arg, scope)?)),
+            };
+            variables.insert(param.id.as_str(), slot);
+        }
+        for var_decl in &func_decl.vars {
+            for id in &var_decl.id_list {
+                let slot = Rc::new(RefCell::new(Value::default_of(&var_decl.kind)));
+                variables.insert(id.as_str(), slot);
+            }
+        }
+        let functions = func_decl
+            .functions
+            .iter()
+            .map(|nested| (nested.id.as_str(), nested))
+            .collect();
+        let frame = Some(Rc::new(Frame {
+            variables,
+            functions,
+            parent,
+        }));
+
+        self.depth += 1;
+        let flow = self.stat_list(&func_decl.body, &frame);
+        self.depth -= 1;
+        match flow? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(Value::Void),
+        }
+    }
+
+    fn variable(&self, name: &str, scope: &Scope<'a>) -> Slot<'a> {
+        find_variable(name, scope, self.globals).expect("variable checked by the semantic analysis")
+    }
+}
+
+fn find_variable<'a>(name: &str, scope: &Scope<'a>, globals: &Interpreter<'a>) -> Option<Slot<'a>> {
+    let mut frame = scope.as_ref();
+    while let Some(current) = frame {
+        if let Some(slot) = current.variables.get(name) {
+            return Some(slot.clone());
+        }
+        frame = current.parent.as_ref();
+    }
+    globals.variables.get(name).cloned()
+}
+
+// the flow after a loop iteration, None to keep looping
+fn leave_loop<'a>(flow: Flow<'a>, label: &Option<String>) -> Option<Flow<'a>> {
+    match flow {
+        Flow::Next => None,
+        Flow::Break(target) if target.is_none() || target == label => Some(Flow::Next),
+        Flow::Continue(target) if target.is_none() || target == label => None,
+        flow => Some(flow),
+    }
+}
+
+fn constant<'a>(value: &Const) -> Value<'a> {
+    match value {
+        Const::IntConst(value) => Value::Int(*value),
+        Const::LongConst(value) => Value::Long(*value),
+        Const::RealConst(value) => Value::Real(*value),
+        Const::StrConst(value) => Value::Str(value.clone()),
+        Const::CharConst(value) => Value::Char(*value),
+        Const::BoolConst(value) => Value::Bool(*value),
+    }
+}
+
+fn kind_of(value: &Value) -> Kind {
+    match value {
+        Value::Int(_) => Kind::Int,
+        Value::Long(_) => Kind::Long,
+        Value::Real(_) => Kind::Real,
+        Value::Str(_) => Kind::Str,
+        Value::Char(_) => Kind::Char,
+        Value::Bool(_) => Kind::Bool,
+        Value::Func(_) | Value::Void => Kind::Void,
+    }
+}
+
+fn kind_name(kind: &Kind) -> String {
+    simpla_parser::printer::kind_name(kind)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::byte_code::{self, Exit};
+    use super::*;
+    use crate::generator::{translate_to_byte_code, ByteCodeFormat};
+    use crate::semantic_analysis::{analyze_program, Promotion};
+    use simpla_parser::ProgramParser;
+    use std::fs;
+    use std::path::Path;
+
+    fn run(code: &str, input: &str) -> (String, Result<(), Stop>) {
+        let program = ProgramParser::new().parse(code).unwrap();
+        let sources = SourceMap::from(code);
+        analyze_program(&program, &sources, Promotion::Allow).unwrap();
+
+        let mut interpreter = Interpreter::new();
+        for var_decl in &program.global_vars {
+            interpreter.declare_variables(var_decl);
+        }
+        for func_decl in &program.functions {
+            interpreter.declare_function(func_decl);
+        }
+        let mut input = input.as_bytes();
+        let mut output = Vec::new();
+        let mut console = Console {
+            input: &mut input,
+            output: &mut output,
+        };
+        let res = program
+            .body
+            .iter()
+            .try_for_each(|stat| interpreter.execute(stat, &mut console));
+        (String::from_utf8(output).unwrap(), res)
+    }
+
+    /*
+        Runs the code with the interpreter and its version 2
+        bytecode with the reader of the tests: the output and
+        the way the program stops of the first, then the ones
+        of the second.
+    */
+    fn run_both(code: &str, input: &str) -> [(String, Result<(), Exit>); 2] {
+        let (output, res) = run(code, input);
+        let sources = SourceMap::from(code);
+        let res = res.map_err(|stop| match stop {
+            Stop::AssertFailed { message, loc } => Exit::AssertFailed {
+                message,
+                line: sources.line_number(loc.begin),
+            },
+            Stop::Halt(code) => Exit::Halt(code),
+            Stop::Error { message, .. } => panic!("runtime error: {}", message),
+        });
+
+        let program = ProgramParser::new().parse(code).unwrap();
+        analyze_program(&program, &sources, Promotion::Allow).unwrap();
+        let byte_code = translate_to_byte_code(&program, &sources, ByteCodeFormat::V2).unwrap();
+        [(output, res), byte_code::run(&byte_code, input)]
+    }
+
+    #[test]
+    fn test_byte_code_agreement() {
+        let exprs = [
+            "1 + 2.5",
+            "7 / 2",
+            "-7 / 2",
+            "7.0 / 2",
+            "real(7) / 2",
+            "integer(3.9) + 1",
+            "integer(-3.9)",
+            "long(2147483647) + 1",
+            "5L + 1",
+            "-(2.5)",
+            "not (1 > 2)",
+            "2 * 3.5 > 6",
+            "\"abc\" < \"abd\"",
+            "'a' < 'b'",
+            "char(98)",
+            "integer('a') + 1",
+            "true > false",
+            "false or 3.0 / 2 > 1",
+            "true and 1.5 < 1",
+            "if 1.5 > 1 then \"yes\" else \"no\" end",
+        ];
+        let body: String = exprs
+            .iter()
+            .map(|expr| format!("writeln({});\n", expr))
+            .collect();
+        let code = format!("body\n{}end.", body);
+        let [interpreted, compiled] = run_both(&code, "");
+        assert_eq!(interpreted.1, Ok(()));
+        assert_eq!(interpreted, compiled);
+    }
+
+    // the input of each run of the sample programs, some run twice
+    const SAMPLE_INPUTS: &[(&str, &str)] = &[
+        ("assertions", "17\n"),
+        ("assertions", "-4\n"),
+        ("calculator", "3\n+\n4.5\ny\n1\n%\n2\ny\n7\n/\n2\nn\n"),
+        ("circle", "2.5\n"),
+        ("compare_string", "a\na\na\nb\nb\na\na\nb\nb\na\na\nb\n"),
+        ("count_steps", "7\n"),
+        ("escapes", "Ada\n"),
+        ("factorial", "12\n"),
+        ("factorial_long", "20\n"),
+        ("fibonacci", "20\n"),
+        ("fibonacci_long", "60\n"),
+        ("higher_order", "4\n"),
+        ("higher_order", "2\n"),
+        ("math_functions", "3\n-5\n"),
+        ("nested_functions", "4\n"),
+        ("palindrome", "level\n"),
+        ("palindrome", "zebra\n"),
+        ("prime_numbers", "100\n"),
+        ("square_root", "2\n"),
+        ("square_root", "-1\n"),
+        ("swap", "3\n1\n2\n"),
+        ("test_compare", "Filippo\n"),
+        ("test_compare", "Ada\n"),
+        ("unary_test", ""),
+    ];
+
+    #[test]
+    fn test_sample_programs_agreement() {
+        let dir = Path::new("tests").join("simpla_programs");
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_stem().unwrap().to_str().unwrap();
+            assert!(
+                SAMPLE_INPUTS.iter().any(|(sample, _)| *sample == name),
+                "no input for {}",
+                name
+            );
+        }
+        for (name, input) in SAMPLE_INPUTS {
+            let code = fs::read_to_string(dir.join(format!("{}.simpla", name))).unwrap();
+            let [interpreted, compiled] = run_both(&code, input);
+            assert_eq!(interpreted, compiled, "{} with input {:?}", name, input);
+        }
+    }
+
+    #[test]
+    fn test_sample_programs() {
+        let code = include_str!("../../tests/simpla_programs/higher_order.simpla");
+        let (output, res) = run(code, "4\n");
+        assert_eq!(res, Ok(()));
+        assert_eq!(
+            output,
+            "Insert a number Sum of doubles: 20\nSum of squares: 30\nApplied twice: 256\n"
+        );
+
+        let code = include_str!("../../tests/simpla_programs/nested_functions.simpla");
+        let (output, res) = run(code, "4\n");
+        assert_eq!(res, Ok(()));
+        // the text before the call is written first, as by the bytecode
+        assert_eq!(
+            output,
+            "Insert a number Sum of squares: square called 4 times\n30\n"
+        );
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let code = "i, j: integer;
+            s: string;
+            body
+                outer: for i = 1 to 10 do
+                    for j = 1 to 10 do
+                        if j > i then continue outer; end;
+                        if i * j > 6 then break outer; end;
+                        write(i * j, \" \");
+                    end;
+                end;
+                case i of
+                    1, 2: s = \"small\";
+                    3: s = \"three\";
+                else
+                    s = \"big\";
+                end;
+                writeln(s, \" \", 1 + 2.5, \" \", false or 3 / i > 0);
+            end.";
+        let (output, res) = run(code, "");
+        assert_eq!(res, Ok(()));
+        assert_eq!(output, "1 2 4 3 6 three 3.5 true\n");
+    }
+
+    #[test]
+    fn test_for_step_evaluated_once() {
+        let code = "i, s: integer;
+            body
+                s = 2;
+                for i = 1 to 9 step s do
+                    s = s + 1;
+                    write(i, \" \");
+                end;
+                for i = 9 downto 1 step s - 2 do
+                    write(i, \" \");
+                end;
+            end.";
+        let (output, res) = run(code, "");
+        assert_eq!(res, Ok(()));
+        assert_eq!(output, "1 3 5 7 9 9 4 ");
+
+        let code = "i, s: integer;
+            body
+                s = 0;
+                for i = 1 to 9 step s do
+                    writeln(i);
+                end;
+            end.";
+        let (output, res) = run(code, "");
+        assert_eq!(output, "");
+        match res {
+            Err(Stop::AssertFailed { message, .. }) => assert_eq!(message, STEP_MESSAGE),
+            res => panic!("unexpected {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_stop() {
+        let code = "n: integer;
+            body
+                read(n);
+                writeln(n);
+                n = 10 / (n - 2);
+            end.";
+        let (output, res) = run(code, "2\n");
+        assert_eq!(output, "2\n");
+        match res {
+            Err(Stop::Error { message, .. }) => assert_eq!(message, "division by zero"),
+            res => panic!("unexpected {:?}", res),
+        }
+
+        let (_, res) = run(code, "two\n");
+        assert!(matches!(res, Err(Stop::Error { .. })));
+
+        let code = "body assert(1 > 2, \"wrong\"); end.";
+        let (_, res) = run(code, "");
+        assert!(matches!(res, Err(Stop::AssertFailed { .. })));
+        let (_, res) = run("body halt(4); writeln(1); end.", "");
+        assert_eq!(res, Err(Stop::Halt(4)));
+    }
+}
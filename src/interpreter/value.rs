@@ -0,0 +1,315 @@
+use simpla_parser::syntax_tree::{FuncDecl, Kind, Operator};
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+
+/*
+    Values of the checked program: the semantic analysis
+    guarantees that the operands of every operation have the
+    same variant, so a mismatch is a bug of the analysis.
+    Integer arithmetic is checked and reports overflows
+    instead of wrapping. A function value refers to a top
+    level function, None until a function is assigned.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value<'a> {
+    Int(i32),
+    Long(i64),
+    Real(f64),
+    Str(String),
+    Char(char),
+    Bool(bool),
+    Func(Option<&'a FuncDecl>),
+    Void,
+}
+
+impl<'a> Value<'a> {
+    // initial value of a declared variable
+    pub fn default_of(kind: &Kind) -> Self {
+        match kind {
+            Kind::Int => Self::Int(0),
+            Kind::Long => Self::Long(0),
+            Kind::Real => Self::Real(0.0),
+            Kind::Str => Self::Str(String::new()),
+            Kind::Char => Self::Char('\0'),
+            Kind::Bool => Self::Bool(false),
+            Kind::Func(_) => Self::Func(None),
+            Kind::Void => Self::Void,
+        }
+    }
+
+    // explicit casts and implicit conversions
+    pub fn cast(self, target: &Kind) -> Result<Self, String> {
+        match (self, target) {
+            (Self::Int(value), Kind::Long) => Ok(Self::Long(value.into())),
+            (Self::Int(value), Kind::Real) => Ok(Self::Real(value.into())),
+            (Self::Int(value), Kind::Char) => u32::try_from(value)
+                .ok()
+                .and_then(std::char::from_u32)
+                .map(Self::Char)
+                .ok_or_else(|| format!("{} is not a valid character code", value)),
+            (Self::Long(value), Kind::Int) => i32::try_from(value)
+                .map(Self::Int)
+                .map_err(|_| format!("{} does not fit in an integer", value)),
+            (Self::Long(value), Kind::Real) => Ok(Self::Real(value as f64)),
+            (Self::Real(value), Kind::Int) => truncate(value, i32::MIN.into(), i32::MAX.into())
+                .map(|value| Self::Int(value as i32)),
+            (Self::Real(value), Kind::Long) => truncate(value, i64::MIN as f64, i64::MAX as f64)
+                .map(|value| Self::Long(value as i64)),
+            (Self::Char(value), Kind::Int) => Ok(Self::Int(u32::from(value) as i32)),
+            (value, _) => Ok(value),
+        }
+    }
+
+    pub fn to_bool(&self) -> bool {
+        matches!(self, Self::Bool(true))
+    }
+}
+
+fn truncate(value: f64, min: f64, max: f64) -> Result<f64, String> {
+    let value = value.trunc();
+    if value.is_nan() || value < min || value > max {
+        Err(format!(
+            "{:?} is out of the range of the target type",
+            value
+        ))
+    } else {
+        Ok(value)
+    }
+}
+
+/*
+    The text written by write and writeln: the same of
+    the virtual machine, that writes a real in decimal
+    notation, never with an exponent.
+*/
+impl<'a> fmt::Display for Value<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Int(value) => write!(f, "{}", value),
+            Self::Long(value) => write!(f, "{}", value),
+            Self::Real(value) => write!(f, "{}", value),
+            Self::Str(value) => write!(f, "{}", value),
+            Self::Char(value) => write!(f, "{}", value),
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::Func(Some(func_decl)) => write!(f, "func {}", func_decl.id),
+            Self::Func(None) => write!(f, "func"),
+            Self::Void => Ok(()),
+        }
+    }
+}
+
+/*
+    Arithmetic and relational operators, the logic ones
+    are short circuited by the interpreter.
+*/
+pub fn apply_operator<'a>(
+    left: Value<'a>,
+    op: &Operator,
+    right: Value<'a>,
+) -> Result<Value<'a>, String> {
+    match op {
+        Operator::Add | Operator::Sub | Operator::Mul | Operator::Div => {
+            arithmetic(left, op, right)
+        }
+        Operator::And => Ok(Value::Bool(left.to_bool() && right.to_bool())),
+        Operator::Or => Ok(Value::Bool(left.to_bool() || right.to_bool())),
+        _ => {
+            let ordering = compare(&left, &right);
+            let result = match op {
+                Operator::Equal => ordering == Some(Ordering::Equal),
+                Operator::NotEqual => ordering != Some(Ordering::Equal),
+                Operator::Greater => ordering == Some(Ordering::Greater),
+                Operator::GreaterEqual => {
+                    matches!(ordering, Some(Ordering::Greater) | Some(Ordering::Equal))
+                }
+                Operator::Less => ordering == Some(Ordering::Less),
+                _ => matches!(ordering, Some(Ordering::Less) | Some(Ordering::Equal)),
+            };
+            Ok(Value::Bool(result))
+        }
+    }
+}
+
+fn arithmetic<'a>(left: Value<'a>, op: &Operator, right: Value<'a>) -> Result<Value<'a>, String> {
+    match (left, right) {
+        (Value::Int(l), Value::Int(r)) => checked(l, op, r).map(Value::Int),
+        (Value::Long(l), Value::Long(r)) => checked(l, op, r).map(Value::Long),
+        (Value::Real(l), Value::Real(r)) => Ok(Value::Real(match op {
+            Operator::Add => l + r,
+            Operator::Sub => l - r,
+            Operator::Mul => l * r,
+            _ => l / r,
+        })),
+        (left, right) => unreachable!("arithmetic on {:?} and {:?}", left, right),
+    }
+}
+
+trait CheckedArithmetic: Sized {
+    fn add(self, other: Self) -> Option<Self>;
+    fn sub(self, other: Self) -> Option<Self>;
+    fn mul(self, other: Self) -> Option<Self>;
+    fn div(self, other: Self) -> Option<Self>;
+    fn is_zero(&self) -> bool;
+}
+
+macro_rules! checked_arithmetic {
+    ($type: ty) => {
+        impl CheckedArithmetic for $type {
+            fn add(self, other: Self) -> Option<Self> {
+                self.checked_add(other)
+            }
+
+            fn sub(self, other: Self) -> Option<Self> {
+                self.checked_sub(other)
+            }
+
+            fn mul(self, other: Self) -> Option<Self> {
+                self.checked_mul(other)
+            }
+
+            fn div(self, other: Self) -> Option<Self> {
+                self.checked_div(other)
+            }
+
+            fn is_zero(&self) -> bool {
+                *self == 0
+            }
+        }
+    };
+}
+
+checked_arithmetic!(i32);
+checked_arithmetic!(i64);
+
+fn checked<T: CheckedArithmetic>(left: T, op: &Operator, right: T) -> Result<T, String> {
+    let result = match op {
+        Operator::Add => left.add(right),
+        Operator::Sub => left.sub(right),
+        Operator::Mul => left.mul(right),
+        _ if right.is_zero() => return Err("division by zero".to_owned()),
+        _ => left.div(right),
+    };
+    result.ok_or_else(|| "integer overflow".to_owned())
+}
+
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Int(l), Value::Int(r)) => l.partial_cmp(r),
+        (Value::Long(l), Value::Long(r)) => l.partial_cmp(r),
+        (Value::Real(l), Value::Real(r)) => l.partial_cmp(r),
+        (Value::Str(l), Value::Str(r)) => l.partial_cmp(r),
+        (Value::Char(l), Value::Char(r)) => l.partial_cmp(r),
+        (Value::Bool(l), Value::Bool(r)) => l.partial_cmp(r),
+        (left, right) => unreachable!("comparison of {:?} and {:?}", left, right),
+    }
+}
+
+pub fn negate(value: Value) -> Result<Value, String> {
+    match value {
+        Value::Int(value) => value.checked_neg().map(Value::Int),
+        Value::Long(value) => value.checked_neg().map(Value::Long),
+        Value::Real(value) => Some(Value::Real(-value)),
+        Value::Bool(value) => Some(Value::Bool(!value)),
+        value => unreachable!("negation of {:?}", value),
+    }
+    .ok_or_else(|| "integer overflow".to_owned())
+}
+
+// the character at a position, the first one has index 0
+pub fn index(string: &str, index: i32) -> Result<Value<'static>, String> {
+    usize::try_from(index)
+        .ok()
+        .and_then(|index| string.chars().nth(index))
+        .map(Value::Char)
+        .ok_or_else(|| {
+            format!(
+                "index {} out of range for a string of length {}",
+                index,
+                string.chars().count()
+            )
+        })
+}
+
+pub fn call_built_in<'a>(name: &str, args: &[Value<'a>]) -> Result<Value<'a>, String> {
+    let value = match (name, args) {
+        ("sqrt", [Value::Real(x)]) => Value::Real(x.sqrt()),
+        ("sin", [Value::Real(x)]) => Value::Real(x.sin()),
+        ("cos", [Value::Real(x)]) => Value::Real(x.cos()),
+        ("exp", [Value::Real(x)]) => Value::Real(x.exp()),
+        ("log", [Value::Real(x)]) => Value::Real(x.ln()),
+        ("abs", [Value::Real(x)]) => Value::Real(x.abs()),
+        ("abs", [Value::Int(x)]) => Value::Int(x.checked_abs().ok_or("integer overflow")?),
+        ("abs", [Value::Long(x)]) => Value::Long(x.checked_abs().ok_or("integer overflow")?),
+        ("floor", [Value::Real(x)]) => Value::Real(x.floor()).cast(&Kind::Int)?,
+        ("ceil", [Value::Real(x)]) => Value::Real(x.ceil()).cast(&Kind::Int)?,
+        ("round", [Value::Real(x)]) => Value::Real(x.round()).cast(&Kind::Int)?,
+        ("min", [Value::Real(x), Value::Real(y)]) => Value::Real(x.min(*y)),
+        ("max", [Value::Real(x), Value::Real(y)]) => Value::Real(x.max(*y)),
+        ("min", [Value::Int(x), Value::Int(y)]) => Value::Int(*x.min(y)),
+        ("max", [Value::Int(x), Value::Int(y)]) => Value::Int(*x.max(y)),
+        ("min", [Value::Long(x), Value::Long(y)]) => Value::Long(*x.min(y)),
+        ("max", [Value::Long(x), Value::Long(y)]) => Value::Long(*x.max(y)),
+        ("ord", [Value::Char(c)]) => Value::Char(*c).cast(&Kind::Int)?,
+        ("chr", [Value::Int(code)]) => Value::Int(*code).cast(&Kind::Char)?,
+        ("length", [Value::Str(s)]) => Value::Int(s.chars().count() as i32),
+        _ => unreachable!("built-in call {}({:?})", name, args),
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_arithmetic() {
+        let add = apply_operator(Value::Int(2), &Operator::Add, Value::Int(3));
+        assert_eq!(add, Ok(Value::Int(5)));
+        let div = apply_operator(Value::Int(7), &Operator::Div, Value::Int(2));
+        assert_eq!(div, Ok(Value::Int(3)));
+        let overflow = apply_operator(Value::Int(i32::MAX), &Operator::Add, Value::Int(1));
+        assert_eq!(overflow, Err("integer overflow".to_owned()));
+        let zero = apply_operator(Value::Long(1), &Operator::Div, Value::Long(0));
+        assert_eq!(zero, Err("division by zero".to_owned()));
+        let real = apply_operator(Value::Real(1.0), &Operator::Div, Value::Real(4.0));
+        assert_eq!(real, Ok(Value::Real(0.25)));
+    }
+
+    #[test]
+    fn test_comparison() {
+        let less = apply_operator(
+            Value::Str("abc".to_owned()),
+            &Operator::Less,
+            Value::Str("abd".to_owned()),
+        );
+        assert_eq!(less, Ok(Value::Bool(true)));
+        let equal = apply_operator(Value::Char('a'), &Operator::GreaterEqual, Value::Char('a'));
+        assert_eq!(equal, Ok(Value::Bool(true)));
+        let nan = apply_operator(Value::Real(f64::NAN), &Operator::NotEqual, Value::Real(1.0));
+        assert_eq!(nan, Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_cast() {
+        assert_eq!(Value::Real(-2.7).cast(&Kind::Int), Ok(Value::Int(-2)));
+        assert!(Value::Real(3e10).cast(&Kind::Int).is_err());
+        assert!(Value::Long(1 << 40).cast(&Kind::Int).is_err());
+        assert_eq!(Value::Int(97).cast(&Kind::Char), Ok(Value::Char('a')));
+        assert!(Value::Int(-1).cast(&Kind::Char).is_err());
+        assert_eq!(Value::Int(3).cast(&Kind::Real), Ok(Value::Real(3.0)));
+    }
+
+    #[test]
+    fn test_built_in() {
+        let args = [Value::Real(2.5)];
+        assert_eq!(call_built_in("round", &args), Ok(Value::Int(3)));
+        let args = [Value::Str("città".to_owned())];
+        assert_eq!(call_built_in("length", &args), Ok(Value::Int(5)));
+        assert_eq!(index("città", 4), Ok(Value::Char('à')));
+        assert!(index("città", 5).is_err());
+        assert_eq!(Value::Real(2.0).to_string(), "2");
+        assert_eq!(Value::Real(1.5e-8).to_string(), "0.000000015");
+    }
+}
@@ -0,0 +1,33 @@
+/*
+    The Simpla compiler as a library: `Compiler` runs the
+    same steps as the command line, from the source code
+    to the checked syntax tree and the bytecode, returning
+    the messages as diagnostics instead of printing them.
+    The other commands of the binary, from the renaming
+    to the language server, are run through it as well;
+    the formatter and the doc generator need no settings.
+*/
+mod ast_dump;
+mod built_in;
+mod compiler;
+mod constant_fold;
+mod doc;
+mod formatter;
+mod generator;
+mod interpreter;
+mod loader;
+mod lsp;
+mod rename;
+mod repl;
+mod semantic_analysis;
+mod xref;
+
+pub use ast_dump::DumpFormat;
+pub use compiler::{Compilation, Compiler, Diagnostic, Severity};
+pub use doc::{document_file, DocFormat};
+pub use formatter::format_file;
+pub use generator::ByteCodeFormat;
+pub use rename::Position;
+pub use semantic_analysis::Promotion;
+pub use simpla_parser::syntax_tree;
+pub use xref::GraphFormat;
@@ -0,0 +1,235 @@
+use extract_line_error::SourceMap;
+use simpla_parser::relocate::Relocate;
+use simpla_parser::syntax_tree::{FuncDeclList, Import, ImportList, Library, Program, VarDeclList};
+use simpla_parser::LibraryParser;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/*
+    Loads a program together with every library it imports.
+    An imported path is resolved relative to the importing file
+    first and then relative to each include path, in order.
+    Every library is loaded once, even when imported by many
+    files; the global variables and the functions of the libraries
+    are placed before the ones of the program, so
+    name collisions between files are reported by the semantic
+    analysis with the locations in both files.
+*/
+pub struct LoadedProgram {
+    pub program: Program,
+    pub sources: SourceMap,
+}
+
+/*
+    Loads the libraries imported by a program that is
+    already parsed from the given code. The path of a
+    program without imports does not need to exist.
+*/
+pub fn link_program(
+    path: &Path,
+    mut program: Program,
+    code: String,
+    include_paths: &[PathBuf],
+) -> Result<LoadedProgram, String> {
+    let mut loader = Loader::new(include_paths);
+    loader.sources.add_file(file_name(path), code);
+
+    if !program.imports.is_empty() {
+        let path = canonical_path(path)?;
+        loader.stack.push(path.clone());
+        loader.load_imports(&program.imports, &path)?;
+    }
+
+    loader.global_vars.append(&mut program.global_vars);
+    loader.functions.append(&mut program.functions);
+    program.global_vars = loader.global_vars;
+    program.functions = loader.functions;
+
+    Ok(LoadedProgram {
+        program,
+        sources: loader.sources,
+    })
+}
+
+struct Loader<'a> {
+    include_paths: &'a [PathBuf],
+    sources: SourceMap,
+    loaded: HashSet<PathBuf>,
+    stack: Vec<PathBuf>,
+    global_vars: VarDeclList,
+    functions: FuncDeclList,
+}
+
+impl<'a> Loader<'a> {
+    fn new(include_paths: &'a [PathBuf]) -> Self {
+        Self {
+            include_paths,
+            sources: SourceMap::new(),
+            loaded: HashSet::new(),
+            stack: Vec::new(),
+            global_vars: Vec::new(),
+            functions: Vec::new(),
+        }
+    }
+
+    fn load_imports(&mut self, imports: &ImportList, importer: &Path) -> Result<(), String> {
+        for import in imports {
+            let path = self.resolve_import(import, importer)?;
+            if self.stack.contains(&path) {
+                return Err(self.format_cycle(&path));
+            }
+            if !self.loaded.contains(&path) {
+                self.load_library(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn load_library(&mut self, path: PathBuf) -> Result<(), String> {
+        let code = read_file(&path)?;
+        let parser = LibraryParser::new();
+        let mut library = match parser.parse(&code) {
+            Ok(library) => library,
+            Err(err) => {
+                let err = simpla_parser::format_syntax_error(&code, err);
+                return Err(format!("In file: {}\n{}", file_name(&path), err));
+            }
+        };
+        let base = self.sources.add_file(file_name(&path), code);
+        library.relocate(base);
+
+        self.stack.push(path.clone());
+        self.load_imports(&library.imports, &path)?;
+        self.stack.pop();
+
+        let Library {
+            mut global_vars,
+            mut functions,
+            ..
+        } = library;
+        self.global_vars.append(&mut global_vars);
+        self.functions.append(&mut functions);
+        self.loaded.insert(path);
+        Ok(())
+    }
+
+    fn resolve_import(&self, import: &Import, importer: &Path) -> Result<PathBuf, String> {
+        let local_dir = importer.parent().unwrap_or_else(|| Path::new(""));
+        let found = std::iter::once(local_dir)
+            .chain(self.include_paths.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(&import.path))
+            .find(|path| path.is_file());
+        match found {
+            Some(path) => canonical_path(&path),
+            None => Err(format!(
+                "import error: cannot find {}\n{}",
+                import.path,
+                self.sources
+                    .extract_error_code(import.loc.begin, import.loc.end)
+            )),
+        }
+    }
+
+    fn format_cycle(&self, path: &Path) -> String {
+        let begin = self.stack.iter().position(|p| p == path).unwrap();
+        let cycle: Vec<String> = self.stack[begin..]
+            .iter()
+            .chain(std::iter::once(&path.to_path_buf()))
+            .map(|p| file_name(p))
+            .collect();
+        format!("import error: cyclic import {}", cycle.join(" -> "))
+    }
+}
+
+pub fn read_file(path: &Path) -> Result<String, String> {
+    let mut output = String::new();
+    match File::open(path).and_then(|mut file| file.read_to_string(&mut output)) {
+        Ok(_) => Ok(output),
+        Err(err) => Err(format!("{}: {}", path.display(), err)),
+    }
+}
+
+fn canonical_path(path: &Path) -> Result<PathBuf, String> {
+    path.canonicalize()
+        .map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+pub fn file_name(path: &Path) -> String {
+    match path.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => path.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::semantic_analysis::{analyze_program, Promotion};
+    use simpla_parser::ProgramParser;
+
+    fn load(name: &str) -> Result<LoadedProgram, String> {
+        let path = PathBuf::from("test_code").join("imports").join(name);
+        let code = read_file(&path)?;
+        let program = ProgramParser::new().parse(&code).unwrap();
+        link_program(
+            &path,
+            program,
+            code.clone(),
+            &[PathBuf::from("test_code").join("imports").join("lib")],
+        )
+    }
+
+    #[test]
+    fn test_load_imports() {
+        let loaded = load("shapes-correct.simpla").unwrap();
+        let names: Vec<&str> = loaded
+            .program
+            .functions
+            .iter()
+            .map(|func| func.id.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            ["square", "circle_area", "rectangle_area", "describe"]
+        );
+        assert_eq!(loaded.program.global_vars.len(), 2);
+
+        let circle = &loaded.program.functions[1];
+        assert_eq!(
+            loaded.sources.file_name(circle.loc.begin),
+            "geometry.simpla"
+        );
+    }
+
+    #[test]
+    fn test_name_collision() {
+        let loaded = load("name_collision-error.simpla").unwrap();
+        let err = analyze_program(&loaded.program, &loaded.sources, Promotion::Forbid)
+            .unwrap_err()
+            .message;
+        assert!(err.contains("In file: geometry.simpla"), "{}", err);
+        assert!(
+            err.contains("In file: name_collision-error.simpla"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_missing_import() {
+        let err = load("missing_import-error.simpla").err().unwrap();
+        assert!(err.starts_with("import error: cannot find"), "{}", err);
+    }
+
+    #[test]
+    fn test_cyclic_import() {
+        let err = load("cyclic_import-error.simpla").err().unwrap();
+        assert_eq!(
+            err,
+            "import error: cyclic import cycle_a.simpla -> cycle_b.simpla -> cycle_a.simpla"
+        );
+    }
+}
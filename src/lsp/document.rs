@@ -0,0 +1,224 @@
+use crate::loader::{self, LoadedProgram};
+use crate::semantic_analysis::{self, Promotion};
+use simpla_parser::syntax_tree::Location;
+use simpla_parser::ProgramParser;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub loc: Location,
+    pub message: String,
+    pub warning: bool,
+}
+
+impl Diagnostic {
+    fn error(loc: Location, message: String) -> Self {
+        Self {
+            loc,
+            message,
+            warning: false,
+        }
+    }
+}
+
+/*
+    A file open in the editor, analyzed on every change.
+    When the code does not parse there is no program: the
+    locations of an older one would not match the text, so
+    the requests about a position get no answer from it.
+*/
+pub struct Document {
+    pub text: String,
+    pub loaded: Option<LoadedProgram>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+pub struct Settings {
+    pub include_paths: Vec<PathBuf>,
+    pub promotion: Promotion,
+}
+
+impl Document {
+    pub fn analyze(path: &Path, text: String, settings: &Settings) -> Self {
+        let mut document = Self {
+            text,
+            loaded: None,
+            diagnostics: Vec::new(),
+        };
+        let program = match ProgramParser::new().parse(&document.text) {
+            Ok(program) => program,
+            Err(err) => {
+                let loc = simpla_parser::syntax_error_location(&err);
+                let message = simpla_parser::syntax_error_message(&err);
+                document.diagnostics.push(Diagnostic::error(loc, message));
+                return document;
+            }
+        };
+        let import_loc = program.imports.first().map(|import| import.loc.clone());
+        let text = document.text.clone();
+        let loaded = match loader::link_program(path, program, text, &settings.include_paths) {
+            Ok(loaded) => loaded,
+            Err(message) => {
+                let loc = import_loc.unwrap_or_else(|| Location::new(0, 0));
+                document.diagnostics.push(Diagnostic::error(loc, message));
+                return document;
+            }
+        };
+        document.check(&loaded, settings.promotion);
+        document.loaded = Some(loaded);
+        document
+    }
+
+    fn check(&mut self, loaded: &LoadedProgram, promotion: Promotion) {
+        match semantic_analysis::analyze_program(&loaded.program, &loaded.sources, promotion) {
+            Ok(conversions) => {
+                for conversion in conversions {
+                    self.diagnostics.push(Diagnostic {
+                        message: conversion.format_warning(&loaded.sources),
+                        loc: self.clamp(conversion.loc),
+                        warning: true,
+                    });
+                }
+            }
+            Err(err) => {
+                let loc = match (err.loc, err.name) {
+                    (Some(loc), _) => self.clamp(loc),
+                    (None, Some(name)) => self.find_word(&name),
+                    (None, None) => Location::new(0, 0),
+                };
+                self.diagnostics.push(Diagnostic::error(loc, err.message));
+            }
+        }
+    }
+
+    // errors in imported files are shown at the beginning of the document
+    fn clamp(&self, loc: Location) -> Location {
+        if loc.end <= self.text.len() {
+            loc
+        } else {
+            Location::new(0, 0)
+        }
+    }
+
+    fn find_word(&self, name: &str) -> Location {
+        let mut begin = 0;
+        while let Some(found) = self.text[begin..].find(name) {
+            let found = begin + found;
+            match self.word_at(found) {
+                Some(word) if word.begin == found && word.end == found + name.len() => return word,
+                _ => begin = found + name.len(),
+            }
+        }
+        Location::new(0, 0)
+    }
+
+    // location of the identifier that contains or ends at the offset
+    pub fn word_at(&self, offset: usize) -> Option<Location> {
+        let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        let offset = offset.min(self.text.len());
+        let begin = self.text[..offset]
+            .rfind(|c: char| !is_word(c))
+            .map_or(0, |index| index + 1);
+        let end = self.text[offset..]
+            .find(|c: char| !is_word(c))
+            .map_or(self.text.len(), |index| offset + index);
+        let word = &self.text[begin..end];
+        if word.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            Some(Location::new(begin, end))
+        } else {
+            None
+        }
+    }
+
+    // positions count lines from 0 and characters in UTF-16 code units
+    pub fn offset(&self, line: usize, character: usize) -> usize {
+        let mut line_begin = 0;
+        for _ in 0..line {
+            match self.text[line_begin..].find('\n') {
+                Some(index) => line_begin += index + 1,
+                None => return self.text.len(),
+            }
+        }
+        let mut units = 0;
+        for (index, c) in self.text[line_begin..].char_indices() {
+            if units >= character || c == '\n' {
+                return line_begin + index;
+            }
+            units += c.len_utf16();
+        }
+        self.text.len()
+    }
+
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let before = &self.text[..offset.min(self.text.len())];
+        let line = before.matches('\n').count();
+        let line_begin = before.rfind('\n').map_or(0, |index| index + 1);
+        let character = before[line_begin..].encode_utf16().count();
+        (line, character)
+    }
+
+    // true for the locations in this file, not in an imported one
+    pub fn is_local(&self, loc: &Location) -> bool {
+        loc.end <= self.text.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn settings() -> Settings {
+        Settings {
+            include_paths: Vec::new(),
+            promotion: Promotion::Forbid,
+        }
+    }
+
+    fn analyze(text: &str) -> Document {
+        Document::analyze(Path::new("test.simpla"), text.to_owned(), &settings())
+    }
+
+    #[test]
+    fn test_positions() {
+        let document = analyze("body\n    s = \"é😀x\";\nend.");
+        assert_eq!(document.offset(1, 4), 9);
+        assert_eq!(document.position(9), (1, 4));
+        let x = document.text.find('x').unwrap();
+        assert_eq!(document.position(x), (1, 12));
+        assert_eq!(document.offset(1, 12), x);
+        assert_eq!(document.offset(5, 0), document.text.len());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let document = analyze("body\n    x = 1;\nend.");
+        assert_eq!(document.diagnostics.len(), 1);
+        let loc = &document.diagnostics[0].loc;
+        assert_eq!(&document.text[loc.begin..loc.end], "x");
+        assert!(document.loaded.is_some());
+
+        let broken = analyze("body\n    x = ;\nend.");
+        assert_eq!(broken.diagnostics.len(), 1);
+        assert!(broken.diagnostics[0].message.contains("Unrecognized Token"));
+        assert_eq!(broken.diagnostics[0].loc, Location::new(13, 14));
+        assert!(broken.loaded.is_none());
+
+        let unterminated = analyze("body\n  (* oops\nend.\n");
+        assert_eq!(unterminated.diagnostics.len(), 1);
+        assert_eq!(unterminated.diagnostics[0].message, "Unterminated Comment");
+        assert_eq!(unterminated.diagnostics[0].loc.begin, 7);
+
+        let correct = analyze("x: integer;\nbody\n    x = 1;\nend.");
+        assert!(correct.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_word_at() {
+        let document = analyze("body\n    writeln(count_1);\nend.");
+        let offset = document.text.find("unt").unwrap();
+        let word = document.word_at(offset).unwrap();
+        assert_eq!(&document.text[word.begin..word.end], "count_1");
+        assert_eq!(document.word_at(6), None);
+    }
+}
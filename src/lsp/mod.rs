@@ -0,0 +1,38 @@
+mod document;
+mod protocol;
+mod server;
+
+use crate::semantic_analysis::Promotion;
+use document::Settings;
+use server::Server;
+use std::io::{self, BufReader};
+use std::path::PathBuf;
+
+/*
+    Runs a language server on the standard input and
+    output until the client asks it to exit. Every open
+    document is checked like `simplac check` would do,
+    with the given promotion and include paths.
+*/
+pub fn run_server(include_paths: Vec<PathBuf>, promotion: Promotion) -> Result<(), String> {
+    let mut server = Server::new(Settings {
+        include_paths,
+        promotion,
+    });
+    let mut input = BufReader::new(io::stdin());
+    let mut output = io::stdout();
+    while !server.exited() {
+        let message = match protocol::read_message(&mut input)? {
+            Some(message) => message,
+            None => break,
+        };
+        for response in server.handle(&message) {
+            protocol::write_message(&mut output, &response)?;
+        }
+    }
+    if server.clean_shutdown() {
+        Ok(())
+    } else {
+        Err("the language server exited without a shutdown request".to_owned())
+    }
+}
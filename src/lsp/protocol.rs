@@ -0,0 +1,73 @@
+use serde_json::Value;
+use std::io::{BufRead, Write};
+
+/*
+    Messages of the Language Server Protocol are JSON-RPC
+    objects preceded by a header, in the HTTP style, that
+    gives the length in bytes of the content.
+*/
+pub fn read_message(input: &mut impl BufRead) -> Result<Option<Value>, String> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        let read = input.read_line(&mut line).map_err(|err| err.to_string())?;
+        if read == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                let value = value.trim().parse::<usize>();
+                length = Some(value.map_err(|err| format!("invalid Content-Length: {}", err))?);
+            }
+        }
+    }
+    let length = length.ok_or_else(|| "missing Content-Length header".to_owned())?;
+    let mut content = vec![0; length];
+    input
+        .read_exact(&mut content)
+        .map_err(|err| err.to_string())?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|err| format!("invalid message: {}", err))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Value) -> Result<(), String> {
+    let content = message.to_string();
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )
+    .and_then(|_| output.flush())
+    .map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_round_trip() {
+        let message = json!({"jsonrpc": "2.0", "id": 1, "method": "shutdown"});
+        let mut buff = Vec::new();
+        write_message(&mut buff, &message).unwrap();
+        write_message(&mut buff, &message).unwrap();
+        let mut input = buff.as_slice();
+        assert_eq!(read_message(&mut input), Ok(Some(message.clone())));
+        assert_eq!(read_message(&mut input), Ok(Some(message)));
+        assert_eq!(read_message(&mut input), Ok(None));
+    }
+
+    #[test]
+    fn test_missing_length() {
+        let mut input = "Content-Type: json\r\n\r\n{}".as_bytes();
+        assert!(read_message(&mut input).is_err());
+    }
+}
@@ -0,0 +1,468 @@
+use super::document::{Diagnostic, Document, Settings};
+use crate::semantic_analysis::{self, Symbol};
+use serde_json::{json, Value};
+use simpla_parser::printer::kind_name;
+use simpla_parser::syntax_tree::{FuncDecl, Location, VarDecl};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const KEYWORDS: &[&str] = &[
+    "and", "assert", "body", "boolean", "break", "case", "char", "continue", "do", "downto",
+    "else", "end", "false", "for", "func", "halt", "if", "import", "integer", "long", "not", "of",
+    "or", "read", "real", "repeat", "return", "step", "string", "then", "to", "true", "until",
+    "var", "void", "while", "write", "writeln",
+];
+
+// numeric codes defined by the protocol
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_REQUEST: i64 = -32600;
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+const SYMBOL_FUNCTION: u8 = 12;
+const SYMBOL_VARIABLE: u8 = 13;
+const COMPLETION_FUNCTION: u8 = 3;
+const COMPLETION_VARIABLE: u8 = 6;
+const COMPLETION_KEYWORD: u8 = 14;
+
+/*
+    Keeps the open documents and answers the messages of
+    the client: every request gets exactly one response,
+    a change of a document is followed by its diagnostics.
+*/
+pub struct Server {
+    documents: HashMap<String, Document>,
+    settings: Settings,
+    shutdown: bool,
+    exit: bool,
+}
+
+impl Server {
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            documents: HashMap::new(),
+            settings,
+            shutdown: false,
+            exit: false,
+        }
+    }
+
+    pub fn exited(&self) -> bool {
+        self.exit
+    }
+
+    pub fn clean_shutdown(&self) -> bool {
+        self.shutdown
+    }
+
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => return self.notification(method, params),
+        };
+        if self.shutdown && method != "exit" {
+            return vec![error_response(
+                id,
+                INVALID_REQUEST,
+                "the server is shut down",
+            )];
+        }
+        let result = match method {
+            "initialize" => Ok(capabilities()),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/hover" => Ok(self.hover(params)),
+            "textDocument/definition" => Ok(self.definition(params)),
+            "textDocument/documentSymbol" => Ok(self.document_symbols(params)),
+            "textDocument/completion" => Ok(self.completion(params)),
+            _ => Err(format!("unknown method: {}", method)),
+        };
+        match result {
+            Ok(result) => vec![json!({"jsonrpc": "2.0", "id": id, "result": result})],
+            Err(message) => vec![error_response(id, METHOD_NOT_FOUND, &message)],
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match method {
+            "exit" => {
+                self.exit = true;
+                Vec::new()
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                vec![self.update(uri, text.to_owned())]
+            }
+            // the server asks for full synchronization, the last change is the whole text
+            "textDocument/didChange" => match params["contentChanges"].as_array() {
+                Some(changes) => match changes.last().and_then(|change| change["text"].as_str()) {
+                    Some(text) => vec![self.update(uri, text.to_owned())],
+                    None => Vec::new(),
+                },
+                None => Vec::new(),
+            },
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                vec![publish_diagnostics(uri, Vec::new())]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn update(&mut self, uri: &str, text: String) -> Value {
+        let path = uri_to_path(uri);
+        let document = Document::analyze(&path, text, &self.settings);
+        let diagnostics = document
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic_json(&document, diagnostic))
+            .collect();
+        self.documents.insert(uri.to_owned(), document);
+        publish_diagnostics(uri, diagnostics)
+    }
+
+    // the document and the offset of a text document position
+    fn position(&self, params: &Value) -> Option<(&Document, usize)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let document = self.documents.get(uri)?;
+        let line = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+        Some((document, document.offset(line, character)))
+    }
+
+    fn symbol_at(&self, params: &Value) -> Option<(&Document, Symbol, Location)> {
+        let (document, offset) = self.position(params)?;
+        let word = document.word_at(offset)?;
+        let name = &document.text[word.begin..word.end];
+        if KEYWORDS.contains(&name) {
+            return None;
+        }
+        let loaded = document.loaded.as_ref()?;
+        let symbol = semantic_analysis::find_symbol(&loaded.program, offset, name)?;
+        Some((document, symbol, word))
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        match self.symbol_at(params) {
+            Some((document, symbol, word)) => json!({
+                "contents": {
+                    "kind": "markdown",
+                    "value": format!("```simpla\n{}\n```", symbol.detail),
+                },
+                "range": range(document, &word),
+            }),
+            None => Value::Null,
+        }
+    }
+
+    fn definition(&self, params: &Value) -> Value {
+        let uri = &params["textDocument"]["uri"];
+        match self.symbol_at(params) {
+            Some((document, Symbol { loc: Some(loc), .. }, _)) if document.is_local(&loc) => {
+                json!({"uri": uri, "range": range(document, &loc)})
+            }
+            _ => Value::Null,
+        }
+    }
+
+    fn document_symbols(&self, params: &Value) -> Value {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let (document, loaded) = match self.documents.get(uri) {
+            Some(document) => match &document.loaded {
+                Some(loaded) => (document, loaded),
+                None => return json!([]),
+            },
+            None => return Value::Null,
+        };
+        let program = &loaded.program;
+        let mut symbols = variable_symbols(document, &program.global_vars);
+        symbols.extend(function_symbols(document, &program.functions));
+        Value::Array(symbols)
+    }
+
+    fn completion(&self, params: &Value) -> Value {
+        let mut items: Vec<Value> = Vec::new();
+        if let Some((document, offset)) = self.position(params) {
+            if let Some(loaded) = &document.loaded {
+                let symbols = semantic_analysis::visible_symbols(&loaded.program, offset);
+                items.extend(symbols.into_iter().map(|symbol| {
+                    let kind = if symbol.function {
+                        COMPLETION_FUNCTION
+                    } else {
+                        COMPLETION_VARIABLE
+                    };
+                    json!({"label": symbol.name, "kind": kind, "detail": symbol.detail})
+                }));
+            }
+        }
+        items.extend(
+            KEYWORDS
+                .iter()
+                .map(|keyword| json!({"label": keyword, "kind": COMPLETION_KEYWORD})),
+        );
+        Value::Array(items)
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": 1,
+            "hoverProvider": true,
+            "definitionProvider": true,
+            "documentSymbolProvider": true,
+            "completionProvider": {},
+        },
+        "serverInfo": {
+            "name": "simplac",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+    })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": code, "message": message},
+    })
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": {"uri": uri, "diagnostics": diagnostics},
+    })
+}
+
+fn diagnostic_json(document: &Document, diagnostic: &Diagnostic) -> Value {
+    let severity = if diagnostic.warning {
+        SEVERITY_WARNING
+    } else {
+        SEVERITY_ERROR
+    };
+    json!({
+        "range": range(document, &diagnostic.loc),
+        "severity": severity,
+        "source": "simplac",
+        "message": diagnostic.message,
+    })
+}
+
+fn range(document: &Document, loc: &Location) -> Value {
+    let (begin_line, begin_char) = document.position(loc.begin);
+    let (end_line, end_char) = document.position(loc.end);
+    json!({
+        "start": {"line": begin_line, "character": begin_char},
+        "end": {"line": end_line, "character": end_char},
+    })
+}
+
+fn variable_symbols(document: &Document, vars: &[VarDecl]) -> Vec<Value> {
+    vars.iter()
+        .filter(|var_decl| document.is_local(&var_decl.loc))
+        .flat_map(|var_decl| {
+            var_decl.id_list.iter().map(move |id| {
+                json!({
+                    "name": id,
+                    "detail": kind_name(&var_decl.kind),
+                    "kind": SYMBOL_VARIABLE,
+                    "range": range(document, &var_decl.loc),
+                    "selectionRange": range(document, &var_decl.loc),
+                })
+            })
+        })
+        .collect()
+}
+
+fn function_symbols(document: &Document, functions: &[FuncDecl]) -> Vec<Value> {
+    functions
+        .iter()
+        .filter(|func_decl| document.is_local(&func_decl.loc))
+        .map(|func_decl| {
+            let mut children = variable_symbols(document, &func_decl.vars);
+            children.extend(function_symbols(document, &func_decl.functions));
+            json!({
+                "name": func_decl.id,
+                "detail": semantic_analysis::signature(func_decl),
+                "kind": SYMBOL_FUNCTION,
+                "range": range(document, &func_decl.loc),
+                "selectionRange": range(document, &func_decl.loc),
+                "children": children,
+            })
+        })
+        .collect()
+}
+
+// only file URIs are supported, other schemes keep the URI as a file name
+fn uri_to_path(uri: &str) -> PathBuf {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escape = bytes.get(index + 1..index + 3).and_then(|hex| {
+            let hex = std::str::from_utf8(hex).ok()?;
+            u8::from_str_radix(hex, 16).ok()
+        });
+        match (bytes[index], escape) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    PathBuf::from(String::from_utf8_lossy(&decoded).to_string())
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::semantic_analysis::Promotion;
+
+    const URI: &str = "file:///tmp/test%20dir/test.simpla";
+    const CODE: &str = "
+count: integer;
+func twice(x: real): real
+    y: real;
+body
+    y = x * 2.0;
+    return y;
+end;
+body
+    count = 1;
+    writeln(twice(1.0));
+end.";
+
+    fn open(server: &mut Server, text: &str) -> Vec<Value> {
+        server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {"textDocument": {"uri": URI, "languageId": "simpla", "version": 1, "text": text}},
+        }))
+    }
+
+    fn request(server: &mut Server, method: &str, line: usize, character: usize) -> Value {
+        let mut response = server.handle(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": {
+                "textDocument": {"uri": URI},
+                "position": {"line": line, "character": character},
+            },
+        }));
+        assert_eq!(response.len(), 1);
+        response.remove(0)
+    }
+
+    fn server() -> Server {
+        Server::new(Settings {
+            include_paths: Vec::new(),
+            promotion: Promotion::Forbid,
+        })
+    }
+
+    #[test]
+    fn test_uri_to_path() {
+        assert_eq!(uri_to_path(URI), PathBuf::from("/tmp/test dir/test.simpla"));
+        assert_eq!(uri_to_path("file:///a%2"), PathBuf::from("/a%2"));
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let mut server = server();
+        let messages = open(&mut server, CODE);
+        assert_eq!(messages[0]["params"]["diagnostics"], json!([]));
+
+        let messages = open(&mut server, &CODE.replace("count = 1", "count = 1.0"));
+        let diagnostics = &messages[0]["params"]["diagnostics"];
+        assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+        assert_eq!(diagnostics[0]["severity"], 1);
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 9);
+    }
+
+    #[test]
+    fn test_hover_and_definition() {
+        let mut server = server();
+        open(&mut server, CODE);
+        let hover = request(&mut server, "textDocument/hover", 5, 9);
+        assert_eq!(
+            hover["result"]["contents"]["value"],
+            "```simpla\nx: real\n```"
+        );
+        let hover = request(&mut server, "textDocument/hover", 10, 13);
+        assert_eq!(
+            hover["result"]["contents"]["value"],
+            "```simpla\nfunc twice(x: real): real\n```"
+        );
+        let hover = request(&mut server, "textDocument/hover", 10, 5);
+        assert_eq!(hover["result"], Value::Null);
+
+        let definition = request(&mut server, "textDocument/definition", 9, 6);
+        assert_eq!(
+            definition["result"]["range"]["start"],
+            json!({"line": 1, "character": 0})
+        );
+        let definition = request(&mut server, "textDocument/definition", 10, 13);
+        assert_eq!(
+            definition["result"]["range"]["start"],
+            json!({"line": 2, "character": 0})
+        );
+
+        open(&mut server, &CODE.replace("count = 1;", "count = ;"));
+        let hover = request(&mut server, "textDocument/hover", 10, 13);
+        assert_eq!(hover["result"], Value::Null);
+        let definition = request(&mut server, "textDocument/definition", 9, 6);
+        assert_eq!(definition["result"], Value::Null);
+    }
+
+    #[test]
+    fn test_symbols_and_completion() {
+        let mut server = server();
+        open(&mut server, CODE);
+        let symbols = request(&mut server, "textDocument/documentSymbol", 0, 0);
+        let symbols = symbols["result"].as_array().unwrap();
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[0]["name"], "count");
+        assert_eq!(symbols[1]["name"], "twice");
+        assert_eq!(symbols[1]["children"][0]["name"], "y");
+
+        let completion = request(&mut server, "textDocument/completion", 6, 4);
+        let labels: Vec<&str> = completion["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["label"].as_str().unwrap())
+            .collect();
+        for label in &["count", "twice", "x", "y", "sqrt", "while"] {
+            assert!(labels.contains(label), "{} not completed", label);
+        }
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let mut server = server();
+        let response = request(&mut server, "textDocument/rename", 0, 0);
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+        let response = request(&mut server, "shutdown", 0, 0);
+        assert_eq!(response["result"], Value::Null);
+        assert!(server.clean_shutdown());
+        let response = request(&mut server, "textDocument/hover", 0, 0);
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+        assert!(server
+            .handle(&json!({"jsonrpc": "2.0", "method": "exit"}))
+            .is_empty());
+        assert!(server.exited());
+    }
+}
@@ -1,22 +1,157 @@
-use structopt::StructOpt;
-mod generator;
-mod semantic_analysis;
-use simpla_parser;
-use simpla_parser::syntax_tree;
+use simplac::{
+    ByteCodeFormat, Compilation, Compiler, DocFormat, DumpFormat, GraphFormat, Position, Promotion,
+};
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
-#[structopt(about = "Compile or Check a Simpla program")]
+#[structopt(about = "Compile, Check or Format a Simpla program")]
 enum Action {
     #[structopt(about = "Check a Simpla program for correctness")]
     Check {
         #[structopt(help = "Simpla source code input file")]
         source_file: PathBuf,
+        #[structopt(
+            long,
+            default_value = "forbid",
+            help = "Implicit integer to real promotion: forbid, warn or allow"
+        )]
+        promotion: Promotion,
+        #[structopt(
+            short = "I",
+            long = "include",
+            number_of_values = 1,
+            help = "Directory searched for imported files"
+        )]
+        include_paths: Vec<PathBuf>,
     },
     #[structopt(about = "Compile a Simpla program to Simpla bytecode")]
     Translate(Arguments),
+    #[structopt(about = "Rewrite a Simpla source file in the canonical layout")]
+    Fmt {
+        #[structopt(help = "Simpla source code file")]
+        source_file: PathBuf,
+        #[structopt(long, help = "Fail if the file is not formatted, without rewriting it")]
+        check: bool,
+    },
+    #[structopt(about = "Print the syntax tree of a Simpla program")]
+    DumpAst {
+        #[structopt(help = "Simpla source code input file")]
+        source_file: PathBuf,
+        #[structopt(
+            long,
+            default_value = "tree",
+            help = "Output format: json, sexp or tree"
+        )]
+        format: DumpFormat,
+        #[structopt(long, help = "Print the tree before the semantic analysis")]
+        parse_only: bool,
+        #[structopt(
+            long,
+            default_value = "forbid",
+            help = "Implicit integer to real promotion: forbid, warn or allow"
+        )]
+        promotion: Promotion,
+        #[structopt(
+            short = "I",
+            long = "include",
+            number_of_values = 1,
+            help = "Directory searched for imported files"
+        )]
+        include_paths: Vec<PathBuf>,
+    },
+    #[structopt(about = "Print the reference of the functions and globals of a Simpla file")]
+    Doc {
+        #[structopt(help = "Simpla program or library file")]
+        source_file: PathBuf,
+        #[structopt(
+            long,
+            default_value = "markdown",
+            help = "Output format: markdown or html"
+        )]
+        format: DocFormat,
+    },
+    #[structopt(about = "Run a language server on the standard input and output")]
+    Lsp {
+        #[structopt(
+            long,
+            default_value = "forbid",
+            help = "Implicit integer to real promotion: forbid, warn or allow"
+        )]
+        promotion: Promotion,
+        #[structopt(
+            short = "I",
+            long = "include",
+            number_of_values = 1,
+            help = "Directory searched for imported files"
+        )]
+        include_paths: Vec<PathBuf>,
+    },
+    #[structopt(about = "Run Simpla declarations and statements interactively")]
+    Repl {
+        #[structopt(
+            long,
+            default_value = "forbid",
+            help = "Implicit integer to real promotion: forbid, warn or allow"
+        )]
+        promotion: Promotion,
+    },
+    #[structopt(about = "Rename a variable or a function and every reference to it")]
+    Rename {
+        #[structopt(long, help = "Simpla source code file, rewritten in place")]
+        file: PathBuf,
+        #[structopt(long, help = "Position of the name, as LINE:COL counted from 1")]
+        at: Position,
+        #[structopt(long, help = "The new name")]
+        to: String,
+        #[structopt(
+            short = "I",
+            long = "include",
+            number_of_values = 1,
+            help = "Directory searched for imported files"
+        )]
+        include_paths: Vec<PathBuf>,
+    },
+    #[structopt(about = "List where each function and global variable is defined and used")]
+    Xref {
+        #[structopt(help = "Simpla source code input file")]
+        source_file: PathBuf,
+        #[structopt(
+            long,
+            default_value = "forbid",
+            help = "Implicit integer to real promotion: forbid, warn or allow"
+        )]
+        promotion: Promotion,
+        #[structopt(
+            short = "I",
+            long = "include",
+            number_of_values = 1,
+            help = "Directory searched for imported files"
+        )]
+        include_paths: Vec<PathBuf>,
+    },
+    #[structopt(about = "Print the calls among the functions of a Simpla program")]
+    Callgraph {
+        #[structopt(help = "Simpla source code input file")]
+        source_file: PathBuf,
+        #[structopt(long, default_value = "text", help = "Output format: text or dot")]
+        format: GraphFormat,
+        #[structopt(
+            long,
+            default_value = "forbid",
+            help = "Implicit integer to real promotion: forbid, warn or allow"
+        )]
+        promotion: Promotion,
+        #[structopt(
+            short = "I",
+            long = "include",
+            number_of_values = 1,
+            help = "Directory searched for imported files"
+        )]
+        include_paths: Vec<PathBuf>,
+    },
 }
 
 #[derive(StructOpt, Debug)]
@@ -25,14 +160,25 @@ struct Arguments {
     source_file: PathBuf,
     #[structopt(help = "Simpla bytecode output file")]
     output_file: Option<PathBuf>,
-}
-
-fn load_file(file: &Path) -> std::io::Result<String> {
-    let mut file = File::open(file)?;
-    let mut output = String::new();
-    file.read_to_string(&mut output)?;
-
-    Ok(output)
+    #[structopt(
+        long,
+        default_value = "forbid",
+        help = "Implicit integer to real promotion: forbid, warn or allow"
+    )]
+    promotion: Promotion,
+    #[structopt(
+        short = "I",
+        long = "include",
+        number_of_values = 1,
+        help = "Directory searched for imported files"
+    )]
+    include_paths: Vec<PathBuf>,
+    #[structopt(
+        long,
+        default_value = "v1",
+        help = "Bytecode format: v1 with 16-bit operands, v2 with 32-bit operands"
+    )]
+    format: ByteCodeFormat,
 }
 
 fn get_file_name(arg: Arguments) -> PathBuf {
@@ -59,37 +205,38 @@ fn save_to_file(arg: Arguments, code: Vec<u8>) -> std::io::Result<()> {
     Ok(())
 }
 
-fn parse_code(code: &str) -> Result<syntax_tree::Program, String> {
-    let parser = simpla_parser::ProgramParser::new();
-    let parse_result = parser.parse(code);
-    match parse_result {
-        Ok(ast) => Ok(ast),
-        Err(err) => {
-            let err = simpla_parser::format_syntax_error(code, err);
-            Err(err)
-        }
-    }
-}
-
-fn generate_ast(code: &str) -> Result<syntax_tree::Program, String> {
-    let program = parse_code(code)?;
-    semantic_analysis::semantic_check(&program, code)?;
-    Ok(program)
+fn compiler(include_paths: Vec<PathBuf>, promotion: Promotion) -> Compiler {
+    include_paths
+        .into_iter()
+        .fold(Compiler::new().promotion(promotion), Compiler::include_path)
 }
 
-fn compile_to_ast(path: &Path) -> Result<syntax_tree::Program, String> {
-    match load_file(path) {
-        Ok(code) => generate_ast(&code),
-        Err(err) => Err(format!("{}", err)),
+fn compile_to_ast(
+    path: &Path,
+    include_paths: Vec<PathBuf>,
+    promotion: Promotion,
+) -> Result<Compilation, String> {
+    let compilation = compiler(include_paths, promotion)
+        .check_path(path)
+        .map_err(|err| err.to_string())?;
+    for warning in &compilation.warnings {
+        eprintln!("{}", warning);
     }
+    Ok(compilation)
 }
 
-fn compile<Func>(arg: Arguments, translator: Func) -> Result<(), String>
-where
-    Func: Fn(&syntax_tree::Program) -> Vec<u8>,
-{
-    let ast = compile_to_ast(&arg.source_file)?;
-    let code = translator(&ast);
+fn compile(arg: Arguments) -> Result<(), String> {
+    let compilation = compiler(arg.include_paths.clone(), arg.promotion)
+        .format(arg.format)
+        .check_path(&arg.source_file)
+        .map_err(|err| err.to_string())?;
+    for warning in &compilation.warnings {
+        eprintln!("{}", warning);
+    }
+    let code = compilation.bytecode().map_err(|errors| {
+        let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        messages.join("\n")
+    })?;
     match save_to_file(arg, code) {
         Ok(()) => Ok(()),
         Err(err) => Err(format!("{}", err)),
@@ -98,10 +245,77 @@ where
 
 fn run_program(action: Action) -> Result<(), String> {
     match action {
-        Action::Check { source_file } => {
-            let _ = compile_to_ast(&source_file)?;
+        Action::Check {
+            source_file,
+            promotion,
+            include_paths,
+        } => {
+            let _ = compile_to_ast(&source_file, include_paths, promotion)?;
+        }
+        Action::Translate(arg) => compile(arg)?,
+        Action::Fmt { source_file, check } => simplac::format_file(&source_file, check)?,
+        Action::DumpAst {
+            source_file,
+            format,
+            parse_only,
+            promotion,
+            include_paths,
+        } => {
+            let compilation = if parse_only {
+                compiler(include_paths, promotion)
+                    .parse_path(&source_file)
+                    .map_err(|err| err.to_string())?
+            } else {
+                compile_to_ast(&source_file, include_paths, promotion)?
+            };
+            print!("{}", compilation.dump_ast(format)?);
+        }
+        Action::Doc {
+            source_file,
+            format,
+        } => print!("{}", simplac::document_file(&source_file, format)?),
+        Action::Lsp {
+            promotion,
+            include_paths,
+        } => compiler(include_paths, promotion).run_language_server()?,
+        Action::Rename {
+            file,
+            at,
+            to,
+            include_paths,
+        } => {
+            let count = compiler(include_paths, Promotion::Forbid).rename_file(&file, at, &to)?;
+            println!("renamed {} occurrences", count);
+        }
+        Action::Xref {
+            source_file,
+            promotion,
+            include_paths,
+        } => {
+            let compilation = compile_to_ast(&source_file, include_paths, promotion)?;
+            let xref = compilation
+                .cross_reference()
+                .map_err(|err| err.to_string())?;
+            print!("{}", xref);
+        }
+        Action::Callgraph {
+            source_file,
+            format,
+            promotion,
+            include_paths,
+        } => {
+            let compilation = compile_to_ast(&source_file, include_paths, promotion)?;
+            let graph = compilation
+                .call_graph(format)
+                .map_err(|err| err.to_string())?;
+            print!("{}", graph);
+        }
+        Action::Repl { promotion } => {
+            let code = Compiler::new().promotion(promotion).run_repl()?;
+            if code != 0 {
+                std::process::exit(code);
+            }
         }
-        Action::Translate(arg) => compile(arg, generator::translate_to_byte_code)?,
     }
 
     Ok(())
@@ -0,0 +1,340 @@
+use crate::compiler::{Compilation, Compiler};
+use crate::loader;
+use crate::semantic_analysis::{find_symbol, Promotion, Symbol};
+use simpla_parser::cst::{tokenize, Token, TokenKind};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+/*
+    A position in a file as editors show it: lines and
+    columns are counted from 1, columns in characters.
+*/
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl FromStr for Position {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = || format!("invalid position {}, expected LINE:COL", text);
+        let (line, column) = text.split_once(':').ok_or_else(error)?;
+        let line = line.parse().map_err(|_| error())?;
+        let column = column.parse().map_err(|_| error())?;
+        if line == 0 || column == 0 {
+            return Err(error());
+        }
+        Ok(Self { line, column })
+    }
+}
+
+impl Position {
+    fn offset(self, code: &str) -> Option<usize> {
+        let line = code.split('\n').nth(self.line - 1)?;
+        let line_begin = line.as_ptr() as usize - code.as_ptr() as usize;
+        let column = line
+            .char_indices()
+            .map(|(index, _)| index)
+            .chain(std::iter::once(line.len()))
+            .nth(self.column - 1)?;
+        Some(line_begin + column)
+    }
+}
+
+/*
+    Renames the variable or the function named at the
+    position and every reference to it, rewriting the file.
+    Only names declared in the file itself can be renamed.
+*/
+pub fn rename_file(
+    compiler: &Compiler,
+    path: &Path,
+    at: Position,
+    new_name: &str,
+) -> Result<usize, String> {
+    let code = loader::read_file(path)?;
+    let offset = at
+        .offset(&code)
+        .ok_or_else(|| format!("{}: no position {}:{}", path.display(), at.line, at.column))?;
+    let (renamed, count) = rename(compiler, path, &code, offset, new_name)?;
+    fs::write(path, renamed).map_err(|err| format!("{}: {}", path.display(), err))?;
+    Ok(count)
+}
+
+/*
+    Returns the new code and the number of rewritten names.
+    The program must be correct before the renaming and
+    after it: a new name that collides with a declaration
+    in the same scope is rejected by the semantic analysis,
+    one that hides another declaration, or that is hidden
+    by it, is rejected because some reference would change
+    the symbol it resolves to.
+*/
+pub fn rename(
+    compiler: &Compiler,
+    path: &Path,
+    code: &str,
+    offset: usize,
+    new_name: &str,
+) -> Result<(String, usize), String> {
+    check_name(new_name)?;
+    let before = analyzed(compiler, path, code.to_owned())?;
+    let tokens = Tokens::new(code);
+
+    let target = tokens
+        .at(offset)
+        .ok_or_else(|| "no identifier at the given position".to_owned())?;
+    let old_name = tokens.text(target).to_owned();
+    if tokens.is_label(target) {
+        return Err(format!(
+            "{} is a loop label, not a variable or a function",
+            old_name
+        ));
+    }
+    let symbol = find_symbol(&before.program, tokens.offsets[target], &old_name)
+        .ok_or_else(|| format!("unknown name {}", old_name))?;
+    let declaration = match &symbol.loc {
+        None => return Err(format!("{} is a built-in function", old_name)),
+        Some(loc) if loc.begin >= code.len() => {
+            return Err(format!("{} is declared in an imported file", old_name))
+        }
+        Some(_) => tokens.identity(&symbol),
+    };
+    if old_name == new_name {
+        return Ok((code.to_owned(), 0));
+    }
+
+    let references = tokens.resolve(&before, &old_name);
+    let renamed: Vec<usize> = references
+        .iter()
+        .filter(|(_, identity)| *identity == declaration)
+        .map(|(index, _)| *index)
+        .collect();
+    let bystanders = tokens.resolve(&before, new_name);
+
+    let new_code = tokens.replace(&renamed, new_name);
+    let after = analyzed(compiler, path, new_code.clone())
+        .map_err(|err| format!("cannot rename {} to {}:\n{}", old_name, new_name, err))?;
+    let new_tokens = Tokens::new(&new_code);
+    let resolved = new_tokens.resolve(&after, new_name);
+
+    let mut expected: Vec<(usize, Identity)> = renamed
+        .iter()
+        .map(|index| (*index, declaration))
+        .chain(bystanders)
+        .collect();
+    expected.sort_by_key(|(index, _)| *index);
+    if resolved != expected {
+        return Err(format!(
+            "cannot rename {} to {}: a reference would resolve to a different declaration",
+            old_name, new_name
+        ));
+    }
+    Ok((new_code, renamed.len()))
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    match tokenize(name).as_slice() {
+        [Token {
+            kind: TokenKind::Identifier,
+            ..
+        }] => Ok(()),
+        _ => Err(format!("{} is not a valid name", name)),
+    }
+}
+
+// any promotion is allowed, the renaming does not change the kinds
+fn analyzed(compiler: &Compiler, path: &Path, code: String) -> Result<Compilation, String> {
+    compiler
+        .clone()
+        .promotion(Promotion::Allow)
+        .check_source(path, code)
+        .map_err(|err| err.to_string())
+}
+
+/*
+    A declaration, by the kind of symbol and by the token its
+    location begins at, since the offsets change when a name
+    of different length is written. Built-ins have no token.
+*/
+type Identity = (bool, Option<usize>);
+
+struct Tokens {
+    tokens: Vec<Token>,
+    offsets: Vec<usize>,
+}
+
+impl Tokens {
+    fn new(code: &str) -> Self {
+        let tokens = tokenize(code);
+        let mut offsets = Vec::with_capacity(tokens.len());
+        let mut offset = 0;
+        for token in &tokens {
+            offsets.push(offset);
+            offset += token.text.len();
+        }
+        Self { tokens, offsets }
+    }
+
+    fn text(&self, index: usize) -> &str {
+        &self.tokens[index].text
+    }
+
+    // the identifier containing the offset or ending at it
+    fn at(&self, offset: usize) -> Option<usize> {
+        (0..self.tokens.len()).find(|index| {
+            let begin = self.offsets[*index];
+            self.tokens[*index].kind == TokenKind::Identifier
+                && begin <= offset
+                && offset <= begin + self.tokens[*index].text.len()
+        })
+    }
+
+    fn identity(&self, symbol: &Symbol) -> Identity {
+        let token = symbol
+            .loc
+            .as_ref()
+            .and_then(|loc| self.offsets.iter().position(|offset| *offset == loc.begin));
+        (symbol.function, token)
+    }
+
+    // the identifiers with the name and the declaration each refers to
+    fn resolve(&self, compilation: &Compilation, name: &str) -> Vec<(usize, Identity)> {
+        (0..self.tokens.len())
+            .filter(|index| {
+                self.tokens[*index].kind == TokenKind::Identifier
+                    && self.tokens[*index].text == name
+                    && !self.is_label(*index)
+            })
+            .filter_map(|index| {
+                let symbol = find_symbol(&compilation.program, self.offsets[index], name)?;
+                Some((index, self.identity(&symbol)))
+            })
+            .collect()
+    }
+
+    /*
+        Labels are identifiers too: they follow break and
+        continue, or come before the colon opening a loop.
+    */
+    fn is_label(&self, index: usize) -> bool {
+        let previous = self.significant(index, -1);
+        let next = self.significant(index, 1);
+        let after_next = next.and_then(|next| self.significant(next, 1));
+        let is = |index: Option<usize>, texts: &[&str]| {
+            index.is_some_and(|index| texts.contains(&self.text(index)))
+        };
+        is(previous, &["break", "continue"])
+            || (is(next, &[":"]) && is(after_next, &["while", "for", "repeat"]))
+    }
+
+    fn significant(&self, index: usize, step: isize) -> Option<usize> {
+        let mut index = index as isize + step;
+        while index >= 0 && (index as usize) < self.tokens.len() {
+            if !self.tokens[index as usize].kind.is_trivia() {
+                return Some(index as usize);
+            }
+            index += step;
+        }
+        None
+    }
+
+    fn replace(&self, indexes: &[usize], name: &str) -> String {
+        self.tokens
+            .iter()
+            .enumerate()
+            .map(|(index, token)| {
+                if indexes.contains(&index) {
+                    name
+                } else {
+                    token.text.as_str()
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    const CODE: &str = "n, total: integer;
+
+func add(n: integer): integer
+    result: integer;
+body
+    result = n + total; # n is the parameter here
+    return result;
+end;
+
+body
+    total = 1;
+    outer: for n = 1 to 3 do
+        total = add(n);
+        if total > 5 then break outer; end;
+    end;
+    writeln(total);
+end.
+";
+
+    fn rename_at(pattern: &str, nth: usize, new_name: &str) -> Result<(String, usize), String> {
+        let offset = CODE.match_indices(pattern).nth(nth).unwrap().0;
+        rename(
+            &Compiler::new(),
+            Path::new("test.simpla"),
+            CODE,
+            offset,
+            new_name,
+        )
+    }
+
+    #[test]
+    fn test_rename_global() {
+        let (code, count) = rename_at("total", 0, "sum").unwrap();
+        assert_eq!(count, 6);
+        assert!(code.contains("n, sum: integer;"));
+        assert!(code.contains("result = n + sum; # n is the parameter here"));
+        assert!(code.contains("writeln(sum);"));
+    }
+
+    #[test]
+    fn test_rename_shadowed() {
+        // the parameter hides the global variable
+        let (code, count) = rename_at("n: integer)", 0, "value").unwrap();
+        assert_eq!(count, 2);
+        assert!(code.contains("func add(value: integer): integer"));
+        assert!(code.contains("result = value + total; # n is the parameter here"));
+        assert!(code.contains("for n = 1 to 3 do"));
+
+        let (code, count) = rename_at("add", 1, "increase").unwrap();
+        assert_eq!(count, 2);
+        assert!(code.contains("func increase(n: integer)"));
+        assert!(code.contains("total = increase(n);"));
+    }
+
+    #[test]
+    fn test_rename_refused() {
+        // same scope
+        let err = rename_at("result", 0, "n").unwrap_err();
+        assert!(err.contains("name error"), "{}", err);
+        // the global total would be hidden by the local variable
+        assert!(rename_at("result", 0, "total").is_err());
+        assert!(rename_at("total", 0, "end").is_err());
+        assert!(rename_at("outer", 0, "inner").is_err());
+        assert!(rename_at("writeln", 0, "print").is_err());
+        assert!(rename_at("# n", 0, "value").is_err());
+    }
+
+    #[test]
+    fn test_position() {
+        let at: Position = "3:6".parse().unwrap();
+        assert_eq!(at.offset(CODE), CODE.find("add"));
+        assert!("3".parse::<Position>().is_err());
+        assert!("0:1".parse::<Position>().is_err());
+        assert_eq!("100:1".parse::<Position>().unwrap().offset(CODE), None);
+    }
+}
@@ -0,0 +1,75 @@
+mod session;
+
+use crate::interpreter::{self, Console};
+use crate::semantic_analysis::Promotion;
+use session::{Reply, Session};
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+Enter declarations, statements or expressions, the final ';' is optional.
+An empty line submits an incomplete input to show its error.
+:help   show this message
+:quit   leave the REPL";
+
+/*
+    Runs the interactive loop on the standard input and
+    output; `read` statements take their input from the
+    same stream of lines. Returns the exit code given to
+    `halt`, 0 when the input ends or the user quits.
+*/
+pub fn run_repl(promotion: Promotion) -> Result<i32, String> {
+    interpreter::with_interpreter_stack(|| run_session(promotion))
+}
+
+fn run_session(promotion: Promotion) -> Result<i32, String> {
+    let mut session = Session::new(promotion);
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+    let mut pending = String::new();
+    loop {
+        let prompt = if pending.is_empty() { "> " } else { ". " };
+        write!(output, "{}", prompt)
+            .and_then(|_| output.flush())
+            .map_err(|err| err.to_string())?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line).map_err(|err| err.to_string())? == 0 {
+            writeln!(output).map_err(|err| err.to_string())?;
+            return Ok(0);
+        }
+        let force = line.trim().is_empty();
+        if pending.is_empty() {
+            match line.trim() {
+                "" => continue,
+                ":quit" => return Ok(0),
+                ":help" => {
+                    writeln!(output, "{}", HELP).map_err(|err| err.to_string())?;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        pending.push_str(&line);
+
+        let mut console = Console {
+            input: &mut input,
+            output: &mut output,
+        };
+        match session.submit(&pending, &mut console, force) {
+            Reply::Incomplete => continue,
+            Reply::Done { warnings, echo } => {
+                for warning in warnings {
+                    eprintln!("{}", warning);
+                }
+                if let Some(echo) = echo {
+                    writeln!(output, "{}", echo).map_err(|err| err.to_string())?;
+                }
+            }
+            Reply::Failed(err) => eprintln!("{}", err),
+            Reply::Halted(code) => return Ok(code),
+        }
+        pending.clear();
+    }
+}
@@ -0,0 +1,337 @@
+use crate::interpreter::{Console, Interpreter, Stop, Value};
+use crate::semantic_analysis::{IncrementalAnalysis, Promotion};
+use extract_line_error::SourceMap;
+use simpla_parser::relocate::Relocate;
+use simpla_parser::syntax_tree::{Expr, FuncDecl, Kind, Stat, VarDecl};
+use simpla_parser::{
+    format_syntax_error, syntax_error_location, ExprParser, FuncDeclParser, StatParser,
+    VarDeclParser,
+};
+
+/*
+    What the session did with a piece of input. Warnings
+    and errors are meant for the standard error, the echo
+    of an expression value for the standard output.
+*/
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    Incomplete,
+    Done {
+        warnings: Vec<String>,
+        echo: Option<String>,
+    },
+    Failed(String),
+    Halted(i32),
+}
+
+enum Fragment {
+    Variables(Box<VarDecl>),
+    Function(Box<FuncDecl>),
+    Expression(Box<Expr>),
+    Statement(Box<Stat>),
+}
+
+/*
+    The state of a REPL: every accepted fragment is kept
+    for the whole session, since both the name tables and
+    the interpreter borrow it, so its syntax tree is leaked.
+    The source map holds the text of all the fragments, so
+    that errors point to the input they come from.
+*/
+pub struct Session {
+    analysis: IncrementalAnalysis<'static>,
+    interpreter: Interpreter<'static>,
+    sources: SourceMap,
+    inputs: usize,
+}
+
+impl Session {
+    pub fn new(promotion: Promotion) -> Self {
+        Self {
+            analysis: IncrementalAnalysis::new(promotion),
+            interpreter: Interpreter::new(),
+            sources: SourceMap::new(),
+            inputs: 0,
+        }
+    }
+
+    /*
+        Accepts a declaration of variables or of a function,
+        an expression whose value is echoed or a statement;
+        the trailing semicolon is optional. Input that could
+        still become valid is Incomplete, unless `force` is
+        set because the user wants to see the error.
+    */
+    pub fn submit(&mut self, input: &str, console: &mut Console, force: bool) -> Reply {
+        let input = input.trim();
+        let (text, fragment) = match parse(input) {
+            Ok(parsed) => parsed,
+            Err((_, true)) if !force => return Reply::Incomplete,
+            Err((err, _)) => return Reply::Failed(err),
+        };
+        self.inputs += 1;
+        let base = self
+            .sources
+            .add_file(format!("input {}", self.inputs), text);
+        match fragment {
+            Fragment::Variables(mut var_decl) => {
+                var_decl.relocate(base);
+                let var_decl = Box::leak(var_decl);
+                match self.analysis.add_variables(var_decl, &self.sources) {
+                    Ok(warnings) => {
+                        self.interpreter.declare_variables(var_decl);
+                        done(warnings, None)
+                    }
+                    Err(err) => Reply::Failed(err),
+                }
+            }
+            Fragment::Function(mut func_decl) => {
+                func_decl.relocate(base);
+                let func_decl = Box::leak(func_decl);
+                match self.analysis.add_function(func_decl, &self.sources) {
+                    Ok(warnings) => {
+                        self.interpreter.declare_function(func_decl);
+                        done(warnings, None)
+                    }
+                    Err(err) => Reply::Failed(err),
+                }
+            }
+            Fragment::Expression(mut expr) => {
+                expr.relocate(base);
+                let expr = Box::leak(expr);
+                let (kind, warnings) = match self.analysis.check_expression(expr, &self.sources) {
+                    Ok(checked) => checked,
+                    Err(err) => return Reply::Failed(err),
+                };
+                match self.interpreter.evaluate(expr, console) {
+                    Ok(_) if kind == Kind::Void => done(warnings, None),
+                    Ok(value) => done(warnings, Some(echo(&value))),
+                    Err(stop) => self.stopped(stop),
+                }
+            }
+            Fragment::Statement(mut stat) => {
+                stat.relocate(base);
+                let stat = Box::leak(stat);
+                let warnings = match self.analysis.check_statement(stat, &self.sources) {
+                    Ok(warnings) => warnings,
+                    Err(err) => return Reply::Failed(err),
+                };
+                match self.interpreter.execute(stat, console) {
+                    Ok(()) => done(warnings, None),
+                    Err(stop) => self.stopped(stop),
+                }
+            }
+        }
+    }
+
+    fn stopped(&self, stop: Stop) -> Reply {
+        match stop {
+            Stop::Halt(code) => Reply::Halted(code),
+            stop => Reply::Failed(stop.format(&self.sources)),
+        }
+    }
+}
+
+fn done(warnings: Vec<String>, echo: Option<String>) -> Reply {
+    Reply::Done { warnings, echo }
+}
+
+// strings and characters are quoted, like they are written in the code
+fn echo(value: &Value) -> String {
+    match value {
+        Value::Str(text) => format!("{:?}", text),
+        Value::Char(c) => format!("{:?}", c),
+        value => value.to_string(),
+    }
+}
+
+/*
+    Declarations are parsed with their semicolon, statements
+    and expressions without it. On failure the error that got
+    further in its text is formatted, and the input counts as
+    incomplete if that error is at the end of it.
+*/
+fn parse(input: &str) -> Result<(String, Fragment), (String, bool)> {
+    let declaration = if input.ends_with(';') {
+        input.to_owned()
+    } else {
+        format!("{};", input)
+    };
+    let body = input.trim_end_matches(';').trim_end().to_owned();
+
+    let var_err = match VarDeclParser::new().parse(&declaration) {
+        Ok(var_decl) => return Ok((declaration, Fragment::Variables(Box::new(var_decl)))),
+        Err(err) => err,
+    };
+    let func_err = match FuncDeclParser::new().parse(&declaration) {
+        Ok(func_decl) => return Ok((declaration, Fragment::Function(Box::new(func_decl)))),
+        Err(err) => err,
+    };
+    let expr_err = match ExprParser::new().parse(&body) {
+        Ok(expr) => return Ok((body, Fragment::Expression(Box::new(expr)))),
+        Err(err) => err,
+    };
+    let stat_err = match StatParser::new().parse(&body) {
+        Ok(stat) => return Ok((body, Fragment::Statement(Box::new(stat)))),
+        Err(err) => err,
+    };
+    // the appended semicolon is where the input ends
+    let declaration_end = declaration.len() - 1;
+    let errors = vec![
+        (&declaration, declaration_end, var_err),
+        (&declaration, declaration_end, func_err),
+        (&body, body.len(), expr_err),
+        (&body, body.len(), stat_err),
+    ];
+    let (text, end, err) = errors
+        .into_iter()
+        .max_by_key(|(_, end, err)| {
+            let begin = syntax_error_location(err).begin;
+            (begin >= *end, begin)
+        })
+        .expect("four parsers");
+    let incomplete = syntax_error_location(&err).begin >= end;
+    Err((format_syntax_error(text, err), incomplete))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    fn submit_all(session: &mut Session, inputs: &[&str], input: &str) -> (Vec<Reply>, String) {
+        let mut input = input.as_bytes();
+        let mut output = Vec::new();
+        let mut console = Console {
+            input: &mut input,
+            output: &mut output,
+        };
+        let replies = inputs
+            .iter()
+            .map(|text| session.submit(text, &mut console, false))
+            .collect();
+        (replies, String::from_utf8(output).unwrap())
+    }
+
+    fn echo(text: &str) -> Reply {
+        done(vec![], Some(text.to_owned()))
+    }
+
+    #[test]
+    fn test_session() {
+        let mut session = Session::new(Promotion::Forbid);
+        let (replies, output) = submit_all(
+            &mut session,
+            &[
+                "n: integer",
+                "func next(k: integer): integer body return k + 1; end",
+                "n = next(n);",
+                "read(n)",
+                "next(n) * 2",
+                "writeln(\"n = \", n)",
+                "'a'",
+                "\"text\"",
+            ],
+            "20\n",
+        );
+        assert_eq!(
+            replies,
+            vec![
+                done(vec![], None),
+                done(vec![], None),
+                done(vec![], None),
+                done(vec![], None),
+                echo("42"),
+                done(vec![], None),
+                echo("'a'"),
+                echo("\"text\""),
+            ]
+        );
+        assert_eq!(output, "n = 20\n");
+    }
+
+    #[test]
+    fn test_for_step() {
+        let mut session = Session::new(Promotion::Forbid);
+        let (replies, output) = submit_all(
+            &mut session,
+            &[
+                "i, s: integer",
+                "s = 2",
+                "for i = 1 to 9 step s do s = s + 1; writeln(i); end",
+            ],
+            "",
+        );
+        assert_eq!(
+            replies,
+            vec![done(vec![], None), done(vec![], None), done(vec![], None)]
+        );
+        assert_eq!(output, "1\n3\n5\n7\n9\n");
+    }
+
+    #[test]
+    fn test_write_real() {
+        let mut session = Session::new(Promotion::Forbid);
+        let (replies, output) = submit_all(
+            &mut session,
+            &["writeln(1.5e-8, \" \", 2.0, \" \", 3.25)", "0.5 * 3.0"],
+            "",
+        );
+        assert_eq!(replies, vec![done(vec![], None), echo("1.5")]);
+        assert_eq!(output, "0.000000015 2 3.25\n");
+    }
+
+    #[test]
+    fn test_session_errors() {
+        let mut session = Session::new(Promotion::Warn);
+        let (replies, _) = submit_all(
+            &mut session,
+            &[
+                "r: real;",
+                "r = 2",
+                "m = 1",
+                "1 / (2 - 2)",
+                "func f(): integer body return 1.5; end;",
+                "f()",
+                "halt(2)",
+            ],
+            "",
+        );
+        assert!(matches!(&replies[1], Reply::Done { warnings, .. } if warnings.len() == 1));
+        for reply in &replies[2..6] {
+            assert!(matches!(reply, Reply::Failed(_)), "{:?}", reply);
+        }
+        assert_eq!(replies[6], Reply::Halted(2));
+    }
+
+    #[test]
+    fn test_incomplete_input() {
+        let mut session = Session::new(Promotion::Forbid);
+        let (replies, _) = submit_all(
+            &mut session,
+            &[
+                "if true then",
+                "func f(): integer body",
+                "1 +",
+                "x y",
+                "1 )",
+            ],
+            "",
+        );
+        assert_eq!(
+            replies[..3],
+            [Reply::Incomplete, Reply::Incomplete, Reply::Incomplete]
+        );
+        assert!(matches!(replies[3], Reply::Failed(_)));
+        assert!(matches!(replies[4], Reply::Failed(_)));
+
+        let mut input = "".as_bytes();
+        let mut output = Vec::new();
+        let mut console = Console {
+            input: &mut input,
+            output: &mut output,
+        };
+        let reply = session.submit("1 +\n\n", &mut console, true);
+        assert!(matches!(reply, Reply::Failed(_)));
+    }
+}
@@ -19,7 +19,7 @@ fn check_return<'a>(
     kind: &'a syntax_tree::Kind,
 ) -> Result<(), MissingReturn<'a>> {
     match &stat.stat {
-        syntax_tree::StatType::ReturnStat(_) => Ok(()),
+        syntax_tree::StatType::ReturnStat(_) | syntax_tree::StatType::HaltStat(_) => Ok(()),
         syntax_tree::StatType::IfStat(if_stat) => {
             if let Some(else_part) = &if_stat.else_body {
                 check_full_return_cover(&if_stat.if_body, func_loc, kind)?;
@@ -28,6 +28,16 @@ fn check_return<'a>(
                 Err(MissingReturn::new(func_loc, &stat.loc, kind))
             }
         }
+        syntax_tree::StatType::CaseStat(case_stat) => {
+            if let Some(else_part) = &case_stat.else_body {
+                for branch in &case_stat.branches {
+                    check_full_return_cover(&branch.body, func_loc, kind)?;
+                }
+                check_full_return_cover(else_part, func_loc, kind)
+            } else {
+                Err(MissingReturn::new(func_loc, &stat.loc, kind))
+            }
+        }
         _ => Err(MissingReturn::new(func_loc, &stat.loc, kind)),
     }
 }
@@ -82,6 +92,14 @@ mod test {
         assert!(stat.is_ok(), "{:?}", stat);
     }
 
+    #[test]
+    fn test_return_into_case_stat() {
+        let prog = compile_file("return_inside_case-correct.simpla");
+        let func = &prog.functions[0];
+        let stat = check_full_return_cover(&func.body, &func.loc, &func.kind);
+        assert!(stat.is_ok(), "{:?}", stat);
+    }
+
     fn compile_file(name: &str) -> syntax_tree::Program {
         let file = PathBuf::from(BASE_DIR).join(name);
         let mut file = File::open(file).unwrap();
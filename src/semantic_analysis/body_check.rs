@@ -6,9 +6,13 @@ use simpla_parser::syntax_tree;
 
 pub fn check_function_declaration<'a>(
     func_decl: &'a syntax_tree::FuncDecl,
-    table: &'a mut LocalVariableTable<'a>,
+    table: &mut LocalVariableTable<'a>,
 ) -> Result<(), SemanticError<'a>> {
     build_lookup_table(func_decl, table)?;
+    for nested in &func_decl.functions {
+        let mut nested_table = table.nested_table();
+        check_function_declaration(nested, &mut nested_table)?;
+    }
     check_stat_list(
         &func_decl.body,
         table,
@@ -26,7 +30,7 @@ pub fn check_function_declaration<'a>(
 
 pub fn check_main_body<'a>(
     body: &'a syntax_tree::StatList,
-    table: &'a LocalVariableTable<'a>,
+    table: &LocalVariableTable<'a>,
 ) -> Result<(), SemanticError<'a>> {
     check_stat_list(body, &table, stat_check::Contex::Global)?;
     Ok(())
@@ -34,7 +38,7 @@ pub fn check_main_body<'a>(
 
 fn check_stat_list<'a>(
     stat_list: &'a syntax_tree::StatList,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
     contex: stat_check::Contex,
 ) -> Result<(), SemanticError<'a>> {
     for stat in stat_list {
@@ -56,6 +60,10 @@ fn build_lookup_table<'a>(
             table.insert_variable(var, &var_decl.kind, &var_decl.loc)?;
         }
     }
+
+    for nested in &func_decl.functions {
+        table.insert_function(&nested.id, nested)?;
+    }
     Ok(())
 }
 
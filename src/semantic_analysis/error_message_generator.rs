@@ -1,9 +1,12 @@
+use super::promotion;
 use super::semantic_error;
-use extract_line_error::extract_error_code;
+use super::stat_check::CASE_SELECTOR_KINDS;
+use crate::built_in;
+use extract_line_error::SourceMap;
 use simpla_parser::syntax_tree;
 
 impl<'a> semantic_error::SemanticError<'a> {
-    pub fn format_error(&self, code: &str) -> String {
+    pub fn format_error(&self, code: &SourceMap) -> String {
         let msg = match self {
             Self::NameRidefinition(err) => format!("name error: {}", err.format_error(code)),
             Self::VoidVariableDeclaration(err) => {
@@ -31,24 +34,54 @@ impl<'a> semantic_error::SemanticError<'a> {
             Self::MismatchedArgumentType(err) => {
                 format!("argument type error: {}", err.format_error(code))
             }
+            Self::NonAssignableArgument(err) => {
+                format!("reference argument error: {}", err.format_error(code))
+            }
+            Self::BuiltInCallError(err) => {
+                format!("built-in function error: {}", err.format_error(code))
+            }
             Self::MismatchedAssignment(err) => {
                 format!("assignment error: {}", err.format_error(code))
             }
             Self::BreakOutsideLoop => format!("break error: break outside loop"),
+            Self::ContinueOutsideLoop => "continue error: continue outside loop".to_owned(),
+            Self::LoopLabelError(err) => format!("loop label error: {}", err.format_error(code)),
             Self::ForLoopError(err) => format!("for loop error: {}", err.format_error(code)),
+            Self::CaseError(err) => format!("case error: {}", err.format_error(code)),
             Self::ReturnError(err) => format!("return error: {}", err.format_error(code)),
+            Self::RuntimeCheckError(err) => {
+                format!("runtime check error: {}", err.format_error(code))
+            }
+            Self::FunctionValueError(err) => {
+                format!("function value error: {}", err.format_error(code))
+            }
+            Self::ConstantError(err) => {
+                format!("constant expression error: {}", err.format_error(code))
+            }
+            Self::IndexError(err) => format!("index error: {}", err.format_error(code)),
             Self::MissingReturn(err) => err.format_error(code),
         };
         format!("{}", msg)
     }
 }
 
-fn format_wrong_code(code: &str, loc: &syntax_tree::Location) -> String {
-    extract_error_code(code, loc.begin, loc.end)
+impl promotion::ImplicitConversion {
+    pub fn format_warning(&self, code: &SourceMap) -> String {
+        format!(
+            "warning: implicit conversion from {} to {}\n{}",
+            kind_to_string(&self.from),
+            kind_to_string(&self.to),
+            code.extract_warning_code(self.loc.begin, self.loc.end)
+        )
+    }
+}
+
+fn format_wrong_code(code: &SourceMap, loc: &syntax_tree::Location) -> String {
+    code.extract_error_code(loc.begin, loc.end)
 }
 
 impl semantic_error::NameRidefinition {
-    fn format_error(&self, code: &str) -> String {
+    fn format_error(&self, code: &SourceMap) -> String {
         format!(
             "{} defined multiple times, originally: {}, redefined: {}",
             self.name,
@@ -58,7 +91,7 @@ impl semantic_error::NameRidefinition {
     }
 }
 impl semantic_error::Ridefinition {
-    fn format_error(&self, code: &str) -> String {
+    fn format_error(&self, code: &SourceMap) -> String {
         match self {
             Self::Function(loc) => format!("function here:\n{}", format_wrong_code(code, loc)),
             Self::Variable(loc) => format!("variable here:\n{}", format_wrong_code(code, loc)),
@@ -66,7 +99,7 @@ impl semantic_error::Ridefinition {
     }
 }
 impl<'a> semantic_error::VoidVariableDeclaration<'a> {
-    fn format_error(&self, code: &str) -> String {
+    fn format_error(&self, code: &SourceMap) -> String {
         let tmp = self.names.id_list.join(", ");
         format!(
             "Variables: [{}] defined as type void: only function can have type void:\n{}",
@@ -76,7 +109,7 @@ impl<'a> semantic_error::VoidVariableDeclaration<'a> {
     }
 }
 impl<'a> semantic_error::MismatchedTypes<'a> {
-    fn format_error(&self, code: &str) -> String {
+    fn format_error(&self, code: &SourceMap) -> String {
         format!(
             "left type: {} right type: {}:\n{}",
             kind_to_string(&self.left),
@@ -86,7 +119,7 @@ impl<'a> semantic_error::MismatchedTypes<'a> {
     }
 }
 impl<'a> semantic_error::IncoherentOperation<'a> {
-    fn format_error(&self, code: &str) -> String {
+    fn format_error(&self, code: &SourceMap) -> String {
         format!(
             "cannot apply operator {} to type {}\n{}",
             operator_to_string(&self.operator),
@@ -96,7 +129,7 @@ impl<'a> semantic_error::IncoherentOperation<'a> {
     }
 }
 impl<'a> semantic_error::CastError<'a> {
-    fn format_error(&self, code: &str) -> String {
+    fn format_error(&self, code: &SourceMap) -> String {
         let token = format_wrong_code(code, &self.loc);
         match &self.error {
             semantic_error::CastErrorType::ToInt(k) => {
@@ -105,15 +138,21 @@ impl<'a> semantic_error::CastError<'a> {
             semantic_error::CastErrorType::ToReal(k) => {
                 format!("cannot cast {} into real:\n{}", kind_to_string(k), token)
             }
+            semantic_error::CastErrorType::ToLong(k) => {
+                format!("cannot cast {} into long:\n{}", kind_to_string(k), token)
+            }
+            semantic_error::CastErrorType::ToChar(k) => {
+                format!("cannot cast {} into char:\n{}", kind_to_string(k), token)
+            }
         }
     }
 }
 impl<'a> semantic_error::NonBooleanCondition<'a> {
-    fn format_error(&self, code: &str) -> String {
+    fn format_error(&self, code: &SourceMap) -> String {
         fn fmt_err(
             stat: &str,
             kind: &syntax_tree::Kind,
-            code: &str,
+            code: &SourceMap,
             loc: &syntax_tree::Location,
         ) -> String {
             format!(
@@ -128,14 +167,20 @@ impl<'a> semantic_error::NonBooleanCondition<'a> {
             semantic_error::NonBooleanConditionType::WhileStat(k) => {
                 fmt_err("while", k, code, self.loc)
             }
+            semantic_error::NonBooleanConditionType::RepeatStat(k) => {
+                fmt_err("repeat", k, code, self.loc)
+            }
             semantic_error::NonBooleanConditionType::CondStat(k) => {
                 fmt_err("conditional", k, code, self.loc)
             }
+            semantic_error::NonBooleanConditionType::AssertStat(k) => {
+                fmt_err("assert", k, code, self.loc)
+            }
         }
     }
 }
 impl<'a> semantic_error::MismatchedUnary<'a> {
-    fn format_error(&self, code: &str) -> String {
+    fn format_error(&self, code: &SourceMap) -> String {
         fn fmt_err(unary: &str, kind: &syntax_tree::Kind, err: String) -> String {
             format!(
                 "{} cannot be applied to type: {}\n{}",
@@ -154,7 +199,7 @@ impl<'a> semantic_error::MismatchedUnary<'a> {
     }
 }
 impl<'a> semantic_error::MismatchedAssignment<'a> {
-    fn format_error(&self, code: &str) -> String {
+    fn format_error(&self, code: &SourceMap) -> String {
         format!(
             "expected {}, found {} in variable {} assignment:\n{}",
             kind_to_string(&self.correct),
@@ -165,7 +210,7 @@ impl<'a> semantic_error::MismatchedAssignment<'a> {
     }
 }
 impl<'a> semantic_error::ForLoopError<'a> {
-    fn format_error(&self, code: &str) -> String {
+    fn format_error(&self, code: &SourceMap) -> String {
         let descr = match &self.error {
             semantic_error::ForLoopErrorType::CountVariableAssignment(name) => {
                 format!("count variable {} is modified into loop body", name)
@@ -182,13 +227,146 @@ impl<'a> semantic_error::ForLoopError<'a> {
                 "for loop end expression of type {}, expected integer",
                 kind_to_string(k)
             ),
+            semantic_error::ForLoopErrorType::NonIntegerStep(k) => format!(
+                "for loop step expression of type {}, expected integer",
+                kind_to_string(k)
+            ),
+            semantic_error::ForLoopErrorType::NonPositiveStep(step) => {
+                format!("for loop step is {}, expected a positive step", step)
+            }
+        };
+        let token = format_wrong_code(code, self.loc);
+        format!("{}\n{}", descr, token)
+    }
+}
+impl<'a> semantic_error::LoopLabelError<'a> {
+    fn format_error(&self, code: &SourceMap) -> String {
+        let descr = match &self.error {
+            semantic_error::LoopLabelErrorType::UnknownLabel(label) => {
+                format!("no enclosing loop is labelled {}", label)
+            }
+            semantic_error::LoopLabelErrorType::DuplicatedLabel(label) => {
+                format!("label {} is already used by an enclosing loop", label)
+            }
+        };
+        let token = format_wrong_code(code, self.loc);
+        format!("{}\n{}", descr, token)
+    }
+}
+impl<'a> semantic_error::CaseError<'a> {
+    fn format_error(&self, code: &SourceMap) -> String {
+        let descr = match &self.error {
+            semantic_error::CaseErrorType::InvalidSelector(k) => {
+                let kinds: Vec<String> = CASE_SELECTOR_KINDS.iter().map(kind_to_string).collect();
+                let (last, others) = kinds.split_last().unwrap();
+                format!(
+                    "case selector of type {}, expected {} or {}",
+                    kind_to_string(k),
+                    others.join(", "),
+                    last
+                )
+            }
+            semantic_error::CaseErrorType::MismatchedLabel(expected, given) => format!(
+                "case label of type {}, expected {}",
+                kind_to_string(given),
+                kind_to_string(expected)
+            ),
+            semantic_error::CaseErrorType::DuplicatedLabel(value) => {
+                format!("case label {} is used more than once", format_const(value))
+            }
         };
         let token = format_wrong_code(code, self.loc);
         format!("{}\n{}", descr, token)
     }
 }
+
+impl<'a> semantic_error::RuntimeCheckError<'a> {
+    fn format_error(&self, code: &SourceMap) -> String {
+        let descr = match &self.error {
+            semantic_error::RuntimeCheckErrorType::AssertMessage(k) => format!(
+                "assert statement requires a string as message, found: {}",
+                kind_to_string(k)
+            ),
+            semantic_error::RuntimeCheckErrorType::HaltCode(k) => format!(
+                "halt statement requires an integer as exit code, found: {}",
+                kind_to_string(k)
+            ),
+        };
+        format!("{}\n{}", descr, format_wrong_code(code, self.loc))
+    }
+}
+
+impl<'a> semantic_error::ConstantError<'a> {
+    fn format_error(&self, code: &SourceMap) -> String {
+        let descr = match &self.error {
+            semantic_error::ConstantErrorType::Overflow(k) => {
+                format!("the value does not fit in {}", kind_to_string(k))
+            }
+            semantic_error::ConstantErrorType::DivisionByZero => "division by zero".to_owned(),
+        };
+        format!("{}\n{}", descr, format_wrong_code(code, self.loc))
+    }
+}
+
+impl<'a> semantic_error::IndexError<'a> {
+    fn format_error(&self, code: &SourceMap) -> String {
+        let descr = match &self.error {
+            semantic_error::IndexErrorType::NonString(name, k) => format!(
+                "{} has type {}, only strings can be indexed",
+                name,
+                kind_to_string(k)
+            ),
+            semantic_error::IndexErrorType::NonIntegerIndex(k) => {
+                format!("index must be an integer, found: {}", kind_to_string(k))
+            }
+        };
+        format!("{}\n{}", descr, format_wrong_code(code, self.loc))
+    }
+}
+
+impl<'a> semantic_error::FunctionValueError<'a> {
+    fn format_error(&self, code: &SourceMap) -> String {
+        let descr = match &self.error {
+            semantic_error::FunctionValueErrorType::NotCallable(name, k) => format!(
+                "{} has type {}, only functions can be called",
+                name,
+                kind_to_string(k)
+            ),
+            semantic_error::FunctionValueErrorType::ArgumentCount(name, expected, given) => {
+                format!(
+                    "function value {} expected {} args, but {} are used in function call",
+                    name, expected, given
+                )
+            }
+            semantic_error::FunctionValueErrorType::ArgumentType(name, index, expected, given) => {
+                format!(
+                    "calling function value {} argument expected type: {}, found {} in position {}",
+                    name,
+                    kind_to_string(expected),
+                    kind_to_string(given),
+                    index + 1
+                )
+            }
+            semantic_error::FunctionValueErrorType::NestedFunction(name) => format!(
+                "nested function {} cannot be used as a value, only top level functions can",
+                name
+            ),
+            semantic_error::FunctionValueErrorType::ReferenceParameters(name) => format!(
+                "function {} has var parameters and cannot be used as a value",
+                name
+            ),
+            semantic_error::FunctionValueErrorType::InputOutput(k) => format!(
+                "values of type {} cannot be read or written",
+                kind_to_string(k)
+            ),
+        };
+        let token = format_wrong_code(code, self.loc);
+        format!("{}\n{}", descr, token)
+    }
+}
+
 impl<'a> semantic_error::ReturnError<'a> {
-    fn format_error(&self, code: &str) -> String {
+    fn format_error(&self, code: &SourceMap) -> String {
         let token = format_wrong_code(code, self.loc);
         match &self.error {
             semantic_error::ReturnErrorType::ReturnOutsideFunction => format!(
@@ -205,7 +383,7 @@ impl<'a> semantic_error::ReturnError<'a> {
     }
 }
 impl<'a> semantic_error::ArgumentCountError<'a> {
-    fn format_error(&self, code: &str) -> String {
+    fn format_error(&self, code: &SourceMap) -> String {
         format!(
             "function: {} expected {} args, but {} are used in function call:\nFunction '{1}' definition:\n{}\nFunction '{1}' call:\n{}",
             self.func_decl.id,
@@ -217,7 +395,7 @@ impl<'a> semantic_error::ArgumentCountError<'a> {
     }
 }
 impl<'a> semantic_error::MismatchedArgumentType<'a> {
-    fn format_error(&self, code: &str) -> String {
+    fn format_error(&self, code: &SourceMap) -> String {
         format!(
             "calling function {} argument expected type: {}, found {} in position {}:\nFunction declaration: {}\nFunction call: {}",
             self.func.id,
@@ -230,8 +408,40 @@ impl<'a> semantic_error::MismatchedArgumentType<'a> {
     }
 }
 
+impl<'a> semantic_error::NonAssignableArgument<'a> {
+    fn format_error(&self, code: &SourceMap) -> String {
+        format!(
+            "calling function {} argument in position {} is a var parameter, only a variable can be passed:\nFunction declaration: {}\nArgument: {}",
+            self.func.id,
+            (self.index + 1),
+            format_wrong_code(code, &self.func.loc),
+            format_wrong_code(code, self.loc)
+        )
+    }
+}
+
+impl<'a> semantic_error::BuiltInCallError<'a> {
+    fn format_error(&self, code: &SourceMap) -> String {
+        let available: Vec<String> = built_in::get_overloads(self.name)
+            .map(|func| format_signature(func.name, func.params))
+            .collect();
+        format!(
+            "no version of built-in function {} accepts arguments {}, available: {}\n{}",
+            self.name,
+            format_signature("", &self.given),
+            available.join(", "),
+            format_wrong_code(code, self.loc)
+        )
+    }
+}
+
+fn format_signature(name: &str, params: &[syntax_tree::Kind]) -> String {
+    let params: Vec<String> = params.iter().map(kind_to_string).collect();
+    format!("{}({})", name, params.join(", "))
+}
+
 impl<'a> semantic_error::MissingReturn<'a> {
-    fn format_error(&self, code: &str) -> String {
+    fn format_error(&self, code: &SourceMap) -> String {
         format!(
             "Missing return statement in non void [actual type: {}] function:\nFound:\n{}\nIn Function:\n{}",
             kind_to_string(&self.kind),
@@ -245,13 +455,34 @@ fn kind_to_string(k: &syntax_tree::Kind) -> String {
     let output = match k {
         syntax_tree::Kind::Bool => "bool",
         syntax_tree::Kind::Int => "integer",
+        syntax_tree::Kind::Long => "long",
         syntax_tree::Kind::Real => "real",
         syntax_tree::Kind::Str => "string",
+        syntax_tree::Kind::Char => "char",
         syntax_tree::Kind::Void => "void",
+        syntax_tree::Kind::Func(func) => {
+            let params: Vec<String> = func.params.iter().map(kind_to_string).collect();
+            return format!(
+                "func({}): {}",
+                params.join(", "),
+                kind_to_string(&func.kind)
+            );
+        }
     };
     output.to_owned()
 }
 
+fn format_const(value: &syntax_tree::Const) -> String {
+    match value {
+        syntax_tree::Const::IntConst(v) => format!("{}", v),
+        syntax_tree::Const::LongConst(v) => format!("{}L", v),
+        syntax_tree::Const::RealConst(v) => format!("{}", v),
+        syntax_tree::Const::StrConst(v) => format!("\"{}\"", v),
+        syntax_tree::Const::CharConst(v) => format!("{:?}", v),
+        syntax_tree::Const::BoolConst(v) => format!("{}", v),
+    }
+}
+
 fn operator_to_string(o: &syntax_tree::Operator) -> String {
     let output = match o {
         syntax_tree::Operator::Equal => "Equal",
@@ -0,0 +1,160 @@
+use extract_line_error::SourceMap;
+use simpla_parser::syntax_tree::{Expr, FuncDecl, Kind, Stat, VarDecl};
+
+use super::body_check::check_function_declaration;
+use super::name_table::{name_table_factory, FactoryLocalVariableTable};
+use super::promotion::Promotion;
+use super::semantic_error::{SemanticError, VoidVariableDeclaration};
+use super::stat_check::{statement_check, Contex};
+use super::type_check::type_check;
+
+/*
+    Semantic analysis of a program entered one fragment at
+    a time, like in the REPL: every accepted declaration is
+    added to the global tables, a rejected one leaves them
+    unchanged. Statements and expressions are checked as
+    parts of the main body. On success the warnings about
+    implicit conversions are returned.
+*/
+pub struct IncrementalAnalysis<'a> {
+    tables: FactoryLocalVariableTable<'a>,
+}
+
+impl<'a> IncrementalAnalysis<'a> {
+    pub fn new(promotion: Promotion) -> Self {
+        let mut tables = name_table_factory()
+            .switch_to_function_table()
+            .switch_to_local_table();
+        tables.set_promotion(promotion);
+        Self { tables }
+    }
+
+    pub fn add_variables(
+        &mut self,
+        var_decl: &'a VarDecl,
+        code: &SourceMap,
+    ) -> Result<Vec<String>, String> {
+        let res = if var_decl.kind == Kind::Void {
+            let err = VoidVariableDeclaration::new(var_decl);
+            Err(SemanticError::VoidVariableDeclaration(err))
+        } else {
+            self.tables.insert_global_variables(var_decl)
+        };
+        self.finish(res, code)
+    }
+
+    // the function is visible in its own body, so that it can be recursive
+    pub fn add_function(
+        &mut self,
+        func_decl: &'a FuncDecl,
+        code: &SourceMap,
+    ) -> Result<Vec<String>, String> {
+        if let Err(err) = self.tables.insert_global_function(func_decl) {
+            return self.finish(Err(err), code);
+        }
+        let res = {
+            let mut table = self.tables.factory_local_table();
+            let res = check_function_declaration(func_decl, &mut table);
+            self.finish(res, code)
+        };
+        if res.is_err() {
+            self.tables.remove_global_function(&func_decl.id);
+        }
+        res
+    }
+
+    pub fn check_statement(&self, stat: &'a Stat, code: &SourceMap) -> Result<Vec<String>, String> {
+        let res = {
+            let table = self.tables.factory_local_table();
+            statement_check(stat, &table, &Contex::Global)
+        };
+        self.finish(res, code)
+    }
+
+    pub fn check_expression(
+        &self,
+        expr: &'a Expr,
+        code: &SourceMap,
+    ) -> Result<(Kind, Vec<String>), String> {
+        let res = {
+            let table = self.tables.factory_local_table();
+            type_check(expr, &table)
+        };
+        match res {
+            Ok(kind) => self.finish(Ok(()), code).map(|warnings| (kind, warnings)),
+            Err(err) => self
+                .finish(Err(err), code)
+                .map(|warnings| (Kind::Void, warnings)),
+        }
+    }
+
+    // conversions of a rejected fragment are dropped with it
+    fn finish(
+        &self,
+        res: Result<(), SemanticError>,
+        code: &SourceMap,
+    ) -> Result<Vec<String>, String> {
+        let conversions = self.tables.take_conversions();
+        match res {
+            Ok(()) => Ok(conversions
+                .iter()
+                .map(|conversion| conversion.format_warning(code))
+                .collect()),
+            Err(err) => Err(err.format_error(code)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use simpla_parser::{ExprParser, FuncDeclParser, StatParser, VarDeclParser};
+
+    #[test]
+    fn test_incremental_declarations() {
+        let sources = SourceMap::from("n: integer;");
+        let count = VarDeclParser::new().parse("n: integer;").unwrap();
+        let twice = FuncDeclParser::new()
+            .parse("func twice(x: integer): integer body return x * 2; end;")
+            .unwrap();
+        let again = VarDeclParser::new().parse("m, twice: real;").unwrap();
+        let wrong = FuncDeclParser::new()
+            .parse("func wrong(): integer body return true; end;")
+            .unwrap();
+        let call = StatParser::new().parse("n = twice(n)").unwrap();
+        let expr = ExprParser::new().parse("twice(n) > 3").unwrap();
+        let unknown = StatParser::new().parse("m = 1.0").unwrap();
+        let recall = StatParser::new().parse("n = wrong()").unwrap();
+
+        let mut analysis = IncrementalAnalysis::new(Promotion::Forbid);
+        analysis.add_variables(&count, &sources).unwrap();
+        analysis.add_function(&twice, &sources).unwrap();
+        let err = analysis.add_variables(&again, &sources).unwrap_err();
+        assert!(err.starts_with("name error"), "{}", err);
+        assert!(analysis.add_function(&wrong, &sources).is_err());
+
+        analysis.check_statement(&call, &sources).unwrap();
+        let (kind, _) = analysis.check_expression(&expr, &sources).unwrap();
+        assert_eq!(kind, Kind::Bool);
+        // names of rejected declarations are not visible
+        assert!(analysis.check_statement(&unknown, &sources).is_err());
+        assert!(analysis.check_statement(&recall, &sources).is_err());
+    }
+
+    #[test]
+    fn test_incremental_warnings() {
+        let sources = SourceMap::from("r = 1 + r");
+        let var_decl = VarDeclParser::new().parse("r: real;").unwrap();
+        let stat = StatParser::new().parse("r = 1 + r").unwrap();
+
+        let mut analysis = IncrementalAnalysis::new(Promotion::Warn);
+        assert!(analysis
+            .add_variables(&var_decl, &sources)
+            .unwrap()
+            .is_empty());
+        let warnings = analysis.check_statement(&stat, &sources).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("implicit conversion"));
+    }
+}
@@ -1,11 +1,20 @@
 mod analyze_return_stat;
 mod body_check;
 mod error_message_generator;
+mod incremental;
 mod name_table;
+mod promotion;
 mod semantic_check;
 mod semantic_error;
 mod stat_check;
+mod symbol_lookup;
 mod type_check;
 mod variable_check;
 
-pub use semantic_check::semantic_check;
+pub use incremental::IncrementalAnalysis;
+pub use name_table::{FactoryLocalVariableTable, LocalVariableTable};
+pub use promotion::Promotion;
+pub use semantic_check::analyze_program;
+pub use symbol_lookup::{
+    declare_locals, find_symbol, global_tables, resolve_symbol, signature, visible_symbols, Symbol,
+};
@@ -1,6 +1,9 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
 
+use super::promotion::{ImplicitConversion, Promotion};
 use super::semantic_error::{NameRidefinition, Ridefinition, SemanticError};
+use crate::built_in;
 use simpla_parser::syntax_tree;
 
 pub fn name_table_factory<'a>() -> GlobalVariableTable<'a> {
@@ -82,6 +85,8 @@ impl<'a> FunctionTable<'a> {
 pub struct FactoryLocalVariableTable<'a> {
     global_table: VarNameTable<'a>,
     function_table: NameTable<'a, &'a syntax_tree::FuncDecl>,
+    promotion: Promotion,
+    conversions: RefCell<Vec<ImplicitConversion>>,
 }
 
 impl<'b, 'a: 'b> FactoryLocalVariableTable<'a> {
@@ -92,49 +97,225 @@ impl<'b, 'a: 'b> FactoryLocalVariableTable<'a> {
         Self {
             global_table,
             function_table,
+            promotion: Promotion::Forbid,
+            conversions: RefCell::new(Vec::new()),
         }
     }
 
-    pub fn factory_local_table(&'a self) -> LocalVariableTable<'b> {
-        LocalVariableTable::new(&self.global_table, &self.function_table)
+    pub fn set_promotion(&mut self, promotion: Promotion) {
+        self.promotion = promotion;
+    }
+
+    pub fn factory_local_table(&'b self) -> LocalVariableTable<'b> {
+        LocalVariableTable::new(
+            &self.global_table,
+            &self.function_table,
+            self.promotion,
+            &self.conversions,
+        )
+    }
+
+    pub fn take_conversions(&self) -> Vec<ImplicitConversion> {
+        self.conversions.take()
+    }
+
+    /*
+        Global declarations added after the tables have been
+        built, when a program is entered one declaration at a
+        time: a variable can follow a function, so it is checked
+        against both tables. The names of a declaration are
+        inserted all together, or none of them is.
+    */
+    pub fn insert_global_variables(
+        &mut self,
+        var_decl: &'a syntax_tree::VarDecl,
+    ) -> Result<(), SemanticError<'a>> {
+        let mut global_table = self.global_table.clone();
+        for id in &var_decl.id_list {
+            self.function_table
+                .check_collision(id, &var_decl.loc, Entry::Variable)?;
+            global_table.check_collision(id, &var_decl.loc, Entry::Variable)?;
+            global_table.insert(id, (&var_decl.kind, &var_decl.loc));
+        }
+        self.global_table = global_table;
+        Ok(())
+    }
+
+    pub fn insert_global_function(
+        &mut self,
+        func_decl: &'a syntax_tree::FuncDecl,
+    ) -> Result<(), SemanticError<'a>> {
+        self.global_table
+            .check_collision(&func_decl.id, &func_decl.loc, Entry::Function)?;
+        self.function_table
+            .check_collision(&func_decl.id, &func_decl.loc, Entry::Function)?;
+        self.function_table.insert(&func_decl.id, func_decl);
+        Ok(())
+    }
+
+    // drops a function rejected by the analysis of its body
+    pub fn remove_global_function(&mut self, name: &str) {
+        self.function_table.remove(name);
     }
 }
 
+/*
+    The scopes of the functions enclosing a nested
+    function, outermost first: a nested function can
+    use the variables and call the functions declared
+    in any of them.
+*/
+#[derive(Clone)]
+struct EnclosingScope<'a> {
+    variables: VarNameTable<'a>,
+    functions: NameTable<'a, &'a syntax_tree::FuncDecl>,
+}
+
 pub struct LocalVariableTable<'a> {
     global_table: &'a VarNameTable<'a>,
     function_table: &'a NameTable<'a, &'a syntax_tree::FuncDecl>,
+    enclosing: Vec<EnclosingScope<'a>>,
     local_table: VarNameTable<'a>,
+    local_functions: NameTable<'a, &'a syntax_tree::FuncDecl>,
+    promotion: Promotion,
+    conversions: &'a RefCell<Vec<ImplicitConversion>>,
 }
 
 impl<'a> LocalVariableTable<'a> {
     fn new(
         global_table: &'a VarNameTable<'a>,
         function_table: &'a NameTable<'a, &'a syntax_tree::FuncDecl>,
+        promotion: Promotion,
+        conversions: &'a RefCell<Vec<ImplicitConversion>>,
     ) -> Self {
         Self {
             global_table,
             function_table,
+            enclosing: Vec::new(),
+            local_table: NameTable::new(Entry::Variable),
+            local_functions: NameTable::new(Entry::Function),
+            promotion,
+            conversions,
+        }
+    }
+
+    pub fn nested_table(&self) -> Self {
+        let mut enclosing = self.enclosing.clone();
+        enclosing.push(EnclosingScope {
+            variables: self.local_table.clone(),
+            functions: self.local_functions.clone(),
+        });
+        Self {
+            global_table: self.global_table,
+            function_table: self.function_table,
+            enclosing,
             local_table: NameTable::new(Entry::Variable),
+            local_functions: NameTable::new(Entry::Function),
+            promotion: self.promotion,
+            conversions: self.conversions,
         }
     }
 
+    pub fn insert_function(
+        &mut self,
+        name: &'a str,
+        func_decl: &'a syntax_tree::FuncDecl,
+    ) -> Result<(), SemanticError<'a>> {
+        self.local_table
+            .check_collision(name, &func_decl.loc, Entry::Function)?;
+        self.local_functions
+            .check_collision(name, &func_decl.loc, Entry::Function)?;
+        // a visible variable would hide the function from the calls
+        for scope in self.enclosing.iter().rev() {
+            scope
+                .variables
+                .check_collision(name, &func_decl.loc, Entry::Function)?;
+        }
+        self.global_table
+            .check_collision(name, &func_decl.loc, Entry::Function)?;
+        self.local_functions.insert(name, func_decl);
+        Ok(())
+    }
+
+    pub fn promotion(&self) -> Promotion {
+        self.promotion
+    }
+
+    pub fn report_conversion(&self, conversion: ImplicitConversion) {
+        self.conversions.borrow_mut().push(conversion);
+    }
+
     pub fn get_variable(&self, name: &'a str) -> Result<&syntax_tree::Kind, SemanticError<'a>> {
-        if let Some((output, _)) = self.local_table.get(name) {
-            Ok(output)
-        } else if let Some((output, _)) = self.global_table.get(name) {
-            Ok(output)
-        } else {
-            Err(SemanticError::UnknownVariable(name))
+        match self.get_variable_declaration(name) {
+            Some((output, _)) => Ok(output),
+            None => Err(SemanticError::UnknownVariable(name)),
         }
     }
 
-    pub fn get_function(&self, name: &'a str) -> Result<&syntax_tree::FuncDecl, SemanticError<'a>> {
-        if let Some(output) = self.function_table.get(name) {
-            Ok(output)
+    // kind and location of the declaration of the visible variable
+    pub fn get_variable_declaration(
+        &self,
+        name: &str,
+    ) -> Option<(&'a syntax_tree::Kind, &'a syntax_tree::Location)> {
+        let enclosing = self.enclosing.iter().rev().map(|scope| &scope.variables);
+        std::iter::once(&self.local_table)
+            .chain(enclosing)
+            .chain(std::iter::once(self.global_table))
+            .find_map(|table| table.get(name))
+            .copied()
+    }
+
+    // names of the visible variables and declared functions, without duplicates
+    pub fn visible_names(&self) -> Vec<&'a str> {
+        let enclosing = self
+            .enclosing
+            .iter()
+            .flat_map(|scope| scope.variables.names().chain(scope.functions.names()));
+        let mut names: Vec<&'a str> = self
+            .local_table
+            .names()
+            .chain(self.local_functions.names())
+            .chain(enclosing)
+            .chain(self.global_table.names())
+            .chain(self.function_table.names())
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    pub fn get_function(&self, name: &'a str) -> Result<FunctionEntry<'a>, SemanticError<'a>> {
+        if let Some(output) = self.get_function_declaration(name) {
+            Ok(FunctionEntry::Declared(output))
+        } else if built_in::is_built_in(name) {
+            Ok(FunctionEntry::BuiltIn(name))
         } else {
             Err(SemanticError::UnknownFunction(name))
         }
     }
+
+    // declaration of the visible user defined function
+    pub fn get_function_declaration(&self, name: &str) -> Option<&'a syntax_tree::FuncDecl> {
+        let enclosing = self.enclosing.iter().rev().map(|scope| &scope.functions);
+        std::iter::once(&self.local_functions)
+            .chain(enclosing)
+            .chain(std::iter::once(self.function_table))
+            .find_map(|table| table.get(name))
+            .copied()
+    }
+
+    // true when the visible function with this name is declared inside another function
+    pub fn is_nested_function(&self, name: &'a str) -> bool {
+        let enclosing = self.enclosing.iter().map(|scope| &scope.functions);
+        std::iter::once(&self.local_functions)
+            .chain(enclosing)
+            .any(|table| table.get(name).is_some())
+    }
+}
+
+pub enum FunctionEntry<'a> {
+    Declared(&'a syntax_tree::FuncDecl),
+    BuiltIn(&'a str),
 }
 
 impl<'a> VariableTable<'a> for LocalVariableTable<'a> {
@@ -146,6 +327,13 @@ impl<'a> VariableTable<'a> for LocalVariableTable<'a> {
     ) -> Result<(), SemanticError<'a>> {
         self.function_table
             .check_collision(name, loc, Entry::Variable)?;
+        for scope in &self.enclosing {
+            scope
+                .functions
+                .check_collision(name, loc, Entry::Variable)?;
+        }
+        self.local_functions
+            .check_collision(name, loc, Entry::Variable)?;
         self.local_table
             .check_collision(name, loc, Entry::Variable)?;
         self.local_table.insert(name, (kind, loc));
@@ -153,11 +341,13 @@ impl<'a> VariableTable<'a> for LocalVariableTable<'a> {
     }
 }
 
+#[derive(Clone)]
 enum Entry {
     Variable,
     Function,
 }
 
+#[derive(Clone)]
 struct NameTable<'a, T>
 where
     T: 'a,
@@ -179,10 +369,18 @@ impl<'a, T: Localizable> NameTable<'a, T> {
         self.table.get(name)
     }
 
+    fn names(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.table.keys().copied()
+    }
+
     fn insert(&mut self, name: &'a str, entry: T) {
         self.table.insert(name, entry);
     }
 
+    fn remove(&mut self, name: &str) {
+        self.table.remove(name);
+    }
+
     fn check_collision(
         &self,
         name: &str,
@@ -337,6 +535,81 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_nested_scope() {
+        let outer_loc = syntax_tree::Location::new(10, 20);
+        let inner_loc = syntax_tree::Location::new(30, 40);
+        let nested = syntax_tree::FuncDecl::new(
+            "nested".to_owned(),
+            vec![],
+            syntax_tree::Kind::Void,
+            vec![],
+            vec![],
+            50,
+            60,
+        );
+
+        let table = name_table_factory().switch_to_function_table();
+        let table_factory = table.switch_to_local_table();
+        let mut outer = table_factory.factory_local_table();
+        outer
+            .insert_variable("count", &syntax_tree::Kind::Int, &outer_loc)
+            .unwrap();
+        outer.insert_function("nested", &nested).unwrap();
+
+        let mut inner = outer.nested_table();
+        inner
+            .insert_variable("value", &syntax_tree::Kind::Real, &inner_loc)
+            .unwrap();
+        assert_eq!(
+            inner.get_variable("count").unwrap(),
+            &syntax_tree::Kind::Int
+        );
+        assert_eq!(
+            inner.get_variable("value").unwrap(),
+            &syntax_tree::Kind::Real
+        );
+        assert!(matches!(
+            inner.get_function("nested"),
+            Ok(FunctionEntry::Declared(decl)) if decl.id == "nested"
+        ));
+
+        // enclosing variables can be shadowed, enclosing functions cannot
+        inner
+            .insert_variable("count", &syntax_tree::Kind::Str, &inner_loc)
+            .unwrap();
+        assert_eq!(
+            inner.get_variable("count").unwrap(),
+            &syntax_tree::Kind::Str
+        );
+        let err = inner.insert_variable("nested", &syntax_tree::Kind::Int, &inner_loc);
+        check_status(
+            err,
+            "nested",
+            Ridefinition::Function(syntax_tree::Location::new(50, 60)),
+            Ridefinition::Variable(inner_loc.clone()),
+        );
+
+        assert!(outer.get_variable("value").is_err());
+
+        // nor can a nested function take the name of a visible variable
+        let err = inner.insert_function("count", &nested);
+        check_status(
+            err,
+            "count",
+            Ridefinition::Variable(inner_loc.clone()),
+            Ridefinition::Function(syntax_tree::Location::new(50, 60)),
+        );
+        let mut innermost = inner.nested_table();
+        let err = innermost.insert_function("value", &nested);
+        check_status(
+            err,
+            "value",
+            Ridefinition::Variable(inner_loc.clone()),
+            Ridefinition::Function(syntax_tree::Location::new(50, 60)),
+        );
+    }
+
     fn check_status(
         stat: Result<(), SemanticError>,
         original_name: &str,
@@ -0,0 +1,57 @@
+use simpla_parser::syntax_tree;
+use std::str::FromStr;
+
+/*
+    Controls the implicit widening of integer values
+    where a real one is expected. By default it is
+    forbidden and an explicit real(...) cast is required.
+*/
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Promotion {
+    Forbid,
+    Warn,
+    Allow,
+}
+
+impl FromStr for Promotion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "forbid" => Ok(Self::Forbid),
+            "warn" => Ok(Self::Warn),
+            "allow" => Ok(Self::Allow),
+            other => Err(format!(
+                "unknown promotion level {}, expected one of: forbid, warn, allow",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct ImplicitConversion {
+    pub loc: syntax_tree::Location,
+    pub from: syntax_tree::Kind,
+    pub to: syntax_tree::Kind,
+}
+
+impl ImplicitConversion {
+    pub fn new(loc: syntax_tree::Location, from: syntax_tree::Kind, to: syntax_tree::Kind) -> Self {
+        Self { loc, from, to }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_promotion() {
+        assert_eq!("forbid".parse(), Ok(Promotion::Forbid));
+        assert_eq!("warn".parse(), Ok(Promotion::Warn));
+        assert_eq!("allow".parse(), Ok(Promotion::Allow));
+        assert!("always".parse::<Promotion>().is_err());
+    }
+}
@@ -1,15 +1,49 @@
-use simpla_parser::syntax_tree::Program;
+use extract_line_error::SourceMap;
+use simpla_parser::syntax_tree::{Location, Program};
 
 use super::body_check::{check_function_declaration, check_main_body};
 use super::name_table::{name_table_factory, FactoryLocalVariableTable};
+use super::promotion::{ImplicitConversion, Promotion};
 use super::semantic_error::SemanticError;
 use super::variable_check::check_variables;
 
-pub fn semantic_check<'a>(program: &'a Program, code: &'a str) -> Result<(), String> {
-    let table_factory = match init_table(&program) {
+/*
+    The formatted error with the location of the wrong
+    code, for tools that mark it in the source: when the
+    location is not known the error may name the
+    identifier that caused it.
+*/
+#[derive(Debug, PartialEq)]
+pub struct LocatedError {
+    pub message: String,
+    pub loc: Option<Location>,
+    pub name: Option<String>,
+}
+
+impl LocatedError {
+    fn new(err: &SemanticError, code: &SourceMap) -> Self {
+        Self {
+            message: err.format_error(code),
+            loc: err.location().cloned(),
+            name: err.name().map(str::to_owned),
+        }
+    }
+}
+
+/*
+    On success returns the implicit conversions, the
+    warnings of the analysis, without formatting them.
+*/
+pub fn analyze_program<'a>(
+    program: &'a Program,
+    code: &'a SourceMap,
+    promotion: Promotion,
+) -> Result<Vec<ImplicitConversion>, LocatedError> {
+    let mut table_factory = match init_table(&program) {
         Ok(table_factory) => table_factory,
-        Err(err) => return Err(err.format_error(code)),
+        Err(err) => return Err(LocatedError::new(&err, code)),
     };
+    table_factory.set_promotion(promotion);
 
     for decl in &program.functions {
         let mut local_table = table_factory.factory_local_table();
@@ -19,7 +53,9 @@ pub fn semantic_check<'a>(program: &'a Program, code: &'a str) -> Result<(), Str
 
     let mut local_table = table_factory.factory_local_table();
     let stat = check_main_body(&program.body, &mut local_table);
-    convert_error(stat, code)
+    convert_error(stat, code)?;
+
+    Ok(table_factory.take_conversions())
 }
 
 fn init_table<'a>(
@@ -37,10 +73,13 @@ fn init_table<'a>(
     Ok(func_tabl.switch_to_local_table())
 }
 
-fn convert_error<'a>(res: Result<(), SemanticError<'a>>, code: &'a str) -> Result<(), String> {
+fn convert_error<'a>(
+    res: Result<(), SemanticError<'a>>,
+    code: &'a SourceMap,
+) -> Result<(), LocatedError> {
     match res {
         Ok(()) => Ok(()),
-        Err(err) => Err(err.format_error(code)),
+        Err(err) => Err(LocatedError::new(&err, code)),
     }
 }
 
@@ -57,16 +96,36 @@ mod test {
     fn test_variable_shadowing() {
         let var_shadow_code = PathBuf::from("test_code").join("variable_shadowing-correct.simpla");
         let code = load_file(&var_shadow_code);
+        let sources = SourceMap::from(code.as_str());
         let parser = ProgramParser::new();
 
         let prog = parser.parse(&code).unwrap();
 
-        let result = semantic_check(&prog, &code);
+        let result = analyze_program(&prog, &sources, Promotion::Forbid);
         if let Err(err) = result {
-            assert!(false, "{}", err)
+            assert!(false, "{}", err.message)
         }
     }
 
+    #[test]
+    fn test_implicit_promotion() {
+        let promotion_code = PathBuf::from("test_code").join("implicit_promotion-correct.simpla");
+        let code = load_file(&promotion_code);
+        let sources = SourceMap::from(code.as_str());
+        let parser = ProgramParser::new();
+
+        let prog = parser.parse(&code).unwrap();
+        assert!(analyze_program(&prog, &sources, Promotion::Forbid).is_err());
+
+        let prog = parser.parse(&code).unwrap();
+        let warnings = analyze_program(&prog, &sources, Promotion::Warn).unwrap();
+        assert_eq!(warnings.len(), 6);
+
+        let prog = parser.parse(&code).unwrap();
+        let warnings = analyze_program(&prog, &sources, Promotion::Allow).unwrap();
+        assert!(warnings.is_empty());
+    }
+
     fn load_file(file: &Path) -> String {
         let mut buff = String::new();
         let mut file = File::open(file).unwrap();
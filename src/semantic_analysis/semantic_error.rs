@@ -16,11 +16,69 @@ pub enum SemanticError<'a> {
     MismatchedUnary(MismatchedUnary<'a>),
     ArgumentCountError(ArgumentCountError<'a>),
     MismatchedArgumentType(MismatchedArgumentType<'a>),
+    NonAssignableArgument(NonAssignableArgument<'a>),
+    BuiltInCallError(BuiltInCallError<'a>),
     MismatchedAssignment(MismatchedAssignment<'a>),
     MissingReturn(MissingReturn<'a>),
     BreakOutsideLoop,
+    ContinueOutsideLoop,
+    LoopLabelError(LoopLabelError<'a>),
     ForLoopError(ForLoopError<'a>),
+    CaseError(CaseError<'a>),
     ReturnError(ReturnError<'a>),
+    FunctionValueError(FunctionValueError<'a>),
+    RuntimeCheckError(RuntimeCheckError<'a>),
+    ConstantError(ConstantError<'a>),
+    IndexError(IndexError<'a>),
+}
+
+impl<'a> SemanticError<'a> {
+    /*
+        The code where the error is reported, used by tools
+        that mark the error in the source. Errors about a
+        name without a location, like an unknown variable,
+        give the name instead.
+    */
+    pub fn location(&self) -> Option<&syntax_tree::Location> {
+        match self {
+            Self::NameRidefinition(err) => match &err.new {
+                Ridefinition::Function(loc) | Ridefinition::Variable(loc) => Some(loc),
+            },
+            Self::VoidVariableDeclaration(err) => Some(&err.names.loc),
+            Self::MismatchedOperationTypes(err) => Some(err.loc),
+            Self::IncoherentOperation(err) => Some(err.loc),
+            Self::CastError(err) => Some(err.loc),
+            Self::NonBooleanCondition(err) => Some(err.loc),
+            Self::MismatchedConditionalExpression(err) => Some(err.loc),
+            Self::MismatchedUnary(err) => Some(err.loc),
+            Self::MismatchedArgumentType(err) => Some(err.loc),
+            Self::NonAssignableArgument(err) => Some(err.loc),
+            Self::BuiltInCallError(err) => Some(err.loc),
+            Self::MismatchedAssignment(err) => Some(err.loc),
+            Self::MissingReturn(err) => Some(err.func_loc),
+            Self::LoopLabelError(err) => Some(err.loc),
+            Self::ForLoopError(err) => Some(err.loc),
+            Self::CaseError(err) => Some(err.loc),
+            Self::ReturnError(err) => Some(err.loc),
+            Self::FunctionValueError(err) => Some(err.loc),
+            Self::RuntimeCheckError(err) => Some(err.loc),
+            Self::ConstantError(err) => Some(err.loc),
+            Self::IndexError(err) => Some(err.loc),
+            Self::UnknownFunction(_)
+            | Self::UnknownVariable(_)
+            | Self::ArgumentCountError(_)
+            | Self::BreakOutsideLoop
+            | Self::ContinueOutsideLoop => None,
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::UnknownFunction(name) | Self::UnknownVariable(name) => Some(name),
+            Self::ArgumentCountError(err) => Some(&err.func_call.id),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -111,12 +169,25 @@ impl<'a> CastError<'a> {
         let error = CastErrorType::ToReal(kind);
         Self { loc, error }
     }
+
+    pub fn new_to_long(loc: &'a syntax_tree::Location, kind: syntax_tree::Kind) -> Self {
+        let error = CastErrorType::ToLong(kind);
+        Self { loc, error }
+    }
+
+    pub fn new_to_char(loc: &'a syntax_tree::Location, kind: syntax_tree::Kind) -> Self {
+        let error = CastErrorType::ToChar(kind);
+        Self { loc, error }
+    }
 }
 
 #[derive(PartialEq, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum CastErrorType {
     ToInt(syntax_tree::Kind),
     ToReal(syntax_tree::Kind),
+    ToLong(syntax_tree::Kind),
+    ToChar(syntax_tree::Kind),
 }
 
 #[derive(PartialEq, Debug)]
@@ -136,17 +207,29 @@ impl<'a> NonBooleanCondition<'a> {
         Self { loc, error }
     }
 
+    pub fn new_repeat_stat(loc: &'a syntax_tree::Location, kind: syntax_tree::Kind) -> Self {
+        let error = NonBooleanConditionType::RepeatStat(kind);
+        Self { loc, error }
+    }
+
     pub fn new_cond_stat(loc: &'a syntax_tree::Location, kind: syntax_tree::Kind) -> Self {
         let error = NonBooleanConditionType::CondStat(kind);
         Self { loc, error }
     }
+
+    pub fn new_assert_stat(loc: &'a syntax_tree::Location, kind: syntax_tree::Kind) -> Self {
+        let error = NonBooleanConditionType::AssertStat(kind);
+        Self { loc, error }
+    }
 }
 
 #[derive(PartialEq, Debug)]
 pub enum NonBooleanConditionType {
     IfStat(syntax_tree::Kind),
     WhileStat(syntax_tree::Kind),
+    RepeatStat(syntax_tree::Kind),
     CondStat(syntax_tree::Kind),
+    AssertStat(syntax_tree::Kind),
 }
 
 #[derive(PartialEq, Debug)]
@@ -219,6 +302,16 @@ impl<'a> ForLoopError<'a> {
         Self { loc, error }
     }
 
+    pub fn new_non_integer_step(loc: &'a syntax_tree::Location, kind: syntax_tree::Kind) -> Self {
+        let error = ForLoopErrorType::NonIntegerStep(kind);
+        Self { loc, error }
+    }
+
+    pub fn new_non_positive_step(loc: &'a syntax_tree::Location, step: i32) -> Self {
+        let error = ForLoopErrorType::NonPositiveStep(step);
+        Self { loc, error }
+    }
+
     pub fn new_count_variable_assignment(loc: &'a syntax_tree::Location, name: &'a str) -> Self {
         let error = ForLoopErrorType::CountVariableAssignment(name);
         Self { loc, error }
@@ -230,9 +323,211 @@ pub enum ForLoopErrorType<'a> {
     NonIntegerCount(syntax_tree::Kind),
     NonIntegerStart(syntax_tree::Kind),
     NonIntegerEnd(syntax_tree::Kind),
+    NonIntegerStep(syntax_tree::Kind),
+    NonPositiveStep(i32),
     CountVariableAssignment(&'a str),
 }
 
+#[derive(PartialEq, Debug)]
+pub struct LoopLabelError<'a> {
+    pub loc: &'a syntax_tree::Location,
+    pub error: LoopLabelErrorType<'a>,
+}
+
+impl<'a> LoopLabelError<'a> {
+    pub fn new_unknown_label(loc: &'a syntax_tree::Location, label: &'a str) -> Self {
+        let error = LoopLabelErrorType::UnknownLabel(label);
+        Self { loc, error }
+    }
+
+    pub fn new_duplicated_label(loc: &'a syntax_tree::Location, label: &'a str) -> Self {
+        let error = LoopLabelErrorType::DuplicatedLabel(label);
+        Self { loc, error }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum LoopLabelErrorType<'a> {
+    UnknownLabel(&'a str),
+    DuplicatedLabel(&'a str),
+}
+
+#[derive(PartialEq, Debug)]
+pub struct CaseError<'a> {
+    pub loc: &'a syntax_tree::Location,
+    pub error: CaseErrorType<'a>,
+}
+
+impl<'a> CaseError<'a> {
+    pub fn new_invalid_selector(loc: &'a syntax_tree::Location, kind: syntax_tree::Kind) -> Self {
+        let error = CaseErrorType::InvalidSelector(kind);
+        Self { loc, error }
+    }
+
+    pub fn new_mismatched_label(
+        loc: &'a syntax_tree::Location,
+        expected: syntax_tree::Kind,
+        given: syntax_tree::Kind,
+    ) -> Self {
+        let error = CaseErrorType::MismatchedLabel(expected, given);
+        Self { loc, error }
+    }
+
+    pub fn new_duplicated_label(
+        loc: &'a syntax_tree::Location,
+        value: &'a syntax_tree::Const,
+    ) -> Self {
+        let error = CaseErrorType::DuplicatedLabel(value);
+        Self { loc, error }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum CaseErrorType<'a> {
+    InvalidSelector(syntax_tree::Kind),
+    MismatchedLabel(syntax_tree::Kind, syntax_tree::Kind),
+    DuplicatedLabel(&'a syntax_tree::Const),
+}
+
+#[derive(PartialEq, Debug)]
+pub struct RuntimeCheckError<'a> {
+    pub loc: &'a syntax_tree::Location,
+    pub error: RuntimeCheckErrorType,
+}
+
+impl<'a> RuntimeCheckError<'a> {
+    pub fn new_assert_message(loc: &'a syntax_tree::Location, kind: syntax_tree::Kind) -> Self {
+        let error = RuntimeCheckErrorType::AssertMessage(kind);
+        Self { loc, error }
+    }
+
+    pub fn new_halt_code(loc: &'a syntax_tree::Location, kind: syntax_tree::Kind) -> Self {
+        let error = RuntimeCheckErrorType::HaltCode(kind);
+        Self { loc, error }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum RuntimeCheckErrorType {
+    AssertMessage(syntax_tree::Kind),
+    HaltCode(syntax_tree::Kind),
+}
+
+#[derive(PartialEq, Debug)]
+pub struct ConstantError<'a> {
+    pub loc: &'a syntax_tree::Location,
+    pub error: ConstantErrorType,
+}
+
+impl<'a> ConstantError<'a> {
+    pub fn new_overflow(loc: &'a syntax_tree::Location, kind: syntax_tree::Kind) -> Self {
+        let error = ConstantErrorType::Overflow(kind);
+        Self { loc, error }
+    }
+
+    pub fn new_division_by_zero(loc: &'a syntax_tree::Location) -> Self {
+        let error = ConstantErrorType::DivisionByZero;
+        Self { loc, error }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum ConstantErrorType {
+    Overflow(syntax_tree::Kind),
+    DivisionByZero,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct IndexError<'a> {
+    pub loc: &'a syntax_tree::Location,
+    pub error: IndexErrorType<'a>,
+}
+
+impl<'a> IndexError<'a> {
+    pub fn new_non_string(
+        loc: &'a syntax_tree::Location,
+        name: &'a str,
+        kind: syntax_tree::Kind,
+    ) -> Self {
+        let error = IndexErrorType::NonString(name, kind);
+        Self { loc, error }
+    }
+
+    pub fn new_non_integer_index(loc: &'a syntax_tree::Location, kind: syntax_tree::Kind) -> Self {
+        let error = IndexErrorType::NonIntegerIndex(kind);
+        Self { loc, error }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum IndexErrorType<'a> {
+    NonString(&'a str, syntax_tree::Kind),
+    NonIntegerIndex(syntax_tree::Kind),
+}
+
+#[derive(PartialEq, Debug)]
+pub struct FunctionValueError<'a> {
+    pub loc: &'a syntax_tree::Location,
+    pub error: FunctionValueErrorType<'a>,
+}
+
+impl<'a> FunctionValueError<'a> {
+    pub fn new_not_callable(
+        loc: &'a syntax_tree::Location,
+        name: &'a str,
+        kind: syntax_tree::Kind,
+    ) -> Self {
+        let error = FunctionValueErrorType::NotCallable(name, kind);
+        Self { loc, error }
+    }
+
+    pub fn new_argument_count(
+        loc: &'a syntax_tree::Location,
+        name: &'a str,
+        expected: usize,
+        given: usize,
+    ) -> Self {
+        let error = FunctionValueErrorType::ArgumentCount(name, expected, given);
+        Self { loc, error }
+    }
+
+    pub fn new_argument_type(
+        loc: &'a syntax_tree::Location,
+        name: &'a str,
+        index: usize,
+        expected: syntax_tree::Kind,
+        given: syntax_tree::Kind,
+    ) -> Self {
+        let error = FunctionValueErrorType::ArgumentType(name, index, expected, given);
+        Self { loc, error }
+    }
+
+    pub fn new_nested_function(loc: &'a syntax_tree::Location, name: &'a str) -> Self {
+        let error = FunctionValueErrorType::NestedFunction(name);
+        Self { loc, error }
+    }
+
+    pub fn new_reference_parameters(loc: &'a syntax_tree::Location, name: &'a str) -> Self {
+        let error = FunctionValueErrorType::ReferenceParameters(name);
+        Self { loc, error }
+    }
+
+    pub fn new_input_output(loc: &'a syntax_tree::Location, kind: syntax_tree::Kind) -> Self {
+        let error = FunctionValueErrorType::InputOutput(kind);
+        Self { loc, error }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum FunctionValueErrorType<'a> {
+    NotCallable(&'a str, syntax_tree::Kind),
+    ArgumentCount(&'a str, usize, usize),
+    ArgumentType(&'a str, usize, syntax_tree::Kind, syntax_tree::Kind),
+    NestedFunction(&'a str),
+    ReferenceParameters(&'a str),
+    InputOutput(syntax_tree::Kind),
+}
+
 #[derive(PartialEq, Debug)]
 pub struct ReturnError<'a> {
     pub loc: &'a syntax_tree::Location,
@@ -308,6 +603,40 @@ impl<'a> MismatchedArgumentType<'a> {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub struct NonAssignableArgument<'a> {
+    pub func: &'a syntax_tree::FuncDecl,
+    pub index: usize,
+    pub loc: &'a syntax_tree::Location,
+}
+
+impl<'a> NonAssignableArgument<'a> {
+    pub fn new(
+        func: &'a syntax_tree::FuncDecl,
+        index: usize,
+        loc: &'a syntax_tree::Location,
+    ) -> Self {
+        Self { func, index, loc }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BuiltInCallError<'a> {
+    pub name: &'a str,
+    pub given: Vec<syntax_tree::Kind>,
+    pub loc: &'a syntax_tree::Location,
+}
+
+impl<'a> BuiltInCallError<'a> {
+    pub fn new(
+        name: &'a str,
+        given: Vec<syntax_tree::Kind>,
+        loc: &'a syntax_tree::Location,
+    ) -> Self {
+        Self { name, given, loc }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct MissingReturn<'a> {
     pub func_loc: &'a syntax_tree::Location,
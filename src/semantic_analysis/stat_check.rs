@@ -2,11 +2,22 @@ use std::collections::HashSet;
 
 use super::name_table::LocalVariableTable;
 use super::semantic_error::{
-    ForLoopError, MismatchedAssignment, NonBooleanCondition, ReturnError, SemanticError,
+    CaseError, ForLoopError, FunctionValueError, LoopLabelError, MismatchedAssignment,
+    NonBooleanCondition, ReturnError, RuntimeCheckError, SemanticError,
 };
-use super::type_check::{function_call_check, type_check};
+use super::type_check::{check_const, function_call_check, promote, type_check};
+use crate::constant_fold::{self, ConstValue};
 use simpla_parser::syntax_tree;
 
+// the kinds a case statement can select on
+pub const CASE_SELECTOR_KINDS: [syntax_tree::Kind; 5] = [
+    syntax_tree::Kind::Int,
+    syntax_tree::Kind::Long,
+    syntax_tree::Kind::Char,
+    syntax_tree::Kind::Bool,
+    syntax_tree::Kind::Str,
+];
+
 pub enum Contex<'a> {
     Global,
     Function(&'a syntax_tree::FuncDecl),
@@ -14,7 +25,7 @@ pub enum Contex<'a> {
 
 pub fn statement_check<'a>(
     stat: &'a syntax_tree::Stat,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
     contex: &Contex,
 ) -> Result<(), SemanticError<'a>> {
     let mut loop_contex = LoopContext::new();
@@ -23,7 +34,7 @@ pub fn statement_check<'a>(
 
 fn stat_check<'b, 'a: 'b>(
     stat: &'a syntax_tree::Stat,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
     contex: &Contex,
     loop_contex: &mut LoopContext<'b>,
 ) -> Result<(), SemanticError<'a>> {
@@ -31,7 +42,10 @@ fn stat_check<'b, 'a: 'b>(
         syntax_tree::StatType::AssignStat(assign_stat) => {
             check_assign_stat(assign_stat, table, loop_contex, &stat.loc)
         }
-        syntax_tree::StatType::Break => check_break_stat(loop_contex),
+        syntax_tree::StatType::Break(label) => check_break_stat(label, loop_contex, &stat.loc),
+        syntax_tree::StatType::Continue(label) => {
+            check_continue_stat(label, loop_contex, &stat.loc)
+        }
         syntax_tree::StatType::ForStat(for_stat) => {
             check_for_stat(for_stat, table, contex, loop_contex, &stat.loc)
         }
@@ -50,13 +64,84 @@ fn stat_check<'b, 'a: 'b>(
         syntax_tree::StatType::WhileStat(while_stat) => {
             check_while_stat(while_stat, table, contex, loop_contex, &stat.loc)
         }
-        syntax_tree::StatType::WriteStat(write_stat) => check_write_stat(write_stat, table),
+        syntax_tree::StatType::RepeatStat(repeat_stat) => {
+            check_repeat_stat(repeat_stat, table, contex, loop_contex, &stat.loc)
+        }
+        syntax_tree::StatType::CaseStat(case_stat) => {
+            check_case_stat(case_stat, table, contex, loop_contex, &stat.loc)
+        }
+        syntax_tree::StatType::WriteStat(write_stat) => {
+            check_write_stat(write_stat, table, &stat.loc)
+        }
+        syntax_tree::StatType::AssertStat(assert_stat) => {
+            check_assert_stat(assert_stat, table, &stat.loc)
+        }
+        syntax_tree::StatType::HaltStat(code) => check_halt_stat(code, table, &stat.loc),
+    }?;
+    check_reference_arguments(stat, table, loop_contex)
+}
+
+/*
+    A count variable passed to a var parameter could be
+    changed by the callee, so it is rejected like an
+    assignment. Only the expressions of the statement itself
+    are searched, the nested statements are checked on their own.
+*/
+fn check_reference_arguments<'b, 'a: 'b>(
+    stat: &'a syntax_tree::Stat,
+    table: &LocalVariableTable<'a>,
+    loop_contex: &LoopContext<'b>,
+) -> Result<(), SemanticError<'a>> {
+    let mut arguments = ReferenceArguments {
+        table,
+        loop_contex,
+        count_variable: None,
+    };
+    syntax_tree::walk_stat(&mut arguments, stat);
+    match arguments.count_variable {
+        Some(name) => {
+            let err = ForLoopError::new_count_variable_assignment(&stat.loc, name);
+            Err(SemanticError::ForLoopError(err))
+        }
+        None => Ok(()),
+    }
+}
+
+struct ReferenceArguments<'t, 'b, 'a: 'b> {
+    table: &'t LocalVariableTable<'a>,
+    loop_contex: &'t LoopContext<'b>,
+    count_variable: Option<&'a str>,
+}
+
+impl<'t, 'b, 'a: 'b> syntax_tree::Visitor<'a> for ReferenceArguments<'t, 'b, 'a> {
+    fn visit_stat(&mut self, _stat: &'a syntax_tree::Stat) {}
+
+    fn visit_func_call(&mut self, func_call: &'a syntax_tree::FuncCall) {
+        let callee = match self.table.get_variable_declaration(&func_call.id) {
+            Some(_) => None,
+            None => self.table.get_function_declaration(&func_call.id),
+        };
+        if let Some(callee) = callee {
+            for (param, arg) in callee.params.iter().zip(&func_call.args) {
+                if let syntax_tree::ExprTree::Factor(syntax_tree::Factor {
+                    fact: syntax_tree::FactorValue::Id(name),
+                    ..
+                }) = &arg.expr
+                {
+                    let assignable = self.loop_contex.check_assign(name);
+                    if param.by_ref && matches!(assignable, CheckStatus::Failure) {
+                        self.count_variable.get_or_insert(name);
+                    }
+                }
+            }
+        }
+        syntax_tree::walk_func_call(self, func_call);
     }
 }
 
 fn stat_list_check<'b, 'a: 'b>(
     stat_list: &'a syntax_tree::StatList,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
     contex: &Contex,
     loop_contex: &mut LoopContext<'b>,
 ) -> Result<(), SemanticError<'a>> {
@@ -73,6 +158,7 @@ enum CheckStatus {
 
 struct LoopContext<'a> {
     indexes: HashSet<&'a str>,
+    labels: Vec<&'a str>,
     nested_loops: usize,
 }
 
@@ -80,10 +166,26 @@ impl<'a> LoopContext<'a> {
     fn new() -> Self {
         Self {
             indexes: HashSet::new(),
+            labels: Vec::new(),
             nested_loops: 0,
         }
     }
 
+    fn enter_label(&mut self, label: &'a str) -> CheckStatus {
+        if self.labels.contains(&label) {
+            CheckStatus::Failure
+        } else {
+            self.labels.push(label);
+            CheckStatus::Success
+        }
+    }
+
+    fn exit_label(&mut self, label: &'a Option<String>) {
+        if label.is_some() {
+            self.labels.pop();
+        }
+    }
+
     fn enter_for_loop(&mut self, index: &'a str) -> CheckStatus {
         if self.indexes.contains(index) {
             CheckStatus::Failure
@@ -122,17 +224,25 @@ impl<'a> LoopContext<'a> {
             CheckStatus::Success
         }
     }
+
+    fn check_label(&self, label: &str) -> CheckStatus {
+        if self.labels.contains(&label) {
+            CheckStatus::Success
+        } else {
+            CheckStatus::Failure
+        }
+    }
 }
 
 fn check_assign_stat<'b, 'a: 'b>(
     assign_stat: &'a syntax_tree::AssignStat,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
     contex: &LoopContext<'b>,
     loc: &'a syntax_tree::Location,
 ) -> Result<(), SemanticError<'a>> {
     let right_kind = type_check(&assign_stat.expr, table)?;
     let left_kind = table.get_variable(&assign_stat.id)?;
-    if left_kind == &right_kind {
+    if left_kind == &right_kind || promote(&assign_stat.expr, &right_kind, left_kind, table) {
         match contex.check_assign(&assign_stat.id) {
             CheckStatus::Success => Ok(()),
             CheckStatus::Failure => {
@@ -146,16 +256,65 @@ fn check_assign_stat<'b, 'a: 'b>(
     }
 }
 
-fn check_break_stat<'a>(loop_contex: &LoopContext) -> Result<(), SemanticError<'a>> {
+fn check_break_stat<'a>(
+    label: &'a Option<String>,
+    loop_contex: &LoopContext,
+    loc: &'a syntax_tree::Location,
+) -> Result<(), SemanticError<'a>> {
     match loop_contex.check_break() {
-        CheckStatus::Success => Ok(()),
+        CheckStatus::Success => check_jump_label(label, loop_contex, loc),
         CheckStatus::Failure => Err(SemanticError::BreakOutsideLoop),
     }
 }
 
+fn check_continue_stat<'a>(
+    label: &'a Option<String>,
+    loop_contex: &LoopContext,
+    loc: &'a syntax_tree::Location,
+) -> Result<(), SemanticError<'a>> {
+    match loop_contex.check_break() {
+        CheckStatus::Success => check_jump_label(label, loop_contex, loc),
+        CheckStatus::Failure => Err(SemanticError::ContinueOutsideLoop),
+    }
+}
+
+fn check_jump_label<'a>(
+    label: &'a Option<String>,
+    loop_contex: &LoopContext,
+    loc: &'a syntax_tree::Location,
+) -> Result<(), SemanticError<'a>> {
+    match label {
+        Some(label) => match loop_contex.check_label(label) {
+            CheckStatus::Success => Ok(()),
+            CheckStatus::Failure => {
+                let err = LoopLabelError::new_unknown_label(loc, label);
+                Err(SemanticError::LoopLabelError(err))
+            }
+        },
+        None => Ok(()),
+    }
+}
+
+fn enter_loop_label<'b, 'a: 'b>(
+    label: &'a Option<String>,
+    loop_contex: &mut LoopContext<'b>,
+    loc: &'a syntax_tree::Location,
+) -> Result<(), SemanticError<'a>> {
+    match label {
+        Some(label) => match loop_contex.enter_label(label) {
+            CheckStatus::Success => Ok(()),
+            CheckStatus::Failure => {
+                let err = LoopLabelError::new_duplicated_label(loc, label);
+                Err(SemanticError::LoopLabelError(err))
+            }
+        },
+        None => Ok(()),
+    }
+}
+
 fn check_for_stat<'b, 'a: 'b>(
     for_stat: &'a syntax_tree::ForStat,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
     block_contex: &Contex,
     loop_contex: &mut LoopContext<'b>,
     loc: &'a syntax_tree::Location,
@@ -184,10 +343,29 @@ fn check_for_stat<'b, 'a: 'b>(
         }
     }
 
+    if let Some(step) = &for_stat.step {
+        match type_check(step, table)? {
+            syntax_tree::Kind::Int => {}
+            other => {
+                let err = ForLoopError::new_non_integer_step(loc, other.clone());
+                return Err(SemanticError::ForLoopError(err));
+            }
+        }
+        // the direction is given by to/downto, the step must be positive
+        if let Some(Ok(ConstValue::Int(value))) = constant_fold::fold_expr(step) {
+            if value <= 0 {
+                let err = ForLoopError::new_non_positive_step(loc, value);
+                return Err(SemanticError::ForLoopError(err));
+            }
+        }
+    }
+
+    enter_loop_label(&for_stat.label, loop_contex, loc)?;
     match loop_contex.enter_for_loop(&for_stat.id) {
         CheckStatus::Success => {
             stat_list_check(&for_stat.body, table, block_contex, loop_contex)?;
             loop_contex.exit_for_loop(&for_stat.id);
+            loop_contex.exit_label(&for_stat.label);
             Ok(())
         }
         CheckStatus::Failure => {
@@ -199,7 +377,7 @@ fn check_for_stat<'b, 'a: 'b>(
 
 fn check_if_stat<'b, 'a: 'b>(
     if_stat: &'a syntax_tree::IfStat,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
     block_contex: &Contex,
     loop_contex: &mut LoopContext<'b>,
     loc: &'a syntax_tree::Location,
@@ -221,12 +399,15 @@ fn check_if_stat<'b, 'a: 'b>(
 
 fn check_read_stat<'a>(
     read_stat: &'a syntax_tree::IdList,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
     contex: &LoopContext,
     loc: &'a syntax_tree::Location,
 ) -> Result<(), SemanticError<'a>> {
     for id in read_stat {
-        table.get_variable(id)?;
+        if let kind @ syntax_tree::Kind::Func(_) = table.get_variable(id)? {
+            let err = FunctionValueError::new_input_output(loc, kind.clone());
+            return Err(SemanticError::FunctionValueError(err));
+        }
         match contex.check_assign(id) {
             CheckStatus::Success => {}
             CheckStatus::Failure => {
@@ -240,14 +421,15 @@ fn check_read_stat<'a>(
 
 fn check_return_stat<'a>(
     return_stat: &'a Option<syntax_tree::Expr>,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
     block_contex: &Contex,
     loc: &'a syntax_tree::Location,
 ) -> Result<(), SemanticError<'a>> {
     match block_contex {
         Contex::Function(func_decl) => {
             let kind = get_return_kind(table, return_stat)?;
-            if kind == func_decl.kind {
+            if kind == func_decl.kind || promote_return(return_stat, &kind, &func_decl.kind, table)
+            {
                 Ok(())
             } else {
                 let err = ReturnError::new_mismatched_type(loc, func_decl.kind.clone(), kind);
@@ -261,8 +443,20 @@ fn check_return_stat<'a>(
     }
 }
 
+fn promote_return(
+    return_stat: &Option<syntax_tree::Expr>,
+    kind: &syntax_tree::Kind,
+    target: &syntax_tree::Kind,
+    table: &LocalVariableTable,
+) -> bool {
+    match return_stat {
+        Some(expr) => promote(expr, kind, target, table),
+        None => false,
+    }
+}
+
 fn get_return_kind<'a>(
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
     expr: &'a Option<syntax_tree::Expr>,
 ) -> Result<syntax_tree::Kind, SemanticError<'a>> {
     if let Some(expr) = expr {
@@ -274,16 +468,18 @@ fn get_return_kind<'a>(
 
 fn check_while_stat<'b, 'a: 'b>(
     while_stat: &'a syntax_tree::WhileStat,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
     block_contex: &Contex,
     loop_contex: &mut LoopContext<'b>,
     loc: &'a syntax_tree::Location,
 ) -> Result<(), SemanticError<'a>> {
     match type_check(&while_stat.cond, table)? {
         syntax_tree::Kind::Bool => {
+            enter_loop_label(&while_stat.label, loop_contex, loc)?;
             loop_contex.enter_while_loop();
             stat_list_check(&while_stat.body, table, block_contex, loop_contex)?;
             loop_contex.exit_while_loop();
+            loop_contex.exit_label(&while_stat.label);
             Ok(())
         }
         other => {
@@ -293,22 +489,117 @@ fn check_while_stat<'b, 'a: 'b>(
     }
 }
 
+fn check_repeat_stat<'b, 'a: 'b>(
+    repeat_stat: &'a syntax_tree::RepeatStat,
+    table: &LocalVariableTable<'a>,
+    block_contex: &Contex,
+    loop_contex: &mut LoopContext<'b>,
+    loc: &'a syntax_tree::Location,
+) -> Result<(), SemanticError<'a>> {
+    enter_loop_label(&repeat_stat.label, loop_contex, loc)?;
+    loop_contex.enter_while_loop();
+    stat_list_check(&repeat_stat.body, table, block_contex, loop_contex)?;
+    loop_contex.exit_while_loop();
+    loop_contex.exit_label(&repeat_stat.label);
+
+    match type_check(&repeat_stat.cond, table)? {
+        syntax_tree::Kind::Bool => Ok(()),
+        other => {
+            let err = NonBooleanCondition::new_repeat_stat(loc, other.clone());
+            Err(SemanticError::NonBooleanCondition(err))
+        }
+    }
+}
+
+fn check_case_stat<'b, 'a: 'b>(
+    case_stat: &'a syntax_tree::CaseStat,
+    table: &LocalVariableTable<'a>,
+    block_contex: &Contex,
+    loop_contex: &mut LoopContext<'b>,
+    loc: &'a syntax_tree::Location,
+) -> Result<(), SemanticError<'a>> {
+    let kind = type_check(&case_stat.expr, table)?;
+    if !CASE_SELECTOR_KINDS.contains(&kind) {
+        let err = CaseError::new_invalid_selector(loc, kind);
+        return Err(SemanticError::CaseError(err));
+    }
+
+    let mut seen: Vec<&syntax_tree::Const> = Vec::new();
+    for branch in &case_stat.branches {
+        for label in &branch.labels {
+            let label_kind = check_const(&label.value);
+            if label_kind != kind {
+                let err = CaseError::new_mismatched_label(&label.loc, kind, label_kind);
+                return Err(SemanticError::CaseError(err));
+            }
+            if seen.contains(&&label.value) {
+                let err = CaseError::new_duplicated_label(&label.loc, &label.value);
+                return Err(SemanticError::CaseError(err));
+            }
+            seen.push(&label.value);
+        }
+        stat_list_check(&branch.body, table, block_contex, loop_contex)?;
+    }
+
+    if let Some(else_body) = &case_stat.else_body {
+        stat_list_check(else_body, table, block_contex, loop_contex)?;
+    }
+    Ok(())
+}
+
+fn check_assert_stat<'a>(
+    assert_stat: &'a syntax_tree::AssertStat,
+    table: &LocalVariableTable<'a>,
+    loc: &'a syntax_tree::Location,
+) -> Result<(), SemanticError<'a>> {
+    let cond_kind = type_check(&assert_stat.cond, table)?;
+    if cond_kind != syntax_tree::Kind::Bool {
+        let err = NonBooleanCondition::new_assert_stat(loc, cond_kind);
+        return Err(SemanticError::NonBooleanCondition(err));
+    }
+    let message_kind = type_check(&assert_stat.message, table)?;
+    if message_kind != syntax_tree::Kind::Str {
+        let err = RuntimeCheckError::new_assert_message(loc, message_kind);
+        return Err(SemanticError::RuntimeCheckError(err));
+    }
+    Ok(())
+}
+
+fn check_halt_stat<'a>(
+    code: &'a syntax_tree::Expr,
+    table: &LocalVariableTable<'a>,
+    loc: &'a syntax_tree::Location,
+) -> Result<(), SemanticError<'a>> {
+    match type_check(code, table)? {
+        syntax_tree::Kind::Int => Ok(()),
+        other => {
+            let err = RuntimeCheckError::new_halt_code(loc, other);
+            Err(SemanticError::RuntimeCheckError(err))
+        }
+    }
+}
+
 fn check_write_stat<'a>(
     write_stat: &'a syntax_tree::WriteStat,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
+    loc: &'a syntax_tree::Location,
 ) -> Result<(), SemanticError<'a>> {
     match write_stat {
-        syntax_tree::WriteStat::Write(expr_list) => check_expr_list(expr_list, table),
-        syntax_tree::WriteStat::WriteLine(expr_list) => check_expr_list(expr_list, table),
+        syntax_tree::WriteStat::Write(expr_list) => check_expr_list(expr_list, table, loc),
+        syntax_tree::WriteStat::WriteLine(expr_list) => check_expr_list(expr_list, table, loc),
     }
 }
 
 fn check_expr_list<'a>(
     list: &'a syntax_tree::ExprList,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
+    loc: &'a syntax_tree::Location,
 ) -> Result<(), SemanticError<'a>> {
     for expr in list {
-        type_check(expr, table)?;
+        if let kind @ syntax_tree::Kind::Func(_) = type_check(expr, table)? {
+            let err = FunctionValueError::new_input_output(loc, kind);
+            return Err(SemanticError::FunctionValueError(err));
+        }
     }
     Ok(())
 }
@@ -318,9 +609,11 @@ mod test {
 
     use super::super::name_table::{name_table_factory, VariableTable};
     use super::super::semantic_error::{
-        ForLoopErrorType, NonBooleanConditionType, ReturnErrorType,
+        CaseErrorType, ForLoopErrorType, LoopLabelErrorType, NonBooleanConditionType,
+        ReturnErrorType, RuntimeCheckErrorType,
     };
     use super::*;
+    use extract_line_error::SourceMap;
 
     #[test]
     fn test_check_assign_stat() {
@@ -386,13 +679,11 @@ mod test {
             ),
         );
 
-        assert!(
-            matches!(
-                    check_assign_stat(&stat, &table, &loop_contex, &syntax_tree::Location::new(0, 0)),
-                    Err(SemanticError::MismatchedAssignment(mistmatch))
-                    if mistmatch == MismatchedAssignment::new(var_name, syntax_tree::Kind::Int, syntax_tree::Kind::Real, &syntax_tree::Location::new(0, 0))
-            )
-        );
+        assert!(matches!(
+                check_assign_stat(&stat, &table, &loop_contex, &syntax_tree::Location::new(0, 0)),
+                Err(SemanticError::MismatchedAssignment(mistmatch))
+                if mistmatch == MismatchedAssignment::new(var_name, syntax_tree::Kind::Int, syntax_tree::Kind::Real, &syntax_tree::Location::new(0, 0))
+        ));
     }
 
     #[test]
@@ -430,6 +721,56 @@ mod test {
         assert_eq!(loop_contex.indexes.len(), 0);
     }
 
+    #[test]
+    fn test_loop_labels() {
+        let fake_location = syntax_tree::Location::new(0, 0);
+        let mut loop_contex = LoopContext::new();
+
+        assert!(matches!(
+            loop_contex.enter_label("outer"),
+            CheckStatus::Success
+        ));
+        assert!(matches!(
+            loop_contex.enter_label("inner"),
+            CheckStatus::Success
+        ));
+        assert!(matches!(
+            loop_contex.enter_label("outer"),
+            CheckStatus::Failure
+        ));
+        assert!(matches!(
+            loop_contex.check_label("outer"),
+            CheckStatus::Success
+        ));
+        assert!(matches!(
+            loop_contex.check_label("other"),
+            CheckStatus::Failure
+        ));
+
+        let inner = Some("inner".to_owned());
+        loop_contex.exit_label(&inner);
+        assert!(matches!(
+            loop_contex.check_label("inner"),
+            CheckStatus::Failure
+        ));
+
+        let unknown = Some("unknown".to_owned());
+        loop_contex.enter_while_loop();
+        assert!(check_break_stat(&None, &loop_contex, &fake_location).is_ok());
+        assert!(check_continue_stat(&None, &loop_contex, &fake_location).is_ok());
+        assert!(matches!(
+            check_continue_stat(&unknown, &loop_contex, &fake_location),
+            Err(SemanticError::LoopLabelError(LoopLabelError {loc: _, error}))
+                if matches!(error, LoopLabelErrorType::UnknownLabel("unknown"))
+        ));
+        loop_contex.exit_while_loop();
+
+        assert!(matches!(
+            check_continue_stat(&None, &loop_contex, &fake_location),
+            Err(SemanticError::ContinueOutsideLoop)
+        ));
+    }
+
     #[test]
     fn test_check_return_stat() {
         let return_stat = Some(syntax_tree::Expr::new(
@@ -559,12 +900,10 @@ mod test {
             &mut loop_contex,
             &fake_location,
         );
-        assert!(
-            matches!(stat,
+        assert!(matches!(stat,
                  Err(SemanticError::ForLoopError(ForLoopError {loc: _, error}))
                   if matches!(&error, ForLoopErrorType::NonIntegerEnd(kind)
-                   if kind == &syntax_tree::Kind::Real))
-        );
+                   if kind == &syntax_tree::Kind::Real)));
     }
 
     fn make_for_stat(
@@ -573,9 +912,86 @@ mod test {
         to: syntax_tree::Const,
     ) -> syntax_tree::ForStat {
         syntax_tree::ForStat::new(
+            None,
             index.to_owned(),
             make_constant_expr(from),
+            syntax_tree::ForDirection::Up,
             make_constant_expr(to),
+            None,
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_check_for_step() {
+        let fake_location = syntax_tree::Location::new(0, 0);
+        let index_var = "index";
+        let loc = syntax_tree::Location::new(45, 70);
+        let table_factory = name_table_factory()
+            .switch_to_function_table()
+            .switch_to_local_table();
+        let mut table = table_factory.factory_local_table();
+        table
+            .insert_variable(index_var, &syntax_tree::Kind::Int, &loc)
+            .unwrap();
+        let mut loop_contex = LoopContext::new();
+
+        let for_stat = make_stepped_for_stat(index_var, syntax_tree::Const::IntConst(2));
+        check_for_stat(
+            &for_stat,
+            &table,
+            &Contex::Global,
+            &mut loop_contex,
+            &fake_location,
+        )
+        .unwrap();
+
+        let for_stat = make_stepped_for_stat(index_var, syntax_tree::Const::RealConst(0.5));
+        let stat = check_for_stat(
+            &for_stat,
+            &table,
+            &Contex::Global,
+            &mut loop_contex,
+            &fake_location,
+        );
+        assert!(matches!(stat,
+                Err(SemanticError::ForLoopError(ForLoopError {loc: _, error}))
+                 if matches!(&error, ForLoopErrorType::NonIntegerStep(kind)
+                    if kind == &syntax_tree::Kind::Real)));
+
+        let for_stat = make_stepped_for_stat(index_var, syntax_tree::Const::IntConst(0));
+        let stat = check_for_stat(
+            &for_stat,
+            &table,
+            &Contex::Global,
+            &mut loop_contex,
+            &fake_location,
+        );
+        assert!(matches!(stat,
+                Err(SemanticError::ForLoopError(ForLoopError {loc: _, error}))
+                 if matches!(&error, ForLoopErrorType::NonPositiveStep(0))));
+
+        let for_stat = make_stepped_for_stat(index_var, syntax_tree::Const::IntConst(-2));
+        let stat = check_for_stat(
+            &for_stat,
+            &table,
+            &Contex::Global,
+            &mut loop_contex,
+            &fake_location,
+        );
+        assert!(matches!(stat,
+                Err(SemanticError::ForLoopError(ForLoopError {loc: _, error}))
+                 if matches!(&error, ForLoopErrorType::NonPositiveStep(-2))));
+    }
+
+    fn make_stepped_for_stat(index: &str, step: syntax_tree::Const) -> syntax_tree::ForStat {
+        syntax_tree::ForStat::new(
+            None,
+            index.to_owned(),
+            make_constant_expr(syntax_tree::Const::IntConst(10)),
+            syntax_tree::ForDirection::Down,
+            make_constant_expr(syntax_tree::Const::IntConst(0)),
+            Some(make_constant_expr(step)),
             vec![],
         )
     }
@@ -622,8 +1038,152 @@ mod test {
         assert_eq!(loop_contex.indexes.len(), 0);
     }
 
+    #[test]
+    fn test_check_case_stat() {
+        let fake_location = syntax_tree::Location::new(0, 0);
+        let table_factory = name_table_factory()
+            .switch_to_function_table()
+            .switch_to_local_table();
+        let table = table_factory.factory_local_table();
+        let mut loop_contex = LoopContext::new();
+
+        let case_stat = make_case_stat(
+            syntax_tree::Const::IntConst(3),
+            vec![
+                vec![
+                    syntax_tree::Const::IntConst(1),
+                    syntax_tree::Const::IntConst(2),
+                ],
+                vec![syntax_tree::Const::IntConst(-2)],
+            ],
+        );
+        check_case_stat(
+            &case_stat,
+            &table,
+            &Contex::Global,
+            &mut loop_contex,
+            &fake_location,
+        )
+        .unwrap();
+
+        let case_stat = make_case_stat(
+            syntax_tree::Const::IntConst(3),
+            vec![
+                vec![
+                    syntax_tree::Const::IntConst(1),
+                    syntax_tree::Const::IntConst(2),
+                ],
+                vec![syntax_tree::Const::IntConst(2)],
+            ],
+        );
+        let stat = check_case_stat(
+            &case_stat,
+            &table,
+            &Contex::Global,
+            &mut loop_contex,
+            &fake_location,
+        );
+        assert!(matches!(stat,
+                Err(SemanticError::CaseError(CaseError {loc: _, error}))
+                if matches!(&error, CaseErrorType::DuplicatedLabel(syntax_tree::Const::IntConst(2)))));
+
+        let case_stat = make_case_stat(
+            syntax_tree::Const::StrConst("a".to_owned()),
+            vec![vec![syntax_tree::Const::BoolConst(true)]],
+        );
+        let stat = check_case_stat(
+            &case_stat,
+            &table,
+            &Contex::Global,
+            &mut loop_contex,
+            &fake_location,
+        );
+        assert!(matches!(stat,
+                Err(SemanticError::CaseError(CaseError {loc: _, error}))
+                if matches!(&error, CaseErrorType::MismatchedLabel(syntax_tree::Kind::Str, syntax_tree::Kind::Bool))));
+
+        let case_stat = make_case_stat(syntax_tree::Const::RealConst(1.5), vec![]);
+        let stat = check_case_stat(
+            &case_stat,
+            &table,
+            &Contex::Global,
+            &mut loop_contex,
+            &fake_location,
+        );
+        let err = stat.unwrap_err();
+        assert!(matches!(&err,
+                SemanticError::CaseError(CaseError {loc: _, error})
+                if matches!(error, CaseErrorType::InvalidSelector(syntax_tree::Kind::Real))));
+        let message = err.format_error(&SourceMap::from("case"));
+        assert!(
+            message.contains("expected integer, long, char, bool or string"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn test_check_assert_and_halt() {
+        let fake_location = syntax_tree::Location::new(0, 0);
+        let table_factory = name_table_factory()
+            .switch_to_function_table()
+            .switch_to_local_table();
+        let table = table_factory.factory_local_table();
+
+        let assert_stat = syntax_tree::AssertStat::new(
+            make_constant_expr(syntax_tree::Const::BoolConst(true)),
+            make_constant_expr(syntax_tree::Const::StrConst("ok".to_owned())),
+        );
+        check_assert_stat(&assert_stat, &table, &fake_location).unwrap();
+
+        let assert_stat = syntax_tree::AssertStat::new(
+            make_constant_expr(syntax_tree::Const::IntConst(1)),
+            make_constant_expr(syntax_tree::Const::StrConst("ok".to_owned())),
+        );
+        let stat = check_assert_stat(&assert_stat, &table, &fake_location);
+        assert!(matches!(stat,
+                Err(SemanticError::NonBooleanCondition(NonBooleanCondition{loc: _, error}))
+                if matches!(&error, NonBooleanConditionType::AssertStat(syntax_tree::Kind::Int))));
+
+        let assert_stat = syntax_tree::AssertStat::new(
+            make_constant_expr(syntax_tree::Const::BoolConst(false)),
+            make_constant_expr(syntax_tree::Const::IntConst(1)),
+        );
+        let stat = check_assert_stat(&assert_stat, &table, &fake_location);
+        assert!(matches!(stat,
+                Err(SemanticError::RuntimeCheckError(RuntimeCheckError{loc: _, error}))
+                if matches!(&error, RuntimeCheckErrorType::AssertMessage(syntax_tree::Kind::Int))));
+
+        let code = make_constant_expr(syntax_tree::Const::IntConst(2));
+        check_halt_stat(&code, &table, &fake_location).unwrap();
+
+        let code = make_constant_expr(syntax_tree::Const::StrConst("2".to_owned()));
+        let stat = check_halt_stat(&code, &table, &fake_location);
+        assert!(matches!(stat,
+                Err(SemanticError::RuntimeCheckError(RuntimeCheckError{loc: _, error}))
+                if matches!(&error, RuntimeCheckErrorType::HaltCode(syntax_tree::Kind::Str))));
+    }
+
+    fn make_case_stat(
+        selector: syntax_tree::Const,
+        labels: Vec<Vec<syntax_tree::Const>>,
+    ) -> syntax_tree::CaseStat {
+        let branches = labels
+            .into_iter()
+            .map(|labels| {
+                let labels = labels
+                    .into_iter()
+                    .map(|value| syntax_tree::CaseLabel::new(value, 0, 0))
+                    .collect();
+                syntax_tree::CaseBranch::new(labels, vec![])
+            })
+            .collect();
+        syntax_tree::CaseStat::new(make_constant_expr(selector), branches, None)
+    }
+
     fn make_while_stat(op: syntax_tree::Operator) -> syntax_tree::WhileStat {
         syntax_tree::WhileStat::new(
+            None,
             syntax_tree::Expr::new(
                 syntax_tree::ExprTree::Node(
                     Box::new(make_constant_expr(syntax_tree::Const::RealConst(7.8))),
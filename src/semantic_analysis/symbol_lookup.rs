@@ -0,0 +1,241 @@
+use simpla_parser::printer::kind_name;
+use simpla_parser::syntax_tree::{FuncDecl, Location, Program};
+
+use super::name_table::{
+    name_table_factory, FactoryLocalVariableTable, LocalVariableTable, VariableTable,
+};
+use crate::built_in;
+
+/*
+    Resolution of the names visible at an offset of the
+    program, with the scope rules of the analysis: the
+    tables are filled with the declarations of every function
+    enclosing the offset, outermost first. Colliding
+    declarations are skipped instead of reported, so
+    that names can be resolved in a wrong program too.
+*/
+#[derive(Debug, PartialEq, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub detail: String,
+    pub loc: Option<Location>,
+    pub function: bool,
+}
+
+impl Symbol {
+    fn variable(name: &str, detail: String, loc: &Location) -> Self {
+        Self {
+            name: name.to_owned(),
+            detail,
+            loc: Some(loc.clone()),
+            function: false,
+        }
+    }
+
+    fn function(func_decl: &FuncDecl) -> Self {
+        Self {
+            name: func_decl.id.clone(),
+            detail: signature(func_decl),
+            loc: Some(func_decl.loc.clone()),
+            function: true,
+        }
+    }
+
+    // overloads are listed one per line
+    fn built_in(name: &str) -> Self {
+        let overloads: Vec<String> = built_in::get_overloads(name)
+            .map(|func| {
+                let params: Vec<String> = func.params.iter().map(kind_name).collect();
+                format!(
+                    "func {}({}): {}",
+                    name,
+                    params.join(", "),
+                    kind_name(&func.kind)
+                )
+            })
+            .collect();
+        Self {
+            name: name.to_owned(),
+            detail: overloads.join("\n"),
+            loc: None,
+            function: true,
+        }
+    }
+}
+
+pub fn signature(func_decl: &FuncDecl) -> String {
+    let params: Vec<String> = func_decl
+        .params
+        .iter()
+        .map(|param| {
+            let prefix = if param.by_ref { "var " } else { "" };
+            format!("{}{}: {}", prefix, param.id, kind_name(&param.kind))
+        })
+        .collect();
+    format!(
+        "func {}({}): {}",
+        func_decl.id,
+        params.join(", "),
+        kind_name(&func_decl.kind)
+    )
+}
+
+pub fn find_symbol(program: &Program, offset: usize, name: &str) -> Option<Symbol> {
+    let factory = global_tables(program);
+    let table = scope_table(factory.factory_local_table(), program, offset);
+    resolve_symbol(&table, name)
+}
+
+pub fn visible_symbols(program: &Program, offset: usize) -> Vec<Symbol> {
+    let factory = global_tables(program);
+    let table = scope_table(factory.factory_local_table(), program, offset);
+    let mut symbols: Vec<Symbol> = table
+        .visible_names()
+        .into_iter()
+        .filter_map(|name| resolve_symbol(&table, name))
+        .collect();
+    for name in built_in::built_in_names() {
+        if !symbols.iter().any(|symbol| symbol.name == name) {
+            symbols.push(Symbol::built_in(name));
+        }
+    }
+    symbols
+}
+
+/*
+    The tables of the main body: a walk of the program
+    that enters a function takes a local table from them,
+    or a nested table from the one of the enclosing function,
+    and fills it with declare_locals.
+*/
+pub fn global_tables(program: &Program) -> FactoryLocalVariableTable<'_> {
+    let mut globals = name_table_factory();
+    for var_decl in &program.global_vars {
+        for var in &var_decl.id_list {
+            let _ = globals.insert_variable(var, &var_decl.kind, &var_decl.loc);
+        }
+    }
+    let mut functions = globals.switch_to_function_table();
+    for func_decl in &program.functions {
+        let _ = functions.insert_function(&func_decl.id, func_decl);
+    }
+    functions.switch_to_local_table()
+}
+
+fn scope_table<'a>(
+    mut table: LocalVariableTable<'a>,
+    program: &'a Program,
+    offset: usize,
+) -> LocalVariableTable<'a> {
+    let mut functions = &program.functions;
+    let mut outermost = true;
+    while let Some(func_decl) = functions.iter().find(|func| contains(&func.loc, offset)) {
+        if !outermost {
+            table = table.nested_table();
+        }
+        declare_locals(func_decl, &mut table);
+        outermost = false;
+        functions = &func_decl.functions;
+    }
+    table
+}
+
+pub fn declare_locals<'a>(func_decl: &'a FuncDecl, table: &mut LocalVariableTable<'a>) {
+    for param in &func_decl.params {
+        let _ = table.insert_variable(&param.id, &param.kind, &param.loc);
+    }
+    for var_decl in &func_decl.vars {
+        for var in &var_decl.id_list {
+            let _ = table.insert_variable(var, &var_decl.kind, &var_decl.loc);
+        }
+    }
+    for nested in &func_decl.functions {
+        let _ = table.insert_function(&nested.id, nested);
+    }
+}
+
+pub fn resolve_symbol(table: &LocalVariableTable, name: &str) -> Option<Symbol> {
+    if let Some((kind, loc)) = table.get_variable_declaration(name) {
+        let detail = format!("{}: {}", name, kind_name(kind));
+        Some(Symbol::variable(name, detail, loc))
+    } else if let Some(func_decl) = table.get_function_declaration(name) {
+        Some(Symbol::function(func_decl))
+    } else if built_in::is_built_in(name) {
+        Some(Symbol::built_in(name))
+    } else {
+        None
+    }
+}
+
+fn contains(loc: &Location, offset: usize) -> bool {
+    loc.begin <= offset && offset < loc.end
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use simpla_parser::ProgramParser;
+
+    const CODE: &str = "
+        n: integer;
+        func outer(x: real): void
+            y: boolean;
+            func inner(n: string): void
+            body
+                writeln(n, x, y);
+            end;
+        body
+            inner(\"a\");
+        end;
+        body
+            outer(1.0);
+        end.";
+
+    fn offset(pattern: &str) -> usize {
+        CODE.find(pattern).unwrap()
+    }
+
+    #[test]
+    fn test_find_symbol() {
+        let program = ProgramParser::new().parse(CODE).unwrap();
+
+        let inner_body = offset("writeln");
+        let symbol = find_symbol(&program, inner_body, "n").unwrap();
+        assert_eq!(symbol.detail, "n: string");
+        let symbol = find_symbol(&program, inner_body, "x").unwrap();
+        assert_eq!(symbol.detail, "x: real");
+        assert_eq!(symbol.loc.unwrap().begin, offset("x: real"));
+
+        let main_body = offset("outer(1.0)");
+        let symbol = find_symbol(&program, main_body, "n").unwrap();
+        assert_eq!(symbol.detail, "n: integer");
+        assert_eq!(find_symbol(&program, main_body, "y"), None);
+        assert_eq!(find_symbol(&program, main_body, "inner"), None);
+
+        let symbol = find_symbol(&program, main_body, "outer").unwrap();
+        assert_eq!(symbol.detail, "func outer(x: real): void");
+        let symbol = find_symbol(&program, main_body, "min").unwrap();
+        assert!(symbol
+            .detail
+            .contains("func min(integer, integer): integer"));
+        assert!(symbol.loc.is_none());
+    }
+
+    #[test]
+    fn test_visible_symbols() {
+        let program = ProgramParser::new().parse(CODE).unwrap();
+        let names = |offset| -> Vec<String> {
+            visible_symbols(&program, offset)
+                .into_iter()
+                .map(|symbol| symbol.name)
+                .collect()
+        };
+        let inner = names(offset("writeln"));
+        for name in &["n", "x", "y", "inner", "outer", "sqrt"] {
+            assert!(inner.iter().any(|n| n == name), "{} not visible", name);
+        }
+        let main = names(offset("outer(1.0)"));
+        assert!(!main.iter().any(|n| n == "y" || n == "inner"));
+    }
+}
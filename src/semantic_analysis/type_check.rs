@@ -1,13 +1,17 @@
-use super::name_table::LocalVariableTable;
+use super::name_table::{FunctionEntry, LocalVariableTable};
+use super::promotion::{ImplicitConversion, Promotion};
 use super::semantic_error::{
-    ArgumentCountError, CastError, IncoherentOperation, MismatchedArgumentType, MismatchedTypes,
-    MismatchedUnary, NonBooleanCondition, SemanticError,
+    ArgumentCountError, BuiltInCallError, CastError, ConstantError, FunctionValueError,
+    IncoherentOperation, IndexError, MismatchedArgumentType, MismatchedTypes, MismatchedUnary,
+    NonAssignableArgument, NonBooleanCondition, SemanticError,
 };
+use crate::built_in;
+use crate::constant_fold::{self, FoldError};
 use simpla_parser::syntax_tree;
 
 pub fn function_call_check<'a>(
     func_call: &'a syntax_tree::FuncCall,
-    table: &'a LocalVariableTable<'a>,
+    table: &LocalVariableTable<'a>,
     loc: &'a syntax_tree::Location,
 ) -> Result<(), SemanticError<'a>> {
     check_function_call(func_call, table, loc)?;
@@ -16,17 +20,20 @@ pub fn function_call_check<'a>(
 
 pub fn type_check<'a>(
     expr: &'a syntax_tree::Expr,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
 ) -> Result<syntax_tree::Kind, SemanticError<'a>> {
     let kind = match &expr.expr {
         syntax_tree::ExprTree::Node(left, op, right) => {
             let left_type = type_check(left, table)?;
             let right_type = type_check(right, table)?;
+            let (left_type, right_type) =
+                promote_operands(left, left_type, op, right, right_type, table);
             let output = coherent_operation(left_type, op, right_type, &expr.loc)?;
             Ok(output)
         }
         syntax_tree::ExprTree::Factor(fact) => check_factor(fact, table, &expr.loc),
     }?;
+    check_constant(expr, &kind)?;
     *expr.kind.borrow_mut() = Some(kind.clone());
     Ok(kind)
 }
@@ -50,6 +57,57 @@ impl OperatorKind {
     }
 }
 
+/*
+    Widens an integer expression to real or long when the
+    promotion level allows it, recording the conversion on the
+    expression so that the generator can emit the cast.
+*/
+pub fn promote(
+    expr: &syntax_tree::Expr,
+    kind: &syntax_tree::Kind,
+    target: &syntax_tree::Kind,
+    table: &LocalVariableTable,
+) -> bool {
+    let widening = matches!(
+        (kind, target),
+        (syntax_tree::Kind::Int, syntax_tree::Kind::Real)
+            | (syntax_tree::Kind::Int, syntax_tree::Kind::Long)
+    );
+    if !widening {
+        return false;
+    }
+    match table.promotion() {
+        Promotion::Forbid => return false,
+        Promotion::Warn => {
+            let conversion =
+                ImplicitConversion::new(expr.loc.clone(), kind.clone(), target.clone());
+            table.report_conversion(conversion);
+        }
+        Promotion::Allow => {}
+    }
+    *expr.coercion.borrow_mut() = Some(target.clone());
+    true
+}
+
+fn promote_operands(
+    left: &syntax_tree::Expr,
+    left_type: syntax_tree::Kind,
+    op: &syntax_tree::Operator,
+    right: &syntax_tree::Expr,
+    right_type: syntax_tree::Kind,
+    table: &LocalVariableTable,
+) -> (syntax_tree::Kind, syntax_tree::Kind) {
+    if let OperatorKind::Logic = OperatorKind::from_operator(op) {
+        (left_type, right_type)
+    } else if promote(left, &left_type, &right_type, table) {
+        (right_type.clone(), right_type)
+    } else if promote(right, &right_type, &left_type, table) {
+        (left_type.clone(), left_type)
+    } else {
+        (left_type, right_type)
+    }
+}
+
 fn coherent_operation<'a>(
     left: syntax_tree::Kind,
     op: &'a syntax_tree::Operator,
@@ -67,7 +125,9 @@ fn coherent_operation<'a>(
                 }
             },
             OperatorKind::Numeric => match left {
-                syntax_tree::Kind::Int | syntax_tree::Kind::Real => Ok(left),
+                syntax_tree::Kind::Int | syntax_tree::Kind::Long | syntax_tree::Kind::Real => {
+                    Ok(left)
+                }
                 _ => {
                     let err = IncoherentOperation::new(left, op.clone(), loc);
                     Err(SemanticError::IncoherentOperation(err))
@@ -75,7 +135,9 @@ fn coherent_operation<'a>(
             },
             OperatorKind::Relational => match left {
                 syntax_tree::Kind::Int
+                | syntax_tree::Kind::Long
                 | syntax_tree::Kind::Real
+                | syntax_tree::Kind::Char
                 | syntax_tree::Kind::Bool
                 | syntax_tree::Kind::Str => Ok(syntax_tree::Kind::Bool),
                 _ => {
@@ -90,9 +152,31 @@ fn coherent_operation<'a>(
     }
 }
 
+/*
+    Integer and long constant expressions are folded to
+    reject at compile time the ones that would overflow
+    or divide by zero when evaluated.
+*/
+fn check_constant<'a>(
+    expr: &'a syntax_tree::Expr,
+    kind: &syntax_tree::Kind,
+) -> Result<(), SemanticError<'a>> {
+    match constant_fold::fold_expr(expr) {
+        Some(Err(FoldError::Overflow)) => {
+            let err = ConstantError::new_overflow(&expr.loc, kind.clone());
+            Err(SemanticError::ConstantError(err))
+        }
+        Some(Err(FoldError::DivisionByZero)) => {
+            let err = ConstantError::new_division_by_zero(&expr.loc);
+            Err(SemanticError::ConstantError(err))
+        }
+        _ => Ok(()),
+    }
+}
+
 fn check_factor<'a>(
     fact: &'a syntax_tree::Factor,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
     loc: &'a syntax_tree::Location,
 ) -> Result<syntax_tree::Kind, SemanticError<'a>> {
     let kind = match &fact.fact {
@@ -101,8 +185,9 @@ fn check_factor<'a>(
         syntax_tree::FactorValue::Const(val) => Ok(check_const(val)),
         syntax_tree::FactorValue::FuncCall(func) => check_function_call(func, table, loc),
         syntax_tree::FactorValue::HighPrecedence(expr) => type_check(expr, table),
-        syntax_tree::FactorValue::Id(name) => check_id(name, table),
+        syntax_tree::FactorValue::Id(name) => check_id(name, table, loc),
         syntax_tree::FactorValue::UnaryOp(unary) => check_unary_operator(unary, table, loc),
+        syntax_tree::FactorValue::IndexExpr(index) => check_index(index, table, loc),
     }?;
 
     *fact.kind.borrow_mut() = Some(kind.clone());
@@ -111,13 +196,15 @@ fn check_factor<'a>(
 
 fn check_cast<'a>(
     cast: &'a syntax_tree::CastExpr,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
     loc: &'a syntax_tree::Location,
 ) -> Result<syntax_tree::Kind, SemanticError<'a>> {
     match cast {
         syntax_tree::CastExpr::Integer(expr) => {
             let kind = type_check(expr, table)?;
-            if kind == syntax_tree::Kind::Real {
+            if let syntax_tree::Kind::Real | syntax_tree::Kind::Long | syntax_tree::Kind::Char =
+                kind
+            {
                 Ok(syntax_tree::Kind::Int)
             } else {
                 let err = SemanticError::CastError(CastError::new_to_int(loc, kind));
@@ -126,19 +213,37 @@ fn check_cast<'a>(
         }
         syntax_tree::CastExpr::Real(expr) => {
             let kind = type_check(expr, table)?;
-            if kind == syntax_tree::Kind::Int {
+            if kind == syntax_tree::Kind::Int || kind == syntax_tree::Kind::Long {
                 Ok(syntax_tree::Kind::Real)
             } else {
                 let err = SemanticError::CastError(CastError::new_to_real(loc, kind));
                 Err(err)
             }
         }
+        syntax_tree::CastExpr::Long(expr) => {
+            let kind = type_check(expr, table)?;
+            if kind == syntax_tree::Kind::Int || kind == syntax_tree::Kind::Real {
+                Ok(syntax_tree::Kind::Long)
+            } else {
+                let err = SemanticError::CastError(CastError::new_to_long(loc, kind));
+                Err(err)
+            }
+        }
+        syntax_tree::CastExpr::Char(expr) => {
+            let kind = type_check(expr, table)?;
+            if kind == syntax_tree::Kind::Int {
+                Ok(syntax_tree::Kind::Char)
+            } else {
+                let err = SemanticError::CastError(CastError::new_to_char(loc, kind));
+                Err(err)
+            }
+        }
     }
 }
 
 fn check_conditional_expression<'a>(
     cond_expr: &'a syntax_tree::CondExpr,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
     loc: &'a syntax_tree::Location,
 ) -> Result<syntax_tree::Kind, SemanticError<'a>> {
     let cond_kind = type_check(&cond_expr.cond, table)?;
@@ -157,28 +262,79 @@ fn check_conditional_expression<'a>(
     }
 }
 
-fn check_const<'a>(value: &'a syntax_tree::Const) -> syntax_tree::Kind {
+pub fn check_const(value: &syntax_tree::Const) -> syntax_tree::Kind {
     match value {
         syntax_tree::Const::BoolConst(_) => syntax_tree::Kind::Bool,
         syntax_tree::Const::IntConst(_) => syntax_tree::Kind::Int,
+        syntax_tree::Const::LongConst(_) => syntax_tree::Kind::Long,
         syntax_tree::Const::RealConst(_) => syntax_tree::Kind::Real,
         syntax_tree::Const::StrConst(_) => syntax_tree::Kind::Str,
+        syntax_tree::Const::CharConst(_) => syntax_tree::Kind::Char,
     }
 }
 
 fn check_id<'a>(
     name: &'a str,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
+    loc: &'a syntax_tree::Location,
 ) -> Result<syntax_tree::Kind, SemanticError<'a>> {
     match table.get_variable(name) {
         Ok(k) => Ok(k.clone()),
-        Err(err) => Err(err),
+        Err(err) => match table.get_function(name) {
+            Ok(FunctionEntry::Declared(func_decl)) => check_function_value(func_decl, table, loc),
+            _ => Err(err),
+        },
+    }
+}
+
+/*
+    A declared function used as a value has a function type
+    built from its parameters and its return type.
+    Only top level functions taking every argument by value
+    can be used this way: a nested function needs the frames
+    of its enclosing functions, that are lost once the value
+    leaves them.
+*/
+fn check_function_value<'a>(
+    func_decl: &'a syntax_tree::FuncDecl,
+    table: &LocalVariableTable<'a>,
+    loc: &'a syntax_tree::Location,
+) -> Result<syntax_tree::Kind, SemanticError<'a>> {
+    if table.is_nested_function(&func_decl.id) {
+        let err = FunctionValueError::new_nested_function(loc, &func_decl.id);
+        Err(SemanticError::FunctionValueError(err))
+    } else if func_decl.params.iter().any(|param| param.by_ref) {
+        let err = FunctionValueError::new_reference_parameters(loc, &func_decl.id);
+        Err(SemanticError::FunctionValueError(err))
+    } else {
+        let params = func_decl.params.iter().map(|p| p.kind.clone()).collect();
+        let func_kind = syntax_tree::FuncKind::new(params, func_decl.kind.clone());
+        Ok(syntax_tree::Kind::Func(func_kind))
+    }
+}
+
+fn check_index<'a>(
+    index: &'a syntax_tree::IndexExpr,
+    table: &LocalVariableTable<'a>,
+    loc: &'a syntax_tree::Location,
+) -> Result<syntax_tree::Kind, SemanticError<'a>> {
+    let kind = table.get_variable(&index.id)?;
+    if kind != &syntax_tree::Kind::Str {
+        let err = IndexError::new_non_string(loc, &index.id, kind.clone());
+        return Err(SemanticError::IndexError(err));
+    }
+    match type_check(&index.index, table)? {
+        syntax_tree::Kind::Int => Ok(syntax_tree::Kind::Char),
+        other => {
+            let err = IndexError::new_non_integer_index(loc, other);
+            Err(SemanticError::IndexError(err))
+        }
     }
 }
 
 fn check_unary_operator<'a>(
     unary: &'a syntax_tree::UnaryOp,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
     loc: &'a syntax_tree::Location,
 ) -> Result<syntax_tree::Kind, SemanticError<'a>> {
     match unary {
@@ -186,6 +342,7 @@ fn check_unary_operator<'a>(
             let kind = check_factor(fact, table, loc)?;
             match kind {
                 syntax_tree::Kind::Int => Ok(syntax_tree::Kind::Int),
+                syntax_tree::Kind::Long => Ok(syntax_tree::Kind::Long),
                 syntax_tree::Kind::Real => Ok(syntax_tree::Kind::Real),
                 other => Err(SemanticError::MismatchedUnary(
                     MismatchedUnary::new_numeric(loc, other),
@@ -207,14 +364,103 @@ fn check_unary_operator<'a>(
 
 fn check_function_call<'a>(
     fcall: &'a syntax_tree::FuncCall,
-    table: &'a LocalVariableTable,
+    table: &LocalVariableTable<'a>,
+    loc: &'a syntax_tree::Location,
+) -> Result<syntax_tree::Kind, SemanticError<'a>> {
+    if let Ok(kind) = table.get_variable(&fcall.id) {
+        return check_function_value_call(kind.clone(), fcall, table, loc);
+    }
+    match table.get_function(&fcall.id)? {
+        FunctionEntry::Declared(func_proto) => {
+            check_declared_function_call(func_proto, fcall, table, loc)
+        }
+        FunctionEntry::BuiltIn(name) => check_built_in_call(name, fcall, table, loc),
+    }
+}
+
+fn check_function_value_call<'a>(
+    kind: syntax_tree::Kind,
+    fcall: &'a syntax_tree::FuncCall,
+    table: &LocalVariableTable<'a>,
+    loc: &'a syntax_tree::Location,
+) -> Result<syntax_tree::Kind, SemanticError<'a>> {
+    let func_kind = match kind {
+        syntax_tree::Kind::Func(func_kind) => func_kind,
+        other => {
+            let err = FunctionValueError::new_not_callable(loc, &fcall.id, other);
+            return Err(SemanticError::FunctionValueError(err));
+        }
+    };
+    if func_kind.params.len() != fcall.args.len() {
+        let err = FunctionValueError::new_argument_count(
+            loc,
+            &fcall.id,
+            func_kind.params.len(),
+            fcall.args.len(),
+        );
+        return Err(SemanticError::FunctionValueError(err));
+    }
+    for (i, (formal, actual)) in func_kind.params.iter().zip(fcall.args.iter()).enumerate() {
+        let actual_kind = type_check(actual, table)?;
+        if &actual_kind != formal && !promote(actual, &actual_kind, formal, table) {
+            let err = FunctionValueError::new_argument_type(
+                loc,
+                &fcall.id,
+                i,
+                formal.clone(),
+                actual_kind,
+            );
+            return Err(SemanticError::FunctionValueError(err));
+        }
+    }
+    Ok(*func_kind.kind)
+}
+
+fn check_built_in_call<'a>(
+    name: &'a str,
+    fcall: &'a syntax_tree::FuncCall,
+    table: &LocalVariableTable<'a>,
+    loc: &'a syntax_tree::Location,
+) -> Result<syntax_tree::Kind, SemanticError<'a>> {
+    let mut args = Vec::with_capacity(fcall.args.len());
+    for arg in &fcall.args {
+        args.push(type_check(arg, table)?);
+    }
+
+    if let Some((_, func)) = built_in::find_built_in(name, &args) {
+        return Ok(func.kind.clone());
+    }
+
+    let promoted = match table.promotion() {
+        Promotion::Forbid => None,
+        Promotion::Warn | Promotion::Allow => built_in::find_promoted_built_in(name, &args),
+    };
+    match promoted {
+        Some((_, func)) => {
+            for ((arg, kind), param) in fcall.args.iter().zip(&args).zip(func.params) {
+                promote(arg, kind, param, table);
+            }
+            Ok(func.kind.clone())
+        }
+        None => {
+            let err = BuiltInCallError::new(name, args, loc);
+            Err(SemanticError::BuiltInCallError(err))
+        }
+    }
+}
+
+fn check_declared_function_call<'a>(
+    func_proto: &'a syntax_tree::FuncDecl,
+    fcall: &'a syntax_tree::FuncCall,
+    table: &LocalVariableTable<'a>,
     loc: &'a syntax_tree::Location,
 ) -> Result<syntax_tree::Kind, SemanticError<'a>> {
-    let func_proto = table.get_function(&fcall.id)?;
     if func_proto.params.len() == fcall.args.len() {
         for (i, (formal, actual)) in func_proto.params.iter().zip(fcall.args.iter()).enumerate() {
             let actual_kind = type_check(actual, table)?;
-            if actual_kind != formal.kind {
+            if actual_kind != formal.kind
+                && (formal.by_ref || !promote(actual, &actual_kind, &formal.kind, table))
+            {
                 let err = SemanticError::MismatchedArgumentType(MismatchedArgumentType::new(
                     func_proto,
                     formal.kind.clone(),
@@ -224,6 +470,10 @@ fn check_function_call<'a>(
                 ));
                 return Err(err);
             }
+            if formal.by_ref && !is_variable(actual, table) {
+                let err = NonAssignableArgument::new(func_proto, i, &actual.loc);
+                return Err(SemanticError::NonAssignableArgument(err));
+            }
         }
         Ok(func_proto.kind.clone())
     } else {
@@ -232,6 +482,16 @@ fn check_function_call<'a>(
     }
 }
 
+fn is_variable<'a>(expr: &'a syntax_tree::Expr, table: &LocalVariableTable<'a>) -> bool {
+    match &expr.expr {
+        syntax_tree::ExprTree::Factor(syntax_tree::Factor {
+            fact: syntax_tree::FactorValue::Id(name),
+            ..
+        }) => table.get_variable(name).is_ok(),
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod test {
 
@@ -261,7 +521,7 @@ mod test {
         let func_name_b = "do_stuff";
         let func_decl_b = FuncDecl::new(
             func_name_b.to_owned(),
-            vec![ParamDecl::new("arg".to_owned(), Kind::Str)],
+            vec![ParamDecl::new("arg".to_owned(), Kind::Str, 0, 0)],
             Kind::Real,
             vec![],
             vec![],
@@ -276,7 +536,7 @@ mod test {
         let table_factory = table.switch_to_local_table();
         let table = table_factory.factory_local_table();
 
-        let func_call = FuncCall::new(func_name_a.to_owned(), vec![]);
+        let func_call = FuncCall::new(func_name_a.to_owned(), vec![], 0, 0);
 
         let stat = check_function_call(&func_call, &table, &Location::new(0, 0)).unwrap();
         assert_eq!(stat, Kind::Int);
@@ -290,6 +550,8 @@ mod test {
                 0,
                 0,
             )],
+            0,
+            0,
         );
         let stat = check_function_call(&func_call, &table, &fake_location);
         check_error_status(
@@ -306,6 +568,8 @@ mod test {
                 0,
                 0,
             )],
+            0,
+            0,
         );
         let stat = check_function_call(&func_call, &table, &fake_location);
         check_error_status(
@@ -320,6 +584,234 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_check_reference_argument() {
+        let fake_location = Location::new(0, 0);
+        let mut table = name_table_factory();
+        let var_name = "value";
+        let loc = Location::new(123, 456);
+        table.insert_variable(var_name, &Kind::Int, &loc).unwrap();
+
+        let func_name = "increment";
+        let func_decl = FuncDecl::new(
+            func_name.to_owned(),
+            vec![ParamDecl::new_reference("n".to_owned(), Kind::Int, 0, 0)],
+            Kind::Void,
+            vec![],
+            vec![],
+            0,
+            0,
+        );
+        let mut table = table.switch_to_function_table();
+        table.insert_function(func_name, &func_decl).unwrap();
+        let table_factory = table.switch_to_local_table();
+        let table = table_factory.factory_local_table();
+
+        let func_call = FuncCall::new(
+            func_name.to_owned(),
+            vec![Expr::new(
+                ExprTree::Factor(syntax_tree::Factor::new(FactorValue::Id(
+                    var_name.to_owned(),
+                ))),
+                0,
+                0,
+            )],
+            0,
+            0,
+        );
+        let stat = check_function_call(&func_call, &table, &fake_location);
+        assert_eq!(stat, Ok(Kind::Void));
+
+        let func_call = FuncCall::new(
+            func_name.to_owned(),
+            vec![make_const_expr(Const::IntConst(4))],
+            0,
+            0,
+        );
+        let stat = check_function_call(&func_call, &table, &fake_location);
+        check_error_status(
+            stat,
+            SemanticError::NonAssignableArgument(NonAssignableArgument::new(
+                &func_decl,
+                0,
+                &func_call.args[0].loc,
+            )),
+        );
+    }
+
+    #[test]
+    fn test_check_function_value_call() {
+        let fake_location = Location::new(0, 0);
+        let func_kind = Kind::Func(FuncKind::new(vec![Kind::Int], Kind::Real));
+        let mut table = name_table_factory();
+        let loc = Location::new(123, 456);
+        table.insert_variable("f", &func_kind, &loc).unwrap();
+        table.insert_variable("value", &Kind::Int, &loc).unwrap();
+
+        let func_decl = FuncDecl::new(
+            "half".to_owned(),
+            vec![ParamDecl::new("n".to_owned(), Kind::Int, 0, 0)],
+            Kind::Real,
+            vec![],
+            vec![],
+            0,
+            0,
+        );
+        let mut table = table.switch_to_function_table();
+        table.insert_function("half", &func_decl).unwrap();
+        let table_factory = table.switch_to_local_table();
+        let table = table_factory.factory_local_table();
+
+        let stat = check_id("half", &table, &fake_location);
+        assert_eq!(stat, Ok(func_kind.clone()));
+
+        let func_call = FuncCall::new(
+            "f".to_owned(),
+            vec![make_const_expr(Const::IntConst(4))],
+            0,
+            0,
+        );
+        let stat = check_function_call(&func_call, &table, &fake_location);
+        assert_eq!(stat, Ok(Kind::Real));
+
+        let func_call = FuncCall::new("f".to_owned(), vec![], 0, 0);
+        let stat = check_function_call(&func_call, &table, &fake_location);
+        check_error_status(
+            stat,
+            SemanticError::FunctionValueError(FunctionValueError::new_argument_count(
+                &fake_location,
+                "f",
+                1,
+                0,
+            )),
+        );
+
+        let func_call = FuncCall::new(
+            "f".to_owned(),
+            vec![make_const_expr(Const::StrConst("four".to_owned()))],
+            0,
+            0,
+        );
+        let stat = check_function_call(&func_call, &table, &fake_location);
+        check_error_status(
+            stat,
+            SemanticError::FunctionValueError(FunctionValueError::new_argument_type(
+                &fake_location,
+                "f",
+                0,
+                Kind::Int,
+                Kind::Str,
+            )),
+        );
+
+        let func_call = FuncCall::new("value".to_owned(), vec![], 0, 0);
+        let stat = check_function_call(&func_call, &table, &fake_location);
+        check_error_status(
+            stat,
+            SemanticError::FunctionValueError(FunctionValueError::new_not_callable(
+                &fake_location,
+                "value",
+                Kind::Int,
+            )),
+        );
+    }
+
+    #[test]
+    fn test_check_built_in_call() {
+        let fake_location = Location::new(0, 0);
+        let table = name_table_factory();
+        let mut table = table.switch_to_function_table();
+
+        // user defined functions shadow built-in ones
+        let shadow_name = "abs";
+        let shadow_decl = FuncDecl::new(
+            shadow_name.to_owned(),
+            vec![ParamDecl::new("x".to_owned(), Kind::Str, 0, 0)],
+            Kind::Str,
+            vec![],
+            vec![],
+            0,
+            0,
+        );
+        table.insert_function(shadow_name, &shadow_decl).unwrap();
+
+        let table_factory = table.switch_to_local_table();
+        let table = table_factory.factory_local_table();
+
+        let func_call = FuncCall::new(
+            "max".to_owned(),
+            vec![
+                make_const_expr(Const::IntConst(3)),
+                make_const_expr(Const::IntConst(4)),
+            ],
+            0,
+            0,
+        );
+        let stat = check_function_call(&func_call, &table, &fake_location);
+        assert_eq!(stat, Ok(Kind::Int));
+
+        let func_call = FuncCall::new(
+            "floor".to_owned(),
+            vec![make_const_expr(Const::RealConst(4.5))],
+            0,
+            0,
+        );
+        let stat = check_function_call(&func_call, &table, &fake_location);
+        assert_eq!(stat, Ok(Kind::Int));
+
+        let func_call = FuncCall::new(
+            shadow_name.to_owned(),
+            vec![make_const_expr(Const::StrConst("test".to_owned()))],
+            0,
+            0,
+        );
+        let stat = check_function_call(&func_call, &table, &fake_location);
+        assert_eq!(stat, Ok(Kind::Str));
+
+        let func_call = FuncCall::new(
+            "sqrt".to_owned(),
+            vec![make_const_expr(Const::IntConst(4))],
+            0,
+            0,
+        );
+        let stat = check_function_call(&func_call, &table, &fake_location);
+        check_error_status(
+            stat,
+            SemanticError::BuiltInCallError(BuiltInCallError::new(
+                "sqrt",
+                vec![Kind::Int],
+                &fake_location,
+            )),
+        );
+
+        let func_call = FuncCall::new(
+            "min".to_owned(),
+            vec![
+                make_const_expr(Const::IntConst(3)),
+                make_const_expr(Const::RealConst(4.0)),
+            ],
+            0,
+            0,
+        );
+        let stat = check_function_call(&func_call, &table, &fake_location);
+        check_error_status(
+            stat,
+            SemanticError::BuiltInCallError(BuiltInCallError::new(
+                "min",
+                vec![Kind::Int, Kind::Real],
+                &fake_location,
+            )),
+        );
+    }
+
+    fn make_const_expr(value: Const) -> Expr {
+        Expr::new(
+            ExprTree::Factor(syntax_tree::Factor::new(FactorValue::Const(value))),
+            0,
+            0,
+        )
+    }
+
     #[test]
     fn test_check_cast() {
         let int_var_name = "int_var";
@@ -392,6 +884,140 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_check_long_cast() {
+        let table_factory = name_table_factory()
+            .switch_to_function_table()
+            .switch_to_local_table();
+        let table = table_factory.factory_local_table();
+        let loc = Location::new(0, 0);
+
+        let to_long = CastExpr::Long(Box::new(make_const_expr(Const::IntConst(3))));
+        assert_eq!(check_cast(&to_long, &table, &loc), Ok(Kind::Long));
+        let to_long = CastExpr::Long(Box::new(make_const_expr(Const::RealConst(3.5))));
+        assert_eq!(check_cast(&to_long, &table, &loc), Ok(Kind::Long));
+        let to_int = CastExpr::Integer(Box::new(make_const_expr(Const::LongConst(3))));
+        assert_eq!(check_cast(&to_int, &table, &loc), Ok(Kind::Int));
+        let to_real = CastExpr::Real(Box::new(make_const_expr(Const::LongConst(3))));
+        assert_eq!(check_cast(&to_real, &table, &loc), Ok(Kind::Real));
+
+        let wrong_cast = CastExpr::Long(Box::new(make_const_expr(Const::LongConst(3))));
+        assert_eq!(
+            check_cast(&wrong_cast, &table, &loc),
+            Err(SemanticError::CastError(CastError::new_to_long(
+                &loc,
+                Kind::Long
+            )))
+        );
+    }
+
+    #[test]
+    fn test_check_char() {
+        let loc = Location::new(123, 456);
+        let mut table = name_table_factory();
+        table.insert_variable("text", &Kind::Str, &loc).unwrap();
+        table.insert_variable("count", &Kind::Int, &loc).unwrap();
+        let table_factory = table.switch_to_function_table().switch_to_local_table();
+        let table = table_factory.factory_local_table();
+        let fake_location = Location::new(0, 0);
+
+        let index = IndexExpr::new("text".to_owned(), make_const_expr(Const::IntConst(2)));
+        assert_eq!(check_index(&index, &table, &fake_location), Ok(Kind::Char));
+
+        let index = IndexExpr::new("count".to_owned(), make_const_expr(Const::IntConst(2)));
+        check_error_status(
+            check_index(&index, &table, &fake_location),
+            SemanticError::IndexError(IndexError::new_non_string(
+                &fake_location,
+                "count",
+                Kind::Int,
+            )),
+        );
+
+        let index = IndexExpr::new("text".to_owned(), make_const_expr(Const::CharConst('a')));
+        check_error_status(
+            check_index(&index, &table, &fake_location),
+            SemanticError::IndexError(IndexError::new_non_integer_index(
+                &fake_location,
+                Kind::Char,
+            )),
+        );
+
+        let to_int = CastExpr::Integer(Box::new(make_const_expr(Const::CharConst('a'))));
+        assert_eq!(check_cast(&to_int, &table, &fake_location), Ok(Kind::Int));
+        let to_char = CastExpr::Char(Box::new(make_const_expr(Const::IntConst(97))));
+        assert_eq!(check_cast(&to_char, &table, &fake_location), Ok(Kind::Char));
+        let to_char = CastExpr::Char(Box::new(make_const_expr(Const::RealConst(9.7))));
+        check_error_status(
+            check_cast(&to_char, &table, &fake_location),
+            SemanticError::CastError(CastError::new_to_char(&fake_location, Kind::Real)),
+        );
+    }
+
+    fn make_node_expr(left: Expr, op: Operator, right: Expr) -> Expr {
+        Expr::new(ExprTree::Node(Box::new(left), op, Box::new(right)), 0, 0)
+    }
+
+    #[test]
+    fn test_check_constant_expression() {
+        let table_factory = name_table_factory()
+            .switch_to_function_table()
+            .switch_to_local_table();
+        let table = table_factory.factory_local_table();
+
+        let max_int = make_node_expr(
+            make_const_expr(Const::IntConst(i32::MAX - 1)),
+            Operator::Add,
+            make_const_expr(Const::IntConst(1)),
+        );
+        assert_eq!(type_check(&max_int, &table), Ok(Kind::Int));
+
+        let overflow = make_node_expr(
+            make_const_expr(Const::IntConst(i32::MAX)),
+            Operator::Add,
+            make_const_expr(Const::IntConst(1)),
+        );
+        check_error_status(
+            type_check(&overflow, &table),
+            SemanticError::ConstantError(ConstantError::new_overflow(&overflow.loc, Kind::Int)),
+        );
+
+        let max_int = Box::new(make_const_expr(Const::IntConst(i32::MAX)));
+        let widened = make_node_expr(
+            Expr::new(
+                ExprTree::Factor(Factor::new(FactorValue::CastExpr(CastExpr::Long(max_int)))),
+                0,
+                0,
+            ),
+            Operator::Mul,
+            make_const_expr(Const::LongConst(4)),
+        );
+        assert_eq!(type_check(&widened, &table), Ok(Kind::Long));
+
+        let long_overflow = make_node_expr(
+            make_const_expr(Const::LongConst(i64::MAX)),
+            Operator::Mul,
+            make_const_expr(Const::LongConst(2)),
+        );
+        check_error_status(
+            type_check(&long_overflow, &table),
+            SemanticError::ConstantError(ConstantError::new_overflow(
+                &long_overflow.loc,
+                Kind::Long,
+            )),
+        );
+
+        let division = make_node_expr(
+            make_const_expr(Const::IntConst(1)),
+            Operator::Div,
+            make_const_expr(Const::IntConst(0)),
+        );
+        check_error_status(
+            type_check(&division, &table),
+            SemanticError::ConstantError(ConstantError::new_division_by_zero(&division.loc)),
+        );
+    }
+
     #[test]
     fn test_unary_operator() {
         let table_factory = name_table_factory()
@@ -502,7 +1128,7 @@ mod test {
         let str_func_name = "str_function";
         let str_func = FuncDecl::new(
             str_func_name.to_owned(),
-            vec![ParamDecl::new("n".to_owned(), Kind::Int)],
+            vec![ParamDecl::new("n".to_owned(), Kind::Int, 0, 0)],
             Kind::Str,
             vec![],
             vec![],
@@ -571,6 +1197,8 @@ mod test {
                             56,
                             156,
                         )],
+                        0,
+                        0,
                     ),
                 ))),
                 0,
@@ -639,6 +1267,8 @@ mod test {
                             0,
                             0,
                         )],
+                        0,
+                        0,
                     ),
                 ))),
                 156,
@@ -707,6 +1337,8 @@ mod test {
                             56,
                             100,
                         )],
+                        0,
+                        0,
                     ),
                 ))),
                 56,
@@ -732,10 +1364,10 @@ mod test {
     #[test]
     fn test_coherent_operation() {
         for op in &[Operator::Add, Operator::Sub, Operator::Mul, Operator::Div] {
-            for kind in &[Kind::Real, Kind::Int] {
+            for kind in &[Kind::Real, Kind::Int, Kind::Long] {
                 run_correct_coherent_test(kind, op, kind, kind);
             }
-            for kind in &[Kind::Bool, Kind::Str] {
+            for kind in &[Kind::Bool, Kind::Str, Kind::Char] {
                 run_inchoerent_operation(kind, op, kind)
             }
         }
@@ -748,7 +1380,14 @@ mod test {
             Operator::Greater,
             Operator::GreaterEqual,
         ] {
-            for kind in &[Kind::Real, Kind::Int, Kind::Bool, Kind::Str] {
+            for kind in &[
+                Kind::Real,
+                Kind::Int,
+                Kind::Long,
+                Kind::Char,
+                Kind::Bool,
+                Kind::Str,
+            ] {
                 run_correct_coherent_test(kind, op, kind, &Kind::Bool);
             }
         }
@@ -0,0 +1,225 @@
+use super::{qualified_name, Access, Entry};
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphFormat {
+    Text,
+    Dot,
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "dot" => Ok(Self::Dot),
+            other => Err(format!(
+                "unknown graph format {}, expected text or dot",
+                other
+            )),
+        }
+    }
+}
+
+/*
+    The calls among the functions of a program and its
+    body: an edge is a direct call, a function passed as a
+    value is not. A function is recursive when it can reach
+    itself, the functions reaching each other form a cycle.
+*/
+pub fn call_graph(entries: &[Entry], format: GraphFormat) -> String {
+    let graph = Graph::new(entries);
+    match format {
+        GraphFormat::Text => graph.text(),
+        GraphFormat::Dot => graph.dot(),
+    }
+}
+
+// node 0 is the body, the others are the functions in order
+struct Graph {
+    names: Vec<String>,
+    edges: BTreeSet<(usize, usize)>,
+    cycles: Vec<Vec<usize>>,
+    cycle_of: Vec<Option<usize>>,
+}
+
+impl Graph {
+    fn new(entries: &[Entry]) -> Self {
+        let functions: Vec<usize> = (0..entries.len())
+            .filter(|index| entries[*index].function)
+            .collect();
+        let node = |entry: Option<usize>| match entry {
+            Some(entry) => 1 + functions.iter().position(|index| *index == entry).unwrap(),
+            None => 0,
+        };
+        let mut names = vec!["body".to_owned()];
+        let mut edges = BTreeSet::new();
+        for index in &functions {
+            names.push(qualified_name(entries, *index));
+            for site in &entries[*index].sites {
+                if site.access == Access::Call {
+                    edges.insert((node(site.caller), node(Some(*index))));
+                }
+            }
+        }
+        let mut graph = Self {
+            cycle_of: vec![None; names.len()],
+            names,
+            edges,
+            cycles: Vec::new(),
+        };
+        graph.find_cycles();
+        graph
+    }
+
+    fn find_cycles(&mut self) {
+        let reach: Vec<Vec<bool>> = (0..self.names.len()).map(|node| self.reach(node)).collect();
+        for (node, reached) in reach.iter().enumerate() {
+            if !reached[node] || self.cycle_of[node].is_some() {
+                continue;
+            }
+            let cycle: Vec<usize> = (node..self.names.len())
+                .filter(|other| reached[*other] && reach[*other][node])
+                .collect();
+            for member in &cycle {
+                self.cycle_of[*member] = Some(self.cycles.len());
+            }
+            self.cycles.push(cycle);
+        }
+    }
+
+    // the nodes reachable with at least one call
+    fn reach(&self, from: usize) -> Vec<bool> {
+        let mut reached = vec![false; self.names.len()];
+        let mut stack = vec![from];
+        while let Some(node) = stack.pop() {
+            for callee in self.callees(node) {
+                if !reached[callee] {
+                    reached[callee] = true;
+                    stack.push(callee);
+                }
+            }
+        }
+        reached
+    }
+
+    fn in_cycle(&self, caller: usize, callee: usize) -> bool {
+        self.cycle_of[caller].is_some() && self.cycle_of[caller] == self.cycle_of[callee]
+    }
+
+    fn callees(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        self.edges
+            .range((node, 0)..(node + 1, 0))
+            .map(|(_, callee)| *callee)
+    }
+
+    fn text(&self) -> String {
+        let mut output = String::new();
+        for (node, name) in self.names.iter().enumerate() {
+            let callees: Vec<&str> = self
+                .callees(node)
+                .map(|callee| self.names[callee].as_str())
+                .collect();
+            if callees.is_empty() {
+                output.push_str(&format!("{}\n", name));
+            } else {
+                output.push_str(&format!("{} -> {}\n", name, callees.join(", ")));
+            }
+        }
+        for cycle in &self.cycles {
+            let names: Vec<&str> = cycle
+                .iter()
+                .map(|node| self.names[*node].as_str())
+                .collect();
+            output.push_str(&format!("recursive: {}\n", names.join(", ")));
+        }
+        output
+    }
+
+    // recursive functions and the calls closing their cycle are red
+    fn dot(&self) -> String {
+        let mut output = String::from("digraph calls {\n");
+        for (node, name) in self.names.iter().enumerate() {
+            let style = if node == 0 {
+                ", shape=box"
+            } else if self.cycle_of[node].is_some() {
+                ", color=red"
+            } else {
+                ""
+            };
+            output.push_str(&format!("    n{} [label=\"{}\"{}];\n", node, name, style));
+        }
+        for (caller, callee) in &self.edges {
+            let style = if self.in_cycle(*caller, *callee) {
+                " [color=red]"
+            } else {
+                ""
+            };
+            output.push_str(&format!("    n{} -> n{}{};\n", caller, callee, style));
+        }
+        output.push_str("}\n");
+        output
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::super::cross_reference;
+    use super::super::test::{parse, CODE};
+    use super::*;
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("dot".parse(), Ok(GraphFormat::Dot));
+        assert!("svg".parse::<GraphFormat>().is_err());
+    }
+
+    #[test]
+    fn test_text() {
+        let (program, _) = parse(CODE);
+        let graph = call_graph(&cross_reference(&program), GraphFormat::Text);
+        let expected = "\
+body -> is_even, add
+is_even -> is_even.is_odd
+is_even.is_odd -> is_even
+add
+recursive: is_even, is_even.is_odd
+";
+        assert_eq!(graph, expected);
+    }
+
+    #[test]
+    fn test_dot() {
+        let code = "func f(n: integer): integer
+body
+    if n > 0 then return f(n - 1); end;
+    return g(n);
+end;
+
+func g(n: integer): integer
+body
+    return n;
+end;
+
+body
+    writeln(f(3));
+end.
+";
+        let (program, _) = parse(code);
+        let graph = call_graph(&cross_reference(&program), GraphFormat::Dot);
+        let expected = "\
+digraph calls {
+    n0 [label=\"body\", shape=box];
+    n1 [label=\"f\", color=red];
+    n2 [label=\"g\"];
+    n0 -> n1;
+    n1 -> n1 [color=red];
+    n1 -> n2;
+}
+";
+        assert_eq!(graph, expected);
+    }
+}
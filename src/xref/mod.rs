@@ -0,0 +1,385 @@
+mod call_graph;
+
+pub use call_graph::{call_graph, GraphFormat};
+
+use crate::semantic_analysis::{
+    declare_locals, global_tables, resolve_symbol, signature, FactoryLocalVariableTable,
+    LocalVariableTable,
+};
+use extract_line_error::SourceMap;
+use simpla_parser::printer::kind_name;
+use simpla_parser::syntax_tree::*;
+use std::collections::HashMap;
+use std::fmt;
+
+/*
+    Cross reference of the global variables and of the
+    functions of a program, nested functions included. Every
+    name in the code is resolved in the tables of the semantic
+    analysis, filled once for each function while the program is
+    walked, so a local variable hiding a global one is not
+    counted as a use of the global.
+*/
+pub struct Entry {
+    pub name: String,
+    pub detail: String,
+    pub function: bool,
+    // the entry of the function declaring a nested function
+    pub parent: Option<usize>,
+    pub sites: Vec<Site>,
+}
+
+/*
+    A variable passed to a var parameter is written, since
+    the callee may change it. The caller is the entry of the
+    innermost function the site is in, none for the body.
+*/
+pub struct Site {
+    pub access: Access,
+    pub loc: Location,
+    pub caller: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access {
+    Definition,
+    Read,
+    Write,
+    Call,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Access::Definition => "defined",
+            Access::Read => "read",
+            Access::Write => "written",
+            Access::Call => "called",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+pub fn cross_reference(program: &Program) -> Vec<Entry> {
+    let globals = global_tables(program);
+    let mut collector = Collector::new(&globals);
+    collector.declare(program);
+    collector.visit_program(program);
+    collector.entries
+}
+
+// outer.inner for nested functions
+pub fn qualified_name(entries: &[Entry], index: usize) -> String {
+    match entries[index].parent {
+        Some(parent) => format!(
+            "{}.{}",
+            qualified_name(entries, parent),
+            entries[index].name
+        ),
+        None => entries[index].name.clone(),
+    }
+}
+
+pub fn format_cross_reference(entries: &[Entry], sources: &SourceMap) -> String {
+    let mut output = String::new();
+    for (index, entry) in entries.iter().enumerate() {
+        output.push_str(&format!(
+            "{}: {}\n",
+            qualified_name(entries, index),
+            entry.detail
+        ));
+        for site in &entry.sites {
+            let scope = match site.caller {
+                Some(caller) => format!("in {}", qualified_name(entries, caller)),
+                None if site.access == Access::Definition => "globally".to_owned(),
+                None => "in the body".to_owned(),
+            };
+            output.push_str(&format!(
+                "    {} at {}:{} {}\n",
+                site.access,
+                sources.file_name(site.loc.begin),
+                sources.line_number(site.loc.begin),
+                scope
+            ));
+        }
+    }
+    output
+}
+
+// a declaration: its name, whether it is a function and where it begins
+type Key = (String, bool, usize);
+
+struct Collector<'a> {
+    globals: &'a FactoryLocalVariableTable<'a>,
+    body: LocalVariableTable<'a>,
+    // the tables of the functions the walk is in, innermost last
+    tables: Vec<LocalVariableTable<'a>>,
+    entries: Vec<Entry>,
+    keys: HashMap<Key, usize>,
+    functions: HashMap<usize, &'a FuncDecl>,
+    scope: Vec<usize>,
+    loc: Location,
+}
+
+impl<'a> Collector<'a> {
+    fn new(globals: &'a FactoryLocalVariableTable<'a>) -> Self {
+        Self {
+            globals,
+            body: globals.factory_local_table(),
+            tables: Vec::new(),
+            entries: Vec::new(),
+            keys: HashMap::new(),
+            functions: HashMap::new(),
+            scope: Vec::new(),
+            loc: Location::new(0, 0),
+        }
+    }
+
+    // the entries are created first, so that uses before a declaration are found
+    fn declare(&mut self, program: &'a Program) {
+        for var_decl in &program.global_vars {
+            for var in &var_decl.id_list {
+                let detail = kind_name(&var_decl.kind);
+                self.add_entry(var, detail, false, &var_decl.loc, None);
+            }
+        }
+        self.declare_functions(&program.functions, None);
+    }
+
+    fn declare_functions(&mut self, functions: &'a [FuncDecl], parent: Option<usize>) {
+        for func_decl in functions {
+            let index = self.add_entry(
+                &func_decl.id,
+                signature(func_decl),
+                true,
+                &func_decl.loc,
+                parent,
+            );
+            self.functions.insert(func_decl.loc.begin, func_decl);
+            self.declare_functions(&func_decl.functions, Some(index));
+        }
+    }
+
+    fn add_entry(
+        &mut self,
+        name: &str,
+        detail: String,
+        function: bool,
+        loc: &Location,
+        parent: Option<usize>,
+    ) -> usize {
+        let index = self.entries.len();
+        self.keys
+            .insert((name.to_owned(), function, loc.begin), index);
+        self.entries.push(Entry {
+            name: name.to_owned(),
+            detail,
+            function,
+            parent,
+            sites: vec![Site {
+                access: Access::Definition,
+                loc: loc.clone(),
+                caller: parent,
+            }],
+        });
+        index
+    }
+
+    // names that resolve to locals, parameters or built-ins are not recorded
+    fn record(&mut self, name: &str, access: Access) -> Option<&'a FuncDecl> {
+        let table = self.tables.last().unwrap_or(&self.body);
+        let symbol = resolve_symbol(table, name)?;
+        let loc = symbol.loc?;
+        let access = match access {
+            Access::Call if !symbol.function => Access::Read,
+            access => access,
+        };
+        if let Some(index) = self.keys.get(&(symbol.name, symbol.function, loc.begin)) {
+            self.entries[*index].sites.push(Site {
+                access,
+                loc: self.loc.clone(),
+                caller: self.scope.last().copied(),
+            });
+        }
+        if symbol.function {
+            self.functions.get(&loc.begin).copied()
+        } else {
+            None
+        }
+    }
+
+    fn at<F: FnOnce(&mut Self)>(&mut self, loc: &Location, f: F) {
+        let outer = std::mem::replace(&mut self.loc, loc.clone());
+        f(self);
+        self.loc = outer;
+    }
+}
+
+impl<'a> Visitor<'a> for Collector<'a> {
+    fn visit_func_decl(&mut self, func_decl: &'a FuncDecl) {
+        let key = (func_decl.id.clone(), true, func_decl.loc.begin);
+        let index = self.keys[&key];
+        let mut table = match self.tables.last() {
+            Some(enclosing) => enclosing.nested_table(),
+            None => self.globals.factory_local_table(),
+        };
+        declare_locals(func_decl, &mut table);
+        self.tables.push(table);
+        self.scope.push(index);
+        walk_func_decl(self, func_decl);
+        self.scope.pop();
+        self.tables.pop();
+    }
+
+    fn visit_stat(&mut self, stat: &'a Stat) {
+        self.at(&stat.loc, |collector| {
+            match &stat.stat {
+                StatType::AssignStat(assign) => {
+                    collector.record(&assign.id, Access::Write);
+                }
+                StatType::ForStat(for_stat) => {
+                    collector.record(&for_stat.id, Access::Write);
+                }
+                StatType::ReadStat(id_list) => {
+                    for id in id_list {
+                        collector.record(id, Access::Write);
+                    }
+                }
+                _ => {}
+            }
+            walk_stat(collector, stat);
+        });
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        self.at(&expr.loc, |collector| walk_expr(collector, expr));
+    }
+
+    fn visit_factor(&mut self, factor: &'a Factor) {
+        match &factor.fact {
+            FactorValue::Id(id) => {
+                self.record(id, Access::Read);
+            }
+            FactorValue::IndexExpr(index) => {
+                self.record(&index.id, Access::Read);
+            }
+            _ => {}
+        }
+        walk_factor(self, factor);
+    }
+
+    fn visit_func_call(&mut self, func_call: &'a FuncCall) {
+        let callee = self.record(&func_call.id, Access::Call);
+        for (index, arg) in func_call.args.iter().enumerate() {
+            let by_ref = callee
+                .and_then(|callee| callee.params.get(index))
+                .is_some_and(|param| param.by_ref);
+            match &arg.expr {
+                ExprTree::Factor(Factor {
+                    fact: FactorValue::Id(id),
+                    ..
+                }) if by_ref => self.at(&arg.loc, |collector| {
+                    collector.record(id, Access::Write);
+                }),
+                _ => self.visit_expr(arg),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use simpla_parser::ProgramParser;
+
+    pub const CODE: &str = "count, total: integer;
+
+func is_even(n: integer): boolean
+    func is_odd(n: integer): boolean
+    body
+        count = count + 1;
+        return n != 0 and is_even(n - 1);
+    end;
+body
+    count = count + 1;
+    return n == 0 or is_odd(n - 1);
+end;
+
+func add(var sum: integer, n: integer): void
+    count: integer;
+body
+    count = n;
+    sum = sum + count;
+end;
+
+body
+    read(total);
+    add(total, 2);
+    writeln(is_even(total));
+end.
+";
+
+    pub fn parse(code: &str) -> (Program, SourceMap) {
+        let program = ProgramParser::new().parse(code).unwrap();
+        let mut sources = SourceMap::new();
+        sources.add_file("parity.simpla".to_owned(), code.to_owned());
+        (program, sources)
+    }
+
+    fn sites(entry: &Entry) -> Vec<(Access, usize)> {
+        entry
+            .sites
+            .iter()
+            .map(|site| (site.access, site.loc.begin))
+            .collect()
+    }
+
+    #[test]
+    fn test_cross_reference() {
+        let (program, _) = parse(CODE);
+        let entries = cross_reference(&program);
+        let names: Vec<String> = (0..entries.len())
+            .map(|index| qualified_name(&entries, index))
+            .collect();
+        assert_eq!(
+            names,
+            vec!["count", "total", "is_even", "is_even.is_odd", "add"]
+        );
+
+        // the local count of add hides the global one
+        let count = sites(&entries[0]);
+        let accesses: Vec<Access> = count.iter().map(|(access, _)| *access).collect();
+        use Access::*;
+        assert_eq!(accesses, vec![Definition, Write, Read, Write, Read]);
+
+        // passed to a var parameter
+        let total = sites(&entries[1]);
+        assert_eq!(total[1], (Write, CODE.find("read(total)").unwrap()));
+        assert_eq!(total[2], (Write, CODE.find("total, 2").unwrap()));
+        assert_eq!(total[3].0, Read);
+
+        let is_even = &entries[2];
+        assert_eq!(is_even.detail, "func is_even(n: integer): boolean");
+        assert_eq!(is_even.sites[1].access, Call);
+        assert_eq!(is_even.sites[1].caller, Some(3));
+        assert_eq!(is_even.sites[2].caller, None);
+        assert_eq!(entries[3].sites[1].caller, Some(2));
+    }
+
+    #[test]
+    fn test_format_cross_reference() {
+        let (program, sources) = parse(CODE);
+        let text = format_cross_reference(&cross_reference(&program), &sources);
+        let expected = "\
+total: integer
+    defined at parity.simpla:1 globally
+    written at parity.simpla:22 in the body
+    written at parity.simpla:23 in the body
+    read at parity.simpla:24 in the body
+";
+        assert!(text.contains(expected), "{}", text);
+        assert!(text.contains("is_even.is_odd: func is_odd(n: integer): boolean\n"));
+        assert!(text.contains("    called at parity.simpla:7 in is_even.is_odd\n"));
+    }
+}
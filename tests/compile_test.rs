@@ -7,6 +7,14 @@ use tempfile::TempDir;
 
 lazy_static::lazy_static! {
     static ref BASE_DIR: PathBuf = Path::new("tests").join("simpla_programs");
+    /*
+        Bytecode of the original compiler, format v1. The one
+        difference is in unary_test.simplac: the negated literal
+        of `for i = -10` is folded, so the code loads -10
+        (LDIC -10) where the original compiler negates 10
+        (LDIC 10, NEGI).
+    */
+    static ref BYTECODE_DIR: PathBuf = Path::new("tests").join("simpla_bytecode");
 }
 
 #[test]
@@ -20,6 +28,24 @@ fn bytecode_translation_test(file: &Path, target: &tempfile::TempDir) {
     run_compile("translate", file, &simpla_bytecode);
 }
 
+#[test]
+fn test_bytecode_compatibility() {
+    let target_dir = TempDir::new().unwrap();
+    for entry in read_dir(BYTECODE_DIR.as_path()).unwrap() {
+        let expected = entry.unwrap().path();
+        let name = expected.file_stem().unwrap();
+        let source = BASE_DIR.join(name).with_extension("simpla");
+        let output = target_dir.path().join(expected.file_name().unwrap());
+        run_compile("translate", &source, &output);
+        assert!(
+            std::fs::read(&output).unwrap() == std::fs::read(&expected).unwrap(),
+            "{:?} does not match {:?}",
+            source,
+            expected
+        );
+    }
+}
+
 fn run_test<F>(callback: F)
 where
     F: Fn(&Path, &tempfile::TempDir),
@@ -71,3 +97,26 @@ fn run_check(file: &Path) {
     assert!(output.status.success(), "{:?}", output);
     assert_eq!(output.stdout.len(), 0);
 }
+
+#[test]
+fn test_source_format() {
+    run_test(format_test);
+}
+
+fn format_test(file: &Path, target: &tempfile::TempDir) {
+    let formatted = target.path().join(file.file_name().unwrap());
+    std::fs::copy(file, &formatted).unwrap();
+    run_format(&formatted, false);
+    run_format(&formatted, true);
+    run_check(&formatted);
+}
+
+fn run_format(file: &Path, check: bool) {
+    let mut command = Command::new("cargo");
+    command.arg("run").arg("--").arg("fmt").arg(file);
+    if check {
+        command.arg("--check");
+    }
+    let output = command.output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+}
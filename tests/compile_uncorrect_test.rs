@@ -10,15 +10,27 @@ lazy_static::lazy_static! {
 
 #[test]
 fn test_run_check_on_uncorrect_program() {
+    run_on_uncorrect_programs("check");
+}
+
+// the reports are given only on programs that pass the analysis
+#[test]
+fn test_run_reports_on_uncorrect_program() {
+    run_on_uncorrect_programs("xref");
+    run_on_uncorrect_programs("callgraph");
+}
+
+fn run_on_uncorrect_programs(command: &str) {
     for entry in read_dir(BASE_DIR.as_path()).unwrap() {
         let file = entry.unwrap().path();
         let output = Command::new("cargo")
             .arg("run")
             .arg("--")
-            .arg("check")
+            .arg(command)
             .arg(file)
             .output();
         let output = output.unwrap();
-        assert!(!output.status.success());
+        // an error is reported, the compiler does not panic
+        assert_eq!(output.status.code(), Some(1), "{:?}", output);
     }
 }
@@ -0,0 +1,138 @@
+use simplac::syntax_tree::{Kind, StatType};
+use simplac::{ByteCodeFormat, Compiler, DumpFormat, GraphFormat, Promotion, Severity};
+use std::path::{Path, PathBuf};
+
+const CODE: &str = "r: real;
+body
+    r = 1 + 2;
+    writeln(r);
+end.";
+
+#[test]
+fn test_compile_source() {
+    let compiler = Compiler::new().promotion(Promotion::Warn);
+    let compilation = compiler
+        .check_source(Path::new("promote.simpla"), CODE.to_owned())
+        .unwrap();
+
+    assert_eq!(compilation.warnings.len(), 1);
+    let warning = &compilation.warnings[0];
+    assert_eq!(warning.severity, Severity::Warning);
+    assert_eq!(warning.file.as_deref(), Some("promote.simpla"));
+    assert_eq!(warning.line, Some(3));
+    assert!(warning.message.contains("Warning on line: 3"));
+    assert!(!warning.message.contains("Error"));
+
+    // the syntax tree is annotated with the kinds
+    match &compilation.program.body[0].stat {
+        StatType::AssignStat(assign) => {
+            assert_eq!(*assign.expr.kind.borrow(), Some(Kind::Int));
+            assert_eq!(*assign.expr.coercion.borrow(), Some(Kind::Real));
+        }
+        stat => panic!("unexpected {:?}", stat),
+    }
+    assert!(!compilation.bytecode().unwrap().is_empty());
+}
+
+#[test]
+fn test_diagnostics() {
+    let compiler = Compiler::new();
+    let err = compiler
+        .check_source(Path::new("promote.simpla"), CODE.to_owned())
+        .err()
+        .unwrap();
+    assert_eq!(err.severity, Severity::Error);
+    assert_eq!(err.line, Some(3));
+    assert!(err.loc.is_some());
+
+    let code = "body\n    writeln(1)\nend.";
+    let err = compiler
+        .check_source(Path::new("syntax.simpla"), code.to_owned())
+        .err()
+        .unwrap();
+    assert_eq!(err.file.as_deref(), Some("syntax.simpla"));
+    assert_eq!(err.line, Some(3));
+
+    let err = compiler
+        .check_path(Path::new("missing.simpla"))
+        .err()
+        .unwrap();
+    assert_eq!(err.loc, None);
+
+    // the translation needs the kinds given by the analysis
+    let parsed = compiler
+        .parse_source(Path::new("promote.simpla"), CODE.to_owned())
+        .unwrap();
+    assert!(parsed.bytecode().is_err());
+}
+
+#[test]
+fn test_compile_path() {
+    let imports = PathBuf::from("test_code").join("imports");
+    let compiler = Compiler::new()
+        .include_path(imports.join("lib"))
+        .format(ByteCodeFormat::V2);
+    let compilation = compiler
+        .check_path(&imports.join("shapes-correct.simpla"))
+        .unwrap();
+    assert!(compilation.warnings.is_empty());
+    let bytecode = compilation.bytecode().unwrap();
+    assert!(!bytecode.is_empty());
+
+    let err = compiler
+        .check_path(&imports.join("name_collision-error.simpla"))
+        .err()
+        .unwrap();
+    assert!(err.message.starts_with("name error"), "{}", err);
+}
+
+#[test]
+fn test_limit_diagnostics() {
+    let code = format!(
+        "s: string;\nbody\n    s = \"{}\";\n    writeln(s);\nend.",
+        "a".repeat(70_000)
+    );
+    let compilation = Compiler::new()
+        .check_source(Path::new("long.simpla"), code.clone())
+        .unwrap();
+    let errors = compilation.bytecode().err().unwrap();
+    assert_eq!(errors.len(), 1);
+    let err = &errors[0];
+    assert_eq!(err.severity, Severity::Error);
+    assert_eq!(err.file.as_deref(), Some("long.simpla"));
+    assert_eq!(err.line, Some(3));
+    assert!(err.loc.is_some());
+    assert!(
+        err.message.contains("70000 bytes in a string constant"),
+        "{}",
+        err
+    );
+    assert!(err.to_string().contains("Error on line: 3"), "{}", err);
+
+    let compilation = Compiler::new()
+        .format(ByteCodeFormat::V2)
+        .check_source(Path::new("long.simpla"), code)
+        .unwrap();
+    assert!(compilation.bytecode().is_ok());
+}
+
+#[test]
+fn test_tools() {
+    let code = "func f(): void body writeln(1); end; body f(); end.";
+    let compilation = Compiler::new()
+        .parse_source(Path::new("tools.simpla"), code.to_owned())
+        .unwrap();
+    let dump = compilation.dump_ast(DumpFormat::Sexp).unwrap();
+    assert!(dump.starts_with("(Program"), "{}", dump);
+    // the reports need the names resolved by the check
+    assert!(compilation.cross_reference().is_err());
+    assert!(compilation.call_graph(GraphFormat::Dot).is_err());
+
+    let compilation = Compiler::new()
+        .check_source(Path::new("tools.simpla"), code.to_owned())
+        .unwrap();
+    let xref = compilation.cross_reference().unwrap();
+    assert!(xref.contains("tools.simpla:1"), "{}", xref);
+    let graph = compilation.call_graph(GraphFormat::Dot).unwrap();
+    assert!(graph.starts_with("digraph"), "{}", graph);
+}
@@ -0,0 +1,174 @@
+use serde_json::{json, Value};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const URI: &str = "file:///tmp/lsp_test.simpla";
+const CODE: &str = "n: integer;
+func square(x: integer): integer
+body
+    return x * x;
+end;
+body
+    n = square(3);
+    writeln(m);
+end.";
+
+fn frame(message: Value) -> String {
+    let content = message.to_string();
+    format!("Content-Length: {}\r\n\r\n{}", content.len(), content)
+}
+
+fn position_request(id: u64, method: &str, line: u64, character: u64) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": method,
+        "params": {
+            "textDocument": {"uri": URI},
+            "position": {"line": line, "character": character},
+        },
+    })
+}
+
+fn parse_messages(mut output: &str) -> Vec<Value> {
+    let mut messages = Vec::new();
+    while let Some(header_end) = output.find("\r\n\r\n") {
+        let length: usize = output["Content-Length: ".len()..header_end]
+            .parse()
+            .unwrap();
+        let content = &output[header_end + 4..header_end + 4 + length];
+        messages.push(serde_json::from_str(content).unwrap());
+        output = &output[header_end + 4 + length..];
+    }
+    messages
+}
+
+fn response(messages: &[Value], id: u64) -> &Value {
+    messages
+        .iter()
+        .find(|message| message["id"] == id)
+        .unwrap_or_else(|| panic!("no response to request {}", id))
+}
+
+fn run_session(script: Vec<Value>) -> Vec<Value> {
+    let input: String = script.into_iter().map(frame).collect();
+
+    let mut child = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "STDERR:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    parse_messages(std::str::from_utf8(&output.stdout).unwrap())
+}
+
+#[test]
+fn test_language_server_session() {
+    let script = vec![
+        json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"capabilities": {}}}),
+        json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {"textDocument": {"uri": URI, "languageId": "simpla", "version": 1, "text": CODE}},
+        }),
+        position_request(2, "textDocument/hover", 6, 9),
+        position_request(3, "textDocument/definition", 6, 9),
+        position_request(4, "textDocument/documentSymbol", 0, 0),
+        position_request(5, "textDocument/completion", 3, 11),
+        json!({"jsonrpc": "2.0", "id": 6, "method": "shutdown"}),
+        json!({"jsonrpc": "2.0", "method": "exit"}),
+    ];
+    let messages = run_session(script);
+
+    let capabilities = &response(&messages, 1)["result"]["capabilities"];
+    assert_eq!(capabilities["hoverProvider"], true);
+    assert_eq!(capabilities["textDocumentSync"], 1);
+
+    let diagnostics = messages
+        .iter()
+        .find(|message| message["method"] == "textDocument/publishDiagnostics")
+        .unwrap();
+    let diagnostics = diagnostics["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0]["range"],
+        json!({"start": {"line": 7, "character": 12}, "end": {"line": 7, "character": 13}})
+    );
+
+    let hover = &response(&messages, 2)["result"]["contents"]["value"];
+    assert_eq!(hover, "```simpla\nfunc square(x: integer): integer\n```");
+
+    let definition = &response(&messages, 3)["result"];
+    assert_eq!(definition["uri"], URI);
+    assert_eq!(
+        definition["range"]["start"],
+        json!({"line": 1, "character": 0})
+    );
+
+    let symbols = response(&messages, 4)["result"].as_array().unwrap();
+    let names: Vec<&Value> = symbols.iter().map(|symbol| &symbol["name"]).collect();
+    assert_eq!(names, vec!["n", "square"]);
+
+    let items = response(&messages, 5)["result"].as_array().unwrap();
+    assert!(items.iter().any(|item| item["label"] == "x"));
+    assert!(items.iter().any(|item| item["label"] == "return"));
+
+    assert_eq!(response(&messages, 6)["result"], Value::Null);
+}
+
+#[test]
+fn test_unterminated_comment_while_editing() {
+    let script = vec![
+        json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"capabilities": {}}}),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {"textDocument": {"uri": URI, "languageId": "simpla", "version": 1, "text": CODE}},
+        }),
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didChange",
+            "params": {
+                "textDocument": {"uri": URI, "version": 2},
+                "contentChanges": [{"text": "body\n  (* oops\nend.\n"}],
+            },
+        }),
+        position_request(2, "textDocument/documentSymbol", 0, 0),
+        json!({"jsonrpc": "2.0", "id": 3, "method": "shutdown"}),
+        json!({"jsonrpc": "2.0", "method": "exit"}),
+    ];
+    let messages = run_session(script);
+
+    let diagnostics = messages
+        .iter()
+        .filter(|message| message["method"] == "textDocument/publishDiagnostics")
+        .last()
+        .unwrap();
+    let diagnostics = diagnostics["params"]["diagnostics"].as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["message"], "Unterminated Comment");
+    assert_eq!(
+        diagnostics[0]["range"]["start"],
+        json!({"line": 1, "character": 2})
+    );
+
+    // the server keeps answering after the broken change
+    assert!(response(&messages, 2)["result"].is_array());
+    assert_eq!(response(&messages, 3)["result"], Value::Null);
+}
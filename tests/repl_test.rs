@@ -0,0 +1,77 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const SCRIPT: &str = "n: integer;
+func fact(k: integer): integer
+body
+    if k <= 1 then
+        return 1;
+    end;
+    return k * fact(k - 1);
+end;
+read(n);
+5
+fact(n)
+writeln(\"done \", n);
+m = 1
+halt(n)
+n
+";
+
+#[test]
+fn test_repl_session() {
+    let mut repl = Command::new("cargo")
+        .args(["run", "--", "repl"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    repl.stdin
+        .take()
+        .unwrap()
+        .write_all(SCRIPT.as_bytes())
+        .unwrap();
+    let output = repl.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(5));
+    assert!(stdout.contains("120\n"), "{}", stdout);
+    assert!(stdout.contains("done 5\n"), "{}", stdout);
+    assert!(stderr.contains("m"), "{}", stderr);
+    // the session ends at halt
+    assert_eq!(stdout.matches("120").count(), 1);
+}
+
+#[test]
+fn test_runaway_recursion() {
+    let script = "func r(n: integer): integer body return r(n + 1); end;
+writeln(r(0));
+writeln(\"still running\");
+";
+    let mut repl = Command::new("cargo")
+        .args(["run", "--", "repl"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    repl.stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+    let output = repl.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    // the recursion is stopped by the interpreter, not by a stack overflow
+    assert_eq!(output.status.code(), Some(0), "{}", stderr);
+    assert!(
+        stderr.contains("more than 1000 nested function calls"),
+        "{}",
+        stderr
+    );
+    assert!(stdout.contains("still running\n"), "{}", stdout);
+}